@@ -0,0 +1,57 @@
+//! Benchmarks for parsing long single-line collections
+//!
+//! The benchmarks use a plain timing loop so that they run on a stable compiler:
+//!
+//! ```bash
+//! cargo bench --bench body
+//! ```
+//!
+//! The time per element should stay about the same as the collections grow, since parsing takes
+//! linear time.
+use std::time::{Duration, Instant};
+
+use ferrous_chloride::parser::parse_str;
+
+const ITERATIONS: u32 = 10;
+
+// A tuple and an object with `n` elements each, each on a single line
+fn document(n: usize) -> String {
+    let elements: Vec<_> = (0..n).map(|i| format!("{}", i)).collect();
+    let attributes: Vec<_> = (0..n).map(|i| format!("a{} = \"{}\"", i, i)).collect();
+    format!(
+        "list = [{}]\nobject = {{{}}}\n",
+        elements.join(", "),
+        attributes.join(", ")
+    )
+}
+
+fn bench(elements: usize) {
+    let input = document(elements);
+    // Sum the lengths of the parsed bodies so that the parsing cannot be optimized away
+    let mut checksum = 0;
+    let mut best = Duration::from_secs(u64::MAX);
+
+    for _ in 0..5 {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            let body = parse_str(&input).expect("document to parse");
+            checksum += body.len();
+        }
+        best = best.min(start.elapsed());
+    }
+
+    let per_iteration = best / ITERATIONS;
+    println!(
+        "{:>6} elements {:>10} µs/document {:>6} ns/element (checksum {})",
+        elements,
+        per_iteration.as_micros(),
+        per_iteration.as_nanos() / (2 * elements as u128),
+        checksum
+    );
+}
+
+fn main() {
+    for elements in [2_000, 4_000, 8_000, 16_000, 32_000].iter() {
+        bench(*elements);
+    }
+}
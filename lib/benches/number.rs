@@ -0,0 +1,55 @@
+//! Benchmarks for the `number` parser
+//!
+//! The benchmarks use a plain timing loop so that they run on a stable compiler:
+//!
+//! ```bash
+//! cargo bench --bench number
+//! ```
+use std::time::{Duration, Instant};
+
+use ferrous_chloride::nom::types::CompleteStr;
+use ferrous_chloride::parser::number::number;
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn bench(name: &str, inputs: &[&str]) {
+    // Sum the lengths of the remaining input so that the parsing cannot be optimized away
+    let mut checksum = 0;
+    let mut best = Duration::from_secs(u64::MAX);
+
+    for _ in 0..5 {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            for input in inputs {
+                let (remaining, _) = number(CompleteStr(input)).expect("number to parse");
+                checksum += remaining.len();
+            }
+        }
+        best = best.min(start.elapsed());
+    }
+
+    let per_iteration = best / (ITERATIONS * inputs.len() as u32);
+    println!(
+        "{:<12} {:>8} ns/number (checksum {})",
+        name,
+        per_iteration.as_nanos(),
+        checksum
+    );
+}
+
+fn main() {
+    bench("integer", &["0", "42", "-17", "1234567890", "+9"]);
+    bench("float", &["1.5", "-0.25", "3.14159", "10.", ".5"]);
+    bench("exponent", &["1e10", "2.5E-3", "-6e+2", "1.0e0"]);
+    bench("radix", &["0xff", "-0o17", "0b1010"]);
+    bench(
+        "precision",
+        &[
+            "123456789012345678901234567890",
+            "3.14159265358979323846264338327950288",
+            "-0.000000000000000000000000000001",
+            "6.02214076000000000000000000e+23",
+        ],
+    );
+    bench("delimited", &["42,", "7]", "100 }", "1.5\n"]);
+}
@@ -0,0 +1,54 @@
+//! Benchmarks for the `string` parser
+//!
+//! The benchmarks use a plain timing loop so that they run on a stable compiler:
+//!
+//! ```bash
+//! cargo bench --bench string
+//! ```
+use std::time::{Duration, Instant};
+
+use ferrous_chloride::nom::types::CompleteStr;
+use ferrous_chloride::parser::string::string;
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn bench(name: &str, inputs: &[&str]) {
+    // Sum the lengths of the parsed strings so that the parsing cannot be optimized away
+    let mut checksum = 0;
+    let mut best = Duration::from_secs(u64::MAX);
+
+    for _ in 0..5 {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            for input in inputs {
+                let (_, parsed) = string(CompleteStr(input)).expect("string to parse");
+                checksum += parsed.len();
+            }
+        }
+        best = best.min(start.elapsed());
+    }
+
+    let per_iteration = best / (ITERATIONS * inputs.len() as u32);
+    println!(
+        "{:<12} {:>8} ns/string (checksum {})",
+        name,
+        per_iteration.as_nanos(),
+        checksum
+    );
+}
+
+fn main() {
+    bench(
+        "short",
+        &[r#""""#, r#""a""#, r#""us-east-1""#, r#""t2.micro""#],
+    );
+    bench(
+        "long",
+        &[r#""The quick brown fox jumps over the lazy dog, again and again and again""#],
+    );
+    bench(
+        "escaped",
+        &[r#""a\"b""#, r#""line\nline""#, r#""tab\there""#],
+    );
+    bench("heredoc", &["<<EOF\nfoo\nbar\nEOF\n"]);
+}
@@ -1,9 +1,7 @@
 empty = ""
 single_line = "foobar"
 
-multi_line = "foo
-
-bar"
+multi_line = "foo\n\nbar"
 
 empty_heredoc = <<EOF
 EOF
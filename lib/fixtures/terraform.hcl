@@ -0,0 +1,54 @@
+# A small Terraform configuration
+terraform {
+  required_version = ">= 0.12"
+}
+
+provider "aws" {
+  region = var.region
+}
+
+variable "region" {
+  default = "us-east-1"
+}
+
+variable "instance_count" { default = 2 }
+
+locals {
+  common_tags = {
+    Project     = "ferrous-chloride"
+    Environment = "test" // Overridden per workspace
+  }
+}
+
+resource "aws_instance" "web" {
+  count         = var.instance_count
+  ami           = "ami-0c55b159cbfafe1f0"
+  instance_type = var.large ? "t2.small" : "t2.micro"
+  description   = "${join(", ", [var.region, "web"])}"
+  monitoring    = true
+
+  tags = local.common_tags
+
+  /* Install a web server on boot */
+  user_data = <<-EOT
+    #!/bin/bash
+    echo "Hello, World" > index.html
+    nohup busybox httpd -f -p 8080 &
+    EOT
+
+  ebs_block_device {
+    device_name = "/dev/sdg"
+    volume_size = 5.5
+  }
+
+  lifecycle {
+    create_before_destroy = true
+    ignore_changes        = [tags, user_data]
+  }
+}
+
+output "instance_ids" {
+  value = aws_instance.web[0].id
+}
+
+output_format = "json"
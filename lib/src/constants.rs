@@ -8,9 +8,22 @@ pub static LIST: &str = "List";
 pub static TUPLE: &str = "Tuple";
 pub static OBJECT: &str = "Object";
 pub static BLOCK: &str = "Block";
+pub static CONDITIONAL: &str = "Conditional";
+pub static VARIABLE: &str = "Variable";
+pub static TRAVERSAL: &str = "Traversal";
+pub static UNARY_OP: &str = "UnaryOp";
+pub static BINARY_OP: &str = "BinaryOp";
+pub static FUNCTION_CALL: &str = "FunctionCall";
+pub static FOR_EXPR: &str = "ForExpr";
 
 pub static MERGED: &str = "Merged";
 pub static UNMERGED: &str = "UNMERGED";
 
+pub static EXPRESSION: &str = "Expression";
 pub static VALUE: &str = "Value";
 pub static MAP_VALUES: &str = "MapValues";
+
+/// Keywords reserved by the HCL syntax, including the template directive keywords
+pub static KEYWORDS: &[&str] = &[
+    "true", "false", "null", "if", "else", "endif", "for", "in", "endfor",
+];
@@ -1,72 +1,270 @@
 use std::fmt::{Debug, Display};
 use std::string::ToString;
 
-use failure_derive::Fail;
 use nom::verbose_errors::Context;
-use nom::ErrorKind;
+use nom::{ErrorKind, IResult};
 
+use crate::span::Span;
 use crate::OneOrMany;
 
 /// Error type for parsing
-#[derive(Debug, Fail)]
+///
+/// The error implements [`std::error::Error`] and is `Send + Sync + 'static`, so it can be
+/// returned with `?` or boxed like any other error.
+#[derive(Debug)]
 pub enum Error {
-    #[fail(display = "Invalid Unicode Code Points \\{}", _0)]
     InvalidUnicodeCodePoint(String),
 
-    #[fail(display = "Invalid Number {}", _0)]
     InvalidNumber(String),
 
-    #[fail(display = "Bytes contain invalid unicode: {:#?}", _0)]
+    InvalidRange {
+        min: String,
+        max: String,
+    },
+
+    /// The number cannot be converted into a float without losing precision
+    LossyConversion {
+        number: String,
+        /// The nearest float to the number
+        value: f64,
+    },
+
     InvalidUnicode(Vec<u8>),
 
-    #[fail(display = "Generic Parse Error {}", _0)]
     ParseError(String),
 
-    #[fail(
-        display = "Variant {} does not allow multiple values with the same key {}",
-        variant, key
-    )]
-    IllegalMultipleEntries { key: String, variant: &'static str },
-
-    #[fail(
-        display = "Error merging key {} into `Value`: existing value of variant {} \
-                   cannot be merged with variant {}",
-        key, existing_variant, incoming_variant
-    )]
+    UnclosedTemplateDirective(String),
+
+    /// The end marker of the Heredoc with the identifier was not found before the end of the
+    /// input
+    UnterminatedHeredoc(String),
+
+    /// A Heredoc uses a carriage return that is not followed by a newline as a line ending
+    BareCarriageReturn,
+
+    /// The input is nested deeper than the
+    /// [maximum depth](crate::parser::ParseOptions::max_depth)
+    RecursionLimitExceeded,
+
+    /// A `=>` separates a key from its value in an object constructor. The string is the start of
+    /// the input from the arrow, up to the end of its line.
+    ArrowInObject(String),
+
+    /// A `\` at the end of a line in a quoted string, which does not continue the string on the
+    /// next line
+    LineContinuation,
+
+    /// An underscore in a number that does not separate two digits, or that is not
+    /// [allowed](crate::parser::ParseOptions::allow_underscore_separators). The string is the start
+    /// of the input from the underscore, up to the end of its line.
+    DigitSeparator(String),
+
+    /// A line of an indented Heredoc is indented with different whitespace characters than its
+    /// end marker, when
+    /// [checked](crate::parser::ParseOptions::strict_heredoc_indentation). The string is the start
+    /// of the line.
+    InconsistentHeredocIndent(String),
+
+    /// The input is longer than the
+    /// [maximum input length](crate::parser::ParseOptions::max_input_bytes)
+    InputTooLarge {
+        /// The length of the input in bytes
+        length: usize,
+        /// The maximum length in bytes
+        limit: usize,
+    },
+
+    UnexpectedSeparator {
+        expected: char,
+        found: char,
+        input: String,
+    },
+
+    DuplicateBlock {
+        block_type: String,
+        /// The span of the type of the first block, if it was borrowed from the source
+        first: Option<Span>,
+        /// The span of the type of the duplicate block, if it was borrowed from the source
+        duplicate: Option<Span>,
+    },
+
+    IllegalMultipleEntries {
+        key: String,
+        variant: &'static str,
+    },
+
     ErrorMergingKeys {
         key: String,
         existing_variant: &'static str,
         incoming_variant: &'static str,
     },
 
-    #[fail(
-        display = "Expected type {} to be of variant {} but got {} instead",
-        enum_type, expected, actual
-    )]
     UnexpectedVariant {
         enum_type: &'static str,
         expected: &'static str,
         actual: &'static str,
     },
 
-    #[fail(display = "IO Error: {}", _0)]
-    IOError(#[cause] std::io::Error),
+    IOError(std::io::Error),
 
-    #[fail(display = "Bytes to be parsed is invalid UTF-8: {}", _0)]
-    InvalidUnicodeToParse(#[cause] std::str::Utf8Error),
+    InvalidUnicodeToParse(std::str::Utf8Error),
 
-    #[fail(
-        display = "Expected all input to be consumed, but the following remains: {}",
-        _0
-    )]
+    /// Input that cannot be parsed was found after what could be parsed. The string is the start
+    /// of the first token that cannot be parsed, up to the end of its line.
     UnexpectedRemainingInput(String),
 
-    #[fail(
-        display = "Possible bug with the library encountered: {}; Please report to \
-                   https://github.com/lawliet89/ferrous-chloride/issues",
-        _0
-    )]
     Bug(String),
+
+    /// An error that occurred at a one-based line and column of the input
+    AtPosition {
+        line: usize,
+        column: usize,
+        /// What was being parsed when the error occurred, innermost first
+        context: Vec<ParseContext>,
+        error: Box<Error>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::InvalidUnicodeCodePoint(escape) => write!(
+                f,
+                "Escape sequence \\{} is not a valid Unicode code point",
+                escape
+            ),
+            Error::InvalidNumber(number) => write!(f, "Invalid number {}", number),
+            Error::InvalidRange { min, max } => write!(
+                f,
+                "Invalid range: minimum {} is greater than maximum {}",
+                min, max
+            ),
+            Error::LossyConversion { number, value } => write!(
+                f,
+                "Number {} cannot be converted to a float exactly, the nearest float is {}",
+                number, value
+            ),
+            Error::InvalidUnicode(bytes) => {
+                write!(f, "Bytes contain invalid Unicode: {:#?}", bytes)
+            }
+            Error::ParseError(error) => write!(f, "Generic Parse Error {}", error),
+            Error::UnclosedTemplateDirective(directive) => {
+                write!(f, "Template directive {} is not closed", directive)
+            }
+            Error::UnterminatedHeredoc(identifier) => {
+                write!(f, "Heredoc {} is not terminated", identifier)
+            }
+            Error::BareCarriageReturn => write!(
+                f,
+                "Heredoc lines must end with `\\n` or `\\r\\n`, a lone `\\r` is not a line ending"
+            ),
+            Error::RecursionLimitExceeded => {
+                write!(f, "Input is nested deeper than the maximum depth")
+            }
+            Error::ArrowInObject(input) => write!(
+                f,
+                "Expected `=` or `:`, found `=>`, which is only valid in `for` expressions, at: {}",
+                input
+            ),
+            Error::LineContinuation => write!(
+                f,
+                "A `\\` at the end of a line does not continue a quoted string; use a Heredoc or \
+                 the `\\n` escape sequence for strings spanning multiple lines"
+            ),
+            Error::DigitSeparator(input) => write!(
+                f,
+                "Unexpected `_` in a number; underscores can only separate two digits, when \
+                 allowed with `allow_underscore_separators`, at: {}",
+                input
+            ),
+            Error::InconsistentHeredocIndent(input) => write!(
+                f,
+                "The indentation of a Heredoc line mixes tabs and spaces differently than its end \
+                 marker, at: {}",
+                input
+            ),
+            Error::InputTooLarge { length, limit } => write!(
+                f,
+                "Input of {} bytes is longer than the maximum of {} bytes",
+                length, limit
+            ),
+            Error::UnexpectedSeparator {
+                expected,
+                found,
+                input,
+            } => write!(
+                f,
+                "Expected `{}`, found `{}` at: {}",
+                expected, found, input
+            ),
+            Error::DuplicateBlock { block_type, .. } => {
+                write!(f, "Block {} must appear at most once", block_type)
+            }
+            Error::IllegalMultipleEntries { key, variant } => write!(
+                f,
+                "Variant {} does not allow multiple values with the same key {}",
+                variant, key
+            ),
+            Error::ErrorMergingKeys {
+                key,
+                existing_variant,
+                incoming_variant,
+            } => write!(
+                f,
+                "Error merging key {} into `Value`: existing value of variant {} cannot be \
+                 merged with variant {}",
+                key, existing_variant, incoming_variant
+            ),
+            Error::UnexpectedVariant {
+                enum_type,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Expected type {} to be of variant {} but got {} instead",
+                enum_type, expected, actual
+            ),
+            Error::IOError(error) => write!(f, "IO Error: {}", error),
+            Error::InvalidUnicodeToParse(error) => {
+                write!(f, "Bytes to be parsed is invalid UTF-8: {}", error)
+            }
+            Error::UnexpectedRemainingInput(remaining) => write!(
+                f,
+                "Expected all input to be consumed, but found input that cannot be parsed: {}",
+                remaining
+            ),
+            Error::Bug(bug) => write!(
+                f,
+                "Possible bug with the library encountered: {}; Please report to \
+                 https://github.com/lawliet89/ferrous-chloride/issues",
+                bug
+            ),
+            Error::AtPosition {
+                line,
+                column,
+                context,
+                error,
+            } => {
+                write!(f, "{}:{}: {}", line, column, error)?;
+                for (index, context) in context.iter().enumerate() {
+                    let preposition = if index == 0 { "while parsing" } else { "in" };
+                    write!(f, ", {} {}", preposition, context.description())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IOError(error) => Some(error),
+            Error::InvalidUnicodeToParse(error) => Some(error),
+            Error::AtPosition { error, .. } => error.source(),
+            _ => None,
+        }
+    }
 }
 
 impl Error {
@@ -95,6 +293,105 @@ impl Error {
         Self::from_err(err, |s| Some(s.as_ref().to_string()))
     }
 
+    /// Convert a Nom Err into something useful, with the line and column in `input` where the
+    /// parser failed
+    ///
+    /// `input` should be the whole input that was given to the parser. The
+    /// [context](Error::context) of the error lists what was being parsed when it occurred.
+    pub fn from_err_str_with_input<I>(err: &nom::Err<I>, input: &str) -> Self
+    where
+        I: nom::AsBytes + AsRef<str> + Debug,
+    {
+        let error = Self::from_err_str(err);
+        let context = match err {
+            nom::Err::Error(context) | nom::Err::Failure(context) => context,
+            nom::Err::Incomplete(_) => return error,
+        };
+        let (remaining, frames) = match context {
+            Context::Code(remaining, _) => (remaining, vec![]),
+            // The innermost error comes first and is closest to the cause
+            Context::List(list) => match list.first() {
+                Some((remaining, _)) => (remaining, ParseContext::from_list(list)),
+                None => return error,
+            },
+        };
+        match error.at_position(input, remaining.as_ref()) {
+            Error::AtPosition {
+                line,
+                column,
+                context,
+                error,
+            } if context.is_empty() => Error::AtPosition {
+                line,
+                column,
+                context: frames,
+                error,
+            },
+            error => error,
+        }
+    }
+
+    /// Attach the line and column in `input` where `remaining` begins to the error
+    ///
+    /// Errors that already have a position are returned unchanged.
+    pub fn at_position(self, input: &str, remaining: &str) -> Self {
+        match self {
+            Error::AtPosition { .. } => self,
+            error => {
+                let (line, column) = crate::utils::error_position(input, remaining);
+                Error::AtPosition {
+                    line,
+                    column,
+                    context: vec![],
+                    error: Box::new(error),
+                }
+            }
+        }
+    }
+
+    /// The one-based line and column of the input where the error occurred, if known
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            Error::AtPosition { line, column, .. } => Some((*line, *column)),
+            _ => None,
+        }
+    }
+
+    /// What was being parsed when the error occurred, innermost first
+    ///
+    /// The context is only known for errors that the parser cannot recover from, like invalid
+    /// escape sequences or unterminated Heredocs, in input parsed with functions like
+    /// [`parse_str`](crate::parse_str).
+    ///
+    /// ```rust
+    /// use ferrous_chloride::{parse_str, ParseContext};
+    ///
+    /// let error = parse_str("a = [1, \"\\uD800\"]\n").unwrap_err();
+    /// assert_eq!(
+    ///     error.context(),
+    ///     &[ParseContext::String, ParseContext::Tuple, ParseContext::Attribute]
+    /// );
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "1:11: Escape sequence \\uD800 is not a valid Unicode code point, while parsing a \
+    ///      string, in a tuple, in an attribute"
+    /// );
+    /// ```
+    pub fn context(&self) -> &[ParseContext] {
+        match self {
+            Error::AtPosition { context, .. } => context,
+            _ => &[],
+        }
+    }
+
+    /// The error without its position
+    pub fn without_position(&self) -> &Self {
+        match self {
+            Error::AtPosition { error, .. } => error,
+            error => error,
+        }
+    }
+
     /// Convert a Nom Err into something useful
     fn from_err<I, F>(err: &nom::Err<I>, convert_fn: F) -> Self
     where
@@ -155,13 +452,11 @@ impl Error {
             Context::Code(input, ErrorKind::Custom(code)) => {
                 Self::from_input_and_code(input, *code, convert_fn)
             }
-            Context::List(list) => {
-                if let Some((input, ErrorKind::Custom(code))) = list.last() {
-                    Self::from_input_and_code(input, *code, convert_fn)
-                } else {
-                    None
-                }
-            }
+            // Errors that wrap a custom error, like `escaped_transform!`, come after it in the list
+            Context::List(list) => list.iter().find_map(|(input, kind)| match kind {
+                ErrorKind::Custom(code) => Self::from_input_and_code(input, *code, &convert_fn),
+                _ => None,
+            }),
             _ => None,
         }
     }
@@ -212,18 +507,83 @@ impl Error {
         F: Fn(&I) -> Option<String>,
         I: nom::AsBytes,
     {
+        // The start of the input where the error occurred, so that the message stays short
+        let snippet = || {
+            convert_fn(input)
+                .map(|s| crate::utils::snippet(&s))
+                .unwrap_or_else(|| "UNKNOWN".to_string())
+        };
         let kind = InternalKind::from_u32(code);
         if let Some(kind) = kind {
             match kind {
-                InternalKind::InvalidUnicodeCodePoint => Some(Error::InvalidUnicodeCodePoint(
-                    convert_fn(input).unwrap_or_else(|| "UNKNOWN".to_string()),
-                )),
+                InternalKind::InvalidUnicodeCodePoint => {
+                    // Only name the escape sequence, like `uD800`
+                    let escape = convert_fn(input)
+                        .map(|mut s| {
+                            let octal_digits =
+                                s.chars().take(3).take_while(|c| c.is_digit(8)).count();
+                            let length = if octal_digits > 0 {
+                                octal_digits
+                            } else if s.starts_with("u{") {
+                                s.find('}').map_or(9, |end| end + 1)
+                            } else if s.starts_with('U') {
+                                9
+                            } else {
+                                5
+                            };
+                            if let Some((end, _)) = s.char_indices().nth(length) {
+                                s.truncate(end);
+                            }
+                            s
+                        })
+                        .unwrap_or_else(|| "UNKNOWN".to_string());
+                    Some(Error::InvalidUnicodeCodePoint(escape))
+                }
                 InternalKind::InvalidUnicode => {
                     Some(Error::InvalidUnicode(input.as_bytes().to_vec()))
                 }
                 InternalKind::InvalidNumber => Some(Error::InvalidNumber(
                     convert_fn(input).unwrap_or_else(|| "UNKNOWN".to_string()),
                 )),
+                InternalKind::UnclosedTemplateDirective => {
+                    // Only name the opening of the directive
+                    let directive = convert_fn(input)
+                        .map(|mut s| {
+                            if let Some(end) = s.find('}') {
+                                s.truncate(end + 1);
+                            }
+                            s
+                        })
+                        .unwrap_or_else(|| "UNKNOWN".to_string());
+                    Some(Error::UnclosedTemplateDirective(directive))
+                }
+                InternalKind::UnterminatedHeredoc => {
+                    // Only name the identifier of the Heredoc, like `EOF` for `<<-EOF`
+                    let identifier = convert_fn(input)
+                        .map(|s| {
+                            s.trim_start_matches("<<")
+                                .trim_start_matches('-')
+                                .split_whitespace()
+                                .next()
+                                .unwrap_or_default()
+                                .to_string()
+                        })
+                        .unwrap_or_else(|| "UNKNOWN".to_string());
+                    Some(Error::UnterminatedHeredoc(identifier))
+                }
+                InternalKind::RecursionLimitExceeded => Some(Error::RecursionLimitExceeded),
+                InternalKind::BareCarriageReturn => Some(Error::BareCarriageReturn),
+                InternalKind::ArrowInObject => Some(Error::ArrowInObject(snippet())),
+                InternalKind::LineContinuation => Some(Error::LineContinuation),
+                InternalKind::DigitSeparator => Some(Error::DigitSeparator(snippet())),
+                InternalKind::InconsistentHeredocIndent => {
+                    Some(Error::InconsistentHeredocIndent(snippet()))
+                }
+                InternalKind::ColonInsteadOfEquals => Some(Error::UnexpectedSeparator {
+                    expected: '=',
+                    found: ':',
+                    input: snippet(),
+                }),
             }
         } else {
             None
@@ -245,7 +605,8 @@ impl From<std::str::Utf8Error> for Error {
 
 // From https://serde.rs/enum-number.html
 macro_rules! enum_number {
-    ($name:ident { $($variant:ident = $value:expr, )* }) => {
+    ($(#[$attr:meta])* $name:ident { $($variant:ident = $value:expr, )* }) => {
+        $(#[$attr])*
         #[derive(Clone, Copy, Debug, Eq, PartialEq)]
         pub enum $name {
             $($variant = $value,)*
@@ -276,8 +637,91 @@ enum_number!(InternalKind {
     InvalidUnicodeCodePoint = 0,
     InvalidUnicode = 1,
     InvalidNumber = 2,
+    UnclosedTemplateDirective = 3,
+    ColonInsteadOfEquals = 4,
+    RecursionLimitExceeded = 5,
+    UnterminatedHeredoc = 6,
+    BareCarriageReturn = 7,
+    ArrowInObject = 8,
+    LineContinuation = 9,
+    DigitSeparator = 10,
+    InconsistentHeredocIndent = 11,
 });
 
+enum_number!(
+    /// What a parser was parsing when it failed
+    ///
+    /// The codes are distinct from the codes of the custom parser errors, so that the contexts can
+    /// be stored in the same list of errors.
+    ParseContext {
+        String = 100,
+        Heredoc = 101,
+        Number = 102,
+        Tuple = 103,
+        Object = 104,
+        FunctionCall = 105,
+        ForExpression = 106,
+        Attribute = 107,
+        Block = 108,
+    }
+);
+
+impl ParseContext {
+    /// A description of what was being parsed, like "a string"
+    pub fn description(self) -> &'static str {
+        match self {
+            ParseContext::String => "a string",
+            ParseContext::Heredoc => "a heredoc",
+            ParseContext::Number => "a number",
+            ParseContext::Tuple => "a tuple",
+            ParseContext::Object => "an object",
+            ParseContext::FunctionCall => "a function call",
+            ParseContext::ForExpression => "a for expression",
+            ParseContext::Attribute => "an attribute",
+            ParseContext::Block => "a block",
+        }
+    }
+
+    // The contexts in a list of errors, without repeating a context nested in itself
+    fn from_list<I>(list: &[(I, ErrorKind)]) -> Vec<Self> {
+        let mut contexts: Vec<Self> = vec![];
+        for (_, kind) in list {
+            if let ErrorKind::Custom(code) = kind {
+                match Self::from_u32(*code) {
+                    Some(context) if contexts.last() != Some(&context) => contexts.push(context),
+                    _ => {}
+                }
+            }
+        }
+        contexts
+    }
+}
+
+/// Adds a frame naming what `input` was being parsed as to the failure of a parser
+///
+/// Only failures are annotated. Errors are discarded when a parser backtracks, so annotating them
+/// would only cost allocations.
+pub(crate) fn in_context<I, O>(
+    input: I,
+    context: ParseContext,
+    result: IResult<I, O, u32>,
+) -> IResult<I, O, u32> {
+    match result {
+        Err(nom::Err::Failure(error)) => {
+            let frame = (input, ErrorKind::Custom(context.into()));
+            let list = match error {
+                Context::Code(remaining, kind) => vec![(remaining, kind), frame],
+                Context::List(mut list) => {
+                    list.push(frame);
+                    list
+                }
+            };
+            Err(nom::Err::Failure(Context::List(list)))
+        }
+        result => result,
+    }
+}
+
 impl From<std::str::Utf8Error> for InternalKind {
     fn from(_: std::str::Utf8Error) -> Self {
         InternalKind::InvalidUnicode
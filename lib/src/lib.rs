@@ -8,7 +8,11 @@ pub mod iter;
 pub mod utils;
 #[macro_use]
 pub mod parser;
+pub mod prelude;
+pub mod schema;
+pub mod span;
 pub mod value;
+pub mod writer;
 
 #[cfg(feature = "serde")]
 pub mod serde;
@@ -17,16 +21,22 @@ pub mod serde;
 #[doc(inline)]
 pub use crate::serde::from_str;
 #[doc(inline)]
-pub use errors::Error;
+pub use errors::{Error, ParseContext};
+#[cfg(feature = "encoding")]
 #[doc(inline)]
-pub use parser::{parse_reader, parse_slice, parse_str};
+pub use parser::parse_slice_with_encoding;
+#[doc(inline)]
+pub use parser::{
+    parse_reader, parse_slice, parse_str, parse_str_with_options, parse_with_spans,
+    parse_with_trivia, try_parse,
+};
 #[doc(inline)]
 pub use value::Value;
 
 pub use nom;
 
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::{BuildHasher, Hash};
 
 /// Has scalar length
@@ -493,6 +503,20 @@ where
     }
 }
 
+impl<K, V, KO, VO> AsOwned for BTreeMap<K, V>
+where
+    K: Ord + AsOwned<Output = KO>,
+    V: AsOwned<Output = VO>,
+    KO: Ord + 'static,
+    VO: 'static,
+{
+    type Output = BTreeMap<KO, VO>;
+
+    fn as_owned(&self) -> Self::Output {
+        self.iter().map(|pair| pair.as_owned()).collect()
+    }
+}
+
 impl AsOwned for String {
     type Output = String;
     fn as_owned(&self) -> Self::Output {
@@ -560,4 +584,5 @@ pub(crate) mod fixtures {
     pub static SIMPLE_BLOCK: &str = include_str!("../fixtures/simple_block.hcl");
     pub static SINGLE: &str = include_str!("../fixtures/single.hcl");
     pub static STRINGS: &str = include_str!("../fixtures/strings.hcl");
+    pub static TERRAFORM: &str = include_str!("../fixtures/terraform.hcl");
 }
@@ -73,3 +73,49 @@ macro_rules! assert_list_eq {
       }
     }};
 }
+
+/// Build a [`Value`](crate::Value) with a syntax resembling HCL
+///
+/// - `null` is [`Value::Null`](crate::Value::Null)
+/// - `[...]` is a list of comma separated values
+/// - `{...}` is an object of comma separated `key = value` pairs. A key is either an identifier
+///   or a string literal.
+/// - Anything else is converted with [`Value::from`](crate::Value). Values that are more than a
+///   single token, like `-1` or `a + b`, must be wrapped in parentheses.
+///
+/// ```rust
+/// use ferrous_chloride::hcl_value;
+///
+/// let value = hcl_value!({
+///     name = "web",
+///     ports = [80, 443],
+///     tags = { "Name" = "web", env = null },
+/// });
+/// assert_eq!(
+///     value.to_string(),
+///     r#"{ name = "web", ports = [80, 443], tags = { "Name" = "web", env = null } }"#
+/// );
+/// ```
+#[macro_export]
+macro_rules! hcl_value {
+    (@key $key:ident) => {
+        $crate::value::__Key::new_identifier(stringify!($key))
+    };
+    (@key $key:literal) => {
+        $crate::value::__Key::new_string($key)
+    };
+    (null) => {
+        $crate::Value::Null
+    };
+    ([ $($element:tt),* $(,)? ]) => {
+        $crate::Value::List(vec![ $( $crate::hcl_value!($element) ),* ])
+    };
+    ({ $($key:tt = $value:tt),* $(,)? }) => {
+        $crate::Value::new_single_map(vec![
+            $( ($crate::hcl_value!(@key $key), $crate::hcl_value!($value)) ),*
+        ])
+    };
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
@@ -6,13 +6,23 @@ pub mod attribute;
 pub mod block;
 pub mod body;
 pub mod boolean;
+pub mod comment;
+pub mod conditional;
 pub mod expression;
+pub mod for_expr;
+pub mod function_call;
 pub mod identifier;
+pub mod lexer;
 pub mod null;
 pub mod number;
 pub mod object;
+pub mod operation;
+pub mod options;
+pub mod printer;
 pub mod string;
+pub mod traversal;
 pub mod tuple;
+pub mod wrapped;
 
 #[doc(inline)]
 pub use attribute::Attribute;
@@ -22,6 +32,8 @@ pub use block::{Block, Blocks};
 pub use body::Body;
 #[doc(inline)]
 pub use expression::Expression;
+#[doc(inline)]
+pub use options::ParseOptions;
 
 use std::borrow::Cow;
 
@@ -120,7 +132,7 @@ named!(
             )
             | do_parse!(
                 identifier: call!(identifier::identifier)
-                >> keys: many0!(string::string_literal)
+                >> keys: many0!(string::single_line_string)
                 >> values: call!(map_expression)
                 >> (Key::Identifier(identifier), Value::Block(vec![(keys, values)].into_iter().collect()))
             )
@@ -174,26 +186,186 @@ See the [HCL specification](https://github.com/hashicorp/hcl2/blob/master/hcl/hc
 for more information on the file format.
 "#],
     pub config_file(CompleteStr) -> ConfigFile,
-    exact!(call!(self::body::body))
+    call!(config_file_with_options, &ParseOptions::default())
 );
 
+/// Parse a HCL Configuration file with the provided [`ParseOptions`]
+///
+/// See [`config_file`].
+pub fn config_file_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> nom::IResult<CompleteStr<'a>, ConfigFile<'a>, u32> {
+    exact!(input, call!(self::body::body_with_options, options))
+}
+
+// Remove a UTF-8 byte order mark at the start of the input, which editors on Windows often write.
+// The input is sliced, so spans computed relative to the original input remain correct.
+pub(crate) fn strip_bom(input: &str) -> &str {
+    input.strip_prefix('\u{feff}').unwrap_or(input)
+}
+
 /// Parse a HCL string into a [`ConfigFile`] which is close to an abstract syntax tree of the
 /// HCL string.
+///
+/// A byte order mark at the start of the input is ignored.
 pub fn parse_str(input: &str) -> Result<ConfigFile, Error> {
-    let (remaining_input, body) =
-        config_file(CompleteStr(input)).map_err(|e| Error::from_err_str(&e))?;
-
-    if !remaining_input.is_empty() {
-        return Err(Error::Bug(format!(
-            r#"Input was not completely parsed:
-Input: {},
-Remaining: {}
-"#,
-            input, remaining_input
-        )));
+    parse_str_with_options(input, &ParseOptions::default())
+}
+
+/// Parse a HCL string into a [`ConfigFile`], catching a panic caused by a bug in the parser
+///
+/// This is the same as [`parse_str`], which is not expected to panic on any input. As a last
+/// resort for applications parsing untrusted input, a panic is caught and returned as
+/// [`Error::Bug`] instead of unwinding into the caller. This cannot guarantee that parsing never
+/// panics: panics cannot be caught when the application is built with `panic = "abort"`, and a
+/// stack overflow aborts the process regardless.
+///
+/// ```rust
+/// use ferrous_chloride::parser::try_parse;
+///
+/// assert_eq!(try_parse("a = 1\n").unwrap().len(), 1);
+/// assert!(try_parse("a = \n").is_err());
+/// ```
+pub fn try_parse(input: &str) -> Result<ConfigFile<'_>, Error> {
+    std::panic::catch_unwind(|| parse_str(input)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(Error::Bug(format!("Parsing panicked: {}", message)))
+    })
+}
+
+/// Parse a HCL string into a [`ConfigFile`] with the provided [`ParseOptions`]
+///
+/// Whitespace and comments may follow the body, but anything else that cannot be parsed is
+/// returned as [`Error::UnexpectedRemainingInput`] with the line and column of the first token that
+/// cannot be parsed. The error holds the start of the token, up to the end of its line.
+///
+/// ```rust
+/// use ferrous_chloride::parser::{parse_str_with_options, ParseOptions};
+/// use ferrous_chloride::Error;
+///
+/// let options = ParseOptions::default().lenient(true);
+/// assert!(parse_str_with_options("@schema = \"x\"\n", &options).is_ok());
+///
+/// let error = parse_str_with_options("a = 1\nb = 2 garbage\n", &options).unwrap_err();
+/// assert_eq!(error.position(), Some((2, 7)));
+/// match error.without_position() {
+///     Error::UnexpectedRemainingInput(remaining) => assert_eq!(remaining, "garbage"),
+///     other => panic!("Unexpected error {:?}", other),
+/// }
+/// ```
+pub fn parse_str_with_options<'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> Result<ConfigFile<'a>, Error> {
+    Body::parse_with_options(input, options)
+}
+
+/// Parse a HCL string into a [`ConfigFile`], recording the spans of attributes and blocks
+///
+/// The [`span`](Attribute::span) of every attribute and [block](Block::span), including the ones
+/// nested in blocks, is set to the range of bytes of `input` it was parsed from. Parsing with
+/// [`parse_str`] leaves the spans empty, which avoids the small overhead of computing them.
+///
+/// Only attributes and blocks have spans. Expressions, including the values of attributes and
+/// the elements of collections, do not record where they were parsed from. Text borrowed from
+/// `input`, like the type of a block, can still be located with
+/// [`Span::from_fragment`](crate::span::Span::from_fragment).
+///
+/// ```rust
+/// use ferrous_chloride::parser::parse_with_spans;
+///
+/// let input = "a = 1\nb = [2, 3] # Comment\n";
+/// let body = parse_with_spans(input).unwrap();
+/// let span = body[1].clone().unwrap_attribute().span.unwrap();
+/// assert_eq!(&input[span.start..span.end], "b = [2, 3]");
+/// ```
+pub fn parse_with_spans(input: &str) -> Result<ConfigFile, Error> {
+    parse_str_with_options(input, &ParseOptions::default().spans_relative_to(input))
+}
+
+/// Parse a HCL string into a [`ConfigFile`], attaching comments to the attributes and blocks they
+/// belong to
+///
+/// This is the same as parsing with [comments preserved](ParseOptions::preserve_comments), so that
+/// formatters like the [`printer`] can write them back.
+///
+/// ```rust
+/// use ferrous_chloride::parser::parse_with_trivia;
+///
+/// let body = parse_with_trivia("# The name\nname = \"app\" # Trailing\n").unwrap();
+/// let attribute = body[0].clone().unwrap_attribute();
+/// assert_eq!(attribute.leading_comments, vec!["# The name"]);
+/// assert_eq!(attribute.trailing_comment.unwrap(), "# Trailing");
+/// ```
+pub fn parse_with_trivia(input: &str) -> Result<ConfigFile, Error> {
+    parse_str_with_options(input, &ParseOptions::default().preserve_comments(true))
+}
+
+/// The result of parsing with [`parse_recovering`]
+#[derive(Debug)]
+pub struct ParseResult<'a> {
+    /// The elements of the body that were parsed successfully
+    pub body: ConfigFile<'a>,
+    /// The errors encountered, in source order
+    pub errors: Vec<Error>,
+}
+
+impl<'a> ParseResult<'a> {
+    /// Returns whether the whole input was parsed without errors
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
     }
+}
+
+/// Parse a HCL string into a [`ConfigFile`], recovering from errors in the elements of the body
+///
+/// When an attribute or block cannot be parsed, the error is recorded and parsing continues on the
+/// next line. Recovery only happens at the top level of the body, so an error inside a block
+/// will cause the rest of the lines of the block to be reported as errors too.
+///
+/// ```rust
+/// use ferrous_chloride::parser::parse_recovering;
+///
+/// let parsed = parse_recovering("a = 1\nb = \nc = 3\n");
+/// assert_eq!(parsed.body.len(), 2);
+/// assert_eq!(parsed.errors.len(), 1);
+/// assert_eq!(parsed.errors[0].position(), Some((2, 1)));
+/// ```
+pub fn parse_recovering(input: &str) -> ParseResult {
+    let input = strip_bom(input);
+    let options = ParseOptions::default();
+    let mut body = ConfigFile::new();
+    let mut errors = vec![];
+
+    let mut remaining = CompleteStr(input);
+    loop {
+        // `whitespace` always succeeds
+        if let Ok((rest, _)) = whitespace::whitespace(remaining) {
+            remaining = rest;
+        }
+        if remaining.is_empty() {
+            break;
+        }
 
-    Ok(body)
+        match body::commented_body_element(remaining, &options) {
+            Ok((rest, element)) => {
+                body.push(element);
+                remaining = rest;
+            }
+            Err(e) => {
+                errors.push(Error::from_err_str_with_input(&e, input));
+                let next_line = remaining.find('\n').map_or(remaining.len(), |i| i + 1);
+                remaining = CompleteStr(&remaining[next_line..]);
+            }
+        }
+    }
+
+    ParseResult { body, errors }
 }
 
 /// Parse a HCL string from a IO stream reader
@@ -212,11 +384,38 @@ pub fn parse_reader<R: std::io::Read>(mut reader: R) -> Result<ConfigFile<'stati
 }
 
 /// Parse a HCL string from a slice of bytes
+///
+/// The bytes must be valid UTF-8 or an error is returned. Use
+/// [`parse_slice_with_encoding`] to parse input in other encodings.
 pub fn parse_slice(bytes: &[u8]) -> Result<ConfigFile, Error> {
     let input = std::str::from_utf8(bytes)?;
     parse_str(input)
 }
 
+/// Parse a HCL string from a slice of bytes in the specified encoding
+///
+/// The bytes are transcoded to UTF-8 before parsing. Malformed byte sequences for the encoding
+/// are reported as an error instead of being replaced. A byte order mark is not used to detect
+/// the encoding, but is ignored once decoded like for [`parse_str`].
+///
+/// Note that per the [Encoding Standard](https://encoding.spec.whatwg.org/), Latin-1
+/// (ISO-8859-1) is decoded with [`WINDOWS_1252`](encoding_rs::WINDOWS_1252).
+///
+/// This requires the `encoding` feature, which depends on
+/// [`encoding_rs`](https://docs.rs/encoding_rs).
+#[cfg(feature = "encoding")]
+pub fn parse_slice_with_encoding(
+    bytes: &[u8],
+    encoding: &'static encoding_rs::Encoding,
+) -> Result<ConfigFile<'static>, Error> {
+    let input = encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .ok_or_else(|| Error::InvalidUnicode(bytes.to_vec()))?;
+
+    let parsed = parse_str(&input)?;
+    Ok(parsed.as_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,6 +434,99 @@ mod tests {
         }
     }
 
+    #[test]
+    fn random_input_does_not_panic() {
+        use crate::parser::expression::Expression;
+
+        // Fragments of HCL syntax, which are more likely to reach deep into the parsers than
+        // random bytes. The inputs start with a few common beginnings of bodies.
+        const PREFIXES: &[&str] = &["", "a = ", "b \"l\" {\n x = ", "a = <<EOF\n", "a = \""];
+        const FRAGMENTS: &[&str] = &[
+            "a", "b_1", "-", "=", ":", " ", "\t", "\n", "\r\n", "\"", "\\", "\\u", "\\U", "\\x",
+            "\\0", "\\377", "\\u{", "${", "%{", "~}", "}", "{", "[", "]", "(", ")", ",", "...",
+            ".", "*", "[*]", "<<", "<<-", "EOF", "#", "//", "/*", "*/", "1", "0x", "1e", "1.5",
+            "true", "null", "for", "in", "if", "endif", "?", "!", "&&", "==", "é", "老虎",
+            "\u{feff}", "$", "%", "@", "::", "=>", "\u{0}", "9e999",
+        ];
+
+        let lenient = ParseOptions::default()
+            .lenient(true)
+            .preserve_comments(true)
+            .braced_unicode_escapes(true)
+            .multiline_strings(true)
+            .keep_heredoc_trailing_newline(true);
+
+        // A fixed xorshift generator, so that failures are reproducible
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        for _ in 0..5000 {
+            let length = random(32);
+            let mut input = PREFIXES[random(PREFIXES.len())].to_string();
+            for _ in 0..length {
+                input.push_str(FRAGMENTS[random(FRAGMENTS.len())]);
+            }
+
+            let parsed = parse_str(&input);
+            assert_eq!(try_parse(&input).is_ok(), parsed.is_ok(), "{:?}", input);
+            let _ = parse_str_with_options(&input, &lenient);
+            let _ = parse_with_spans(&input);
+            let _ = parse_recovering(&input);
+            let _ = Expression::parse(&input);
+            if let Ok(body) = parsed {
+                let _ = body.to_value();
+                let _ = printer::to_hcl_string(&body);
+            }
+        }
+    }
+
+    #[test]
+    fn leading_byte_order_marks_are_ignored() {
+        let input = "\u{feff}name = \"app\"\ncount = 1\n";
+        let expected = parse_str(&input[3..]).unwrap();
+        assert_eq!(expected[0].clone().unwrap_attribute().name, "name");
+
+        assert_eq!(parse_str(input).unwrap(), expected);
+        assert_eq!(parse_slice(input.as_bytes()).unwrap(), expected);
+        assert_eq!(parse_reader(input.as_bytes()).unwrap(), expected);
+        assert_eq!(parse_recovering(input).body, expected);
+        assert!(parse_recovering(input).is_ok());
+
+        // Spans are still relative to the whole input
+        let body = parse_with_spans(input).unwrap();
+        let span = body[0].clone().unwrap_attribute().span.unwrap();
+        assert_eq!(&input[span.start..span.end], "name = \"app\"");
+
+        // Only a byte order mark at the very start is ignored
+        let error = parse_str("name = 1\n\u{feff}count = 1\n").unwrap_err();
+        assert_eq!(error.position(), Some((2, 1)));
+        assert!(parse_str("\u{feff}\u{feff}a = 1\n").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn latin1_slices_are_transcoded() {
+        use crate::parser::attribute::Attribute;
+        use crate::parser::body::BodyElement;
+
+        // `name = "café crème"` in Latin-1
+        let bytes = b"name = \"caf\xe9 cr\xe8me\"\n";
+        assert!(parse_slice(bytes).is_err());
+
+        let parsed = parse_slice_with_encoding(bytes, encoding_rs::WINDOWS_1252).unwrap();
+        assert_eq!(
+            parsed,
+            vec![BodyElement::Attribute(Attribute::new("name", "café crème"))]
+        );
+
+        assert!(parse_slice_with_encoding(b"\xff", encoding_rs::UTF_8).is_err());
+    }
+
     #[test]
     fn list_values_are_parsed_successfully() {
         let test_cases = [
@@ -807,4 +1099,432 @@ foo = "bar"
         .unwrap();
         assert_eq!(&expected_resources, resource);
     }
+
+    #[test]
+    fn documents_without_items_are_parsed_as_empty_bodies() {
+        let test_cases = [
+            "",
+            "   \n\r\n \n",
+            r#"# Hash comment
+// Slash comment
+
+/* Inline
+   comment */
+   /* Indented inline comment */ // Trailing comment
+# Comment without a trailing newline"#,
+        ];
+
+        for input in test_cases.iter() {
+            let parsed = parse_str(input).unwrap();
+            assert!(parsed.is_empty(), "Input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn terraform_configurations_are_parsed_in_source_order() {
+        use crate::parser::body::BodyElement;
+
+        let parsed = parse_str(fixtures::TERRAFORM).unwrap();
+        let elements: Vec<_> = parsed
+            .iter()
+            .map(|element| match element {
+                BodyElement::Attribute(attribute) => (attribute.name.to_string(), vec![]),
+                BodyElement::Block(block) => (
+                    block.r#type.to_string(),
+                    block.labels.iter().map(|label| label.as_str()).collect(),
+                ),
+            })
+            .collect();
+        assert_eq!(
+            elements,
+            vec![
+                ("terraform".to_string(), vec![]),
+                ("provider".to_string(), vec!["aws"]),
+                ("variable".to_string(), vec!["region"]),
+                ("variable".to_string(), vec!["instance_count"]),
+                ("locals".to_string(), vec![]),
+                ("resource".to_string(), vec!["aws_instance", "web"]),
+                ("output".to_string(), vec!["instance_ids"]),
+                ("output_format".to_string(), vec![]),
+            ]
+        );
+
+        let resource = parsed
+            .iter()
+            .find_map(|element| match element {
+                BodyElement::Block(block) if block.r#type == "resource" => Some(block),
+                _ => None,
+            })
+            .unwrap();
+        let attribute = |name: &str| {
+            resource.body.iter().find_map(|element| match element {
+                BodyElement::Attribute(attribute) if attribute.name == name => {
+                    Some(&attribute.expression)
+                }
+                _ => None,
+            })
+        };
+        assert_eq!(
+            attribute("instance_type"),
+            Some(&Expression::new_conditional(
+                Expression::parse("var.large").unwrap(),
+                Expression::from("t2.small"),
+                Expression::from("t2.micro"),
+            ))
+        );
+        assert_eq!(
+            attribute("description"),
+            Some(&Expression::from(r#"${join(", ", [var.region, "web"])}"#))
+        );
+        assert_eq!(
+            attribute("user_data"),
+            Some(&Expression::from(
+                "#!/bin/bash\necho \"Hello, World\" > index.html\nnohup busybox httpd -f -p 8080 &"
+            ))
+        );
+        assert_eq!(
+            resource
+                .body
+                .iter()
+                .filter(|element| element.is_block())
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn interpolations_may_contain_quoted_strings() {
+        use crate::parser::body::BodyElement;
+
+        let test_cases = [
+            (
+                r#"a = "${join(",", var.list)}""#,
+                r#"${join(",", var.list)}"#,
+            ),
+            (
+                r#"a = "${var.a == "" ? "x" : "y"}""#,
+                r#"${var.a == "" ? "x" : "y"}"#,
+            ),
+            (
+                r#"a = "\"${upper("\"")}\" %{ if "}" != "" }x%{ endif }""#,
+                r#""${upper("\"")}" %{ if "}" != "" }x%{ endif }"#,
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let input = format!("{}\n", input);
+            let parsed = parse_str(&input).unwrap();
+            assert_eq!(
+                parsed,
+                vec![BodyElement::Attribute(Attribute::new("a", *expected))]
+            );
+        }
+
+        assert_eq!(
+            Expression::parse(r#""${join(",", var.b)}""#).unwrap(),
+            Expression::from(r#"${join(",", var.b)}"#)
+        );
+    }
+
+    #[test]
+    fn unclosed_template_directives_in_strings_are_errors() {
+        let test_cases = [
+            ("a = \"%{ if a }x\"\n", "%{ if a }", (1, 6)),
+            (
+                "a = \"%{ for x in y }${x}%{ endif }\"\n",
+                "%{ for x in y }",
+                (1, 6),
+            ),
+            ("a = <<EOF\nb\n%{ if a }\nEOF\n", "%{ if a }", (3, 1)),
+            (
+                "b {\n  a = [\"%{ if a }%{ for x in y }%{ endif }\"]\n}\n",
+                "%{ for x in y }",
+                (2, 18),
+            ),
+        ];
+
+        for (input, expected, position) in test_cases.iter() {
+            println!("Testing {}", input);
+            let error = parse_str(input).unwrap_err();
+            assert_eq!(error.position(), Some(*position));
+            match error.without_position() {
+                Error::UnclosedTemplateDirective(directive) => assert_eq!(directive, expected),
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+
+        assert!(parse_str("a = \"%{ if a }x%{ endif }\"\n").is_ok());
+        assert!(parse_str("a = \"${ unclosed\"\n").is_err());
+        assert!(parse_str("a = \"%{ endif }\"\n").is_err());
+    }
+
+    #[test]
+    fn options_are_applied_when_parsing_strings() {
+        let hcl = r#"@schema = "x"
+value = +007.50
+block {
+  script = <<-EOF
+		echo "hello"
+		    echo "world"
+		EOF
+}
+"#;
+        assert!(parse_str(hcl).is_err());
+
+        let options = ParseOptions::default()
+            .lenient(true)
+            .normalize_numbers(true)
+            .heredoc_tab_width(4);
+        let body = parse_str_with_options(hcl, &options).unwrap();
+
+        assert_eq!(body[0].clone().unwrap_attribute().name, "@schema");
+        match body[1].clone().unwrap_attribute().expression {
+            Expression::Number(number) => assert_eq!(&*number, "7.5"),
+            other => panic!("Unexpected expression {:?}", other),
+        }
+
+        let block = body[2].clone().unwrap_block();
+        let script = block.body[0].clone().unwrap_attribute();
+        assert_eq!(
+            script.expression,
+            Expression::from("echo \"hello\"\n    echo \"world\"")
+        );
+    }
+
+    #[test]
+    fn spans_are_recorded_when_requested() {
+        let hcl = r#"name = "outer"
+resource "aws" "main" {
+  tags { count = 1 }
+
+  nested {
+    value = [1, 2]   # Comment
+  }
+}
+"#;
+        let span_text = |span: Option<crate::span::Span>| {
+            let span = span.expect("span to be recorded");
+            &hcl[span.start..span.end]
+        };
+
+        let body = parse_with_spans(hcl).unwrap();
+        assert_eq!(
+            span_text(body[0].clone().unwrap_attribute().span),
+            r#"name = "outer""#
+        );
+
+        let resource = body[1].clone().unwrap_block();
+        assert!(span_text(resource.span).starts_with("resource \"aws\""));
+        assert!(span_text(resource.span).ends_with("  }\n}"));
+
+        let tags = resource.body[0].clone().unwrap_block();
+        assert_eq!(span_text(tags.span), "tags { count = 1 }");
+        assert_eq!(
+            span_text(tags.body[0].clone().unwrap_attribute().span),
+            "count = 1"
+        );
+
+        let nested = resource.body[1].clone().unwrap_block();
+        let value = nested.body[0].clone().unwrap_attribute();
+        assert_eq!(span_text(value.span), "value = [1, 2]");
+
+        // Spans are only recorded on request, and do not affect equality
+        let unspanned = parse_str(hcl).unwrap();
+        assert_eq!(unspanned[0].clone().unwrap_attribute().span, None);
+        assert_eq!(unspanned, body);
+    }
+
+    #[test]
+    fn owned_parse_results_outlive_the_input() {
+        let (body, value, blocks, template) = {
+            let input = r#"
+name = "document"
+resource "type" "web" {
+    tags = ["a", "b"]
+}
+resource "type" "db" {
+}
+"#
+            .to_string();
+            let body = parse_str(&input).unwrap();
+            let value = body.to_value().unwrap();
+            let blocks = Blocks::new(body.blocks().cloned());
+            let template_input = "\"Hello ${name}\"".to_string();
+            let (_, template) = string::template(CompleteStr(&template_input)).unwrap();
+
+            let owned: (ConfigFile<'static>, Value<'static>, Blocks<'static>, _) = (
+                body.as_owned(),
+                value.as_owned(),
+                blocks.as_owned(),
+                template.as_owned(),
+            );
+            owned
+        };
+
+        assert_eq!(
+            body.get_attribute("name"),
+            Some(&Expression::String(Cow::Borrowed("document")))
+        );
+        assert_eq!(body.blocks_of_type("resource").count(), 2);
+        assert!(value.is_map());
+        assert_eq!(value.count_matching(Value::is_string), 3);
+        assert_eq!(blocks.len_blocks(), 2);
+        assert!(blocks.get("resource", &["type", "web"]).is_some());
+        assert_eq!(template.interpolations(), ["name"]);
+    }
+
+    #[test]
+    fn hcl_is_parsed_from_readers() {
+        let hcl = "name = \"x\"\nblock \"label\" {\n    count = 1\n}\n";
+        let body = parse_reader(std::io::Cursor::new(hcl.as_bytes().to_vec())).unwrap();
+        assert_eq!(body, parse_str(hcl).unwrap());
+
+        let error = parse_reader(std::io::Cursor::new(b"a = 1\nb = @\n")).unwrap_err();
+        assert_eq!(error.position(), Some((2, 5)));
+
+        // Invalid UTF-8 is reported by the reader
+        let error = parse_reader(std::io::Cursor::new(b"a = \"\xff\"\n")).unwrap_err();
+        match error {
+            Error::IOError(error) => assert_eq!(error.kind(), std::io::ErrorKind::InvalidData),
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_errors_have_line_and_column() {
+        let test_cases = [
+            (
+                r#"name = "x"
+block "label" {
+    count : 1
+}
+"#,
+                (3, 11),
+            ),
+            (
+                r#"a = 1
+b = "caf\uD800"
+"#,
+                (2, 10),
+            ),
+        ];
+
+        for (input, (line, column)) in test_cases.iter() {
+            println!("Testing {}", input);
+            let error = parse_str(input).unwrap_err();
+            println!("{:#}", error);
+            assert_eq!(error.position(), Some((*line, *column)));
+            assert!(format!("{:#}", error).starts_with(&format!("{}:{}: ", line, column)));
+        }
+    }
+
+    #[test]
+    fn parse_errors_list_what_was_being_parsed() {
+        use crate::errors::ParseContext::*;
+
+        let test_cases = [
+            (
+                r#"resource "x" {
+  tags = { names = [for n in names : upper("\uD800")] }
+}
+"#,
+                vec![
+                    String,
+                    FunctionCall,
+                    ForExpression,
+                    Object,
+                    Attribute,
+                    Block,
+                ],
+            ),
+            // Nested tuples are listed once
+            (
+                "a = [[[\"\\U00110000\"]]]\n",
+                vec![String, Tuple, Attribute],
+            ),
+            ("a = <<EOF\nno end\n", vec![Heredoc, Attribute]),
+            // Errors that are backtracked from have no context
+            ("a = [1 2]\n", vec![]),
+        ];
+
+        for (input, context) in test_cases.iter() {
+            println!("Testing {}", input);
+            let error = parse_str(input).unwrap_err();
+            println!("{}", error);
+            assert_eq!(error.context(), &context[..]);
+        }
+
+        let error = parse_str(test_cases[0].0).unwrap_err();
+        assert!(error.to_string().ends_with(
+            "while parsing a string, in a function call, in a for expression, in an object, in an \
+             attribute, in a block"
+        ));
+    }
+
+    #[test]
+    fn trailing_input_after_a_body_is_an_error() {
+        let input = "a = 1\nb = \"x\"\n} junk\n";
+        let error = parse_str(input).unwrap_err();
+        assert_eq!(error.position(), Some((3, 1)));
+        match error.without_position() {
+            Error::UnexpectedRemainingInput(remaining) => assert_eq!(remaining, "} junk"),
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn trailing_whitespace_and_comments_are_accepted() {
+        let input = "a = 1\n\n  # comment\n// another\n/* block\n comment */\n\n";
+        let body = parse_str(input).unwrap();
+        assert_eq!(body.len(), 1);
+    }
+
+    #[test]
+    fn errors_are_recovered_from_at_the_next_line() {
+        let hcl = r#"first = 1
+broken = = 2
+second = "two"
+block "label" {
+    nested = true
+}
+also broken
+third = [3]
+"#;
+        let parsed = parse_recovering(hcl);
+        assert!(!parsed.is_ok());
+
+        let positions: Vec<_> = parsed.errors.iter().map(Error::position).collect();
+        assert_eq!(positions, [Some((2, 1)), Some((7, 1))]);
+
+        let expected = parse_str(
+            r#"first = 1
+second = "two"
+block "label" {
+    nested = true
+}
+third = [3]
+"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.body, expected);
+    }
+
+    #[test]
+    fn valid_input_is_parsed_without_errors_when_recovering() {
+        for string in fixtures::ALL {
+            let parsed = parse_recovering(string);
+            assert!(parsed.is_ok(), "{:#?}", parsed.errors);
+            assert_eq!(parsed.body, parse_str(string).unwrap());
+        }
+
+        let parsed = parse_recovering(
+            "a = 1 /* one */ # two
+b {
+} /* three */
+",
+        );
+        assert!(parsed.is_ok(), "{:#?}", parsed.errors);
+        assert_eq!(parsed.body.len(), 2);
+    }
 }
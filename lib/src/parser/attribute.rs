@@ -1,35 +1,189 @@
+use std::borrow::Cow;
+
 use nom::types::CompleteStr;
-use nom::{call, char, named};
+use nom::{
+    call, char, cond_reduce, do_parse, error_position, named, opt, pair, recognize, sep, verify,
+    ErrorKind, IResult,
+};
 
-use crate::parser::expression::{expression, Expression};
-use crate::parser::identifier::{identifier, Identifier};
+use crate::errors::{in_context, InternalKind, ParseContext};
+use crate::parser::expression::{expression_with_options, Expression};
+use crate::parser::identifier::{identifier_with_options, Identifier};
+use crate::parser::options::ParseOptions;
+use crate::parser::whitespace::inline_whitespace;
+use crate::span::Span;
 
 /// A HCL Attribute
 ///
 /// ```ebnf
 /// Attribute = Identifier "=" Expression Newline;
 /// ```
-pub type Attribute<'a> = (Identifier<'a>, Expression<'a>);
+///
+/// In lenient mode, the name may be followed by a `?` to mark the attribute as optional, like
+/// `name? = "x"`.
+///
+/// The span and the comments are not compared when comparing attributes.
+#[derive(Clone, Debug)]
+pub struct Attribute<'a> {
+    pub name: Identifier<'a>,
+    pub expression: Expression<'a>,
+    /// Whether the attribute was marked as optional
+    pub optional: bool,
+    /// The span of the attribute, from its name to the end of its expression, if it was parsed
+    /// with [`parse_with_spans`](crate::parser::parse_with_spans)
+    pub span: Option<Span>,
+    /// The comments on the lines before the attribute, including their delimiters, if comments
+    /// are [preserved](ParseOptions::preserve_comments)
+    pub leading_comments: Vec<Cow<'a, str>>,
+    /// The line comment after the attribute on the same line, including its delimiter, if
+    /// comments are [preserved](ParseOptions::preserve_comments)
+    pub trailing_comment: Option<Cow<'a, str>>,
+}
 
-named!(
-    pub attribute(CompleteStr) -> Attribute,
-    inline_whitespace!(
+impl<'a> Attribute<'a> {
+    pub fn new<I, E>(name: I, expression: E) -> Self
+    where
+        I: Into<Identifier<'a>>,
+        E: Into<Expression<'a>>,
+    {
+        Self {
+            name: name.into(),
+            expression: expression.into(),
+            optional: false,
+            span: None,
+            leading_comments: vec![],
+            trailing_comment: None,
+        }
+    }
+
+    /// Mark the attribute as optional
+    pub fn optional(mut self, optional: bool) -> Self {
+        self.optional = optional;
+        self
+    }
+}
+
+impl<'a> PartialEq for Attribute<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.expression == other.expression
+            && self.optional == other.optional
+    }
+}
+
+impl<'a> Eq for Attribute<'a> {}
+
+impl<'a, I, E> From<(I, E)> for Attribute<'a>
+where
+    I: Into<Identifier<'a>>,
+    E: Into<Expression<'a>>,
+{
+    fn from((name, expression): (I, E)) -> Self {
+        Self::new(name, expression)
+    }
+}
+
+/// Parses the name of an attribute
+///
+/// In lenient mode, the name may begin with one of the
+/// [identifier sigils](ParseOptions::identifier_sigils), which is kept as part of the name.
+pub fn attribute_name_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Identifier<'a>, u32> {
+    do_parse!(
+        input,
+        name: recognize!(
+            pair!(
+                opt!(verify!(call!(nom::anychar), |c| options.is_identifier_sigil(c))),
+                call!(identifier_with_options, options)
+            )
+        )
+        >> (Cow::Borrowed(name.0))
+    )
+}
+
+// Parses the `=` between the name and the expression of an attribute
+//
+// A `:` is only valid in object constructors, so finding one here is a fatal error that points at
+// the colon instead of a generic failure of the whole body.
+fn equals(input: CompleteStr) -> IResult<CompleteStr, char, u32> {
+    if input.starts_with(':') {
+        Err(nom::Err::Failure(error_position!(
+            input,
+            ErrorKind::Custom(InternalKind::ColonInsteadOfEquals as u32)
+        )))
+    } else {
+        char!(input, '=')
+    }
+}
+
+pub fn attribute_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Attribute<'a>, u32> {
+    let (start, _) = inline_whitespace(input)?;
+    let result = sep!(
+        start,
+        inline_whitespace,
         do_parse!(
-            identifier: call!(identifier)
-            >> char!('=')
-            >> expression: call!(expression)
-            >> (identifier, expression)
+            identifier: call!(attribute_name_with_options, options)
+            >> optional: opt!(cond_reduce!(options.lenient, char!('?')))
+            >> call!(equals)
+            >> expression: call!(expression_with_options, options)
+            >> (Attribute::new(identifier, expression).optional(optional.is_some()))
+        )
+    );
+    let (end, attribute) = in_context(start, ParseContext::Attribute, result)?;
+    // The comments after the attribute are left for the body to attach to it
+    let (remaining, _) = nom::space0(end)?;
+
+    let span = options.span_between(start, end);
+    Ok((remaining, Attribute { span, ..attribute }))
+}
+
+// Parses an attribute up to its expression, to find where an invalid attribute went wrong
+pub(crate) fn attribute_head<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, (), u32> {
+    let (start, _) = inline_whitespace(input)?;
+    sep!(
+        start,
+        inline_whitespace,
+        do_parse!(
+            call!(attribute_name_with_options, options)
+                >> opt!(cond_reduce!(options.lenient, char!('?')))
+                >> call!(equals)
+                >> ()
         )
     )
+}
+
+named!(
+    pub attribute(CompleteStr) -> Attribute,
+    call!(attribute_with_options, &ParseOptions::default())
 );
 
 impl<'a> crate::AsOwned for Attribute<'a> {
     type Output = Attribute<'static>;
 
     fn as_owned(&self) -> Self::Output {
-        use std::borrow::Cow;
-
-        (Cow::Owned(self.0.as_owned()), self.1.as_owned())
+        Attribute {
+            name: Cow::Owned(self.name.as_owned()),
+            expression: self.expression.as_owned(),
+            optional: self.optional,
+            span: self.span,
+            leading_comments: self
+                .leading_comments
+                .iter()
+                .map(|comment| Cow::Owned(comment.to_string()))
+                .collect(),
+            trailing_comment: self
+                .trailing_comment
+                .as_ref()
+                .map(|comment| Cow::Owned(comment.to_string())),
+        }
     }
 }
 
@@ -37,6 +191,8 @@ impl<'a> crate::AsOwned for Attribute<'a> {
 mod tests {
     use super::*;
 
+    use crate::utils::ResultUtilsString;
+
     #[test]
     fn attribute_pairs_are_parsed_successfully() {
         let test_cases = [
@@ -70,7 +226,11 @@ EOF
                 ("test", Expression::from("new\nline")),
                 "\n",
             ),
-            (r#"test = [],"#, ("test", Expression::Tuple(vec![])), ","),
+            (
+                r#"test = [],"#,
+                ("test", Expression::new_tuple(vec![])),
+                ",",
+            ),
             (
                 r#"test = [1,]"#,
                 ("test", Expression::new_tuple(vec![From::from(1)])),
@@ -158,11 +318,181 @@ EOF
 
         for (input, (expected_key, expected_value), expected_remaining) in test_cases.iter() {
             println!("Testing {}", input);
-            let (remaining, (actual_identifier, actual_expression)) =
-                attribute(CompleteStr(input)).unwrap();
+            let (remaining, actual) = attribute(CompleteStr(input)).unwrap();
             assert_eq!(&remaining.0, expected_remaining);
-            assert_eq!(actual_identifier, *expected_key);
-            assert_eq!(actual_expression, *expected_value);
+            assert_eq!(actual.name, *expected_key);
+            assert_eq!(actual.expression, *expected_value);
+            assert!(!actual.optional);
         }
     }
+
+    #[test]
+    fn attributes_allow_comments_and_no_spaces_around_equals() {
+        let test_cases = [
+            ("foo=1", Attribute::new("foo", 1), ""),
+            ("foo /* one */ = /* two */ 1", Attribute::new("foo", 1), ""),
+            (
+                "foo\t=\t1 # Comment\n",
+                Attribute::new("foo", 1),
+                "# Comment\n",
+            ),
+            (
+                "foo = <<EOF\nbar = 1\nEOF\n",
+                Attribute::new("foo", "bar = 1"),
+                "\n",
+            ),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) = attribute(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, *expected_remaining);
+            assert_eq!(actual, *expected);
+        }
+    }
+
+    #[test]
+    fn attribute_values_may_be_references() {
+        use crate::parser::traversal::{Traversal, TraversalOperator, TraversalStep};
+
+        let get_attr = |variable, attribute, source| {
+            Expression::from(Traversal::new(
+                Expression::Variable(From::from(variable)),
+                vec![TraversalStep::new(
+                    TraversalOperator::GetAttr(From::from(attribute)),
+                    source,
+                )],
+            ))
+        };
+        let test_cases = [
+            ("x = foo", Expression::Variable(From::from("foo"))),
+            ("x = foo.bar", get_attr("foo", "bar", ".bar")),
+            ("x = true", Expression::Boolean(true)),
+            ("x = false", Expression::Boolean(false)),
+            ("x = null", Expression::Null),
+            ("x = trueish", Expression::Variable(From::from("trueish"))),
+            (
+                "x = null_resource.id",
+                get_attr("null_resource", "id", ".id"),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) = attribute(CompleteStr(input)).unwrap();
+            assert!(remaining.is_empty());
+            assert_eq!(actual, Attribute::new("x", expected.clone()));
+        }
+    }
+
+    #[test]
+    fn parenthesized_expressions_may_span_multiple_lines() {
+        let test_cases = [
+            ("x = (\n  1 +\n  2\n)\nnext", "1 + 2", "\nnext"),
+            ("x = (1\n  * 2 # Comment\n  - 3)", "1 * 2 - 3", ""),
+            ("x = (a\n  ? b\n  : c)", "a ? b : c", ""),
+            (
+                "x = [\n  1 +\n  2,\n  f(a +\n    b),\n]",
+                "[1 + 2, f(a + b)]",
+                "",
+            ),
+            ("x = ({\n  a = 1\n  b = 2\n})", "{ a = 1, b = 2 }", ""),
+        ];
+
+        for (input, expected, remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let expected = Attribute::new("x", Expression::parse(expected).unwrap());
+            assert_eq!(
+                attribute(CompleteStr(input)),
+                Ok((CompleteStr(remaining), expected))
+            );
+        }
+    }
+
+    #[test]
+    fn newlines_outside_of_parentheses_end_attributes() {
+        let test_cases = [
+            ("x = 1\n+ 2", Expression::from(1), "\n+ 2"),
+            (
+                "x = a\n? b : c",
+                Expression::parse("a").unwrap(),
+                "\n? b : c",
+            ),
+            (
+                "x = 1 # Comment\n* 2",
+                Expression::from(1),
+                "# Comment\n* 2",
+            ),
+        ];
+
+        for (input, expected, remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let expected = Attribute::new("x", expected.clone());
+            assert_eq!(
+                attribute(CompleteStr(input)),
+                Ok((CompleteStr(remaining), expected))
+            );
+        }
+
+        // Newlines separate the elements of objects, even inside parentheses
+        assert!(attribute(CompleteStr("x = ({ a = 1\n + 2 })")).is_err());
+    }
+
+    #[test]
+    fn invalid_attributes_are_not_accepted() {
+        // The name, the `=` and the start of the expression must be on the same line
+        let test_cases = [
+            "foo == 1", "foo =", "= 1", "1foo = 1", "foo\n= 1", "foo =\n1",
+        ];
+
+        for input in test_cases.iter() {
+            println!("Testing {:?}", input);
+            assert!(attribute(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn sigil_attribute_names_are_parsed_in_lenient_mode() {
+        let options = ParseOptions::default().lenient(true);
+        let test_cases = [("@schema = \"x\"", "@schema"), ("$id = \"x\"", "$id")];
+
+        for (input, expected_name) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = attribute_with_options(CompleteStr(input), &options).unwrap_output();
+            assert_eq!(actual.name, *expected_name);
+            assert_eq!(actual.expression, Expression::from("x"));
+        }
+    }
+
+    #[test]
+    fn sigil_attribute_names_are_rejected_in_strict_mode() {
+        assert!(attribute(CompleteStr("@schema = \"x\"")).is_err());
+
+        let options = ParseOptions::default()
+            .lenient(true)
+            .identifier_sigils(vec!['$']);
+        assert!(attribute_with_options(CompleteStr("@schema = \"x\""), &options).is_err());
+    }
+
+    #[test]
+    fn optional_attribute_markers_are_parsed_in_lenient_mode() {
+        let options = ParseOptions::default().lenient(true);
+        let test_cases = [
+            ("name? = 1", Attribute::new("name", 1).optional(true)),
+            ("name ? = 1", Attribute::new("name", 1).optional(true)),
+            ("@name? = 1", Attribute::new("@name", 1).optional(true)),
+            ("name = 1", Attribute::new("name", 1)),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = attribute_with_options(CompleteStr(input), &options).unwrap_output();
+            assert_eq!(actual, *expected);
+        }
+    }
+
+    #[test]
+    fn optional_attribute_markers_are_rejected_in_strict_mode() {
+        assert!(attribute(CompleteStr("name? = 1")).is_err());
+    }
 }
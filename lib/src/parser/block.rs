@@ -7,35 +7,67 @@
 //! OneLineBlock = Identifier (StringLit|Identifier)* "{" (Identifier "=" Expression)? "}" Newline;
 //! ```
 use std::borrow::{Borrow, Cow};
-use std::collections::hash_map::{self, Entry};
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::cmp::Ordering;
+use std::collections::btree_map::{self, Entry};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::hash::{Hash, Hasher};
 use std::iter::{Extend, FromIterator};
 
-use itertools::Itertools;
 use nom::types::CompleteStr;
-use nom::{alt, call, many0, named, opt, tag};
-
-use crate::parser::attribute::{attribute, Attribute};
-use crate::parser::body::{body, Body};
-use crate::parser::identifier::{identifier, Identifier};
-use crate::parser::string::{string_literal, StringLiteral};
-use crate::parser::whitespace::newline;
+use nom::{alt, call, cond, eol, many0, named, opt, preceded, sep, tag, terminated, IResult};
+
+use crate::errors::{in_context, ParseContext};
+use crate::parser::attribute::{attribute_with_options, Attribute};
+use crate::parser::body::{block_body_with_options, Body};
+use crate::parser::comment::line_comment;
+use crate::parser::identifier::{identifier, identifier_with_options, Identifier};
+use crate::parser::options::{nested_options, ParseOptions};
+use crate::parser::string::{single_line_string, StringLiteral};
+use crate::parser::whitespace::{inline_whitespace, whitespace};
+use crate::span::Span;
 
 /// HCL Block
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// The span and the comments are not compared when comparing blocks.
+#[derive(Debug, Clone)]
 pub struct Block<'a> {
     pub r#type: Identifier<'a>,
     pub labels: Vec<BlockLabel<'a>>,
     pub body: Body<'a>,
+    /// The span of the block, from its type to its closing brace, if it was parsed with
+    /// [`parse_with_spans`](crate::parser::parse_with_spans)
+    pub span: Option<Span>,
+    /// The comments on the lines before the block, including their delimiters, if comments are
+    /// [preserved](ParseOptions::preserve_comments)
+    pub leading_comments: Vec<Cow<'a, str>>,
+    /// The line comment after the closing brace on the same line, including its delimiter, if
+    /// comments are [preserved](ParseOptions::preserve_comments)
+    pub trailing_comment: Option<Cow<'a, str>>,
 }
 
 impl<'a> Block<'a> {
-    pub fn new(r#type: Identifier<'a>, labels: Vec<BlockLabel<'a>>, body: Body<'a>) -> Self {
+    pub fn new<B>(r#type: Identifier<'a>, labels: Vec<BlockLabel<'a>>, body: B) -> Self
+    where
+        B: Into<Body<'a>>,
+    {
         Self {
             r#type,
             labels,
-            body,
+            body: body.into(),
+            span: None,
+            leading_comments: vec![],
+            trailing_comment: None,
+        }
+    }
+
+    /// Compute the span of the type of the block in `source`, the input that was parsed
+    ///
+    /// Returns `None` if the type was not borrowed from `source`, for example if it has been
+    /// converted with [`AsOwned`](crate::AsOwned).
+    pub fn type_span(&self, source: &str) -> Option<Span> {
+        match self.r#type {
+            Cow::Borrowed(r#type) => Span::from_fragment(source, r#type),
+            Cow::Owned(_) => None,
         }
     }
 
@@ -45,19 +77,27 @@ impl<'a> Block<'a> {
         attribute: Option<Attribute<'a>>,
     ) -> Self {
         let body = match attribute {
-            None => vec![],
-            Some(attr) => vec![From::from(attr)],
+            None => Body::new(),
+            Some(attr) => vec![From::from(attr)].into(),
         };
 
-        Self {
-            r#type,
-            labels,
-            body,
-        }
+        Self::new(r#type, labels, body)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl<'a> PartialEq for Block<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.r#type == other.r#type && self.labels == other.labels && self.body == other.body
+    }
+}
+
+impl<'a> Eq for Block<'a> {}
+
+/// A label of a block
+///
+/// Labels are compared, ordered and hashed by their string value, regardless of whether they are
+/// string literals or identifiers.
+#[derive(Debug, Clone)]
 pub enum BlockLabel<'a> {
     StringLiteral(StringLiteral),
     Identifier(Identifier<'a>),
@@ -76,6 +116,26 @@ impl<'a> BlockLabel<'a> {
     }
 }
 
+impl<'a> PartialOrd for BlockLabel<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq for BlockLabel<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<'a> Eq for BlockLabel<'a> {}
+
+impl<'a> Ord for BlockLabel<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
 impl<'a> Hash for BlockLabel<'a> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.as_str().hash(state)
@@ -128,6 +188,16 @@ impl<'a> crate::AsOwned for Block<'a> {
             r#type: Cow::Owned(self.r#type.as_owned()),
             labels: self.labels.as_owned(),
             body: self.body.as_owned(),
+            span: self.span,
+            leading_comments: self
+                .leading_comments
+                .iter()
+                .map(|comment| Cow::Owned(comment.to_string()))
+                .collect(),
+            trailing_comment: self
+                .trailing_comment
+                .as_ref()
+                .map(|comment| Cow::Owned(comment.to_string())),
         }
     }
 }
@@ -137,7 +207,7 @@ named!(
     alt!(
         call!(identifier) =>
             { |ident| BlockLabel::Identifier(ident) }
-        | call!(string_literal) =>
+        | call!(single_line_string) =>
             { |s| BlockLabel::StringLiteral(s) }
     )
 );
@@ -149,44 +219,128 @@ named!(
     )
 );
 
+pub fn one_line_block_body_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Option<Attribute<'a>>, u32> {
+    opt!(input, call!(attribute_with_options, options))
+}
+
 named!(
     pub one_line_block_body(CompleteStr) -> Option<Attribute>,
-    opt!(attribute)
+    call!(one_line_block_body_with_options, &ParseOptions::default())
 );
 
-named!(
-    pub one_line_block(CompleteStr) -> Block,
-    inline_whitespace!(
+// "{"
+//
+// In lenient mode, the brace may be on a line after the block type and labels
+fn block_open_brace<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, CompleteStr<'a>, u32> {
+    preceded!(input, cond!(options.lenient, whitespace), tag!("{"))
+}
+
+pub fn one_line_block_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Block<'a>, u32> {
+    let (start, _) = inline_whitespace(input)?;
+    let (end, block) = sep!(
+        start,
+        inline_whitespace,
         do_parse!(
-            block_type: call!(identifier)
+            block_type: call!(identifier_with_options, options)
             >> labels: call!(block_labels)
-            >> tag!("{")
-            >> attribute: call!(one_line_block_body)
+            >> call!(block_open_brace, options)
+            >> nested: call!(nested_options, options)
+            >> attribute: call!(one_line_block_body_with_options, &nested)
             >> tag!("}")
             >> (Block::new_one_line(block_type, labels, attribute))
         )
-    )
-);
+    )?;
+    let (remaining, _) = nom::space0(end)?;
+
+    let span = options.span_between(start, end);
+    Ok((remaining, Block { span, ..block }))
+}
 
 named!(
-    pub block(CompleteStr) -> Block,
-    inline_whitespace!(
+    pub one_line_block(CompleteStr) -> Block,
+    call!(one_line_block_with_options, &ParseOptions::default())
+);
+
+// The line ending after the opening brace of a block, and the body of the block. They are parsed
+// together so that `sep!` does not skip the comments in front of the first element of the body.
+fn newline_and_block_body<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Body<'a>, u32> {
+    preceded!(
+        input,
+        terminated!(opt!(line_comment), eol),
+        call!(block_body_with_options, options)
+    )
+}
+
+// Parses a block up to the start of its body, to find where an invalid block went wrong
+pub(crate) fn block_head<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, (), u32> {
+    let (start, _) = inline_whitespace(input)?;
+    sep!(
+        start,
+        inline_whitespace,
         do_parse!(
-            block_type: call!(identifier)
+            call!(identifier_with_options, options)
+                >> call!(block_labels)
+                >> call!(block_open_brace, options)
+                >> terminated!(opt!(line_comment), eol)
+                >> ()
+        )
+    )
+}
+
+pub fn block_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Block<'a>, u32> {
+    let (start, _) = inline_whitespace(input)?;
+    let result = sep!(
+        start,
+        inline_whitespace,
+        do_parse!(
+            block_type: call!(identifier_with_options, options)
             >> labels: call!(block_labels)
-            >> tag!("{")
-            >> newline
-            >> body: call!(body)
+            >> call!(block_open_brace, options)
+            >> nested: call!(nested_options, options)
+            >> body: call!(newline_and_block_body, &nested)
             >> tag!("}")
             >> (Block::new(block_type, labels, body))
         )
-    )
+    );
+    let (end, block) = in_context(start, ParseContext::Block, result)?;
+    let (remaining, _) = nom::space0(end)?;
+
+    let span = options.span_between(start, end);
+    Ok((remaining, Block { span, ..block }))
+}
+
+named!(
+    pub block(CompleteStr) -> Block,
+    call!(block_with_options, &ParseOptions::default())
 );
 
 /// Blocks in a body indexed by their type and labels
+///
+/// Block types and labels are kept sorted so that iterating over the blocks is deterministic.
+/// Blocks are therefore iterated in the order of their types and labels, not in the order they
+/// appear in the source: use [`Body::blocks`](crate::parser::body::Body::blocks) for that. Bodies
+/// with the same type and labels are kept in the order they were added.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Blocks<'a> {
-    blocks: HashMap<Identifier<'a>, BlockBody<'a>>,
+    blocks: BTreeMap<Identifier<'a>, BlockBody<'a>>,
 }
 
 impl<'a> Blocks<'a> {
@@ -194,16 +348,11 @@ impl<'a> Blocks<'a> {
     where
         T: IntoIterator<Item = Block<'a>>,
     {
-        let mut hashmap = HashMap::new();
-        for (block_type, blocks) in blocks
-            .into_iter()
-            .group_by(|block| block.r#type.clone())
-            .into_iter()
-        {
-            let blocks = BlockBody::from_iter(blocks.map(|block| (block.labels, block.body)));
-            hashmap.insert(block_type, blocks);
-        }
-        Self { blocks: hashmap }
+        let mut result = Self {
+            blocks: BTreeMap::new(),
+        };
+        result.extend(blocks);
+        result
     }
 
     pub fn append(&mut self, block: Block<'a>) {
@@ -242,11 +391,11 @@ impl<'a> Blocks<'a> {
     }
 
     /// Get an iterator over the types of blocks
-    pub fn iter(&self) -> hash_map::Iter<Identifier<'a>, BlockBody<'a>> {
+    pub fn iter(&self) -> btree_map::Iter<Identifier<'a>, BlockBody<'a>> {
         self.blocks.iter()
     }
 
-    pub fn iter_mut(&mut self) -> hash_map::IterMut<Identifier<'a>, BlockBody<'a>> {
+    pub fn iter_mut(&mut self) -> btree_map::IterMut<Identifier<'a>, BlockBody<'a>> {
         self.blocks.iter_mut()
     }
 
@@ -323,21 +472,21 @@ impl<'a> Blocks<'a> {
     }
 
     /// Get a set of label lengths for this body
-    pub fn label_lens(&self) -> HashMap<&str, BTreeSet<usize>> {
+    pub fn label_lens(&self) -> BTreeMap<&str, BTreeSet<usize>> {
         self.blocks
             .iter()
             .map(|(ident, bodies)| (ident.borrow(), bodies.label_lens()))
             .collect()
     }
 
-    pub fn label_lens_uniform(&self) -> HashMap<&str, Option<usize>> {
+    pub fn label_lens_uniform(&self) -> BTreeMap<&str, Option<usize>> {
         self.blocks
             .iter()
             .map(|(ident, bodies)| (ident.borrow(), bodies.label_lens_uniform()))
             .collect()
     }
 
-    pub fn is_label_lens_uniform(&self) -> HashMap<&str, bool> {
+    pub fn is_label_lens_uniform(&self) -> BTreeMap<&str, bool> {
         self.blocks
             .iter()
             .map(|(ident, bodies)| (ident.borrow(), bodies.is_label_lens_uniform()))
@@ -347,7 +496,7 @@ impl<'a> Blocks<'a> {
 
 impl<'a> IntoIterator for Blocks<'a> {
     type Item = (Identifier<'a>, BlockBody<'a>);
-    type IntoIter = hash_map::IntoIter<Identifier<'a>, BlockBody<'a>>;
+    type IntoIter = btree_map::IntoIter<Identifier<'a>, BlockBody<'a>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.blocks.into_iter()
@@ -356,7 +505,7 @@ impl<'a> IntoIterator for Blocks<'a> {
 
 impl<'a, 'b> IntoIterator for &'b Blocks<'a> {
     type Item = (&'b Identifier<'a>, &'b BlockBody<'a>);
-    type IntoIter = hash_map::Iter<'b, Identifier<'a>, BlockBody<'a>>;
+    type IntoIter = btree_map::Iter<'b, Identifier<'a>, BlockBody<'a>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
@@ -365,7 +514,7 @@ impl<'a, 'b> IntoIterator for &'b Blocks<'a> {
 
 impl<'a, 'b> IntoIterator for &'b mut Blocks<'a> {
     type Item = (&'b Identifier<'a>, &'b mut BlockBody<'a>);
-    type IntoIter = hash_map::IterMut<'b, Identifier<'a>, BlockBody<'a>>;
+    type IntoIter = btree_map::IterMut<'b, Identifier<'a>, BlockBody<'a>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter_mut()
@@ -381,6 +530,20 @@ impl<'a> FromIterator<Block<'a>> for Blocks<'a> {
     }
 }
 
+impl<'a> crate::AsOwned for Blocks<'a> {
+    type Output = Blocks<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        Blocks {
+            blocks: self
+                .blocks
+                .iter()
+                .map(|(block_type, body)| (Cow::Owned(block_type.as_owned()), body.as_owned()))
+                .collect(),
+        }
+    }
+}
+
 impl<'a> Extend<Block<'a>> for Blocks<'a> {
     fn extend<T>(&mut self, iter: T)
     where
@@ -397,12 +560,12 @@ pub enum BlockBody<'a> {
     Body(Vec<Body<'a>>),
     /// Variant where there are block bodies with at least one label
     ///
-    /// You should not construct this enum by hand. It is a logic error for the `labels` HashMap
+    /// You should not construct this enum by hand. It is a logic error for the `labels` map
     /// to be empty and you can expect panics or unexpected behaviour from other functions in this
     /// library.
     Labels {
         empty: Vec<Body<'a>>,
-        labels: HashMap<BlockLabel<'a>, BlockBody<'a>>,
+        labels: BTreeMap<BlockLabel<'a>, BlockBody<'a>>,
     },
 }
 
@@ -422,15 +585,15 @@ impl<'a> BlockBody<'a> {
             }
             BlockBody::Labels {
                 ref mut empty,
-                labels: ref mut hashmap,
+                labels: ref mut map,
             } => {
                 if labels.is_empty() {
                     empty.push(body);
                 } else {
                     let label = labels.drain(0..1).next().expect("to be some");
-                    match hashmap.entry(label) {
+                    match map.entry(label) {
                         Entry::Vacant(vacant) => {
-                            vacant.insert(BlockBody::Body(vec![body]));
+                            vacant.insert(BlockBody::from_iter(vec![(labels, body)]));
                         }
                         Entry::Occupied(mut occupied) => {
                             occupied.get_mut().append(labels, body);
@@ -450,9 +613,8 @@ impl<'a> BlockBody<'a> {
             Some((first, rest)) => match self {
                 BlockBody::Body(_) => None,
                 BlockBody::Labels {
-                    labels: ref hashmap,
-                    ..
-                } => match hashmap.get(first.as_ref()) {
+                    labels: ref map, ..
+                } => match map.get(first.as_ref()) {
                     None => None,
                     Some(inner) => inner.get(rest),
                 },
@@ -469,9 +631,9 @@ impl<'a> BlockBody<'a> {
             Some((first, rest)) => match self {
                 BlockBody::Body(_) => None,
                 BlockBody::Labels {
-                    labels: ref mut hashmap,
+                    labels: ref mut map,
                     ..
-                } => match hashmap.get_mut(first.as_ref()) {
+                } => match map.get_mut(first.as_ref()) {
                     None => None,
                     Some(inner) => inner.get_mut(rest),
                 },
@@ -593,7 +755,9 @@ impl<'a> BlockBody<'a> {
     }
 
     /// Borrow the bodies with additional labels
-    pub fn get_labels(&self) -> Option<&HashMap<BlockLabel<'a>, BlockBody<'a>>> {
+    ///
+    /// The labels are sorted, so they are not necessarily in the order they appear in the source.
+    pub fn get_labels(&self) -> Option<&BTreeMap<BlockLabel<'a>, BlockBody<'a>>> {
         match self {
             BlockBody::Body(_) => None,
             BlockBody::Labels { ref labels, .. } => Some(labels),
@@ -655,25 +819,23 @@ impl<'a> BlockBody<'a> {
 
     /// In place transmute of Body to Labels
     ///
-    /// Must only be called when `labels` are not empty and the enum is of Body type
-    /// Otherwise, this functon will panic
+    /// Must only be called when `labels` are not empty and the enum is of Body type. Otherwise,
+    /// the body is appended as usual.
     fn body_to_labels(&mut self, mut labels: Vec<BlockLabel<'a>>, body: Body<'a>) {
-        take_mut::take(self, move |current| {
-            if let BlockBody::Body(bodies) = current {
-                let label = labels.drain(0..1).next().expect("to be some");
-
-                let mut hashmap = HashMap::new();
-                let mut new_body = BlockBody::default();
-                new_body.append(labels, body);
-                hashmap.insert(label, new_body);
-                BlockBody::Labels {
-                    empty: bodies,
-                    labels: hashmap,
-                }
-            } else {
-                panic!("Unexpected enum variant")
-            }
-        });
+        let bodies = match self {
+            BlockBody::Body(bodies) if !labels.is_empty() => std::mem::take(bodies),
+            _ => return self.append(labels, body),
+        };
+        let label = labels.remove(0);
+
+        let mut map = BTreeMap::new();
+        let mut new_body = BlockBody::default();
+        new_body.append(labels, body);
+        map.insert(label, new_body);
+        *self = BlockBody::Labels {
+            empty: bodies,
+            labels: map,
+        };
     }
 }
 
@@ -705,10 +867,26 @@ impl<'a> Default for BlockBody<'a> {
     }
 }
 
+impl<'a> crate::AsOwned for BlockBody<'a> {
+    type Output = BlockBody<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        match self {
+            BlockBody::Body(bodies) => BlockBody::Body(bodies.as_owned()),
+            BlockBody::Labels { empty, labels } => BlockBody::Labels {
+                empty: empty.as_owned(),
+                labels: labels.as_owned(),
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use itertools::Itertools;
+
     use crate::parser::body::BodyElement;
     use crate::parser::expression::Expression;
     use crate::utils::ResultUtilsString;
@@ -729,6 +907,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn block_labels_are_compared_by_value() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |label: &BlockLabel| {
+            let mut hasher = DefaultHasher::new();
+            label.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let identifier = BlockLabel::from("foobar");
+        let literal = BlockLabel::StringLiteral(From::from("foobar"));
+        assert_eq!(identifier, literal);
+        assert_eq!(identifier.cmp(&literal), Ordering::Equal);
+        assert_eq!(hash(&identifier), hash(&literal));
+
+        let other = BlockLabel::StringLiteral(From::from("other"));
+        assert_ne!(identifier, other);
+        assert_eq!(identifier.cmp(&other), Ordering::Less);
+    }
+
     #[test]
     fn block_labels_are_parsed_successfully() {
         let test_cases = [
@@ -766,7 +965,7 @@ mod tests {
     fn single_line_block_body_is_parsed_correctly() {
         let test_cases = [
             ("", None),
-            ("foo = true", Some((From::from("foo"), From::from(true)))),
+            ("foo = true", Some(Attribute::new("foo", true))),
         ];
 
         for (input, expected_output) in &test_cases {
@@ -784,11 +983,7 @@ mod tests {
             ),
             (
                 "test { foo = 123 }",
-                Block::new_one_line(
-                    From::from("test"),
-                    vec![],
-                    Some((From::from("foo"), From::from(123))),
-                ),
+                Block::new_one_line(From::from("test"), vec![], Some(Attribute::new("foo", 123))),
             ),
             (
                 "test foo bar baz {}",
@@ -811,7 +1006,7 @@ mod tests {
                         BlockLabel::StringLiteral(From::from("bar")),
                         BlockLabel::from("baz"),
                     ],
-                    Some((From::from("foo"), From::from(123))),
+                    Some(Attribute::new("foo", 123)),
                 ),
             ),
         ];
@@ -847,6 +1042,50 @@ mod tests {
         assert_eq!(block, expected);
     }
 
+    #[test]
+    fn empty_and_commented_blocks_are_parsed_correctly() {
+        let empty = Block::new(From::from("foo"), vec![], Body::new());
+        assert_eq!(one_line_block(CompleteStr("foo {}")).unwrap_output(), empty);
+        assert_eq!(
+            one_line_block(CompleteStr("foo { }")).unwrap_output(),
+            empty
+        );
+        assert_eq!(block(CompleteStr("foo {\n}")).unwrap_output(), empty);
+        assert!(block(CompleteStr("foo {}")).is_err());
+
+        let hcl = r#"resource "aws_instance" web { # Comment
+  // Comment
+  ami = "abc" /* Comment */
+
+  /* Multi-line
+     comment */
+  network_interface "eth0" {
+    # Comment
+  }
+  tags = {} # Comment
+}"#;
+        let expected = Block::new(
+            From::from("resource"),
+            vec![
+                BlockLabel::StringLiteral(From::from("aws_instance")),
+                BlockLabel::from("web"),
+            ],
+            vec![
+                From::from((From::from("ami"), Expression::from("abc"))),
+                From::from(Block::new(
+                    From::from("network_interface"),
+                    vec![BlockLabel::StringLiteral(From::from("eth0"))],
+                    Body::new(),
+                )),
+                From::from((
+                    From::from("tags"),
+                    Expression::new_object(Vec::<(&str, Expression)>::new()),
+                )),
+            ],
+        );
+        assert_eq!(block(CompleteStr(hcl)).unwrap_output(), expected);
+    }
+
     #[test]
     fn nested_block_is_parsed_correctly() {
         let hcl = r#"resource "security/group" foobar {
@@ -1065,4 +1304,119 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn blocks_are_ordered_deterministically() {
+        fn flatten(body: Body) -> Vec<(String, Vec<String>, Body)> {
+            let blocks: Blocks = body
+                .into_iter()
+                .filter(BodyElement::is_block)
+                .map(BodyElement::unwrap_block)
+                .collect();
+            blocks
+                .flat_into_iter()
+                .map(|(block_type, labels, body)| {
+                    (
+                        block_type.to_string(),
+                        labels.into_iter().map(|label| label.to_string()).collect(),
+                        body,
+                    )
+                })
+                .collect()
+        }
+
+        let hcl = crate::fixtures::BLOCK;
+        let first = crate::parser::parse_str(hcl).unwrap();
+        let second = crate::parser::parse_str(hcl).unwrap();
+        assert_eq!(first, second);
+
+        let first = flatten(first);
+        let second = flatten(second);
+        assert_eq!(first, second);
+
+        let order: Vec<_> = first
+            .iter()
+            .map(|(block_type, labels, _)| (block_type.as_str(), labels.join(".")))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("resource", "instance.an_instance".to_string()),
+                ("resource", "security/group.foobar".to_string()),
+                ("resource", "security/group.second".to_string()),
+                ("simple_map", "".to_string()),
+                ("simple_map", "".to_string()),
+            ]
+        );
+        assert_eq!(
+            first[3].2[2],
+            BodyElement::Attribute(Attribute::new("index", 1))
+        );
+        assert_eq!(
+            first[4].2[2],
+            BodyElement::Attribute(Attribute::new("index", 0))
+        );
+    }
+
+    #[test]
+    fn blocks_of_the_same_type_are_grouped_when_not_adjacent() {
+        let blocks: Blocks = vec![
+            Block::new(From::from("a"), vec![], vec![]),
+            Block::new(From::from("b"), vec![], vec![]),
+            Block::new(From::from("a"), vec![From::from("label")], vec![]),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks.len_blocks(), 3);
+        assert!(blocks.get("a", &["label"]).is_some());
+    }
+
+    #[test]
+    fn block_braces_on_the_next_line_are_parsed_in_lenient_mode() {
+        let strict = ParseOptions::default();
+        let lenient = ParseOptions::default().lenient(true);
+        let expected = Block::new(
+            From::from("resource"),
+            vec![
+                BlockLabel::StringLiteral(From::from("x")),
+                BlockLabel::StringLiteral(From::from("y")),
+            ],
+            vec![From::from((From::from("foo"), Expression::from(1)))],
+        );
+        let same_line = r#"resource "x" "y" {
+  foo = 1
+}"#;
+        let next_line = r#"resource "x" "y" // Comment
+{
+  foo = 1
+}"#;
+
+        for options in [&strict, &lenient].iter() {
+            assert_eq!(
+                block_with_options(CompleteStr(same_line), options).unwrap_output(),
+                expected
+            );
+        }
+        assert_eq!(
+            block_with_options(CompleteStr(next_line), &lenient).unwrap_output(),
+            expected
+        );
+        assert!(block_with_options(CompleteStr(next_line), &strict).is_err());
+
+        let one_line = Block::new_one_line(
+            From::from("resource"),
+            vec![],
+            Some(Attribute::new("foo", 1)),
+        );
+        assert_eq!(
+            one_line_block_with_options(CompleteStr("resource\n{ foo = 1 }"), &lenient)
+                .unwrap_output(),
+            one_line
+        );
+        assert!(
+            one_line_block_with_options(CompleteStr("resource\n{ foo = 1 }"), &strict).is_err()
+        );
+    }
 }
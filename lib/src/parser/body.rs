@@ -1,12 +1,28 @@
 //! HCL Body
 //!
 //! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#structural-elements)
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
 use nom::types::CompleteStr;
-use nom::{alt, call, do_parse, eof, named_attr, terminated};
+use nom::{alt, call, do_parse, eof, eol, many_m_n, named_attr, space0, IResult};
 
-use crate::parser::attribute::{attribute, Attribute};
-use crate::parser::block::{block, one_line_block, Block};
-use crate::parser::whitespace::newline;
+use crate::constants::LIST;
+use crate::parser::attribute::{attribute_head, attribute_with_options, Attribute};
+use crate::parser::block::{
+    block_head, block_with_options, one_line_block_with_options, Block, BlockLabel,
+};
+use crate::parser::comment::{comments_in_whitespace, trailing_comments};
+use crate::parser::expression::Expression;
+use crate::parser::identifier::Identifier;
+use crate::parser::literals::Key;
+use crate::parser::options::{nested_options, ParseOptions};
+use crate::parser::whitespace::inline_whitespace;
+use crate::utils::snippet;
+use crate::value::Value;
+use crate::{Error, KeyValuePairs};
 
 /// A HCL document body
 ///
@@ -17,7 +33,403 @@ use crate::parser::whitespace::newline;
 /// Block        = Identifier (StringLit|Identifier)* "{" Newline Body "}" Newline;
 /// OneLineBlock = Identifier (StringLit|Identifier)* "{" (Identifier "=" Expression)? "}" Newline;
 /// ```
-pub type Body<'a> = Vec<BodyElement<'a>>;
+///
+/// The elements of the body are kept in the order they appear in the source. `Body` dereferences
+/// to a `Vec` of [`BodyElement`]s.
+#[derive(Clone, Debug, Default)]
+pub struct Body<'a> {
+    elements: Vec<BodyElement<'a>>,
+    /// The comments after the last element of the body, including their delimiters
+    ///
+    /// The comments in front of an element are kept in the element instead. They are only recorded
+    /// when parsing with [comments preserved](ParseOptions::preserve_comments), and are ignored
+    /// when comparing bodies.
+    pub trailing_comments: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Body<'a> {
+    /// Create a new empty body
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Parse a string as a HCL body
+    ///
+    /// The string is expected to be fully consumed during parsing or an error will be returned.
+    /// This is the same as [`parse_str`](crate::parser::parse_str).
+    pub fn parse(s: &'a str) -> Result<Self, Error> {
+        Self::parse_with_options(s, &Default::default())
+    }
+
+    /// Parse a string as a HCL body with the provided [`ParseOptions`]
+    ///
+    /// The string is expected to be fully consumed during parsing or an error will be returned. A
+    /// byte order mark at the start of the string is ignored.
+    pub fn parse_with_options(s: &'a str, options: &ParseOptions) -> Result<Self, Error> {
+        options.check_input_length(s)?;
+        let s = crate::parser::strip_bom(s);
+        let (remaining, body) = body_with_options(CompleteStr(s), options)
+            .map_err(|e| Error::from_err_str_with_input(&e, s))?;
+        if !remaining.is_empty() {
+            let token = unparsed_token(remaining, options);
+            return Err(Error::UnexpectedRemainingInput(snippet(&token)).at_position(s, &token));
+        }
+        Ok(body)
+    }
+
+    /// Unwrap the body into the underlying `Vec` of elements
+    pub fn into_inner(self) -> Vec<BodyElement<'a>> {
+        self.elements
+    }
+
+    /// Returns an iterator over the attributes directly in the body, in source order
+    pub fn attributes<'b>(&'b self) -> impl Iterator<Item = &'b Attribute<'a>> + 'b {
+        self.iter().filter_map(|element| match element {
+            BodyElement::Attribute(attribute) => Some(attribute),
+            BodyElement::Block(_) => None,
+        })
+    }
+
+    /// Returns an iterator over the blocks directly in the body, in source order
+    pub fn blocks<'b>(&'b self) -> impl Iterator<Item = &'b Block<'a>> + 'b {
+        self.iter().filter_map(|element| match element {
+            BodyElement::Block(block) => Some(block),
+            BodyElement::Attribute(_) => None,
+        })
+    }
+
+    /// Returns an iterator over the blocks directly in the body with the type `block_type`, in
+    /// source order
+    pub fn blocks_of_type<'b>(
+        &'b self,
+        block_type: &'b str,
+    ) -> impl Iterator<Item = &'b Block<'a>> + 'b {
+        self.blocks()
+            .filter(move |block| block.r#type == block_type)
+    }
+
+    /// Group the blocks directly in the body by their type
+    ///
+    /// Each group keeps its blocks in source order, and each block keeps its labels and body.
+    /// Labels do not factor into the grouping, so that all the `resource` blocks of a body can
+    /// be processed together whatever their labels are. Use [`Blocks`](crate::parser::Blocks) to index blocks by their
+    /// labels as well.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::body::Body;
+    ///
+    /// let body = Body::parse("resource \"a\" {\n}\nvariable {\n}\nresource \"b\" {\n}\n").unwrap();
+    /// let blocks = body.merge_blocks();
+    /// assert_eq!(blocks["resource"].len(), 2);
+    /// assert_eq!(blocks["variable"].len(), 1);
+    /// ```
+    pub fn merge_blocks<'b>(&'b self) -> BTreeMap<&'b str, Vec<&'b Block<'a>>> {
+        let mut merged: BTreeMap<&'b str, Vec<&'b Block<'a>>> = BTreeMap::new();
+        for block in self.blocks() {
+            merged.entry(&block.r#type).or_default().push(block);
+        }
+        merged
+    }
+
+    /// Look up the expression of the attribute named `name` directly in the body
+    ///
+    /// HCL does not allow an attribute to be defined more than once, but a parsed body might
+    /// still contain duplicates. The first attribute with the name is always returned;
+    /// [`to_value`](Body::to_value) rejects the duplicates.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::body::Body;
+    /// use ferrous_chloride::parser::expression::Expression;
+    ///
+    /// let body = Body::parse("name = true\n").unwrap();
+    /// assert_eq!(body.get_attribute("name"), Some(&Expression::Boolean(true)));
+    /// assert_eq!(body.get_attribute("other"), None);
+    /// ```
+    pub fn get_attribute(&self, name: &str) -> Option<&Expression<'a>> {
+        self.attributes()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| &attribute.expression)
+    }
+
+    /// Look up an expression by a dotted path, like `resource.aws_instance.web.ami`
+    ///
+    /// Each segment of the path names either an attribute or a block type. A block matches when
+    /// its type is followed, in the path, by all of its labels. The remaining segments are then
+    /// looked up in the body of the block. Once an attribute is reached, the remaining segments
+    /// are looked up as keys of nested object expressions.
+    ///
+    /// When more than one block matches, the blocks are tried in source order and the first one
+    /// in which the rest of the path resolves is used. Computed object keys are never matched.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::body::Body;
+    /// use ferrous_chloride::parser::expression::Expression;
+    ///
+    /// let hcl = "resource \"aws_instance\" \"web\" {\n  ami = \"abc\"\n}\n";
+    /// let body = Body::parse(hcl).unwrap();
+    /// assert_eq!(
+    ///     body.query("resource.aws_instance.web.ami"),
+    ///     Some(&Expression::String("abc".into()))
+    /// );
+    /// assert_eq!(body.query("resource.aws_instance.db.ami"), None);
+    /// ```
+    pub fn query(&self, path: &str) -> Option<&Expression<'a>> {
+        let segments: Vec<&str> = path.split('.').collect();
+        self.query_segments(&segments)
+    }
+
+    fn query_segments(&self, segments: &[&str]) -> Option<&Expression<'a>> {
+        let (name, rest) = segments.split_first()?;
+        if let Some(expression) = self.get_attribute(name) {
+            return query_expression(expression, rest);
+        }
+
+        self.blocks().find_map(|block| {
+            let labels = &block.labels;
+            if block.r#type != *name
+                || rest.len() < labels.len()
+                || !labels
+                    .iter()
+                    .zip(rest.iter())
+                    .all(|(label, segment)| label.as_str() == *segment)
+            {
+                return None;
+            }
+            block.body.query_segments(&rest[labels.len()..])
+        })
+    }
+
+    /// Collapse the body into a single [`Value::Object`]
+    ///
+    /// This is the in-memory equivalent of the
+    /// [JSON mapping](https://github.com/hashicorp/hcl2/blob/master/hcl/json/spec.md) of a body:
+    ///
+    /// - Attributes become members of the object
+    /// - Blocks are nested in objects keyed by their type and then each of their labels. Blocks
+    ///   with the same type and labels are collected, in source order, into a list of objects.
+    ///   The list is used even when there is only one such block so that the shape of the value
+    ///   does not depend on the number of blocks.
+    ///
+    /// Members are ordered by their first appearance in the body. An error is returned if an
+    /// attribute and a block share the same name, or if any expression is not a literal value.
+    pub fn to_value(&self) -> Result<Value<'a>, Error> {
+        let mut members = vec![];
+        for element in self.iter() {
+            match element {
+                BodyElement::Attribute(Attribute {
+                    name, expression, ..
+                }) => {
+                    let key = Key::Identifier(name.clone());
+                    if members.iter().any(|(existing, _)| *existing == key) {
+                        return Err(Error::IllegalMultipleEntries {
+                            key: name.to_string(),
+                            variant: crate::constants::OBJECT,
+                        });
+                    }
+                    members.push((key, expression.to_value()?));
+                }
+                BodyElement::Block(block) => {
+                    let path = std::iter::once(Key::Identifier(block.r#type.clone()))
+                        .chain(block.labels.iter().map(label_key))
+                        .collect();
+                    insert_block(&mut members, path, block.body.to_value()?)?;
+                }
+            }
+        }
+        Ok(Value::new_single_map(members))
+    }
+
+    /// Check that each of `block_types` is the type of at most one block of the body
+    ///
+    /// Some block types, like `terraform`, are singletons. Only the blocks directly in this body
+    /// are checked. The error for a duplicate block has the spans of the types of the first
+    /// block and of the duplicate in `source`, the input that was parsed.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::body::body;
+    /// use ferrous_chloride::nom::types::CompleteStr;
+    ///
+    /// let source = "terraform {\n}\nprovider \"a\" {\n}\nprovider \"b\" {\n}\n";
+    /// let (_, parsed) = body(CompleteStr(source)).unwrap();
+    /// assert!(parsed.validate_unique_blocks(source, &["terraform"]).is_ok());
+    /// assert!(parsed.validate_unique_blocks(source, &["provider"]).is_err());
+    /// ```
+    pub fn validate_unique_blocks<S>(&self, source: &str, block_types: &[S]) -> Result<(), Error>
+    where
+        S: AsRef<str>,
+    {
+        let mut seen: Vec<&Block<'a>> = vec![];
+        for element in self.iter() {
+            let block = match element {
+                BodyElement::Block(block) => block,
+                BodyElement::Attribute(_) => continue,
+            };
+            if !block_types
+                .iter()
+                .any(|block_type| block_type.as_ref() == block.r#type)
+            {
+                continue;
+            }
+
+            match seen.iter().find(|first| first.r#type == block.r#type) {
+                Some(first) => {
+                    return Err(Error::DuplicateBlock {
+                        block_type: block.r#type.to_string(),
+                        first: first.type_span(source),
+                        duplicate: block.type_span(source),
+                    })
+                }
+                None => seen.push(block),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn label_key<'a>(label: &BlockLabel<'a>) -> Key<'a> {
+    match label {
+        BlockLabel::StringLiteral(string) => Key::new_string_owned(string.clone()),
+        BlockLabel::Identifier(ident) => Key::Identifier(ident.clone()),
+    }
+}
+
+fn insert_block<'a>(
+    members: &mut Vec<(Key<'a>, Value<'a>)>,
+    mut path: Vec<Key<'a>>,
+    body: Value<'a>,
+) -> Result<(), Error> {
+    let key = path.remove(0);
+    let index = match members.iter().position(|(existing, _)| *existing == key) {
+        Some(index) => index,
+        None => {
+            let value = if path.is_empty() {
+                Value::List(vec![])
+            } else {
+                Value::new_single_map(vec![])
+            };
+            members.push((key, value));
+            members.len() - 1
+        }
+    };
+
+    let (key, value) = &mut members[index];
+    match value {
+        Value::List(ref mut list) if path.is_empty() => {
+            list.push(body);
+            Ok(())
+        }
+        Value::Object(ref mut maps) if !path.is_empty() => match maps.get_mut(0) {
+            Some(KeyValuePairs::Unmerged(ref mut members)) => insert_block(members, path, body),
+            _ => Err(Error::Bug("Block objects should be unmerged".to_string())),
+        },
+        value => Err(Error::ErrorMergingKeys {
+            key: key.to_string(),
+            existing_variant: value.variant_name(),
+            incoming_variant: LIST,
+        }),
+    }
+}
+
+fn query_expression<'a, 'b>(
+    expression: &'b Expression<'a>,
+    segments: &[&str],
+) -> Option<&'b Expression<'a>> {
+    match segments.split_first() {
+        None => Some(expression),
+        Some((key, rest)) => match expression {
+            Expression::Object(object) => object
+                .iter()
+                .find(|(identifier, _)| identifier == key)
+                .and_then(|(_, expression)| query_expression(expression, rest)),
+            _ => None,
+        },
+    }
+}
+
+impl<'a> Deref for Body<'a> {
+    type Target = Vec<BodyElement<'a>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl<'a> DerefMut for Body<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.elements
+    }
+}
+
+impl<'a> From<Vec<BodyElement<'a>>> for Body<'a> {
+    fn from(elements: Vec<BodyElement<'a>>) -> Self {
+        Body {
+            elements,
+            trailing_comments: vec![],
+        }
+    }
+}
+
+impl<'a> FromIterator<BodyElement<'a>> for Body<'a> {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = BodyElement<'a>>,
+    {
+        Body::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> IntoIterator for Body<'a> {
+    type Item = BodyElement<'a>;
+    type IntoIter = std::vec::IntoIter<BodyElement<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Body<'a> {
+    type Item = &'b BodyElement<'a>;
+    type IntoIter = std::slice::Iter<'b, BodyElement<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+impl<'a> PartialEq for Body<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.elements == other.elements
+    }
+}
+
+impl<'a> Eq for Body<'a> {}
+
+impl<'a> PartialEq<Vec<BodyElement<'a>>> for Body<'a> {
+    fn eq(&self, other: &Vec<BodyElement<'a>>) -> bool {
+        self.elements.eq(other)
+    }
+}
+
+impl<'a> PartialEq<Body<'a>> for Vec<BodyElement<'a>> {
+    fn eq(&self, other: &Body<'a>) -> bool {
+        self.eq(&other.elements)
+    }
+}
+
+impl<'a> crate::AsOwned for Body<'a> {
+    type Output = Body<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        Body {
+            elements: self.elements.as_owned(),
+            trailing_comments: self
+                .trailing_comments
+                .iter()
+                .map(|comment| Cow::Owned(comment.to_string()))
+                .collect(),
+        }
+    }
+}
 
 /// An element of `Body`
 ///
@@ -62,6 +474,21 @@ impl<'a> BodyElement<'a> {
             panic!("BodyElement is an attribute")
         }
     }
+
+    fn set_comments(&mut self, leading: Vec<CompleteStr<'a>>, trailing: Option<CompleteStr<'a>>) {
+        let leading = leading.into_iter().map(|comment| Cow::Borrowed(comment.0));
+        let trailing = trailing.map(|comment| Cow::Borrowed(comment.0));
+        match self {
+            BodyElement::Attribute(attr) => {
+                attr.leading_comments = leading.collect();
+                attr.trailing_comment = trailing;
+            }
+            BodyElement::Block(blk) => {
+                blk.leading_comments = leading.collect();
+                blk.trailing_comment = trailing;
+            }
+        }
+    }
 }
 
 impl<'a> From<Attribute<'a>> for BodyElement<'a> {
@@ -70,6 +497,12 @@ impl<'a> From<Attribute<'a>> for BodyElement<'a> {
     }
 }
 
+impl<'a> From<(Identifier<'a>, Expression<'a>)> for BodyElement<'a> {
+    fn from(attr: (Identifier<'a>, Expression<'a>)) -> Self {
+        BodyElement::Attribute(From::from(attr))
+    }
+}
+
 impl<'a> From<Block<'a>> for BodyElement<'a> {
     fn from(blk: Block<'a>) -> Self {
         BodyElement::Block(blk)
@@ -86,6 +519,23 @@ impl<'a> crate::AsOwned for BodyElement<'a> {
     }
 }
 
+/// Parses a `Body` element with the provided [`ParseOptions`]
+///
+/// ```ebnf
+/// Attribute | Block | OneLineBlock
+/// ```
+pub fn body_element_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, BodyElement<'a>, u32> {
+    alt!(
+        input,
+        call!(attribute_with_options, options) => { |attr| BodyElement::Attribute(attr) }
+        | call!(one_line_block_with_options, options) => { |blk| BodyElement::Block(blk) }
+        | call!(block_with_options, options) => { |blk| BodyElement::Block(blk) }
+    )
+}
+
 named_attr!(
     #[doc = r#"Parses a `Body` element
 
@@ -94,13 +544,112 @@ Attribute | Block | OneLineBlock
 ```
 "#],
     pub body_element(CompleteStr) -> BodyElement,
-    alt!(
-        attribute => { |attr| BodyElement::Attribute(attr) }
-        | one_line_block => { |blk| BodyElement::Block(blk) }
-        | block => { |blk| BodyElement::Block(blk) }
-    )
+    call!(body_element_with_options, &ParseOptions::default())
 );
 
+/// Parses a `Body` with the provided [`ParseOptions`]
+///
+/// ```ebnf
+/// Body = (Attribute | Block | OneLineBlock)*;
+/// ```
+///
+/// If [`max_items`](ParseOptions::max_items) is set, parsing stops after that many elements and
+/// the rest of the input is returned unparsed.
+pub fn body_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Body<'a>, u32> {
+    body_elements(input, options, options.max_items.unwrap_or(usize::MAX))
+}
+
+/// Parses the `Body` of a block, which is never limited by
+/// [`max_items`](ParseOptions::max_items)
+pub(crate) fn block_body_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Body<'a>, u32> {
+    body_elements(input, options, usize::MAX)
+}
+
+fn body_elements<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+    max_items: usize,
+) -> IResult<CompleteStr<'a>, Body<'a>, u32> {
+    do_parse!(
+        input,
+        elements: many_m_n!(0, max_items, call!(commented_body_element, options))
+        >> trailing: comments_in_whitespace
+        >> ({
+            let mut body = Body::from(elements);
+            if options.preserve_comments {
+                body.trailing_comments = trailing
+                    .into_iter()
+                    .map(|comment| Cow::Borrowed(comment.0))
+                    .collect();
+            }
+            body
+        })
+    )
+}
+
+// Parses a body element with the whitespace in front of it and the line ending after it. The
+// comments before the element and the comments after it on the same line are attached to it if
+// comments are preserved. The comments on the following lines are left for the next element.
+pub(crate) fn commented_body_element<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, BodyElement<'a>, u32> {
+    do_parse!(
+        input,
+        leading: comments_in_whitespace
+        >> element: call!(body_element_with_options, options)
+        >> trailing: trailing_comments
+        >> space0
+        >> alt!(call!(eol) | eof!())
+        >> ({
+            let mut element = element;
+            if options.preserve_comments {
+                element.set_comments(leading, trailing);
+            }
+            element
+        })
+    )
+}
+
+// Finds the first token that could not be parsed in the input left over after a body, so that the
+// error points at it instead of at the start of the element that contains it
+fn unparsed_token<'a>(input: CompleteStr<'a>, options: &ParseOptions) -> CompleteStr<'a> {
+    let start = match comments_in_whitespace(input) {
+        Ok((start, _)) => start,
+        Err(_) => return input,
+    };
+    // The element is valid, but the body was limited by `max_items`
+    if commented_body_element(start, options).is_ok() {
+        return start;
+    }
+    // The element is valid, but is followed by something other than a line ending
+    if let Ok((rest, _)) = body_element_with_options(start, options) {
+        let after: IResult<_, _, u32> = do_parse!(rest, trailing_comments >> space0 >> ());
+        return after.map(|(after, _)| after).unwrap_or(rest);
+    }
+    // The expression of the attribute is invalid
+    if let Ok((rest, _)) = attribute_head(start, options) {
+        return inline_whitespace(rest)
+            .map(|(expression, _)| expression)
+            .unwrap_or(rest);
+    }
+    // Something in the body of the block is invalid
+    if let Ok((rest, _)) = block_head(start, options) {
+        if let Ok((_, nested)) = nested_options(rest, options) {
+            if let Ok((rest, _)) = block_body_with_options(rest, &nested) {
+                return unparsed_token(rest, &nested);
+            }
+        }
+    }
+    start
+}
+
 named_attr!(
     #[doc = r#"Parses a `Body`
 
@@ -109,20 +658,7 @@ Body = (Attribute | Block | OneLineBlock)*;
 ```
 "#],
     pub body(CompleteStr) -> Body,
-    do_parse!(
-        values: whitespace!(
-            many0!(
-                terminated!(
-                    call!(body_element),
-                    alt!(
-                        call!(newline) => { |_| CompleteStr("") }
-                        | eof!()
-                    )
-                )
-            )
-        )
-        >> (values.into_iter().collect())
-    )
+    call!(body_with_options, &ParseOptions::default())
 );
 
 #[cfg(test)]
@@ -131,7 +667,132 @@ mod tests {
 
     use crate::fixtures;
     use crate::parser::expression::Expression;
+    use crate::span::Span;
     use crate::utils::ResultUtilsString;
+    use crate::AsOwned;
+
+    #[test]
+    fn errors_can_be_returned_with_the_question_mark_operator() {
+        fn assert_error<E: std::error::Error + Send + Sync + 'static>(_: &E) {}
+
+        fn parse(s: &str) -> Result<Body, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(Body::parse(s)?)
+        }
+
+        let hcl = "name = \"x\"\nvalue = \"caf\\uD800\"\n";
+        let error = Body::parse(hcl).unwrap_err();
+        assert_error(&error);
+        match error.without_position() {
+            Error::InvalidUnicodeCodePoint(code_point) => assert_eq!(code_point, "uD800"),
+            other => panic!("Unexpected error {:?}", other),
+        }
+        assert_eq!(
+            error.to_string(),
+            "2:14: Escape sequence \\uD800 is not a valid Unicode code point, while parsing a \
+             string, in an attribute"
+        );
+
+        assert_eq!(parse(hcl).unwrap_err().to_string(), error.to_string());
+        assert_eq!(
+            parse("name = \"x\"\n").unwrap(),
+            body(CompleteStr("name = \"x\"\n")).unwrap().1
+        );
+    }
+
+    #[test]
+    fn bodies_must_be_fully_consumed() {
+        let options = ParseOptions::default().max_items(Some(1));
+        let error = Body::parse_with_options("a = 1\nb = 2\n", &options).unwrap_err();
+        assert_eq!(error.position(), Some((2, 1)));
+        match error.without_position() {
+            Error::UnexpectedRemainingInput(remaining) => assert_eq!(remaining, "b = 2"),
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_first_unparsed_token() {
+        let test_cases = [
+            ("foo = 1 garbage", (1, 9), "garbage"),
+            ("foo = 1 # comment\nbar = 2 ]\n", (2, 9), "]"),
+            ("a = 1\nbé = @", (2, 6), "@"),
+            ("a = 1\n\n# comment\n}\nb = 2\n", (4, 1), "}"),
+            ("block {\n  a = 1\n  b = 2 junk\n}\n", (3, 9), "junk"),
+            (
+                "outer \"x\" {\n  inner {\n    c = %{ long long long long long long long }\n  }\n}\n",
+                (3, 9),
+                "%{ long long long long long long",
+            ),
+        ];
+
+        for (input, position, snippet) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let error = Body::parse(input).unwrap_err();
+            assert_eq!(error.position(), Some(*position));
+            match error.without_position() {
+                Error::UnexpectedRemainingInput(remaining) => assert_eq!(remaining, snippet),
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn elements_are_iterated_in_source_order() {
+        let hcl = "b = 1\nblock \"x\" {\n  c = 2\n}\na = 3\nother {}\nz = 4\n";
+        let body = Body::parse(hcl).unwrap();
+        let name = |element: &BodyElement| match element {
+            BodyElement::Attribute(attribute) => attribute.name.to_string(),
+            BodyElement::Block(block) => format!("{} {{}}", block.r#type),
+        };
+
+        let expected = ["b", "block {}", "a", "other {}", "z"];
+        let borrowed: Vec<_> = (&body).into_iter().map(name).collect();
+        assert_eq!(borrowed, expected);
+
+        let owned: Vec<_> = body
+            .clone()
+            .into_iter()
+            .map(|element| name(&element))
+            .collect();
+        assert_eq!(owned, expected);
+
+        // The typed accessors keep the relative order too
+        let attributes: Vec<_> = body.attributes().map(|a| a.name.to_string()).collect();
+        assert_eq!(attributes, ["b", "a", "z"]);
+        let rebuilt: Body = body.clone().into_iter().collect();
+        assert_eq!(rebuilt, body);
+    }
+
+    #[test]
+    fn input_longer_than_the_limit_is_rejected_before_parsing() {
+        let input = "a = [[1]]\n";
+        let options = ParseOptions::default().max_input_bytes(input.len());
+        assert_eq!(Body::parse_with_options(input, &options).unwrap().len(), 1);
+
+        // Even input that would not parse is only checked for its length
+        for input in ["a = [[1]]\n\n", "}}}}}}}}}}}"].iter() {
+            println!("Testing {:?}", input);
+            match Body::parse_with_options(input, &options) {
+                Err(Error::InputTooLarge { length, limit }) => {
+                    assert_eq!((length, limit), (input.len(), 10))
+                }
+                other => panic!("Unexpected result {:?}", other),
+            }
+        }
+        match Expression::parse_with_options("[1, 2, 3, 4]", &options) {
+            Err(Error::InputTooLarge { length: 12, .. }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        // Both limits apply together
+        let options = options.max_depth(2);
+        let error = Body::parse_with_options(input, &options).unwrap_err();
+        match error.without_position() {
+            Error::RecursionLimitExceeded => {}
+            other => panic!("Unexpected error {:?}", other),
+        }
+        assert!(Body::parse("a = [[1]]\n\n").is_ok());
+    }
 
     #[test]
     fn empty_body_is_parsed_correctly() {
@@ -152,6 +813,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn comments_are_attached_to_the_elements_of_the_body() {
+        let options = ParseOptions::default().preserve_comments(true);
+        fn leading(element: &BodyElement) -> Vec<String> {
+            let comments = match element {
+                BodyElement::Attribute(attribute) => &attribute.leading_comments,
+                BodyElement::Block(block) => &block.leading_comments,
+            };
+            comments.iter().map(|comment| comment.to_string()).collect()
+        }
+        fn trailing(element: &BodyElement) -> Option<String> {
+            let comment = match element {
+                BodyElement::Attribute(attribute) => &attribute.trailing_comment,
+                BodyElement::Block(block) => &block.trailing_comment,
+            };
+            comment.as_ref().map(|comment| comment.to_string())
+        }
+
+        let parsed = Body::parse_with_options("a = 1\n# lead b\nb = 2", &options).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert!(leading(&parsed[0]).is_empty());
+        assert_eq!(leading(&parsed[1]), vec!["# lead b"]);
+
+        let parsed =
+            Body::parse_with_options("a {\n}\n# lead c\n\n// c\nc {\n}\n", &options).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(leading(&parsed[1]), vec!["# lead c", "// c"]);
+
+        let parsed = Body::parse_with_options(
+            "a = 1 /* x */\nb = 2 /* y */ # z  \nc {\n  d = 3 # d\n  # end of c\n}\n# end\n",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(trailing(&parsed[0]).unwrap(), "/* x */");
+        assert_eq!(trailing(&parsed[1]).unwrap(), "/* y */ # z");
+        assert_eq!(parsed.trailing_comments, vec!["# end"]);
+
+        let c = parsed[2].clone().unwrap_block();
+        assert_eq!(trailing(&c.body[0]).unwrap(), "# d");
+        assert_eq!(c.body.trailing_comments, vec!["# end of c"]);
+
+        // Comments are ignored when comparing bodies
+        let plain = Body::parse("a = 1 /* x */\nb = 2 /* y */ # z  \nc {\n  d = 3\n}\n").unwrap();
+        assert!(plain.trailing_comments.is_empty());
+        assert_eq!(plain, parsed);
+    }
+
     #[test]
     fn single_attribute_body_is_parsed_correctly() {
         let hcl = fixtures::SINGLE;
@@ -346,4 +1055,400 @@ mod tests {
 
         assert_eq!(parsed, expected);
     }
+
+    #[test]
+    fn sigil_attributes_in_blocks_are_parsed_in_lenient_mode() {
+        let hcl = r#"@schema = "root"
+block {
+    @schema = "nested"
+}
+"#;
+        let options = ParseOptions::default().lenient(true);
+        let parsed = body_with_options(CompleteStr(hcl), &options).unwrap_output();
+        let expected: Body = vec![
+            From::from((From::from("@schema"), From::from("root"))),
+            From::from(Block::new(
+                From::from("block"),
+                vec![],
+                vec![From::from((From::from("@schema"), From::from("nested")))],
+            )),
+        ]
+        .into();
+        assert_eq!(parsed, expected);
+
+        assert!(crate::parser::parse_str(hcl).is_err());
+    }
+
+    #[test]
+    fn custom_identifier_rules_apply_to_names_block_types_and_heredocs() {
+        let hcl = r#"$name = <<$EOF
+text
+$EOF
+$block "label" {
+    $nested = 1
+}
+"#;
+        let options = ParseOptions::default().identifier_char_validator(|c, position| {
+            c == '$' || c == '_' || c.is_alphabetic() || (position > 0 && c.is_numeric())
+        });
+        let parsed = body_with_options(CompleteStr(hcl), &options).unwrap_output();
+        let expected: Body = vec![
+            From::from((From::from("$name"), From::from("text"))),
+            From::from(Block::new(
+                From::from("$block"),
+                vec![BlockLabel::StringLiteral("label".to_string())],
+                vec![From::from((From::from("$nested"), From::from(1)))],
+            )),
+        ]
+        .into();
+        assert_eq!(parsed, expected);
+
+        assert!(crate::parser::parse_str(hcl).is_err());
+    }
+
+    #[test]
+    fn colons_instead_of_equals_are_reported() {
+        let hcl = r#"name = "x"
+block "label" {
+    count : 1
+}
+"#;
+        let error = body(CompleteStr(hcl))
+            .map_err(|e| Error::from_err_str(&e))
+            .unwrap_err();
+        match error {
+            Error::UnexpectedSeparator {
+                expected,
+                found,
+                input,
+            } => {
+                assert_eq!(expected, '=');
+                assert_eq!(found, ':');
+                assert_eq!(input, ": 1");
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+
+        let parsed = body(CompleteStr("object = { count : 1 }\n")).unwrap_output();
+        assert_eq!(parsed.len(), 1);
+    }
+
+    #[test]
+    fn parsing_stops_after_max_items() {
+        let hcl = r#"a = 1
+block {
+    b = 2
+    c = 3
+    d = 4
+}
+
+# Comment
+e = 5
+f = 6
+"#;
+        let options = ParseOptions::default().max_items(2);
+        let (remaining, parsed) = body_with_options(CompleteStr(hcl), &options).unwrap();
+        let expected: Body = vec![
+            From::from((From::from("a"), From::from(1))),
+            From::from(Block::new(
+                From::from("block"),
+                vec![],
+                vec![
+                    From::from((From::from("b"), From::from(2))),
+                    From::from((From::from("c"), From::from(3))),
+                    From::from((From::from("d"), From::from(4))),
+                ],
+            )),
+        ]
+        .into();
+        assert_eq!(parsed, expected);
+        assert_eq!(remaining.0, "e = 5\nf = 6\n");
+
+        let options = ParseOptions::default().max_items(0);
+        let (remaining, parsed) = body_with_options(CompleteStr(hcl), &options).unwrap();
+        assert!(parsed.is_empty());
+        assert_eq!(remaining.0, hcl);
+
+        let options = ParseOptions::default().max_items(10);
+        let (remaining, parsed) = body_with_options(CompleteStr(hcl), &options).unwrap();
+        assert_eq!(parsed.len(), 4);
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn singleton_blocks_must_be_unique() {
+        let hcl = r#"terraform {
+    required_version = "1.0"
+}
+
+provider "aws" {
+}
+
+provider "google" {
+}
+
+terraform {
+    backend = "s3"
+}
+"#;
+        let parsed = body(CompleteStr(hcl)).unwrap_output();
+
+        let error = parsed
+            .validate_unique_blocks(hcl, &["terraform"])
+            .unwrap_err();
+        match error {
+            Error::DuplicateBlock {
+                block_type,
+                first,
+                duplicate,
+            } => {
+                assert_eq!(block_type, "terraform");
+                assert_eq!(first, Some(Span::new(0, 9)));
+                assert_eq!(duplicate, Some(Span::new(87, 96)));
+                assert_eq!(&hcl[87..96], "terraform");
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+
+        assert!(parsed.validate_unique_blocks(hcl, &["provider"]).is_err());
+        assert!(parsed
+            .validate_unique_blocks(hcl, &["required_version", "backend", "other"])
+            .is_ok());
+        assert!(parsed.validate_unique_blocks::<&str>(hcl, &[]).is_ok());
+
+        let owned = parsed.as_owned();
+        match owned.validate_unique_blocks(hcl, &["terraform"]) {
+            Err(Error::DuplicateBlock {
+                first: None,
+                duplicate: None,
+                ..
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn body_is_converted_to_value() {
+        let hcl = r#"
+name = "document"
+variable "a" {
+    default = 1
+}
+provider {
+    region = "x"
+}
+variable "b" {
+    default = [true, null]
+}
+provider {
+    region = "y"
+}
+resource "type" "web" {
+    tags = { "env" = "prod", owner = "me" }
+}
+"#;
+        let parsed = body(CompleteStr(hcl)).unwrap_output();
+        let expected = Value::new_single_map(vec![
+            (Key::new_identifier("name"), Value::from("document")),
+            (
+                Key::new_identifier("variable"),
+                Value::new_single_map(vec![
+                    (
+                        Key::new_string("a"),
+                        Value::new_list(vec![Value::new_single_map(vec![(
+                            Key::new_identifier("default"),
+                            Value::Integer(1),
+                        )])]),
+                    ),
+                    (
+                        Key::new_string("b"),
+                        Value::new_list(vec![Value::new_single_map(vec![(
+                            Key::new_identifier("default"),
+                            Value::new_list(vec![Value::Boolean(true), Value::Null]),
+                        )])]),
+                    ),
+                ]),
+            ),
+            (
+                Key::new_identifier("provider"),
+                Value::new_list(vec![
+                    Value::new_single_map(vec![(Key::new_identifier("region"), Value::from("x"))]),
+                    Value::new_single_map(vec![(Key::new_identifier("region"), Value::from("y"))]),
+                ]),
+            ),
+            (
+                Key::new_identifier("resource"),
+                Value::new_single_map(vec![(
+                    Key::new_string("type"),
+                    Value::new_single_map(vec![(
+                        Key::new_string("web"),
+                        Value::new_list(vec![Value::new_single_map(vec![(
+                            Key::new_identifier("tags"),
+                            Value::new_single_map(vec![
+                                (Key::new_string("env"), Value::from("prod")),
+                                (Key::new_identifier("owner"), Value::from("me")),
+                            ]),
+                        )])]),
+                    )]),
+                )]),
+            ),
+        ]);
+
+        assert_eq!(parsed.to_value().unwrap(), expected);
+    }
+
+    #[test]
+    fn body_with_conflicting_names_cannot_be_converted_to_value() {
+        let hcl = r#"
+provider = 1
+provider {
+    region = "x"
+}
+"#;
+        let parsed = body(CompleteStr(hcl)).unwrap_output();
+        assert!(parsed.to_value().is_err());
+    }
+
+    #[test]
+    fn attributes_and_blocks_are_looked_up() {
+        let hcl = r#"
+name = "first"
+variable "a" {
+    default = 1
+}
+provider {
+}
+variable "b" {
+}
+name = "duplicate"
+"#;
+        let parsed = body(CompleteStr(hcl)).unwrap_output();
+
+        let names: Vec<_> = parsed
+            .attributes()
+            .map(|attribute| attribute.name.as_ref())
+            .collect();
+        assert_eq!(names, ["name", "name"]);
+
+        let variables: Vec<_> = parsed
+            .blocks_of_type("variable")
+            .map(|block| block.labels.clone())
+            .collect();
+        assert_eq!(
+            variables,
+            [
+                vec![BlockLabel::StringLiteral("a".to_string())],
+                vec![BlockLabel::StringLiteral("b".to_string())],
+            ]
+        );
+        assert_eq!(parsed.blocks_of_type("provider").count(), 1);
+        assert_eq!(parsed.blocks_of_type("resource").count(), 0);
+        assert_eq!(parsed.blocks().count(), 3);
+
+        assert_eq!(
+            parsed.get_attribute("name"),
+            Some(&Expression::String(Cow::Borrowed("first")))
+        );
+        assert_eq!(parsed.get_attribute("default"), None);
+        assert_eq!(parsed.get_attribute("variable"), None);
+    }
+
+    #[test]
+    fn blocks_are_merged_by_type() {
+        let hcl = r#"
+resource "aws_instance" "web" {
+    count = 1
+}
+variable "a" {
+}
+resource "aws_instance" "db" {
+}
+name = "document"
+variable "b" {
+}
+resource "aws_eip" "web" {
+}
+"#;
+        let parsed = body(CompleteStr(hcl)).unwrap_output();
+        let merged = parsed.merge_blocks();
+        assert_eq!(
+            merged.keys().collect::<Vec<_>>(),
+            [&"resource", &"variable"]
+        );
+
+        let labels = |block_type: &str| -> Vec<Vec<BlockLabel>> {
+            merged[block_type]
+                .iter()
+                .map(|block| block.labels.clone())
+                .collect()
+        };
+        assert_eq!(
+            labels("resource"),
+            vec![
+                vec!["aws_instance", "web"],
+                vec!["aws_instance", "db"],
+                vec!["aws_eip", "web"],
+            ]
+        );
+        assert_eq!(labels("variable"), vec![vec!["a"], vec!["b"]]);
+        assert!(merged["resource"][0].body.get_attribute("count").is_some());
+    }
+
+    #[test]
+    fn expressions_are_queried_by_path() {
+        let hcl = r#"
+name = "document"
+resource "aws_instance" "web" {
+    ami = "abc"
+    tags = {
+        owner = "me"
+    }
+    network {
+        subnet = "a"
+    }
+}
+provider "aws" {
+    alias = "first"
+}
+provider "aws" {
+    alias = "second"
+    region = "eu"
+}
+"#;
+        let parsed = body(CompleteStr(hcl)).unwrap_output();
+
+        assert_eq!(
+            parsed.query("name"),
+            Some(&Expression::String(Cow::Borrowed("document")))
+        );
+        assert_eq!(
+            parsed.query("resource.aws_instance.web.ami"),
+            Some(&Expression::String(Cow::Borrowed("abc")))
+        );
+        assert_eq!(
+            parsed.query("resource.aws_instance.web.tags.owner"),
+            Some(&Expression::String(Cow::Borrowed("me")))
+        );
+        assert_eq!(
+            parsed.query("resource.aws_instance.web.network.subnet"),
+            Some(&Expression::String(Cow::Borrowed("a")))
+        );
+
+        assert_eq!(parsed.query(""), None);
+        assert_eq!(parsed.query("name.length"), None);
+        assert_eq!(parsed.query("resource.aws_instance.db.ami"), None);
+        assert_eq!(parsed.query("resource.aws_instance.ami"), None);
+        assert_eq!(parsed.query("resource.aws_instance.web.tags.group"), None);
+
+        // Both `provider "aws"` blocks match: the first one is used, unless the rest of the path
+        // only resolves in a later one
+        assert_eq!(
+            parsed.query("provider.aws.alias"),
+            Some(&Expression::String(Cow::Borrowed("first")))
+        );
+        assert_eq!(
+            parsed.query("provider.aws.region"),
+            Some(&Expression::String(Cow::Borrowed("eu")))
+        );
+    }
 }
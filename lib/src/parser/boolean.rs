@@ -1,14 +1,49 @@
 use nom::types::CompleteStr;
-use nom::{alt, named, tag};
+use nom::{alt, call, named_attr, not, tag, tag_no_case, terminated, IResult};
 
-// Parse a boolean literal
-named!(pub boolean(CompleteStr) -> bool,
-    alt!(
-        tag!("true") => {|_| true}
-        | tag!("false") => {|_| false}
+use crate::parser::identifier::is_identifier_continue;
+use crate::parser::options::ParseOptions;
+
+named_attr!(
+    #[doc = r#"Parses the literals `true` and `false` as a `bool`
+
+The keywords are case sensitive and must not be followed by a character that continues an
+identifier, so that identifiers like `trueish` are not parsed as booleans."#],
+    pub boolean(CompleteStr) -> bool,
+    terminated!(
+        alt!(
+            tag!("true") => {|_| true}
+            | tag!("false") => {|_| false}
+        ),
+        not!(call!(crate::utils::while_predicate1, is_identifier_continue))
     )
 );
 
+/// Parses the literals `true` and `false` as a `bool` with the provided [`ParseOptions`]
+///
+/// The keywords may be written in any case if
+/// [`case_insensitive_keywords`](ParseOptions::case_insensitive_keywords) is set.
+pub fn boolean_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, bool, u32> {
+    if !options.case_insensitive_keywords {
+        return boolean(input);
+    }
+
+    terminated!(
+        input,
+        alt!(
+            tag_no_case!("true") => {|_| true}
+            | tag_no_case!("false") => {|_| false}
+        ),
+        not!(call!(
+            crate::utils::while_predicate1,
+            is_identifier_continue
+        ))
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -20,4 +55,47 @@ mod tests {
         assert_eq!(boolean(CompleteStr("true")).unwrap_output(), true);
         assert_eq!(boolean(CompleteStr("false")).unwrap_output(), false);
     }
+
+    #[test]
+    fn boolean_keywords_are_delimited() {
+        assert_eq!(boolean(CompleteStr("true ")), Ok((CompleteStr(" "), true)));
+        assert_eq!(
+            boolean(CompleteStr("false]")),
+            Ok((CompleteStr("]"), false))
+        );
+
+        for input in ["trueish", "true_", "true-1", "false2", "True", "FALSE"].iter() {
+            println!("Testing {}", input);
+            assert!(boolean(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn boolean_keywords_are_case_insensitive_with_options() {
+        let strict = ParseOptions::default();
+        let insensitive = ParseOptions::default().case_insensitive_keywords(true);
+
+        for (input, expected) in [
+            ("true", true),
+            ("True", true),
+            ("TRUE", true),
+            ("fAlSe", false),
+        ]
+        .iter()
+        {
+            println!("Testing {}", input);
+            assert_eq!(
+                boolean_with_options(CompleteStr(input), &insensitive),
+                Ok((CompleteStr(""), *expected))
+            );
+            let strict_result = boolean_with_options(CompleteStr(input), &strict);
+            if input.chars().all(char::is_lowercase) {
+                assert_eq!(strict_result, Ok((CompleteStr(""), *expected)));
+            } else {
+                assert!(strict_result.is_err());
+            }
+        }
+
+        assert!(boolean_with_options(CompleteStr("TrueIsh"), &insensitive).is_err());
+    }
 }
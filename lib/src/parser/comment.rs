@@ -0,0 +1,207 @@
+//! Comments
+//!
+//! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#comments-and-whitespace)
+//!
+//! - _Line comments_ start with either the `//` or `#` sequences and end with the next newline
+//!   sequence.
+//! - _Inline comments_ start with the `/*` sequence and end with the first `*/` sequence. They
+//!   may span multiple lines but do not nest.
+//!
+//! The comment parsers return the text of the comment, without its delimiters.
+use nom::types::CompleteStr;
+use nom::{
+    alt_complete, delimited, is_a, many0, map, named, named_attr, opt, pair, preceded, recognize,
+    space0, tag, take_until, take_while,
+};
+
+fn not_eol(c: char) -> bool {
+    c != '\r' && c != '\n'
+}
+
+named_attr!(
+    #[doc = "Parses a line comment starting with `#`, without consuming the newline ending it"],
+    pub hash_comment(CompleteStr) -> CompleteStr,
+    preceded!(tag!("#"), take_while!(not_eol))
+);
+
+named_attr!(
+    #[doc = "Parses a line comment starting with `//`, without consuming the newline ending it"],
+    pub slash_comment(CompleteStr) -> CompleteStr,
+    preceded!(tag!("//"), take_while!(not_eol))
+);
+
+named_attr!(
+    #[doc = "Parses a line comment starting with `#` or `//`, without consuming the newline \
+             ending it"],
+    pub line_comment(CompleteStr) -> CompleteStr,
+    alt_complete!(hash_comment | slash_comment)
+);
+
+named_attr!(
+    #[doc = r#"Parses an inline comment delimited by `/*` and `*/`
+
+The comment ends at the first `*/`, so comments do not nest. A comment that is not closed is an
+error."#],
+    pub block_comment(CompleteStr) -> CompleteStr,
+    delimited!(tag!("/*"), take_until!("*/"), tag!("*/"))
+);
+
+named_attr!(
+    #[doc = "Parses a comment of any style"],
+    pub comment(CompleteStr) -> CompleteStr,
+    alt_complete!(line_comment | block_comment)
+);
+
+named_attr!(
+    #[doc = r#"Skips any run of spaces, tabs, newlines and comments, returning the text skipped
+
+This never fails, so it can be used between any two tokens."#],
+    pub whitespace_or_comment(CompleteStr) -> CompleteStr,
+    recognize!(many0!(alt_complete!(comment | is_a!(" \t\r\n"))))
+);
+
+named_attr!(
+    #[doc = r#"Like [`whitespace_or_comment`], but returns each comment skipped
+
+The comments include their `#`, `//` or `/* */` delimiters. Trailing whitespace is trimmed from line
+comments."#],
+    pub comments_in_whitespace(CompleteStr) -> Vec<CompleteStr>,
+    map!(
+        many0!(alt_complete!(
+            recognized_line_comment => { Some }
+            | recognize!(block_comment) => { Some }
+            | is_a!(" \t\r\n") => { |_| None }
+        )),
+        |comments| comments.into_iter().flatten().collect()
+    )
+);
+
+// A line comment including its delimiter, with trailing whitespace trimmed
+named!(
+    pub(crate) recognized_line_comment(CompleteStr) -> CompleteStr,
+    map!(recognize!(line_comment), trim_end)
+);
+
+fn trim_end(comment: CompleteStr) -> CompleteStr {
+    CompleteStr(comment.0.trim_end())
+}
+
+// The comments up to the end of the line, including their delimiters, like `/* one */ # two`.
+// Nothing is consumed if there are none.
+named!(
+    pub(crate) trailing_comments(CompleteStr) -> Option<CompleteStr>,
+    map!(
+        recognize!(pair!(
+            many0!(preceded!(space0, block_comment)),
+            opt!(preceded!(space0, line_comment))
+        )),
+        |comments| {
+            let comments = comments.0.trim_matches(&[' ', '\t'][..]);
+            if comments.is_empty() {
+                None
+            } else {
+                Some(CompleteStr(comments))
+            }
+        }
+    )
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_comments_are_parsed_correctly() {
+        let test_cases = [
+            ("# Comment", " Comment", ""),
+            ("// Comment", " Comment", ""),
+            ("# Comment\nfoo", " Comment", "\nfoo"),
+            ("// Comment\r\nfoo", " Comment", "\r\nfoo"),
+            ("#", "", ""),
+            ("// # /* Nested */\n", " # /* Nested */", "\n"),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            assert_eq!(
+                line_comment(CompleteStr(input)),
+                Ok((CompleteStr(expected_remaining), CompleteStr(expected)))
+            );
+        }
+
+        assert!(line_comment(CompleteStr("/* Comment */")).is_err());
+        assert!(line_comment(CompleteStr("/ Comment")).is_err());
+    }
+
+    #[test]
+    fn block_comments_are_parsed_correctly() {
+        let test_cases = [
+            ("/* Comment */", " Comment ", ""),
+            ("/**/foo", "", "foo"),
+            ("/* Multiple\r\nlines\n*/\n", " Multiple\r\nlines\n", "\n"),
+            ("/* Not /* nested */ */", " Not /* nested ", " */"),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            assert_eq!(
+                block_comment(CompleteStr(input)),
+                Ok((CompleteStr(expected_remaining), CompleteStr(expected)))
+            );
+        }
+    }
+
+    #[test]
+    fn unterminated_block_comments_are_errors() {
+        for input in ["/* Comment", "/* Comment *", "/*/"].iter() {
+            println!("Testing {:?}", input);
+            assert!(block_comment(CompleteStr(input)).is_err());
+            assert!(comment(CompleteStr(input)).is_err());
+            assert_eq!(
+                whitespace_or_comment(CompleteStr(input)),
+                Ok((CompleteStr(input), CompleteStr("")))
+            );
+        }
+    }
+
+    #[test]
+    fn whitespace_and_comments_are_skipped() {
+        let test_cases = [
+            ("", ""),
+            ("foo", "foo"),
+            (" \t\r\n foo", "foo"),
+            ("# Comment\n// Comment\r\n/* Comment */ foo", "foo"),
+            ("/* Multiple\nlines */\n\t# Comment", ""),
+            ("foo # Comment", "foo # Comment"),
+        ];
+
+        for (input, expected_remaining) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, skipped) = whitespace_or_comment(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, *expected_remaining);
+            assert_eq!(skipped.len() + remaining.len(), input.len());
+        }
+    }
+
+    #[test]
+    fn comments_in_whitespace_are_returned() {
+        let (remaining, comments) = comments_in_whitespace(CompleteStr(
+            "# One  \n\n  // Two\r\n/* Three\n */ foo # Four",
+        ))
+        .unwrap();
+        assert_eq!(remaining.0, "foo # Four");
+        assert_eq!(
+            comments,
+            vec![
+                CompleteStr("# One"),
+                CompleteStr("// Two"),
+                CompleteStr("/* Three\n */")
+            ]
+        );
+
+        assert_eq!(
+            comments_in_whitespace(CompleteStr(" \n foo")),
+            Ok((CompleteStr("foo"), vec![]))
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Conditional Operator
+//!
+//! The conditional operator allows selecting from one of two expressions based on the outcome
+//! of a boolean expression.
+//!
+//! ```ebnf
+//! Conditional = Expression "?" Expression ":" Expression;
+//! ```
+//!
+//! In lenient mode, the `":" Expression` part may be omitted, in which case the false branch
+//! is `null`.
+//!
+//! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#conditional-operator)
+use nom::types::CompleteStr;
+use nom::{alt_complete, call, char, cond_reduce, do_parse, named, value, IResult};
+
+use super::expression::{expression_with_options, Expression};
+use super::operation::binary_op_with_options;
+use super::options::{nested_options, ParseOptions};
+use super::whitespace::{expression_whitespace, inline_whitespace};
+
+/// A conditional expression `condition ? true_expr : false_expr`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conditional<'a> {
+    pub condition: Expression<'a>,
+    pub true_expr: Expression<'a>,
+    pub false_expr: Expression<'a>,
+}
+
+impl<'a> Conditional<'a> {
+    pub fn new(
+        condition: Expression<'a>,
+        true_expr: Expression<'a>,
+        false_expr: Expression<'a>,
+    ) -> Self {
+        Self {
+            condition,
+            true_expr,
+            false_expr,
+        }
+    }
+}
+
+impl<'a> crate::AsOwned for Conditional<'a> {
+    type Output = Conditional<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        Conditional {
+            condition: self.condition.as_owned(),
+            true_expr: self.true_expr.as_owned(),
+            false_expr: self.false_expr.as_owned(),
+        }
+    }
+}
+
+// "?" Expression ":" Expression
+//
+// Unless newlines are insignificant, like inside parentheses, the branches must start on the same
+// line as the operators so that a conditional never consumes the newline terminating an
+// attribute.
+pub fn conditional_branches_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, (Expression<'a>, Expression<'a>), u32> {
+    let space = |input| expression_whitespace(input, options);
+    do_parse!(
+        input,
+        call!(space)
+        >> char!('?')
+        >> call!(space)
+        >> true_expr: call!(expression_with_options, options)
+        >> false_expr: alt_complete!(
+            do_parse!(
+                call!(space)
+                >> char!(':')
+                >> call!(space)
+                >> false_expr: call!(expression_with_options, options)
+                >> (false_expr)
+            )
+            | cond_reduce!(options.lenient, value!(Expression::Null))
+        )
+        >> call!(inline_whitespace)
+        >> ((true_expr, false_expr))
+    )
+}
+
+/// Parses a conditional expression with the provided [`ParseOptions`]
+///
+/// Unlike [`expression_with_options`], the input must be a conditional. Like in an expression,
+/// the condition may be an operation, like `a > 1 ? 2 : 3`.
+pub fn conditional_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Conditional<'a>, u32> {
+    do_parse!(
+        input,
+        options: call!(nested_options, options)
+        >> condition: call!(binary_op_with_options, &options)
+        >> branches: call!(conditional_branches_with_options, &options)
+        >> (Conditional::new(condition, branches.0, branches.1))
+    )
+}
+
+named!(
+    pub conditional(CompleteStr) -> Conditional,
+    call!(conditional_with_options, &ParseOptions::default())
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::operation::{BinaryOp, BinaryOperator, UnaryOp, UnaryOperator};
+    use crate::utils::ResultUtilsString;
+
+    #[test]
+    fn conditionals_are_parsed_alone() {
+        let variable = |name| Expression::Variable(From::from(name));
+        let test_cases = [
+            (
+                "true ? 1 : 2",
+                Conditional::new(From::from(true), From::from(1), From::from(2)),
+            ),
+            (
+                "a > 1 ? 2 : 3",
+                Conditional::new(
+                    Expression::from(BinaryOp::new(
+                        BinaryOperator::Greater,
+                        variable("a"),
+                        From::from(1),
+                    )),
+                    From::from(2),
+                    From::from(3),
+                ),
+            ),
+            (
+                "!a ? 1 : 2",
+                Conditional::new(
+                    Expression::from(UnaryOp::new(UnaryOperator::Not, variable("a"))),
+                    From::from(1),
+                    From::from(2),
+                ),
+            ),
+            (
+                "a ? b ? c : d : e",
+                Conditional::new(
+                    variable("a"),
+                    Expression::from(Conditional::new(
+                        variable("b"),
+                        variable("c"),
+                        variable("d"),
+                    )),
+                    variable("e"),
+                ),
+            ),
+            (
+                "a ? b : c ? d : e",
+                Conditional::new(
+                    variable("a"),
+                    variable("b"),
+                    Expression::from(Conditional::new(
+                        variable("c"),
+                        variable("d"),
+                        variable("e"),
+                    )),
+                ),
+            ),
+            (
+                "a/* ? */?/* : */{ b : 1 }/* x */:\t[c ? 1 : 2]",
+                Conditional::new(
+                    variable("a"),
+                    Expression::new_object(vec![("b", Expression::from(1))]),
+                    Expression::new_tuple(vec![Expression::from(Conditional::new(
+                        variable("c"),
+                        From::from(1),
+                        From::from(2),
+                    ))]),
+                ),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = ResultUtilsString::unwrap_output(conditional(CompleteStr(input)));
+            assert_eq!(actual, *expected);
+        }
+
+        for input in ["true", "a ?", "a ? 1 :", "a : 1 ? 2"].iter() {
+            println!("Testing {}", input);
+            assert!(conditional(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn conditionals_do_not_collide_with_object_separators() {
+        let variable = |name| Expression::Variable(From::from(name));
+        let test_cases = [
+            (
+                "{ a : b ? 1 : 2 }",
+                Expression::new_object(vec![(
+                    "a",
+                    Expression::from(Conditional::new(
+                        variable("b"),
+                        From::from(1),
+                        From::from(2),
+                    )),
+                )]),
+            ),
+            (
+                "{ a = b ? { c : 1 } : { d = 2 } }",
+                Expression::new_object(vec![(
+                    "a",
+                    Expression::from(Conditional::new(
+                        variable("b"),
+                        Expression::new_object(vec![("c", Expression::from(1))]),
+                        Expression::new_object(vec![("d", Expression::from(2))]),
+                    )),
+                )]),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(Expression::parse(input).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn conditionals_are_parsed_correctly() {
+        let test_cases = [
+            (
+                "true ? 1 : 2",
+                Conditional::new(From::from(true), From::from(1), From::from(2)),
+            ),
+            (
+                r#"false ? "a" : true ? "b" : "c""#,
+                Conditional::new(
+                    From::from(false),
+                    From::from("a"),
+                    Expression::from(Conditional::new(
+                        From::from(true),
+                        From::from("b"),
+                        From::from("c"),
+                    )),
+                ),
+            ),
+            (
+                "(true) ? [1] : null",
+                Conditional::new(
+                    From::from(true),
+                    Expression::new_tuple(vec![From::from(1)]),
+                    Expression::Null,
+                ),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let options = ParseOptions::default();
+            assert_eq!(
+                Expression::parse_with_options(input, &options).unwrap(),
+                Expression::from(expected.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn conditionals_without_false_branch_are_parsed_in_lenient_mode() {
+        let options = ParseOptions::default().lenient(true);
+        assert_eq!(
+            Expression::parse_with_options("true ? 1", &options).unwrap(),
+            Expression::from(Conditional::new(
+                From::from(true),
+                From::from(1),
+                Expression::Null
+            ))
+        );
+        assert_eq!(
+            Expression::parse_with_options("true ? 1 : 2", &options).unwrap(),
+            Expression::from(Conditional::new(
+                From::from(true),
+                From::from(1),
+                From::from(2)
+            ))
+        );
+
+        assert!(Expression::parse("true ? 1").is_err());
+    }
+}
@@ -6,15 +6,31 @@ use std::borrow::Cow;
 use std::iter::FromIterator;
 
 use nom::types::CompleteStr;
-use nom::{alt_complete, call, do_parse, named, tag};
+use nom::{
+    call, char, cond, do_parse, error_position, named, opt, preceded, tag, ErrorKind, IResult,
+};
 
 use crate::constants::*;
-use crate::parser::boolean::boolean;
-use crate::parser::null::null;
-use crate::parser::number::{number, Number};
-use crate::parser::object::{object, Object, ObjectElementIdentifier};
-use crate::parser::string::string;
-use crate::parser::tuple::{tuple, Tuple};
+use crate::parser::boolean::boolean_with_options;
+use crate::parser::conditional::{conditional_branches_with_options, Conditional};
+use crate::parser::for_expr::{for_expr_with_options, ForExpr};
+use crate::parser::function_call::{function_call_with_options, FunctionCall};
+use crate::parser::identifier::{identifier, Identifier};
+use crate::parser::literals::Key;
+use crate::parser::null::null_with_options;
+use crate::parser::number::{number_with_options, Number};
+use crate::parser::object::{object_with_options, Object, ObjectElementIdentifier};
+use crate::parser::operation::{binary_op_with_options, BinaryOp, UnaryOp};
+use crate::parser::options::{nested_options, newlines_ignored, ParseOptions};
+use crate::parser::string::{
+    heredoc_template_parts, template_string_with_options, Directive, TemplatePart,
+};
+use crate::parser::traversal::{
+    traversal_steps_with_options, traverse, Traversal, TraversalOperator,
+};
+use crate::parser::tuple::{tuple_with_options, Tuple};
+use crate::utils::snippet;
+use crate::value::Value;
 use crate::Error;
 
 /// An Expression
@@ -22,8 +38,8 @@ use crate::Error;
 /// ```enbf
 /// Expression = (
 ///     ExprTerm |
-///     Operation |  # Not supported
-///     Conditional # Not supported
+///     Operation |
+///     Conditional
 /// );
 ///
 /// ExprTerm = (
@@ -62,6 +78,20 @@ pub enum Expression<'a> {
     Tuple(Tuple<'a>),
     /// A HCL object (map)
     Object(Object<'a>),
+    /// A conditional expression `condition ? true_expr : false_expr`
+    Conditional(Box<Conditional<'a>>),
+    /// A reference to a variable by its name
+    Variable(Identifier<'a>),
+    /// A traversal of the value of an expression, like `var.foo`
+    Traversal(Box<Traversal<'a>>),
+    /// A unary operation, like `!a`
+    UnaryOp(Box<UnaryOp<'a>>),
+    /// A binary operation, like `a + b`
+    BinaryOp(Box<BinaryOp<'a>>),
+    /// A function call, like `max(a, b)`
+    FunctionCall(Box<FunctionCall<'a>>),
+    /// A for expression, like `[for x in list : x]`
+    ForExpr(Box<ForExpr<'a>>),
 }
 
 impl<'a> Expression<'a> {
@@ -72,9 +102,19 @@ impl<'a> Expression<'a> {
     /// In general, this method should not be used. Prefer to use
     /// [`parse_str`](crate::parser::parse_str) to parse a HCL configuration file instead.
     pub fn parse(s: &'a str) -> Result<Self, Error> {
-        let (remaining, expr) = expression(CompleteStr(s)).map_err(|e| Error::from_err_str(&e))?;;
+        Self::parse_with_options(s, &Default::default())
+    }
+
+    /// Parse a string as a HCL expression with the provided [`ParseOptions`]
+    ///
+    /// The string is expected to be fully consumed during parsing or an eror will be returned.
+    pub fn parse_with_options(s: &'a str, options: &ParseOptions) -> Result<Self, Error> {
+        options.check_input_length(s)?;
+        let (remaining, expr) = expression_with_options(CompleteStr(s), options)
+            .map_err(|e| Error::from_err_str_with_input(&e, s))?;
         if !remaining.is_empty() {
-            return Err(Error::UnexpectedRemainingInput(remaining.to_string()));
+            let token = remaining.trim_start();
+            return Err(Error::UnexpectedRemainingInput(snippet(token)).at_position(s, token));
         }
         Ok(expr)
     }
@@ -97,6 +137,168 @@ impl<'a> Expression<'a> {
         Expression::Object(iterator.into_iter().map(|(k, v)| (k.into(), v)).collect())
     }
 
+    /// Convenient method to create a new Conditional Expression variant
+    pub fn new_conditional(
+        condition: Expression<'a>,
+        true_expr: Expression<'a>,
+        false_expr: Expression<'a>,
+    ) -> Self {
+        Expression::Conditional(Box::new(Conditional::new(condition, true_expr, false_expr)))
+    }
+
+    /// Convert a literal expression into a [`Value`]
+    ///
+    /// Numbers that fit into an `i64` become integers, and all other numbers become floats.
+    /// Object keys that are expressions must be strings, numbers or booleans. Expressions that
+    /// need to be evaluated, like conditionals, cannot be converted.
+    pub fn to_value(&self) -> Result<Value<'a>, Error> {
+        Ok(match self {
+            Expression::Null => Value::Null,
+            Expression::Number(number) => number
+                .as_i64()
+                .map(Value::Integer)
+                .or_else(|_| number.as_f64().map(Value::Float))
+                .map_err(|_| Error::InvalidNumber(number.to_string()))?,
+            Expression::Boolean(boolean) => Value::Boolean(*boolean),
+            Expression::String(string) => Value::String(string.to_string()),
+            Expression::Tuple(tuple) => tuple
+                .iter()
+                .map(Expression::to_value)
+                .collect::<Result<_, _>>()?,
+            Expression::Object(object) => {
+                let map = object
+                    .iter()
+                    .map(|(key, value)| Ok((object_key(key)?, value.to_value()?)))
+                    .collect::<Result<_, Error>>()?;
+                Value::Object(vec![map])
+            }
+            Expression::Conditional(_)
+            | Expression::Variable(_)
+            | Expression::Traversal(_)
+            | Expression::UnaryOp(_)
+            | Expression::BinaryOp(_)
+            | Expression::FunctionCall(_)
+            | Expression::ForExpr(_) => Err(Error::UnexpectedVariant {
+                enum_type: EXPRESSION,
+                expected: "literal value",
+                actual: self.variant_name(),
+            })?,
+        })
+    }
+
+    /// Evaluate an expression made of literals only into a [`Value`]
+    ///
+    /// Literals, and collections, operations and conditionals whose subexpressions are all
+    /// constant, are evaluated. `None` is returned for expressions that need more than that, like
+    /// variables, traversals, function calls and `for` expressions, and for operations that
+    /// cannot be evaluated, like adding a string to a number or dividing by zero.
+    ///
+    /// Strings are only constant if they have no interpolations or directives. The escaped `$${`
+    /// and `%%{` sequences in them become `${` and `%{`.
+    ///
+    /// Integer arithmetic is exact. Results that are fractional or do not fit into an `i64`
+    /// become floats.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::expression::Expression;
+    /// use ferrous_chloride::Value;
+    ///
+    /// let folded = Expression::parse("1 + 2 * 3").unwrap().eval_const();
+    /// assert_eq!(folded, Some(Value::Integer(7)));
+    ///
+    /// assert_eq!(Expression::parse("var.x + 1").unwrap().eval_const(), None);
+    /// assert_eq!(Expression::parse(r#""${var.x}""#).unwrap().eval_const(), None);
+    /// ```
+    pub fn eval_const(&self) -> Option<Value<'a>> {
+        match self {
+            Expression::Null | Expression::Number(_) | Expression::Boolean(_) => {
+                self.to_value().ok()
+            }
+            Expression::String(string) => literal_template(string).map(Value::String),
+            Expression::Tuple(tuple) => tuple.iter().map(Expression::eval_const).collect(),
+            Expression::Object(object) => {
+                let map = object
+                    .iter()
+                    .map(|(key, value)| Some((object_key(key).ok()?, value.eval_const()?)))
+                    .collect::<Option<_>>()?;
+                Some(Value::Object(vec![map]))
+            }
+            Expression::Conditional(conditional) => {
+                let condition = conditional.condition.eval_const()?.as_bool()?;
+                let true_value = conditional.true_expr.eval_const()?;
+                let false_value = conditional.false_expr.eval_const()?;
+                Some(if condition { true_value } else { false_value })
+            }
+            Expression::UnaryOp(op) => op.eval_const(),
+            Expression::BinaryOp(op) => op.eval_const(),
+            Expression::Variable(_)
+            | Expression::Traversal(_)
+            | Expression::FunctionCall(_)
+            | Expression::ForExpr(_) => None,
+        }
+    }
+
+    /// The string, if the expression is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Expression::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// The string borrowed from the parsed input, if the expression is a string that did not need
+    /// to be unescaped
+    ///
+    /// Unlike [`as_str`](Expression::as_str), the string can outlive the expression, since it
+    /// points into the input. Strings with escape sequences and strings that were built rather
+    /// than parsed are owned, and return `None`.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::Expression;
+    ///
+    /// let input = r#""plain""#;
+    /// let string = Expression::parse(input).unwrap().as_borrowed_str().unwrap();
+    /// assert_eq!(string, "plain");
+    ///
+    /// let escaped = Expression::parse(r#""a\tb""#).unwrap();
+    /// assert_eq!(escaped.as_borrowed_str(), None);
+    /// assert_eq!(escaped.as_str(), Some("a\tb"));
+    /// ```
+    pub fn as_borrowed_str(&self) -> Option<&'a str> {
+        match self {
+            Expression::String(Cow::Borrowed(string)) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// The variables referenced by the expression, in the order they appear in the source
+    ///
+    /// Each reference is a traversal of a variable with the steps that can be resolved without
+    /// evaluating other expressions, like `aws_instance.web[0].id`. The steps stop before an
+    /// index by an expression that is not a literal, and the variables referenced by the index are
+    /// returned separately. A variable that is not traversed is returned as a traversal without
+    /// steps.
+    ///
+    /// The interpolations and directives of templates in strings are included, but the variables
+    /// bound by for expressions and `for` directives are not.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::Expression;
+    ///
+    /// let expression = Expression::parse(r#"var.a[local.i] + "${data.b.c}""#).unwrap();
+    /// let references = expression.references();
+    /// assert_eq!(references.len(), 3);
+    /// assert_eq!(references[0].expression, Expression::Variable("var".into()));
+    /// assert_eq!(references[0].steps[0].source, ".a");
+    /// assert_eq!(references[1].expression, Expression::Variable("local".into()));
+    /// assert_eq!(references[2].steps.len(), 2);
+    /// ```
+    pub fn references(&self) -> Vec<Traversal<'_>> {
+        let mut references = vec![];
+        collect_references(self, &[], &mut references, &ParseOptions::default());
+        references
+    }
+
     /// Get the name of the Expression variant as a string.
     pub fn variant_name(&self) -> &'static str {
         match self {
@@ -106,10 +308,210 @@ impl<'a> Expression<'a> {
             Expression::String(_) => STRING,
             Expression::Tuple(_) => TUPLE,
             Expression::Object(_) => OBJECT,
+            Expression::Conditional(_) => CONDITIONAL,
+            Expression::Variable(_) => VARIABLE,
+            Expression::Traversal(_) => TRAVERSAL,
+            Expression::UnaryOp(_) => UNARY_OP,
+            Expression::BinaryOp(_) => BINARY_OP,
+            Expression::FunctionCall(_) => FUNCTION_CALL,
+            Expression::ForExpr(_) => FOR_EXPR,
         }
     }
 }
 
+pub(crate) fn object_key<'a>(key: &ObjectElementIdentifier<'a>) -> Result<Key<'a>, Error> {
+    match key {
+        ObjectElementIdentifier::Identifier(ident) => Ok(Key::Identifier(ident.clone())),
+        ObjectElementIdentifier::Expression(expr) => match Expression::parse(expr)? {
+            Expression::String(string) => Ok(Key::new_string_owned(string.into_owned())),
+            Expression::Number(number) => Ok(Key::new_string_owned(number.to_string())),
+            Expression::Boolean(boolean) => Ok(Key::new_string_owned(boolean.to_string())),
+            other => Err(Error::UnexpectedVariant {
+                enum_type: EXPRESSION,
+                expected: STRING,
+                actual: other.variant_name(),
+            }),
+        },
+    }
+}
+
+// The text of a template without interpolations or directives, with its escape sequences
+// unescaped
+fn literal_template(text: &str) -> Option<String> {
+    let (_, parts) = heredoc_template_parts(CompleteStr(text), &ParseOptions::default()).ok()?;
+    parts
+        .iter()
+        .map(|part| match part {
+            TemplatePart::Literal(literal) => Some(literal.as_ref()),
+            TemplatePart::Interpolation(_) | TemplatePart::Directive(_) => None,
+        })
+        .collect()
+}
+
+// Collect the references of an expression, except the ones to variables in `locals`
+fn collect_references<'a>(
+    expression: &'a Expression,
+    locals: &[&str],
+    references: &mut Vec<Traversal<'a>>,
+    options: &ParseOptions,
+) {
+    let is_local = |name: &str| locals.contains(&name);
+    match expression {
+        Expression::Null | Expression::Number(_) | Expression::Boolean(_) => {}
+        Expression::String(string) => {
+            collect_text_references(string, true, locals, references, options)
+        }
+        Expression::Tuple(tuple) => {
+            for element in tuple {
+                collect_references(element, locals, references, options);
+            }
+        }
+        Expression::Object(object) => {
+            for (key, value) in object {
+                if let ObjectElementIdentifier::Expression(key) = key {
+                    collect_text_references(key, false, locals, references, options);
+                }
+                collect_references(value, locals, references, options);
+            }
+        }
+        Expression::Conditional(conditional) => {
+            collect_references(&conditional.condition, locals, references, options);
+            collect_references(&conditional.true_expr, locals, references, options);
+            collect_references(&conditional.false_expr, locals, references, options);
+        }
+        Expression::Variable(name) => {
+            if !is_local(name) {
+                references.push(Traversal::new(expression.clone(), vec![]));
+            }
+        }
+        Expression::Traversal(traversal) => {
+            match &traversal.expression {
+                Expression::Variable(name) if is_local(name) => {}
+                Expression::Variable(_) => {
+                    let steps = traversal
+                        .steps
+                        .iter()
+                        .take_while(|step| match &step.operator {
+                            TraversalOperator::Index(Expression::Number(_))
+                            | TraversalOperator::Index(Expression::String(_)) => true,
+                            TraversalOperator::Index(_) => false,
+                            _ => true,
+                        })
+                        .cloned()
+                        .collect();
+                    references.push(Traversal::new(traversal.expression.clone(), steps));
+                }
+                other => collect_references(other, locals, references, options),
+            }
+            for step in &traversal.steps {
+                if let TraversalOperator::Index(index) = &step.operator {
+                    collect_references(index, locals, references, options);
+                }
+            }
+        }
+        Expression::UnaryOp(op) => collect_references(&op.operand, locals, references, options),
+        Expression::BinaryOp(op) => {
+            collect_references(&op.lhs, locals, references, options);
+            collect_references(&op.rhs, locals, references, options);
+        }
+        Expression::FunctionCall(call) => {
+            for argument in &call.arguments {
+                collect_references(argument, locals, references, options);
+            }
+        }
+        Expression::ForExpr(for_expr) => {
+            collect_references(&for_expr.collection, locals, references, options);
+
+            let mut locals = locals.to_vec();
+            locals.extend(for_expr.key_variable.as_deref());
+            locals.push(&for_expr.value_variable);
+            let locals = &locals[..];
+            if let Some(key) = &for_expr.key {
+                collect_references(key, locals, references, options);
+            }
+            collect_references(&for_expr.value, locals, references, options);
+            if let Some(condition) = &for_expr.condition {
+                collect_references(condition, locals, references, options);
+            }
+        }
+    }
+}
+
+// Collect the references of unparsed text, either a template or an expression. Text that cannot
+// be parsed has no references.
+//
+// The text is parsed one level deeper than the expression it belongs to, so that the text of
+// templates nested in the text cannot be parsed over and over beyond the maximum depth.
+fn collect_text_references<'a>(
+    text: &str,
+    template: bool,
+    locals: &[&str],
+    references: &mut Vec<Traversal<'a>>,
+    options: &ParseOptions,
+) {
+    use crate::AsOwned;
+
+    let options = match nested_options(CompleteStr(text), options) {
+        Ok((_, options)) => options,
+        Err(_) => return,
+    };
+    let options = &options;
+
+    if !template {
+        if let Ok(expression) = Expression::parse_with_options(text, options) {
+            let mut found = vec![];
+            collect_references(&expression, locals, &mut found, options);
+            for reference in &found {
+                references.push(reference.as_owned());
+            }
+        }
+        return;
+    }
+
+    fn collect_parts<'a>(
+        parts: &[TemplatePart],
+        locals: &[&str],
+        references: &mut Vec<Traversal<'a>>,
+        options: &ParseOptions,
+    ) {
+        for part in parts {
+            match part {
+                TemplatePart::Literal(_) => {}
+                TemplatePart::Interpolation(expression) => {
+                    collect_text_references(expression, false, locals, references, options)
+                }
+                TemplatePart::Directive(Directive::If {
+                    condition,
+                    truthy,
+                    falsy,
+                    ..
+                }) => {
+                    collect_text_references(condition, false, locals, references, options);
+                    collect_parts(truthy, locals, references, options);
+                    collect_parts(falsy, locals, references, options);
+                }
+                TemplatePart::Directive(Directive::For {
+                    key,
+                    value,
+                    collection,
+                    body,
+                    ..
+                }) => {
+                    collect_text_references(collection, false, locals, references, options);
+                    let mut locals = locals.to_vec();
+                    locals.extend(key.as_deref());
+                    locals.push(value);
+                    collect_parts(body, &locals, references, options);
+                }
+            }
+        }
+    }
+
+    if let Ok((_, parts)) = heredoc_template_parts(CompleteStr(text), options) {
+        collect_parts(&parts, locals, references, options);
+    }
+}
+
 impl<'a> crate::AsOwned for Expression<'a> {
     type Output = Expression<'static>;
 
@@ -121,6 +523,17 @@ impl<'a> crate::AsOwned for Expression<'a> {
             Expression::String(string) => Expression::String(Cow::Owned(string.to_string())),
             Expression::Tuple(tup) => Expression::Tuple(tup.as_owned()),
             Expression::Object(obj) => Expression::Object(obj.as_owned()),
+            Expression::Conditional(conditional) => {
+                Expression::Conditional(Box::new(conditional.as_owned()))
+            }
+            Expression::Variable(ident) => Expression::Variable(Cow::Owned(ident.to_string())),
+            Expression::Traversal(traversal) => {
+                Expression::Traversal(Box::new(traversal.as_owned()))
+            }
+            Expression::UnaryOp(op) => Expression::UnaryOp(Box::new(op.as_owned())),
+            Expression::BinaryOp(op) => Expression::BinaryOp(Box::new(op.as_owned())),
+            Expression::FunctionCall(call) => Expression::FunctionCall(Box::new(call.as_owned())),
+            Expression::ForExpr(expr) => Expression::ForExpr(Box::new(expr.as_owned())),
         }
     }
 }
@@ -159,8 +572,45 @@ impl_from_expr_type!(Number, f32);
 impl_from_expr_type!(Number, f64);
 impl_from_expr_type!(Boolean, bool);
 impl_from_expr_type!(String, Cow<'a, str>);
+impl_from_expr_type!(Tuple, Tuple<'a>);
 impl_from_expr_type!(Tuple, Vec<Expression<'a>>);
 
+impl<'a> From<Conditional<'a>> for Expression<'a> {
+    fn from(conditional: Conditional<'a>) -> Self {
+        Expression::Conditional(Box::new(conditional))
+    }
+}
+
+impl<'a> From<ForExpr<'a>> for Expression<'a> {
+    fn from(expr: ForExpr<'a>) -> Self {
+        Expression::ForExpr(Box::new(expr))
+    }
+}
+
+impl<'a> From<FunctionCall<'a>> for Expression<'a> {
+    fn from(call: FunctionCall<'a>) -> Self {
+        Expression::FunctionCall(Box::new(call))
+    }
+}
+
+impl<'a> From<UnaryOp<'a>> for Expression<'a> {
+    fn from(op: UnaryOp<'a>) -> Self {
+        Expression::UnaryOp(Box::new(op))
+    }
+}
+
+impl<'a> From<BinaryOp<'a>> for Expression<'a> {
+    fn from(op: BinaryOp<'a>) -> Self {
+        Expression::BinaryOp(Box::new(op))
+    }
+}
+
+impl<'a> From<Traversal<'a>> for Expression<'a> {
+    fn from(traversal: Traversal<'a>) -> Self {
+        Expression::Traversal(Box::new(traversal))
+    }
+}
+
 impl<'a> From<()> for Expression<'a> {
     fn from(_: ()) -> Self {
         Expression::Null
@@ -174,47 +624,229 @@ impl<'a> From<&'a str> for Expression<'a> {
 }
 
 // "(" Expression ")"
-named!(
-    pub bracket_expression(CompleteStr) -> Expression,
+//
+// In lenient mode, a parenthesized comma separated group like `(a, b)` is parsed as a tuple
+pub fn bracket_expression_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Expression<'a>, u32> {
+    let options = newlines_ignored(options, true);
     do_parse!(
+        input,
         whitespace!(tag!("("))
-        >> expr: whitespace!(call!(expression))
+        >> expr: whitespace!(call!(expression_with_options, &options))
+        >> rest: cond!(
+            options.lenient,
+            opt!(
+                preceded!(
+                    whitespace!(char!(',')),
+                    whitespace!(
+                        separated_list!(
+                            char!(','),
+                            whitespace!(call!(expression_with_options, &options))
+                        )
+                    )
+                )
+            )
+        )
+        >> cond!(options.lenient, whitespace!(opt!(char!(','))))
         >> tag!(")")
-        >> (expr)
+        >> (match rest.and_then(|rest| rest) {
+            None => expr,
+            Some(rest) => std::iter::once(expr).chain(rest).collect(),
+        })
     )
-);
+}
 
 named!(
-    pub expression(CompleteStr) -> Expression,
-    alt_complete!(
+    pub bracket_expression(CompleteStr) -> Expression,
+    call!(bracket_expression_with_options, &ParseOptions::default())
+);
+
+// A parser for one of the kinds of expression terms
+type TermParser =
+    for<'a> fn(CompleteStr<'a>, &ParseOptions) -> IResult<CompleteStr<'a>, Expression<'a>, u32>;
+
+pub fn expression_term_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Expression<'a>, u32> {
+    // The alternatives are tried in a loop rather than with `alt!`, which takes a lot more stack
+    // for each level of nesting in debug builds
+    let alternatives: &[TermParser] = &[
         // LiteralValue -> "null"
-        call!(null) => { |_| Expression::Null }
+        |i, o| null_with_options(i, o).map(|(i, _)| (i, Expression::Null)),
         // LiteralValue -> NumericLit
-        | call!(number) => { |v| From::from(v) }
+        |i, o| number_with_options(i, o).map(|(i, v)| (i, From::from(v))),
         // LiteralValue -> "true" | "false"
-        | call!(boolean) => { |v| From::from(v) }
+        |i, o| boolean_with_options(i, o).map(|(i, v)| (i, From::from(v))),
         // TemplateExpr
         // https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#template-expressions
-        | string => { |v| From::from(v) }
+        |i, o| template_string_with_options(i, o).map(|(i, v)| (i, From::from(v))),
+        // ForExpr
+        |i, o| for_expr_with_options(i, o).map(|(i, v)| (i, From::from(v))),
         // CollectionValue -> tuple
-        | tuple => { |v| From::from(v) }
+        |i, o| tuple_with_options(i, o).map(|(i, v)| (i, From::from(v))),
         // CollectionValue -> object
-        | object => { |obj| Expression::Object(obj) }
-        // VariableExpr
+        |i, o| object_with_options(i, o).map(|(i, v)| (i, Expression::Object(v))),
         // FunctionCall
-        // ForExpr
-        // ExprTerm Index
-        // ExprTerm GetAttr
-        // ExprTerm Splat
+        |i, o| function_call_with_options(i, o).map(|(i, v)| (i, From::from(v))),
+        // VariableExpr
+        |i, _| identifier(i).map(|(i, v)| (i, Expression::Variable(v))),
         // "(" Expression ")"
-        | call!(bracket_expression)
-    )
+        bracket_expression_with_options,
+    ];
+    let mut term = Err(nom::Err::Error(error_position!(input, ErrorKind::Alt)));
+    for alternative in alternatives {
+        term = alternative(input, options);
+        match term {
+            Err(nom::Err::Error(_)) => continue,
+            _ => break,
+        }
+    }
+    let (remaining, term) = term?;
+
+    // ExprTerm GetAttr
+    // ExprTerm Index
+    // ExprTerm Splat
+    let (remaining, steps) = traversal_steps_with_options(remaining, options)?;
+    Ok((remaining, traverse(term, steps)))
+}
+
+named!(
+    pub expression_term(CompleteStr) -> Expression,
+    call!(expression_term_with_options, &ParseOptions::default())
+);
+
+pub fn expression_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Expression<'a>, u32> {
+    let (input, options) = nested_options(input, options)?;
+    let (remaining, term) = binary_op_with_options(input, &options)?;
+    match conditional_branches_with_options(remaining, &options) {
+        Ok((remaining, (true_expr, false_expr))) => Ok((
+            remaining,
+            Expression::new_conditional(term, true_expr, false_expr),
+        )),
+        Err(nom::Err::Error(_)) => Ok((remaining, term)),
+        Err(e) => Err(e),
+    }
+}
+
+named!(
+    pub expression(CompleteStr) -> Expression,
+    call!(expression_with_options, &ParseOptions::default())
 );
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::parser::number::number;
+    use crate::parser::options::DEFAULT_MAX_DEPTH;
+    use crate::AsOwned;
+
+    fn nested(open: &str, inner: &str, close: &str, depth: usize) -> String {
+        format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+    }
+
+    #[test]
+    fn deeply_nested_expressions_exceed_the_recursion_limit() {
+        let test_cases = [
+            nested("[", "1", "]", 100_000),
+            nested("(", "1", ")", 100_000),
+            nested("{a = ", "1", "}", 100_000),
+            nested("f(", "1", ")", 100_000),
+            nested("-", "1", "", 100_000),
+            nested("a ? 1 : ", "1", "", 100_000),
+        ];
+
+        for input in test_cases.iter() {
+            println!("Testing {}...", &input[..20]);
+            match Expression::parse(input).unwrap_err().without_position() {
+                Error::RecursionLimitExceeded => {}
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+
+        // Nesting within the limit is fine
+        let input = nested("[", "1", "]", DEFAULT_MAX_DEPTH - 1);
+        assert!(Expression::parse(&input).is_ok());
+        let input = nested("[", "1", "]", DEFAULT_MAX_DEPTH);
+        assert!(Expression::parse(&input).is_err());
+    }
+
+    #[test]
+    fn deeply_nested_blocks_exceed_the_recursion_limit() {
+        let input = nested("a {\n", "b = 1\n", "}\n", 100_000);
+        match crate::parser::parse_str(&input)
+            .unwrap_err()
+            .without_position()
+        {
+            Error::RecursionLimitExceeded => {}
+            other => panic!("Unexpected error {:?}", other),
+        }
+
+        let input = nested("a {\n", "b = 1\n", "}\n", DEFAULT_MAX_DEPTH - 1);
+        assert!(crate::parser::parse_str(&input).is_ok());
+    }
+
+    #[test]
+    fn deeply_nested_templates_exceed_the_recursion_limit() {
+        let test_cases = [
+            nested("%{ if a }", "x", "%{ endif }", 200_000),
+            nested("%{ for x in y }", "x", "%{ endfor }", 200_000),
+            nested("${\"", "x", "\"}", 200_000),
+        ];
+
+        for template in test_cases.iter() {
+            println!("Testing {}...", &template[..20]);
+            let input = format!("a = \"{}\"\n", template);
+            match crate::parser::parse_str(&input)
+                .unwrap_err()
+                .without_position()
+            {
+                Error::RecursionLimitExceeded => {}
+                other => panic!("Unexpected error {:?}", other),
+            }
+
+            // Templates of strings that were not parsed are not parsed beyond the limit either
+            let expression = Expression::String(Cow::Borrowed(template));
+            assert_eq!(expression.eval_const(), None);
+            assert!(expression.references().is_empty());
+        }
+
+        let template = nested(
+            "%{ if true }",
+            "${\"x\"}",
+            "%{ endif }",
+            DEFAULT_MAX_DEPTH / 2,
+        );
+        let input = format!("\"{}\"", template);
+        assert!(Expression::parse(&input).is_ok());
+    }
+
+    #[test]
+    fn recursion_limit_is_configurable() {
+        let options = ParseOptions::default().max_depth(3);
+        let test_cases = [
+            ("[[1]]", true),
+            ("[[[1]]]", false),
+            ("((1))", true),
+            ("(((1)))", false),
+            ("- -a", true),
+            ("- - -a", false),
+            ("[1, [2], 3]", true),
+        ];
+
+        for (input, ok) in test_cases.iter() {
+            println!("Testing {}", input);
+            let result = Expression::parse_with_options(input, &options);
+            assert_eq!(result.is_ok(), *ok, "{:?}", result);
+        }
+    }
+
     #[test]
     fn bracket_expression_parses_correctly() {
         let test_cases = [
@@ -252,6 +884,16 @@ EOF
     fn expressions_are_parsed_successfully() {
         let test_cases = [
             ("null", Expression::Null, ""),
+            (
+                "nullable",
+                Expression::Variable(Cow::Borrowed("nullable")),
+                "",
+            ),
+            (
+                "trueish",
+                Expression::Variable(Cow::Borrowed("trueish")),
+                "",
+            ),
             (r#"123"#, Expression::from(123), ""),
             ("123", Expression::from(123), ""),
             ("123", Expression::from(123), ""),
@@ -295,4 +937,211 @@ EOF
             assert_eq!(actual_value, *expected_value);
         }
     }
+
+    #[test]
+    fn nested_literal_expressions_are_parsed_and_owned() {
+        let input = r#"{
+    null   = null
+    bool   = [true, false]
+    number = [1, -2.5, 0x1F, 1e3]
+    string = "a ${b} c"
+    nested = [[], {}, [{ deep = [[null, { deeper = "x" }]] }]]
+}"#;
+        let expected = Expression::new_object(vec![
+            ("null", Expression::Null),
+            (
+                "bool",
+                Expression::new_tuple(vec![From::from(true), From::from(false)]),
+            ),
+            (
+                "number",
+                Expression::new_tuple(vec![
+                    From::from(1),
+                    From::from(-2.5),
+                    Expression::Number(number(CompleteStr("0x1F")).unwrap().1),
+                    Expression::Number(number(CompleteStr("1e3")).unwrap().1),
+                ]),
+            ),
+            ("string", From::from("a ${b} c")),
+            (
+                "nested",
+                Expression::new_tuple(vec![
+                    Expression::new_tuple(vec![]),
+                    Expression::new_object(Vec::<(&str, Expression)>::new()),
+                    Expression::new_tuple(vec![Expression::new_object(vec![(
+                        "deep",
+                        Expression::new_tuple(vec![Expression::new_tuple(vec![
+                            Expression::Null,
+                            Expression::new_object(vec![("deeper", From::from("x"))]),
+                        ])]),
+                    )])]),
+                ]),
+            ),
+        ]);
+
+        let parsed = Expression::parse(input).unwrap();
+        assert_eq!(parsed, expected);
+
+        let owned: Expression<'static> = {
+            let input = input.to_string();
+            Expression::parse(&input).unwrap().as_owned()
+        };
+        assert_eq!(owned, expected);
+    }
+
+    #[test]
+    fn parenthesized_groups_are_tuples_in_lenient_mode() {
+        let options = ParseOptions::default().lenient(true);
+        let test_cases = [
+            ("(1)", Expression::from(1)),
+            ("( (1) )", Expression::from(1)),
+            (
+                "(1, 2)",
+                Expression::new_tuple(vec![From::from(1), From::from(2)]),
+            ),
+            (
+                "(1, \"two\",)",
+                Expression::new_tuple(vec![From::from(1), From::from("two")]),
+            ),
+            ("(1,)", Expression::new_tuple(vec![From::from(1)])),
+            (
+                "((1, 2), 3)",
+                Expression::new_tuple(vec![
+                    Expression::new_tuple(vec![From::from(1), From::from(2)]),
+                    From::from(3),
+                ]),
+            ),
+            (
+                "[(1, 2)]",
+                Expression::new_tuple(vec![Expression::new_tuple(vec![
+                    From::from(1),
+                    From::from(2),
+                ])]),
+            ),
+        ];
+
+        for (input, expected_value) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual_value = Expression::parse_with_options(input, &options).unwrap();
+            assert_eq!(actual_value, *expected_value);
+        }
+    }
+
+    #[test]
+    fn parenthesized_tuples_are_rejected_in_strict_mode() {
+        assert_eq!(Expression::parse("(1)").unwrap(), Expression::from(1));
+        assert!(Expression::parse("(1, 2)").is_err());
+        assert!(Expression::parse("(1,)").is_err());
+        assert!(Expression::parse("[(1, 2)]").is_err());
+    }
+
+    #[test]
+    fn strings_without_escapes_are_borrowed_from_the_input() {
+        let input = r#"["plain", "escaped\n", 1]"#;
+        let tuple = match Expression::parse(input).unwrap() {
+            Expression::Tuple(tuple) => tuple,
+            other => panic!("Unexpected expression {:?}", other),
+        };
+
+        let plain = tuple[0].as_borrowed_str().unwrap();
+        assert_eq!(plain.as_ptr(), input[2..].as_ptr());
+        assert_eq!(plain.len(), "plain".len());
+        assert_eq!(tuple[0].as_str(), Some("plain"));
+
+        assert_eq!(tuple[1].as_borrowed_str(), None);
+        assert_eq!(tuple[1].as_str(), Some("escaped\n"));
+
+        assert_eq!(tuple[2].as_borrowed_str(), None);
+        assert_eq!(tuple[2].as_str(), None);
+
+        // The borrow outlives the expression
+        drop(tuple);
+        assert_eq!(plain, "plain");
+
+        let (_, string) = crate::parser::string::string(CompleteStr(&input[1..])).unwrap();
+        match string {
+            Cow::Borrowed(string) => assert_eq!(string.as_ptr(), input[2..].as_ptr()),
+            Cow::Owned(string) => panic!("Expected a borrowed string, got {:?}", string),
+        }
+    }
+
+    #[test]
+    fn references_are_extracted() {
+        let test_cases: &[(&str, &[&str])] = &[
+            ("var.a + var.b", &["var.a", "var.b"]),
+            (r#""${local.x}""#, &["local.x"]),
+            (
+                r#""Hello ${var.name}, %{ if local.ok }!%{ endif }""#,
+                &["var.name", "local.ok"],
+            ),
+            (
+                "max(var.a, aws_instance.web.id, 1)",
+                &["var.a", "aws_instance.web.id"],
+            ),
+            ("count", &["count"]),
+            ("aws_instance.web[0].id", &["aws_instance.web[0].id"]),
+            ("aws_instance.web[*].id", &["aws_instance.web[*].id"]),
+            ("var.map[local.key].id", &["var.map", "local.key"]),
+            ("[var.a][0] ? {a = var.b} : null", &["var.a", "var.b"]),
+            ("{(var.key) = 1}", &["var.key"]),
+            (
+                "[for x in var.list : x.name if x.ok && local.y]",
+                &["var.list", "local.y"],
+            ),
+            ("{for k, v in var.map : k => v}", &["var.map"]),
+            (
+                r#""%{ for x in var.list }${x}${var.y}%{ endfor }""#,
+                &["var.list", "var.y"],
+            ),
+            ("1 + true", &[]),
+            (r#""${join(",", var.b)}""#, &["var.b"]),
+            (r#""${var.a == "" ? "x" : local.y}""#, &["var.a", "local.y"]),
+        ];
+
+        for (input, expected) in test_cases {
+            println!("Testing {}", input);
+            let expression = Expression::parse(input).unwrap();
+            let references: Vec<_> = expression
+                .references()
+                .iter()
+                .map(|reference| {
+                    let name = match &reference.expression {
+                        Expression::Variable(name) => name.to_string(),
+                        other => panic!("Unexpected expression {:?}", other),
+                    };
+                    let steps: String = reference.steps.iter().map(|step| &*step.source).collect();
+                    name + &steps
+                })
+                .collect();
+            assert_eq!(references, *expected);
+        }
+    }
+
+    #[test]
+    fn capitalized_keywords_are_variables_unless_case_insensitive() {
+        let input = "[true, True, TRUE, Null, Trueish]";
+        let variable = |name: &'static str| Expression::Variable(Cow::Borrowed(name));
+
+        let expected = Expression::new_tuple(vec![
+            Expression::Boolean(true),
+            variable("True"),
+            variable("TRUE"),
+            variable("Null"),
+            variable("Trueish"),
+        ]);
+        assert_eq!(Expression::parse(input).unwrap(), expected);
+
+        let options = ParseOptions::default().case_insensitive_keywords(true);
+        let expected = Expression::new_tuple(vec![
+            Expression::Boolean(true),
+            Expression::Boolean(true),
+            Expression::Boolean(true),
+            Expression::Null,
+            variable("Trueish"),
+        ]);
+        assert_eq!(
+            Expression::parse_with_options(input, &options).unwrap(),
+            expected
+        );
+    }
 }
@@ -0,0 +1,413 @@
+//! For Expressions
+//!
+//! ```ebnf
+//! ForExpr = forTupleExpr | forObjectExpr;
+//! forTupleExpr = "[" forIntro Expression forCond? "]";
+//! forObjectExpr = "{" forIntro Expression "=>" Expression "..."? forCond? "}";
+//! forIntro = "for" Identifier ("," Identifier)? "in" Expression ":";
+//! forCond = "if" Expression;
+//! ```
+//!
+//! A for expression builds a tuple or an object from the elements of a collection, like
+//! `[for x in list : x + 1 if x > 0]` or `{for k, v in map : k => v}`. These are distinct from the
+//! `for` directives of templates.
+//!
+//! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#for-expressions)
+use nom::types::CompleteStr;
+use nom::{alt, call, char, do_parse, named, not, opt, preceded, tag, terminated, IResult};
+
+use crate::errors::{in_context, ParseContext};
+use crate::parser::expression::{expression_with_options, Expression};
+use crate::parser::identifier::{identifier, is_identifier_continue, Identifier};
+use crate::parser::options::ParseOptions;
+use crate::parser::whitespace::whitespace;
+
+/// A for expression like `[for x in list : x]` or `{for k, v in map : k => v}`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForExpr<'a> {
+    /// The variable bound to the key or index of each element, like `k` in `for k, v in map`
+    pub key_variable: Option<Identifier<'a>>,
+    /// The variable bound to the value of each element
+    pub value_variable: Identifier<'a>,
+    /// The collection being iterated over
+    pub collection: Expression<'a>,
+    /// The expression producing the key of each element. Only present in the object form.
+    pub key: Option<Expression<'a>>,
+    /// The expression producing the value of each element
+    pub value: Expression<'a>,
+    /// Whether values with the same key are grouped into tuples with `...`. Only valid in the
+    /// object form.
+    pub grouping: bool,
+    /// The condition an element must fulfill to be included, if any
+    pub condition: Option<Expression<'a>>,
+}
+
+impl<'a> ForExpr<'a> {
+    /// Create a for expression producing a tuple
+    pub fn new_tuple<V>(
+        value_variable: V,
+        collection: Expression<'a>,
+        value: Expression<'a>,
+    ) -> Self
+    where
+        V: Into<Identifier<'a>>,
+    {
+        Self {
+            key_variable: None,
+            value_variable: value_variable.into(),
+            collection,
+            key: None,
+            value,
+            grouping: false,
+            condition: None,
+        }
+    }
+
+    /// Create a for expression producing an object
+    pub fn new_object<V>(
+        value_variable: V,
+        collection: Expression<'a>,
+        key: Expression<'a>,
+        value: Expression<'a>,
+    ) -> Self
+    where
+        V: Into<Identifier<'a>>,
+    {
+        Self {
+            key: Some(key),
+            ..Self::new_tuple(value_variable, collection, value)
+        }
+    }
+
+    /// Bind the key or index of each element to a variable
+    pub fn key_variable<K>(mut self, key_variable: K) -> Self
+    where
+        K: Into<Identifier<'a>>,
+    {
+        self.key_variable = Some(key_variable.into());
+        self
+    }
+
+    /// Group values with the same key into tuples
+    pub fn grouping(mut self, grouping: bool) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    /// Only include the elements for which `condition` is true
+    pub fn condition(mut self, condition: Expression<'a>) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Whether the expression produces an object rather than a tuple
+    pub fn is_object(&self) -> bool {
+        self.key.is_some()
+    }
+}
+
+impl<'a> crate::AsOwned for ForExpr<'a> {
+    type Output = ForExpr<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        ForExpr {
+            key_variable: self.key_variable.as_ref().map(|k| k.to_string().into()),
+            value_variable: self.value_variable.to_string().into(),
+            collection: self.collection.as_owned(),
+            key: self.key.as_ref().map(crate::AsOwned::as_owned),
+            value: self.value.as_owned(),
+            grouping: self.grouping,
+            condition: self.condition.as_ref().map(crate::AsOwned::as_owned),
+        }
+    }
+}
+
+// Parses `keyword` when it is not the start of a longer identifier
+fn keyword<'a>(
+    input: CompleteStr<'a>,
+    keyword: &'static str,
+) -> IResult<CompleteStr<'a>, CompleteStr<'a>, u32> {
+    terminated!(
+        input,
+        tag!(keyword),
+        not!(call!(
+            crate::utils::while_predicate1,
+            is_identifier_continue
+        ))
+    )
+}
+
+// Parses `forIntro` into the key variable, the value variable and the collection
+fn for_intro_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, (Option<Identifier<'a>>, Identifier<'a>, Expression<'a>), u32> {
+    do_parse!(
+        input,
+        call!(keyword, "for")
+        >> whitespace
+        >> first: call!(identifier)
+        >> whitespace
+        >> second: opt!(preceded!(terminated!(char!(','), whitespace), call!(identifier)))
+        >> whitespace
+        >> call!(keyword, "in")
+        >> whitespace
+        >> collection: call!(expression_with_options, options)
+        >> whitespace
+        >> char!(':')
+        >> whitespace
+        >> (match second {
+            Some(value) => (Some(first), value, collection),
+            None => (None, first, collection),
+        })
+    )
+}
+
+fn for_condition_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Expression<'a>, u32> {
+    do_parse!(
+        input,
+        call!(keyword, "if")
+        >> whitespace
+        >> condition: call!(expression_with_options, options)
+        >> whitespace
+        >> (condition)
+    )
+}
+
+pub fn for_tuple_expr_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, ForExpr<'a>, u32> {
+    do_parse!(
+        input,
+        char!('[')
+        >> whitespace
+        >> intro: call!(for_intro_with_options, options)
+        >> value: call!(expression_with_options, options)
+        >> whitespace
+        >> condition: opt!(call!(for_condition_with_options, options))
+        >> char!(']')
+        >> ({
+            let (key_variable, value_variable, collection) = intro;
+            ForExpr {
+                key_variable,
+                condition,
+                ..ForExpr::new_tuple(value_variable, collection, value)
+            }
+        })
+    )
+}
+
+pub fn for_object_expr_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, ForExpr<'a>, u32> {
+    do_parse!(
+        input,
+        char!('{')
+        >> whitespace
+        >> intro: call!(for_intro_with_options, options)
+        >> key: call!(expression_with_options, options)
+        >> whitespace
+        >> tag!("=>")
+        >> whitespace
+        >> value: call!(expression_with_options, options)
+        >> whitespace
+        >> grouping: opt!(terminated!(tag!("..."), whitespace))
+        >> condition: opt!(call!(for_condition_with_options, options))
+        >> char!('}')
+        >> ({
+            let (key_variable, value_variable, collection) = intro;
+            ForExpr {
+                key_variable,
+                grouping: grouping.is_some(),
+                condition,
+                ..ForExpr::new_object(value_variable, collection, key, value)
+            }
+        })
+    )
+}
+
+pub fn for_expr_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, ForExpr<'a>, u32> {
+    let result = alt!(
+        input,
+        call!(for_tuple_expr_with_options, options) | call!(for_object_expr_with_options, options)
+    );
+    in_context(input, ParseContext::ForExpression, result)
+}
+
+named!(
+    pub for_expr(CompleteStr) -> ForExpr,
+    call!(for_expr_with_options, &ParseOptions::default())
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::ResultUtilsString;
+
+    fn variable(name: &str) -> Expression {
+        Expression::Variable(From::from(name))
+    }
+
+    #[test]
+    fn tuple_for_expressions_are_parsed_correctly() {
+        let test_cases = [
+            (
+                "[for x in list : x]",
+                ForExpr::new_tuple("x", variable("list"), variable("x")),
+            ),
+            (
+                "[for x in list : x + 1 if x > 0]",
+                ForExpr::new_tuple("x", variable("list"), Expression::parse("x + 1").unwrap())
+                    .condition(Expression::parse("x > 0").unwrap()),
+            ),
+            (
+                "[for i, x in [1, 2]: i]",
+                ForExpr::new_tuple(
+                    "x",
+                    Expression::new_tuple(vec![From::from(1), From::from(2)]),
+                    variable("i"),
+                )
+                .key_variable("i"),
+            ),
+            (
+                "[\n  for x in list:\n    x\n  if x\n]",
+                ForExpr::new_tuple("x", variable("list"), variable("x")).condition(variable("x")),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = for_expr(CompleteStr(input)).unwrap_output();
+            assert_eq!(actual, *expected);
+            assert!(!actual.is_object());
+        }
+    }
+
+    #[test]
+    fn object_for_expressions_are_parsed_correctly() {
+        let test_cases = [
+            (
+                "{for k, v in map : k => v}",
+                ForExpr::new_object("v", variable("map"), variable("k"), variable("v"))
+                    .key_variable("k"),
+            ),
+            (
+                "{for s in list : s => s if s != \"\"}",
+                ForExpr::new_object("s", variable("list"), variable("s"), variable("s"))
+                    .condition(Expression::parse("s != \"\"").unwrap()),
+            ),
+            (
+                "{for k, v in map : v => k...}",
+                ForExpr::new_object("v", variable("map"), variable("v"), variable("k"))
+                    .key_variable("k")
+                    .grouping(true),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = for_expr(CompleteStr(input)).unwrap_output();
+            assert_eq!(actual, *expected);
+            assert!(actual.is_object());
+        }
+    }
+
+    #[test]
+    fn invalid_for_expressions_are_not_accepted() {
+        let test_cases = [
+            "[for x in list]",
+            "[for x list : x]",
+            "[for x in list : k => x]",
+            "[for x in list : x...]",
+            "{for k, v in map : v}",
+            "[forx in list : x]",
+            "[for x inlist : x]",
+            "[for x in list : x iff x]",
+        ];
+
+        for input in test_cases.iter() {
+            println!("Testing {}", input);
+            assert!(for_expr(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn grouping_is_only_recorded_in_object_for_expressions() {
+        let key = Expression::parse("v.k").unwrap();
+        let value = Expression::parse("v.val").unwrap();
+        let test_cases = [
+            ("{for v in list : v.k => v.val...}", true, None),
+            ("{for v in list : v.k => v.val ... }", true, None),
+            (
+                "{for v in list : v.k => v.val... if v.ok}",
+                true,
+                Some("v.ok"),
+            ),
+            ("{for v in list : v.k => v.val}", false, None),
+            (
+                "{for v in list : v.k => v.val if v.ok}",
+                false,
+                Some("v.ok"),
+            ),
+        ];
+
+        for (input, grouping, condition) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = for_expr(CompleteStr(input)).unwrap_output();
+            let mut expected =
+                ForExpr::new_object("v", variable("list"), key.clone(), value.clone())
+                    .grouping(*grouping);
+            if let Some(condition) = condition {
+                expected = expected.condition(Expression::parse(condition).unwrap());
+            }
+            assert_eq!(actual, expected);
+        }
+
+        for input in ["[for v in list : v...]", "[for v in list : v ... if v.ok]"].iter() {
+            println!("Testing {}", input);
+            assert!(for_expr(CompleteStr(input)).is_err());
+        }
+
+        // The `...` after the final argument of a function call expands it instead
+        let call = match Expression::parse("f([for v in list : v]...)").unwrap() {
+            Expression::FunctionCall(call) => call,
+            other => panic!("Unexpected expression {:?}", other),
+        };
+        assert!(call.expand_final);
+        assert_eq!(
+            call.arguments,
+            [Expression::from(ForExpr::new_tuple(
+                "v",
+                variable("list"),
+                variable("v")
+            ))]
+        );
+    }
+
+    #[test]
+    fn for_expressions_are_parsed_in_expressions() {
+        assert_eq!(
+            Expression::parse("[for x in list : x]").unwrap(),
+            Expression::from(ForExpr::new_tuple("x", variable("list"), variable("x")))
+        );
+
+        // Variables that begin with the keywords are still collection elements
+        assert_eq!(
+            Expression::parse("[for_each, format]").unwrap(),
+            Expression::new_tuple(vec![variable("for_each"), variable("format")])
+        );
+        assert_eq!(
+            Expression::parse("{format = 1}").unwrap(),
+            Expression::new_object(vec![("format", Expression::from(1))])
+        );
+    }
+}
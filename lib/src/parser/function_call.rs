@@ -0,0 +1,287 @@
+//! Function Calls
+//!
+//! ```ebnf
+//! FunctionCall = Identifier "(" Arguments ")";
+//! Arguments = (
+//!     () ||
+//!     (Expression ("," Expression)* ("," | "...")?)
+//! );
+//! ```
+//!
+//! The name of the function may be namespaced, like `provider::aws::arn_parse`. The final
+//! argument may be followed by `...` to expand it into separate arguments.
+//!
+//! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#functions-and-function-calls)
+use std::borrow::Cow;
+
+use nom::types::CompleteStr;
+use nom::{
+    call, char, cond, do_parse, many0, map, named, opt, pair, preceded, recognize, tag, IResult,
+};
+
+use crate::errors::{in_context, ParseContext};
+use crate::parser::expression::{expression_with_options, Expression};
+use crate::parser::identifier::{identifier, Identifier};
+use crate::parser::options::{newlines_ignored, ParseOptions};
+
+/// A function call like `max(1, 2)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionCall<'a> {
+    /// The name of the function, including its namespaces
+    pub name: Identifier<'a>,
+    pub arguments: Vec<Expression<'a>>,
+    /// Whether the final argument is expanded into separate arguments with `...`
+    pub expand_final: bool,
+}
+
+impl<'a> FunctionCall<'a> {
+    pub fn new<N>(name: N, arguments: Vec<Expression<'a>>) -> Self
+    where
+        N: Into<Identifier<'a>>,
+    {
+        Self {
+            name: name.into(),
+            arguments,
+            expand_final: false,
+        }
+    }
+
+    /// Mark the final argument as expanded
+    pub fn expand_final(mut self, expand_final: bool) -> Self {
+        self.expand_final = expand_final;
+        self
+    }
+
+    /// The namespaces of the function, if any, like `provider::aws` in `provider::aws::arn_parse`
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::function_call::FunctionCall;
+    ///
+    /// let call = FunctionCall::new("provider::aws::arn_parse", vec![]);
+    /// assert_eq!(call.namespace(), Some("provider::aws"));
+    /// assert_eq!(call.base_name(), "arn_parse");
+    ///
+    /// let call = FunctionCall::new("max", vec![]);
+    /// assert_eq!(call.namespace(), None);
+    /// assert_eq!(call.base_name(), "max");
+    /// ```
+    pub fn namespace(&self) -> Option<&str> {
+        self.name.rfind("::").map(|index| &self.name[..index])
+    }
+
+    /// The name of the function without its namespaces
+    pub fn base_name(&self) -> &str {
+        match self.name.rfind("::") {
+            Some(index) => &self.name[index + 2..],
+            None => &self.name,
+        }
+    }
+
+    /// The namespaces and the name of the function, in order
+    pub fn name_segments(&self) -> Vec<&str> {
+        self.name.split("::").collect()
+    }
+}
+
+impl<'a> crate::AsOwned for FunctionCall<'a> {
+    type Output = FunctionCall<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        FunctionCall {
+            name: Cow::Owned(self.name.to_string()),
+            arguments: self.arguments.as_owned(),
+            expand_final: self.expand_final,
+        }
+    }
+}
+
+named!(
+    pub function_name(CompleteStr) -> Identifier,
+    map!(
+        recognize!(pair!(identifier, many0!(preceded!(tag!("::"), identifier)))),
+        |name| Cow::Borrowed(name.0)
+    )
+);
+
+pub fn function_call_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, FunctionCall<'a>, u32> {
+    let result = do_parse!(
+        input,
+        name: function_name
+        >> char!('(')
+        >> arguments: whitespace!(
+            separated_list!(
+                char!(','),
+                call!(expression_with_options, &newlines_ignored(options, true))
+            )
+        )
+        // Either can only follow an argument
+        >> expand_final: cond!(
+            !arguments.is_empty(),
+            whitespace!(alt!(
+                tag!("...") => { |_| true }
+                | opt!(char!(',')) => { |_| false }
+            ))
+        )
+        >> char!(')')
+        >> (FunctionCall::new(name, arguments).expand_final(expand_final == Some(true)))
+    );
+    in_context(input, ParseContext::FunctionCall, result)
+}
+
+named!(
+    pub function_call(CompleteStr) -> FunctionCall,
+    call!(function_call_with_options, &ParseOptions::default())
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::ResultUtilsString;
+
+    fn variable(name: &str) -> Expression {
+        Expression::Variable(From::from(name))
+    }
+
+    #[test]
+    fn function_calls_are_parsed_correctly() {
+        let test_cases = [
+            ("f()", FunctionCall::new("f", vec![])),
+            ("f( \n )", FunctionCall::new("f", vec![])),
+            (
+                "f(1, 2,)",
+                FunctionCall::new("f", vec![From::from(1), From::from(2)]),
+            ),
+            (
+                "f(a...)",
+                FunctionCall::new("f", vec![variable("a")]).expand_final(true),
+            ),
+            (
+                "f(1, [a, b] ...)",
+                FunctionCall::new(
+                    "f",
+                    vec![
+                        From::from(1),
+                        Expression::new_tuple(vec![variable("a"), variable("b")]),
+                    ],
+                )
+                .expand_final(true),
+            ),
+            (
+                "max(min(1, 2), 3)",
+                FunctionCall::new(
+                    "max",
+                    vec![
+                        Expression::from(FunctionCall::new(
+                            "min",
+                            vec![From::from(1), From::from(2)],
+                        )),
+                        From::from(3),
+                    ],
+                ),
+            ),
+            (
+                "jsonencode({a = 1})",
+                FunctionCall::new(
+                    "jsonencode",
+                    vec![Expression::new_object(vec![("a", Expression::from(1))])],
+                ),
+            ),
+            (
+                r#"provider::aws::arn_parse("arn")"#,
+                FunctionCall::new("provider::aws::arn_parse", vec![From::from("arn")]),
+            ),
+            (
+                "core::max(1)",
+                FunctionCall::new("core::max", vec![From::from(1)]),
+            ),
+            ("a::b::c()", FunctionCall::new("a::b::c", vec![])),
+            (
+                r#"concat(
+    a, # First
+    /* Second */ b,
+)"#,
+                FunctionCall::new("concat", vec![variable("a"), variable("b")]),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = ResultUtilsString::unwrap_output(function_call(CompleteStr(input)));
+            assert_eq!(actual, *expected);
+        }
+    }
+
+    #[test]
+    fn invalid_function_calls_are_not_accepted() {
+        let test_cases = [
+            "f", "f(", "f(,)", "f(...)", "f(a...,)", "f(a,...)", "f(1 2)", "f (1)", "::f()",
+            "f::()", "a::::b()", "a:b()", "a ::b()",
+        ];
+
+        for input in test_cases.iter() {
+            println!("Testing {}", input);
+            let result = function_call(CompleteStr(input));
+            assert!(
+                result.is_err() || !result.unwrap().0.is_empty(),
+                "{} was parsed",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn function_names_are_split_into_namespaces() {
+        let test_cases = [
+            ("max(1)", None, "max", vec!["max"]),
+            ("core::max(1)", Some("core"), "max", vec!["core", "max"]),
+            ("a::b::c()", Some("a::b"), "c", vec!["a", "b", "c"]),
+        ];
+
+        for (input, namespace, base_name, segments) in test_cases.iter() {
+            println!("Testing {}", input);
+            let call = ResultUtilsString::unwrap_output(function_call(CompleteStr(input)));
+            assert_eq!(call.namespace(), *namespace);
+            assert_eq!(call.base_name(), *base_name);
+            assert_eq!(call.name_segments(), *segments);
+        }
+    }
+
+    #[test]
+    fn function_calls_are_parsed_in_expressions() {
+        let call = |name, arguments| Expression::from(FunctionCall::new(name, arguments));
+        let test_cases = [
+            ("f(x)", call("f", vec![variable("x")])),
+            ("f", variable("f")),
+            (
+                "a ? f(1) : g(2)",
+                Expression::new_conditional(
+                    variable("a"),
+                    call("f", vec![From::from(1)]),
+                    call("g", vec![From::from(2)]),
+                ),
+            ),
+            (
+                "max(1, 2) + 1",
+                Expression::parse("(max(1, 2)) + 1").unwrap(),
+            ),
+            ("[f()]", Expression::new_tuple(vec![call("f", vec![])])),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(Expression::parse(input).unwrap(), *expected);
+        }
+
+        // A function call can be traversed
+        match Expression::parse("f(x).y").unwrap() {
+            Expression::Traversal(traversal) => {
+                assert_eq!(traversal.expression, call("f", vec![variable("x")]))
+            }
+            other => panic!("Unexpected expression {:?}", other),
+        }
+    }
+}
@@ -26,31 +26,66 @@
 use std::borrow::Cow;
 
 use nom::types::CompleteStr;
-use nom::{call, do_parse, named_attr, verify};
+use nom::{call, do_parse, error_position, named_attr, peek, verify, ErrorKind, IResult, Slice};
 use unic_ucd_ident::{is_id_continue, is_id_start};
 
+use crate::parser::options::{IdentifierCharValidator, ParseOptions};
+
 pub type Identifier<'a> = Cow<'a, str>;
 
-// Parse an identifier
+/// Whether `c` may appear after the first character of an identifier
+pub(crate) fn is_identifier_continue(c: char) -> bool {
+    is_id_continue(c) || c == '-'
+}
+
 named_attr!(
-    #[allow(clippy::block_in_if_condition_stmt)],
+    #[doc = r#"Parses an identifier
+
+The identifier must start with an `ID_Start` character or an underscore, like HCL's own scanner
+allows, and may be followed by `ID_Continue` characters and dashes."#],
     pub identifier(CompleteStr) -> Identifier,
     do_parse!(
-        identifier: verify!(
-            call!(crate::utils::while_predicate1, |c| is_id_continue(c) || c == '-'),
-            |s: CompleteStr| {
-                let first = s.chars().nth(0);
-                match first {
-                    None => false,
-                    // FIXME: ID_START doesn't allow underscores. But I think HCL does?
-                    Some(c) => is_id_start(c) || c == '_'
-                }
-            }
-        )
+        // Check the first character before scanning, so that a long run of dashes is not scanned
+        // each time an identifier is attempted
+        peek!(verify!(call!(nom::anychar), |c: char| is_id_start(c) || c == '_'))
+        >> identifier: call!(crate::utils::while_predicate1, is_identifier_continue)
         >> (Cow::Borrowed(identifier.0))
     )
 );
 
+/// Parses an identifier using the identifier rule of the options, if any
+pub fn identifier_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Identifier<'a>, u32> {
+    match options.get_identifier_char_validator() {
+        Some(is_valid) => custom_identifier(input, is_valid),
+        None => identifier(input),
+    }
+}
+
+// Parse an identifier made of the characters accepted by a custom rule
+pub(crate) fn custom_identifier<'a>(
+    input: CompleteStr<'a>,
+    is_valid: &IdentifierCharValidator,
+) -> IResult<CompleteStr<'a>, Identifier<'a>, u32> {
+    let end = input
+        .char_indices()
+        .enumerate()
+        .find(|(position, (_, c))| !is_valid(*c, *position))
+        .map(|(_, (index, _))| index)
+        .unwrap_or_else(|| input.len());
+
+    if end == 0 {
+        Err(nom::Err::Error(error_position!(
+            input,
+            ErrorKind::TakeWhile1
+        )))
+    } else {
+        Ok((input.slice(end..), Cow::Borrowed(&input[..end])))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,6 +99,9 @@ mod tests {
             ("abcd_123", "abcd_123"),
             ("abcd-123", "abcd-123"),
             ("_abc", "_abc"),
+            ("_foo", "_foo"),
+            ("foo-bar", "foo-bar"),
+            ("变量", "变量"),
             ("゛藏_a", "゛藏_a"),
         ];
 
@@ -75,11 +113,39 @@ mod tests {
 
     #[test]
     fn incorrect_identifiers_are_not_accepted() {
-        let test_cases = ["1abc", "①_is_some_number"];
+        let test_cases = ["1abc", "-abc", "①_is_some_number"];
 
         for input in test_cases.iter() {
             println!("Testing {}", input);
             assert!(identifier(CompleteStr(input)).is_err());
         }
     }
+
+    #[test]
+    fn identifiers_are_parsed_with_custom_rules() {
+        let options = ParseOptions::default().identifier_char_validator(|c, position| {
+            c == '$' || c == '_' || c.is_alphabetic() || (position > 0 && c.is_numeric())
+        });
+        let test_cases = [
+            ("$abc", "$abc", ""),
+            ("a$b1 = 2", "a$b1", " = 2"),
+            ("abc-def", "abc", "-def"),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) =
+                identifier_with_options(CompleteStr(input), &options).unwrap();
+            assert_eq!(actual, *expected);
+            assert_eq!(remaining.0, *expected_remaining);
+        }
+
+        assert!(identifier_with_options(CompleteStr("1abc"), &options).is_err());
+        assert!(identifier(CompleteStr("$abc")).is_err());
+        assert_eq!(
+            identifier_with_options(CompleteStr("abc-def"), &ParseOptions::default())
+                .unwrap_output(),
+            "abc-def"
+        );
+    }
 }
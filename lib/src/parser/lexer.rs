@@ -0,0 +1,375 @@
+//! Tokens
+//!
+//! [`tokenize`] splits HCL source into a flat sequence of [`Token`]s for consumers that do not need
+//! a syntax tree, like syntax highlighters. The tokens are recognized by the same parsers used to
+//! parse expressions, so a number or a string is a single token exactly when the parser accepts
+//! it.
+//!
+//! Spaces and tabs between tokens are skipped, but newlines are tokens since they end attributes.
+//! Quoted strings are a single token, including their quotes and any template sequences. Heredocs
+//! are split into their opening marker, their lines and their end marker.
+//!
+//! Input that cannot be tokenized is returned as an [`Error`](TokenKind::Error) token, and
+//! tokenizing continues after it. An unterminated string or block comment is an error up to the
+//! end of its line, and an unterminated Heredoc is an error up to the end of the input.
+//!
+//! ```rust
+//! use ferrous_chloride::parser::lexer::{tokenize, TokenKind};
+//!
+//! let kinds: Vec<_> = tokenize("a = [1]\n").map(|token| token.kind).collect();
+//! assert_eq!(
+//!     kinds,
+//!     [
+//!         TokenKind::Identifier,
+//!         TokenKind::Operator,
+//!         TokenKind::Brace,
+//!         TokenKind::Number,
+//!         TokenKind::Brace,
+//!         TokenKind::Newline,
+//!     ]
+//! );
+//! ```
+use std::collections::VecDeque;
+
+use nom::types::CompleteStr;
+
+use crate::parser::comment::comment;
+use crate::parser::identifier::identifier;
+use crate::parser::number::number;
+use crate::parser::options::ParseOptions;
+use crate::parser::string::{heredoc_begin, heredoc_content, quoted_string};
+use crate::span::Span;
+
+/// Operators and punctuation, longest first so that `==` is not taken for two `=`
+const OPERATORS: &[&str] = &[
+    "...", "=>", "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "=",
+    "?", ":", ",", ".",
+];
+
+/// The kind of a [`Token`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// An identifier, including keywords like `true`, `null` or `for`
+    Identifier,
+    /// A number literal, like `1.5e3` or `0xFF`
+    Number,
+    /// A quoted string, including its quotes
+    String,
+    /// The opening marker of a Heredoc, like `<<-EOF`, and the newline after it
+    HeredocStart,
+    /// The lines of a Heredoc, including the newline in front of the end marker. Empty Heredocs
+    /// have no body.
+    HeredocBody,
+    /// The end marker of a Heredoc, including its indentation
+    HeredocEnd,
+    /// An operator or punctuation, like `+`, `=>`, `=` or `,`
+    Operator,
+    /// One of `{`, `}`, `[`, `]`, `(` or `)`
+    Brace,
+    /// A comment, including its delimiters. Line comments do not include the newline ending them.
+    Comment,
+    /// A `\n` or `\r\n` line ending
+    Newline,
+    /// Input that is not a valid token
+    Error,
+}
+
+/// A token of HCL source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// The text of the token in the source
+    pub text: &'a str,
+    /// The location of the token in the source
+    pub span: Span,
+}
+
+/// Iterator over the tokens of HCL source, returned by [`tokenize`]
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+    input: &'a str,
+    position: usize,
+    // Tokens recognized together, like the parts of a Heredoc, waiting to be returned
+    pending: VecDeque<Token<'a>>,
+}
+
+/// Split `input` into tokens
+pub fn tokenize(input: &str) -> Tokens<'_> {
+    Tokens {
+        input,
+        position: 0,
+        pending: VecDeque::new(),
+    }
+}
+
+impl<'a> Tokens<'a> {
+    fn token(&self, kind: TokenKind, start: usize, end: usize) -> Token<'a> {
+        Token {
+            kind,
+            text: &self.input[start..end],
+            span: Span::new(start, end),
+        }
+    }
+
+    // The length of the input recognized by `parser` at the start of `rest`
+    fn recognize<'r, O, P>(rest: &'r str, parser: P) -> Option<usize>
+    where
+        P: Fn(CompleteStr<'r>) -> nom::IResult<CompleteStr<'r>, O, u32>,
+    {
+        parser(CompleteStr(rest))
+            .ok()
+            .map(|(remaining, _)| rest.len() - remaining.len())
+    }
+
+    // The length of `rest` up to the end of its first line, excluding the line ending
+    fn line_length(rest: &str) -> usize {
+        rest.find(&['\r', '\n'][..]).unwrap_or(rest.len())
+    }
+
+    // Queue the parts of the Heredoc at the start of `rest`
+    fn heredoc(&mut self, rest: &'a str) -> Option<usize> {
+        let options = ParseOptions::default();
+        let (after_begin, _) = heredoc_begin(CompleteStr(rest)).ok()?;
+        let (after_line_ending, _) = nom::eol(after_begin).ok()?;
+        let (remaining, (lines, _)) = match heredoc_content(CompleteStr(rest), &options) {
+            Ok(content) => content,
+            Err(_) => {
+                let token = self.token(TokenKind::Error, self.position, self.input.len());
+                self.pending.push_back(token);
+                return Some(rest.len());
+            }
+        };
+
+        let start = self.position;
+        let body = start + rest.len() - after_line_ending.len();
+        let end = body + lines.len();
+        let length = rest.len() - remaining.len();
+        self.pending
+            .push_back(self.token(TokenKind::HeredocStart, start, body));
+        if !lines.is_empty() {
+            self.pending
+                .push_back(self.token(TokenKind::HeredocBody, body, end));
+        }
+        self.pending
+            .push_back(self.token(TokenKind::HeredocEnd, end, start + length));
+        Some(length)
+    }
+
+    // The kind and length of the token at the start of `rest`
+    fn next_token(&mut self, rest: &'a str) -> (TokenKind, usize) {
+        let options = ParseOptions::default();
+        let first = rest.chars().next().expect("rest to not be empty");
+
+        if rest.starts_with("\r\n") {
+            (TokenKind::Newline, 2)
+        } else if first == '\n' {
+            (TokenKind::Newline, 1)
+        } else if let Some(length) = Self::recognize(rest, comment) {
+            (TokenKind::Comment, length)
+        } else if rest.starts_with("/*") {
+            (TokenKind::Error, Self::line_length(rest))
+        } else if first == '"' {
+            match Self::recognize(rest, |input| quoted_string(input, &options)) {
+                Some(length) => (TokenKind::String, length),
+                None => (TokenKind::Error, Self::line_length(rest)),
+            }
+        } else if first.is_ascii_digit() {
+            match Self::recognize(rest, number) {
+                Some(length) => (TokenKind::Number, length),
+                None => (TokenKind::Error, first.len_utf8()),
+            }
+        } else if let Some(length) = Self::recognize(rest, identifier) {
+            (TokenKind::Identifier, length)
+        } else if "{}[]()".contains(first) {
+            (TokenKind::Brace, 1)
+        } else if let Some(operator) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            (TokenKind::Operator, operator.len())
+        } else {
+            (TokenKind::Error, first.len_utf8())
+        }
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+
+        let input = self.input;
+        let rest = &input[self.position..];
+        let rest = rest.trim_start_matches(&[' ', '\t'][..]);
+        self.position = input.len() - rest.len();
+        if rest.is_empty() {
+            return None;
+        }
+
+        if rest.starts_with("<<") {
+            if let Some(length) = self.heredoc(rest) {
+                self.position += length;
+                return self.pending.pop_front();
+            }
+        }
+
+        let (kind, length) = self.next_token(rest);
+        let token = self.token(kind, self.position, self.position + length);
+        self.position += length;
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(input)
+            .map(|token| {
+                assert_eq!(&input[token.span.start..token.span.end], token.text);
+                (token.kind, token.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn snippets_are_tokenized() {
+        use TokenKind::*;
+
+        let hcl = r#"# Comment
+resource "aws" web {
+  count = var.n >= 2 ? 1.5e3 : -0x1F /* inline */
+  tags = {for k, v in local.tags : k => v...}
+  text = <<-EOF
+    hello
+    EOF
+}
+"#;
+        let expected = [
+            (Comment, "# Comment"),
+            (Newline, "\n"),
+            (Identifier, "resource"),
+            (String, r#""aws""#),
+            (Identifier, "web"),
+            (Brace, "{"),
+            (Newline, "\n"),
+            (Identifier, "count"),
+            (Operator, "="),
+            (Identifier, "var"),
+            (Operator, "."),
+            (Identifier, "n"),
+            (Operator, ">="),
+            (Number, "2"),
+            (Operator, "?"),
+            (Number, "1.5e3"),
+            (Operator, ":"),
+            (Operator, "-"),
+            (Number, "0x1F"),
+            (Comment, "/* inline */"),
+            (Newline, "\n"),
+            (Identifier, "tags"),
+            (Operator, "="),
+            (Brace, "{"),
+            (Identifier, "for"),
+            (Identifier, "k"),
+            (Operator, ","),
+            (Identifier, "v"),
+            (Identifier, "in"),
+            (Identifier, "local"),
+            (Operator, "."),
+            (Identifier, "tags"),
+            (Operator, ":"),
+            (Identifier, "k"),
+            (Operator, "=>"),
+            (Identifier, "v"),
+            (Operator, "..."),
+            (Brace, "}"),
+            (Newline, "\n"),
+            (Identifier, "text"),
+            (Operator, "="),
+            (HeredocStart, "<<-EOF\n"),
+            (HeredocBody, "    hello\n"),
+            (HeredocEnd, "    EOF"),
+            (Newline, "\n"),
+            (Brace, "}"),
+            (Newline, "\n"),
+        ];
+        assert_eq!(tokens(hcl), expected);
+
+        let spans: Vec<_> = tokenize("a = \"b\"\r\n").map(|token| token.span).collect();
+        assert_eq!(
+            spans,
+            [
+                Span::new(0, 1),
+                Span::new(2, 3),
+                Span::new(4, 7),
+                Span::new(7, 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_heredocs_have_no_body() {
+        use TokenKind::*;
+
+        assert_eq!(
+            tokens("<<EOF\nEOF\n"),
+            [
+                (HeredocStart, "<<EOF\n"),
+                (HeredocEnd, "EOF"),
+                (Newline, "\n")
+            ]
+        );
+        assert_eq!(
+            tokens("<<EOF\r\na\r\nb\r\nEOF\r\n"),
+            [
+                (HeredocStart, "<<EOF\r\n"),
+                (HeredocBody, "a\r\nb\r\n"),
+                (HeredocEnd, "EOF"),
+                (Newline, "\r\n")
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_are_tokens() {
+        use TokenKind::*;
+
+        assert_eq!(
+            tokens("a = \"unterminated\nb = 1 @ 2"),
+            [
+                (Identifier, "a"),
+                (Operator, "="),
+                (Error, "\"unterminated"),
+                (Newline, "\n"),
+                (Identifier, "b"),
+                (Operator, "="),
+                (Number, "1"),
+                (Error, "@"),
+                (Number, "2"),
+            ]
+        );
+        assert_eq!(
+            tokens("x /* open\ny"),
+            [
+                (Identifier, "x"),
+                (Error, "/* open"),
+                (Newline, "\n"),
+                (Identifier, "y")
+            ]
+        );
+        assert_eq!(
+            tokens("a = <<EOF\nno end\n"),
+            [
+                (Identifier, "a"),
+                (Operator, "="),
+                (Error, "<<EOF\nno end\n")
+            ]
+        );
+        assert_eq!(
+            tokens("1 < 2"),
+            [(Number, "1"), (Operator, "<"), (Number, "2")]
+        );
+    }
+}
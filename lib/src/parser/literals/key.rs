@@ -9,7 +9,10 @@ use nom::{alt_complete, call, named};
 pub use self::serde::*;
 
 /// A "key" in a map
-#[derive(Eq, PartialEq, Debug, Clone)]
+///
+/// Keys are compared and hashed by their names, so a quoted key like `"a"` is the same key as the
+/// identifier `a`. The variant only records how the key was written.
+#[derive(Eq, Debug, Clone)]
 pub enum Key<'a> {
     Identifier(Cow<'a, str>),
     String(Cow<'a, str>),
@@ -88,6 +91,12 @@ impl<'a> Borrow<str> for Key<'a> {
     }
 }
 
+impl<'a> PartialEq for Key<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
 impl<'a> Hash for Key<'a> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.deref().hash(state);
@@ -99,7 +108,7 @@ named!(
     pub key(CompleteStr) -> Key,
     alt_complete!(
         call!(crate::parser::identifier::identifier) => { |s| Key::Identifier(s) }
-        | crate::parser::string::string_literal => { |s| Key::String(Cow::Owned(s)) }
+        | crate::parser::string::single_line_string => { |s| Key::String(Cow::Owned(s)) }
     )
 );
 
@@ -1,16 +1,46 @@
 use nom::types::CompleteStr;
-use nom::{do_parse, named_attr, tag};
+use nom::{call, do_parse, named_attr, not, tag, tag_no_case, IResult};
+
+use crate::parser::identifier::is_identifier_continue;
+use crate::parser::options::ParseOptions;
 
 named_attr!(
     #[doc = r#"Parses the literal `null` as
-              [`()`](https://doc.rust-lang.org/std/primitive.unit.html)"#],
+              [`()`](https://doc.rust-lang.org/std/primitive.unit.html)
+
+The keyword is case sensitive and must not be followed by a character that continues an
+identifier, so that identifiers like `nullable` are not parsed as `null`."#],
     pub null(CompleteStr) -> (),
     do_parse!(
         tag!("null")
+        >> not!(call!(crate::utils::while_predicate1, is_identifier_continue))
         >> (())
     )
 );
 
+/// Parses the literal `null` with the provided [`ParseOptions`]
+///
+/// The keyword may be written in any case if
+/// [`case_insensitive_keywords`](ParseOptions::case_insensitive_keywords) is set.
+pub fn null_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, (), u32> {
+    if !options.case_insensitive_keywords {
+        return null(input);
+    }
+
+    do_parse!(
+        input,
+        tag_no_case!("null")
+            >> not!(call!(
+                crate::utils::while_predicate1,
+                is_identifier_continue
+            ))
+            >> (())
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -20,6 +50,36 @@ mod tests {
         null(CompleteStr("null")).unwrap_output();
     }
 
+    #[test]
+    fn null_keyword_is_delimited() {
+        assert_eq!(null(CompleteStr("null ")), Ok((CompleteStr(" "), ())));
+        assert_eq!(null(CompleteStr("null,")), Ok((CompleteStr(","), ())));
+
+        for input in [
+            "nullx", "nullable", "null_", "null-1", "null1", "NULL", "Null",
+        ]
+        .iter()
+        {
+            println!("Testing {}", input);
+            assert!(null(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn null_keyword_is_case_insensitive_with_options() {
+        let insensitive = ParseOptions::default().case_insensitive_keywords(true);
+        for input in ["null", "Null", "NULL"].iter() {
+            println!("Testing {}", input);
+            assert_eq!(
+                null_with_options(CompleteStr(input), &insensitive),
+                Ok((CompleteStr(""), ()))
+            );
+            let strict = null_with_options(CompleteStr(input), &ParseOptions::default());
+            assert_eq!(strict.is_ok(), *input == "null");
+        }
+        assert!(null_with_options(CompleteStr("Nullable"), &insensitive).is_err());
+    }
+
     #[test]
     fn errors_for_non_null() {
         use nom;
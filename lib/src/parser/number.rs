@@ -1,16 +1,27 @@
 //! Number
 
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU128, NonZeroU16,
+    NonZeroU32, NonZeroU64, NonZeroU8,
+};
 use std::ops::Deref;
 use std::str::FromStr;
 
 use nom::types::CompleteStr;
 use nom::IResult;
-use nom::{alt, char, digit, opt, pair, tuple};
+use nom::{
+    alt, call, char, digit, do_parse, error_position, one_of, opt, pair, take_while, tuple,
+    ErrorKind,
+};
 
+use crate::errors::{in_context, InternalKind, ParseContext};
+use crate::parser::options::ParseOptions;
 use crate::AsOwned;
 
-#[derive(Debug, Clone, Eq, Hash)]
+#[derive(Debug, Clone, Eq)]
 pub struct Number<'a> {
     /// The original input number literal
     input: Cow<'a, str>,
@@ -22,6 +33,9 @@ pub struct Number<'a> {
     fraction: Option<Cow<'a, str>>,
     /// Whether an exponent is present
     exponent: Option<Exponent<'a>>,
+    /// The radix of the number, like `16` for `0xff`. The whole number part holds the digits
+    /// without the prefix.
+    radix: u32,
 }
 
 impl<'a> Number<'a> {
@@ -38,6 +52,7 @@ impl<'a> Number<'a> {
             whole,
             fraction,
             exponent,
+            radix: 10,
         }
     }
 
@@ -51,9 +66,229 @@ impl<'a> Number<'a> {
         self.is_signed() && self.positive
     }
 
-    /// Is a float
+    /// Is an integer, written without a decimal point or an exponent
+    ///
+    /// `-0` and `0xff` are integers, but `1e3` and `1.0` are not even though their values are
+    /// integral.
+    pub fn is_integer(&self) -> bool {
+        self.is_signed()
+    }
+
+    /// Is a float, written with a decimal point or an exponent
     pub fn is_float(&self) -> bool {
-        !self.is_signed()
+        !self.is_integer()
+    }
+
+    /// The number as it was written, including its sign and any leading or trailing zeros
+    ///
+    /// This is the text that printers write, so that numbers are not reformatted. It is the same
+    /// as dereferencing the number. Numbers converted from Rust numbers are written as Rust
+    /// formats them. Underscores separating digits, if they are
+    /// [allowed](crate::parser::ParseOptions::allow_underscore_separators), are removed when
+    /// parsing and are not part of the text, so `1_000` is `1000`.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::number::Number;
+    ///
+    /// let number: Number = "+1.50E3".parse().unwrap();
+    /// assert_eq!(number.as_raw_str(), "+1.50E3");
+    /// assert_eq!(Number::from(-2).as_raw_str(), "-2");
+    /// ```
+    pub fn as_raw_str(&self) -> &str {
+        &self.input
+    }
+
+    /// The radix the number was written in: `16` for `0x`, `8` for `0o`, `2` for `0b` and `10`
+    /// otherwise
+    pub fn radix(&self) -> u32 {
+        self.radix
+    }
+
+    /// The digits of an integer written with a radix prefix, with the sign but without the prefix
+    fn radix_digits(&self) -> String {
+        let digits = self.whole.as_ref().map(AsRef::as_ref).unwrap_or_default();
+        if self.positive {
+            digits.to_string()
+        } else {
+            format!("-{}", digits)
+        }
+    }
+
+    /// The value of an integer written with a radix prefix as a float
+    fn radix_float(&self) -> f64 {
+        let radix = f64::from(self.radix);
+        let value = self
+            .whole
+            .iter()
+            .flat_map(|digits| digits.chars())
+            .filter_map(|c| c.to_digit(self.radix))
+            .fold(0.0, |value, digit| value * radix + f64::from(digit));
+        if self.positive {
+            value
+        } else {
+            -value
+        }
+    }
+
+    /// Is zero, like `0`, `-0`, `0.0` or `0e5`
+    ///
+    /// The digits of the number are checked without converting it, so a zero with any exponent
+    /// is detected.
+    pub fn is_zero(&self) -> bool {
+        self.whole
+            .iter()
+            .chain(self.fraction.iter())
+            .all(|digits| digits.chars().all(|c| c == '0'))
+    }
+
+    /// The sign of the number: `0` if the number is zero, `1` if it is positive and `-1` if it
+    /// is negative
+    ///
+    /// Negative zero like `-0.0` has a sign of `0`.
+    pub fn signum(&self) -> i8 {
+        if self.is_zero() {
+            0
+        } else if self.positive {
+            1
+        } else {
+            -1
+        }
+    }
+
+    /// Is less than zero
+    ///
+    /// Negative zero like `-0.0` is not negative. Use
+    /// [`is_sign_negative`](Number::is_sign_negative) to check how the number was written.
+    pub fn is_negative(&self) -> bool {
+        self.signum() < 0
+    }
+
+    /// Is greater than zero
+    pub fn is_positive(&self) -> bool {
+        self.signum() > 0
+    }
+
+    /// Was written with a `-` sign, including negative zero like `-0`
+    pub fn is_sign_negative(&self) -> bool {
+        !self.positive
+    }
+
+    /// Bound the number to the inclusive range `[min, max]`
+    ///
+    /// The numbers are compared by their exact values, like [`Ord`] compares them. If the number
+    /// is within the range, it is returned unchanged with its original representation.
+    /// Otherwise, the bound that was exceeded is returned. Unlike [`Ord::clamp`], which panics,
+    /// an error is returned if `min` is greater than `max`.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::number::Number;
+    ///
+    /// let number = |s: &str| s.parse::<Number>().unwrap();
+    /// let clamped = number("0.10000000000000001").checked_clamp(number("0"), number("0.1"));
+    /// assert_eq!(clamped.unwrap().as_raw_str(), "0.1");
+    /// assert!(number("1").checked_clamp(number("1"), number("0")).is_err());
+    /// ```
+    pub fn checked_clamp(
+        self,
+        min: Number<'a>,
+        max: Number<'a>,
+    ) -> Result<Number<'a>, crate::Error> {
+        if min > max {
+            return Err(crate::Error::InvalidRange {
+                min: min.to_string(),
+                max: max.to_string(),
+            });
+        }
+
+        if self < min {
+            Ok(min)
+        } else if self > max {
+            Ok(max)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// The number written in a canonical form
+    ///
+    /// Numbers that are written differently but have the same value and kind are normalized
+    /// to the same text:
+    ///
+    /// - An explicit `+` and the sign of zero are removed
+    /// - Leading zeros of the whole number part are removed, and a missing whole number part is
+    ///   written as `0`
+    /// - Trailing zeros of the fraction are removed, but at least one digit is kept so that the
+    ///   number stays a float
+    /// - The exponent marker is lowercased, and an explicit `+` and leading zeros of the exponent
+    ///   are removed
+    /// - The radix prefix and digits of integers written in another radix are lowercased
+    ///
+    /// Integers stay integers and floats stay floats, so `1.0` is not normalized to `1`. Use
+    /// `==` to compare numbers by value regardless of how they are written.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::number::number;
+    /// use ferrous_chloride::nom::types::CompleteStr;
+    ///
+    /// let (_, number) = number(CompleteStr("+007.50E+01")).unwrap();
+    /// assert_eq!(&*number.normalized(), "7.5e1");
+    /// ```
+    pub fn normalized(&self) -> Number<'static> {
+        fn trim_zeros<'s>(digits: &'s str, trim: fn(&'s str, char) -> &'s str) -> String {
+            match trim(digits, '0') {
+                "" => "0".to_string(),
+                trimmed => trimmed.to_ascii_lowercase(),
+            }
+        }
+
+        let whole = trim_zeros(
+            self.whole.as_ref().map(AsRef::as_ref).unwrap_or_default(),
+            str::trim_start_matches,
+        );
+        let fraction = self
+            .fraction
+            .as_ref()
+            .map(|fraction| trim_zeros(fraction, str::trim_end_matches));
+        let exponent = self.exponent.as_ref().map(|exponent| {
+            let digits = trim_zeros(&exponent.exponent, str::trim_start_matches);
+            Exponent {
+                positive: exponent.positive || digits == "0",
+                exponent: Cow::Owned(digits),
+            }
+        });
+        let positive = self.positive || self.is_zero();
+
+        let mut input = String::new();
+        if !positive {
+            input.push('-');
+        }
+        match self.radix {
+            16 => input.push_str("0x"),
+            8 => input.push_str("0o"),
+            2 => input.push_str("0b"),
+            _ => {}
+        }
+        input.push_str(&whole);
+        if let Some(ref fraction) = fraction {
+            input.push('.');
+            input.push_str(fraction);
+        }
+        if let Some(ref exponent) = exponent {
+            input.push('e');
+            if !exponent.positive {
+                input.push('-');
+            }
+            input.push_str(&exponent.exponent);
+        }
+
+        Number {
+            input: Cow::Owned(input),
+            positive,
+            whole: Some(Cow::Owned(whole)),
+            fraction: fraction.map(Cow::Owned),
+            exponent,
+            radix: self.radix,
+        }
     }
 }
 
@@ -88,15 +323,11 @@ macro_rules! from_float {
     ($($from:ty )*) => {$(
         impl<'a> From<$from> for Number<'a> {
             fn from(n: $from) -> Self {
-                let string = if n >= 0.0 {
-                    n.to_string()
-                } else {
-                    (n*-1.0).to_string()
-                };
+                let string = n.abs().to_string();
                 let mut parts = string.split(".");
                 let whole = parts.next().map(|s| Cow::Owned(s.to_string()));
                 let fraction = parts.next().map(|s| Cow::Owned(s.to_string()));
-                Self::new(Cow::Owned(n.to_string()), n >= 0.0, whole, fraction, None)
+                Self::new(Cow::Owned(n.to_string()), n.is_sign_positive(), whole, fraction, None)
             }
         }
     )*};
@@ -104,20 +335,57 @@ macro_rules! from_float {
 
 from_float!(f32 f64);
 
-macro_rules! to_number {
+macro_rules! to_integer {
+    ($($name:ident => $to:ty, )*) => {$(
+        to_integer!($name => $to => stringify!(Attempt conversion to $to));
+    )*};
+    ($name:ident => $to:ty => $doc:expr) => {
+        #[doc=$doc]
+        pub fn $name(&self) -> Result<$to, <$to as FromStr>::Err> {
+            match self.radix {
+                10 => self.input.as_ref().parse(),
+                radix => <$to>::from_str_radix(&self.radix_digits(), radix),
+            }
+        }
+    };
+}
+
+macro_rules! to_nonzero {
+    ($($name:ident => $to:ty => $integer:ty, )*) => {$(
+        to_nonzero!(
+            $name => $to => $integer => stringify!(Attempt conversion to $to, failing if zero)
+        );
+    )*};
+    ($name:ident => $to:ty => $integer:ty => $doc:expr) => {
+        #[doc=$doc]
+        pub fn $name(&self) -> Result<$to, <$to as FromStr>::Err> {
+            match self.radix {
+                10 => self.input.as_ref().parse(),
+                radix => <$integer>::from_str_radix(&self.radix_digits(), radix)?
+                    .to_string()
+                    .parse(),
+            }
+        }
+    };
+}
+
+macro_rules! to_float {
     ($($name:ident => $to:ty, )*) => {$(
-        to_number!($name => $to => stringify!(Attempt conversion to $to));
+        to_float!($name => $to => stringify!(Attempt conversion to $to));
     )*};
     ($name:ident => $to:ty => $doc:expr) => {
         #[doc=$doc]
         pub fn $name(&self) -> Result<$to, <$to as FromStr>::Err> {
-            self.input.as_ref().parse()
+            match self.radix {
+                10 => self.input.as_ref().parse(),
+                _ => Ok(self.radix_float() as $to),
+            }
         }
     };
 }
 
 impl<'a> Number<'a> {
-    to_number!(
+    to_integer!(
         as_u8 => u8,
         as_u16 => u16,
         as_u32 => u32,
@@ -128,15 +396,98 @@ impl<'a> Number<'a> {
         as_i32 => i32,
         as_i64 => i64,
         as_i128 => i128,
+    );
+    to_nonzero!(
+        as_nonzero_u8 => NonZeroU8 => u8,
+        as_nonzero_u16 => NonZeroU16 => u16,
+        as_nonzero_u32 => NonZeroU32 => u32,
+        as_nonzero_u64 => NonZeroU64 => u64,
+        as_nonzero_u128 => NonZeroU128 => u128,
+        as_nonzero_i8 => NonZeroI8 => i8,
+        as_nonzero_i16 => NonZeroI16 => i16,
+        as_nonzero_i32 => NonZeroI32 => i32,
+        as_nonzero_i64 => NonZeroI64 => i64,
+        as_nonzero_i128 => NonZeroI128 => i128,
+    );
+    to_float!(
         as_f32 => f32,
         as_f64 => f64,
     );
+
+    /// Convert the number into a `f64`, returning an error if the conversion is not exact
+    ///
+    /// The conversion is exact when formatting the `f64` gives back a number with the same value,
+    /// so `0.1` is converted exactly even though its binary value is only the nearest float.
+    /// Integers beyond 2<sup>53</sup>, numbers with more significant digits than a `f64` holds
+    /// and numbers outside of the range of `f64` are reported with
+    /// [`Error::LossyConversion`](crate::Error::LossyConversion), which has the nearest `f64`.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::number::Number;
+    ///
+    /// let exact: Number = "0.5".parse().unwrap();
+    /// assert_eq!(exact.to_f64_checked().unwrap(), 0.5);
+    ///
+    /// let lossy: Number = "9007199254740993".parse().unwrap();
+    /// assert!(lossy.to_f64_checked().is_err());
+    /// ```
+    pub fn to_f64_checked(&self) -> Result<f64, crate::Error> {
+        let value = self
+            .as_f64()
+            .map_err(|_| crate::Error::InvalidNumber(self.input.to_string()))?;
+
+        let formatted = format!("{:e}", value);
+        let exact = value.is_finite()
+            && match number(CompleteStr(&formatted)) {
+                Ok((_, round_tripped)) => round_tripped == *self,
+                Err(_) => false,
+            };
+        if exact {
+            Ok(value)
+        } else {
+            Err(crate::Error::LossyConversion {
+                number: self.input.to_string(),
+                value,
+            })
+        }
+    }
+
+    /// The value of an integer as the widest signed integer type
+    ///
+    /// Returns `None` for floats, even if their value is integral, and for integers that do not
+    /// fit in an `i128`.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::number::Number;
+    ///
+    /// assert_eq!("-0x10".parse::<Number>().unwrap().as_int(), Some(-16));
+    /// assert_eq!("1.0".parse::<Number>().unwrap().as_int(), None);
+    /// ```
+    pub fn as_int(&self) -> Option<i128> {
+        if self.is_integer() {
+            self.as_i128().ok()
+        } else {
+            None
+        }
+    }
+
+    /// The value of an integer as the widest unsigned integer type
+    ///
+    /// Returns `None` for floats, even if their value is integral, for negative integers, including
+    /// `-0`, and for integers that do not fit in an `u128`.
+    pub fn as_uint(&self) -> Option<u128> {
+        if self.is_integer() {
+            self.as_u128().ok()
+        } else {
+            None
+        }
+    }
 }
 
 impl<'a> Deref for Number<'a> {
     type Target = str;
     fn deref(&self) -> &Self::Target {
-        self.input.as_ref()
+        self.as_raw_str()
     }
 }
 
@@ -150,54 +501,529 @@ impl<'a> AsOwned for Number<'a> {
             whole: self.whole.as_ref().map(|s| Cow::Owned(s.to_string())),
             fraction: self.fraction.as_ref().map(|s| Cow::Owned(s.to_string())),
             exponent: self.exponent.as_owned(),
+            radix: self.radix,
+        }
+    }
+}
+
+/// Parses a number literal, like `12.34` or `-1e5`
+///
+/// Unlike the `From` implementations, the input is validated: the whole string must be a single
+/// number literal without any surrounding whitespace.
+impl<'a> FromStr for Number<'a> {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (remaining, number) =
+            number(CompleteStr(s)).map_err(|e| crate::Error::from_err_str_with_input(&e, s))?;
+        if !remaining.is_empty() {
+            return Err(
+                crate::Error::UnexpectedRemainingInput(crate::utils::snippet(&remaining))
+                    .at_position(s, &remaining),
+            );
         }
+        Ok(number.as_owned())
     }
 }
 
+/// Numbers are equal if they have the same exact value
+///
+/// The text of the numbers is not compared, so `1`, `1.0`, `10e-1` and `0x1` are all equal. Floats
+/// are not converted to `f64`, so `0.1` and `0.10000000000000002` are different numbers.
 impl<'a> std::cmp::PartialEq for Number<'a> {
     fn eq(&self, other: &Self) -> bool {
-        if self.is_float() || other.is_float() {
-            // Good enough comparison
-            // From https://users.rust-lang.org/t/assert-eq-for-float-numbers/7034/4
-            let us = self.as_f64();
-            let them = other.as_f64();
-            if us.is_err() || them.is_err() {
-                println!("Error converting to float!");
-                return false;
-            }
-
-            let us = us.unwrap().abs();
-            let them = them.unwrap().abs();
-            let diff = (us - them).abs();
-
-            if us == them {
-                // Handle infinities.
-                true
-            } else if us == 0.0 || them == 0.0 || diff < std::f64::MIN_POSITIVE {
-                // One of a or b is zero (or both are extremely close to it,) use absolute error.
-                diff < (std::f64::EPSILON * std::f64::MIN_POSITIVE)
-            } else {
-                // Use relative error.
-                (diff / f64::min(us + them, std::f64::MAX)) < std::f64::EPSILON
-            }
-        } else if self.is_unsigned() && other.is_unsigned() {
-            // Unsigned
-            let us = self.as_u128();
-            let them = other.as_u128();
-            if us.is_err() || them.is_err() {
-                println!("Error converting to unsigned!");
-                return false;
-            }
-            us.unwrap().eq(&them.unwrap())
+        self.exact() == other.exact()
+    }
+}
+
+/// A number is equal to a string holding a number literal with the same value
+///
+/// Like comparing two numbers, the values are compared rather than the text, so `1.0` is equal to
+/// `"1"` and to `"0x1"`. Strings that are not a single number literal are never equal.
+///
+/// ```rust
+/// use ferrous_chloride::parser::number::Number;
+///
+/// let number = Number::from(12345);
+/// assert_eq!(number, "12345");
+/// assert_eq!(number, *"1.2345e4");
+/// assert_ne!(number, "twelve");
+/// ```
+impl<'a> PartialEq<str> for Number<'a> {
+    fn eq(&self, other: &str) -> bool {
+        match number(CompleteStr(other)) {
+            Ok((remaining, other)) => remaining.is_empty() && *self == other,
+            Err(_) => false,
+        }
+    }
+}
+
+impl<'a, 'b> PartialEq<&'b str> for Number<'a> {
+    fn eq(&self, other: &&'b str) -> bool {
+        *self == **other
+    }
+}
+
+/// Numbers are ordered by their exact values, consistently with [`PartialEq`]
+///
+/// Numbers converted from floats that are not finite are ordered like `f64::total_cmp` orders
+/// them: negative infinity is below and infinity above every finite number, and NaN is above
+/// infinity and equal to itself.
+impl<'a> Ord for Number<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.exact().cmp(&other.exact())
+    }
+}
+
+impl<'a> PartialOrd for Number<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Numbers are hashed by their exact values, consistently with [`PartialEq`]
+///
+/// Numbers that are equal, like `1` and `1.0`, have the same hash, so they are the same key of a
+/// `HashMap`.
+///
+/// ```rust
+/// use std::collections::HashSet;
+///
+/// use ferrous_chloride::parser::number::Number;
+///
+/// let set: HashSet<Number> = vec![Number::from(1), "1.0".parse().unwrap(), "0x1".parse().unwrap()]
+///     .into_iter()
+///     .collect();
+/// assert_eq!(set.len(), 1);
+/// ```
+impl<'a> std::hash::Hash for Number<'a> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.exact().hash(state)
+    }
+}
+
+impl<'a> Number<'a> {
+    /// The exact value of the number, which is compared without allocating unless the number has
+    /// an exponent too large for an `i128`
+    fn exact(&self) -> Exact<'_> {
+        if self.radix == 10 {
+            match self.whole.as_ref().map(AsRef::as_ref) {
+                Some("inf") if self.positive => return Exact::Infinity,
+                Some("inf") => return Exact::NegativeInfinity,
+                Some("NaN") => return Exact::NaN,
+                _ => {}
+            }
+        }
+
+        let whole = self.whole.as_ref().map(AsRef::as_ref).unwrap_or_default();
+        let fraction = self
+            .fraction
+            .as_ref()
+            .map(AsRef::as_ref)
+            .unwrap_or_default();
+        let digits = if self.radix == 10 {
+            Digits::Decimal(whole, fraction)
+        } else {
+            self.radix_to_decimal()
+        };
+        let (digits, offset) = digits.significant();
+        if digits.is_empty() {
+            return Exact::Finite(Finite {
+                negative: false,
+                digits,
+                magnitude: Magnitude::Small(0),
+            });
+        }
+
+        let magnitude = match self.exponent {
+            Some(ref exponent) if self.radix == 10 => {
+                Magnitude::new(!exponent.positive, &exponent.exponent, offset)
+            }
+            _ => Magnitude::Small(offset),
+        };
+        Exact::Finite(Finite {
+            negative: !self.positive,
+            digits,
+            magnitude,
+        })
+    }
+
+    /// The decimal digits of an integer written with a radix prefix
+    fn radix_to_decimal(&self) -> Digits<'_> {
+        let digits = self
+            .whole
+            .iter()
+            .flat_map(|digits| digits.chars())
+            .filter_map(|c| c.to_digit(self.radix));
+
+        let mut value: Option<u128> = Some(0);
+        for digit in digits.clone() {
+            value = value
+                .and_then(|value| value.checked_mul(u128::from(self.radix)))
+                .and_then(|value| value.checked_add(u128::from(digit)));
+        }
+        if let Some(mut value) = value {
+            let mut buffer = [b'0'; 39];
+            let mut start = buffer.len();
+            while value > 0 {
+                start -= 1;
+                buffer[start] = b'0' + (value % 10) as u8;
+                value /= 10;
+            }
+            return Digits::Buffer(buffer, start, buffer.len());
+        }
+
+        // Little endian decimal digits
+        let mut decimal: Vec<u32> = vec![];
+        for digit in digits {
+            let mut carry = digit;
+            for decimal_digit in decimal.iter_mut() {
+                let value = *decimal_digit * self.radix + carry;
+                *decimal_digit = value % 10;
+                carry = value / 10;
+            }
+            while carry > 0 {
+                decimal.push(carry % 10);
+                carry /= 10;
+            }
+        }
+        Digits::Long(
+            decimal
+                .iter()
+                .rev()
+                .filter_map(|digit| std::char::from_digit(*digit, 10))
+                .collect(),
+        )
+    }
+}
+
+/// The exact value of a number
+///
+/// Floats that are not finite can only come from converting a Rust float. They are ordered below
+/// and above all the finite numbers, and NaN is equal to itself and above infinity, like
+/// `f64::total_cmp` orders them, so that numbers can be `Eq` and `Ord`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Exact<'n> {
+    NegativeInfinity,
+    Finite(Finite<'n>),
+    Infinity,
+    NaN,
+}
+
+/// A finite number as `0.digits × 10^magnitude`
+///
+/// The digits have no leading or trailing zeros, so numbers with the same value, like `1`, `1.0`,
+/// `10e-1` and `0x1`, have the same representation. Zero has no digits and is not negative.
+#[derive(Debug)]
+struct Finite<'n> {
+    negative: bool,
+    digits: Digits<'n>,
+    magnitude: Magnitude,
+}
+
+impl<'n> Finite<'n> {
+    fn signum(&self) -> i8 {
+        match (self.negative, self.digits.is_empty()) {
+            (_, true) => 0,
+            (true, false) => -1,
+            (false, false) => 1,
+        }
+    }
+}
+
+impl<'n> PartialEq for Finite<'n> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'n> Eq for Finite<'n> {}
+
+impl<'n> Ord for Finite<'n> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.signum().cmp(&other.signum()) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+
+        // The position of the most significant digit decides first, and then the digits, which
+        // have no trailing zeros, can be compared as strings
+        let ordering = self
+            .magnitude
+            .cmp(&other.magnitude)
+            .then_with(|| self.digits.iter().cmp(other.digits.iter()));
+        if self.negative {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl<'n> PartialOrd for Finite<'n> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'n> std::hash::Hash for Finite<'n> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.signum().hash(state);
+        self.magnitude.hash(state);
+        for digit in self.digits.iter() {
+            digit.hash(state);
+        }
+    }
+}
+
+/// The decimal digits of a number, borrowed from the number where possible
+#[derive(Debug)]
+enum Digits<'n> {
+    /// The digits of the whole number part, followed by the digits of the fraction
+    Decimal(&'n str, &'n str),
+    /// The digits of an integer written in another radix that fits in an `u128`, between two
+    /// indices of the buffer
+    Buffer([u8; 39], usize, usize),
+    /// The digits of an integer written in another radix that does not fit in an `u128`
+    Long(String),
+}
+
+impl<'n> Digits<'n> {
+    fn parts(&self) -> (&str, &str) {
+        match self {
+            Digits::Decimal(whole, fraction) => (whole, fraction),
+            Digits::Buffer(buffer, start, end) => (
+                std::str::from_utf8(&buffer[*start..*end]).unwrap_or_default(),
+                "",
+            ),
+            Digits::Long(digits) => (digits, ""),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        let (whole, fraction) = self.parts();
+        whole.bytes().chain(fraction.bytes())
+    }
+
+    fn is_empty(&self) -> bool {
+        let (whole, fraction) = self.parts();
+        whole.is_empty() && fraction.is_empty()
+    }
+
+    /// The digits without leading and trailing zeros, and the power of ten of the digits once
+    /// the leading zeros are removed, like `2` for `012.3` or `-1` for `0.0123`
+    fn significant(self) -> (Digits<'n>, i128) {
+        let (whole, fraction) = self.parts();
+        let whole_start = whole.len() - whole.trim_start_matches('0').len();
+        let (fraction_start, offset) = if whole_start == whole.len() {
+            let leading = fraction.len() - fraction.trim_start_matches('0').len();
+            (leading, -(leading as i128))
+        } else {
+            (0, (whole.len() - whole_start) as i128)
+        };
+        let fraction_end = fraction_start + fraction[fraction_start..].trim_end_matches('0').len();
+        let whole_end = if fraction_end == fraction_start {
+            whole_start + whole[whole_start..].trim_end_matches('0').len()
+        } else {
+            whole.len()
+        };
+
+        let digits = match self {
+            Digits::Decimal(whole, fraction) => Digits::Decimal(
+                &whole[whole_start..whole_end],
+                &fraction[fraction_start..fraction_end],
+            ),
+            Digits::Buffer(buffer, start, _) => {
+                Digits::Buffer(buffer, start + whole_start, start + whole_end)
+            }
+            Digits::Long(mut digits) => {
+                digits.truncate(whole_end);
+                digits.drain(..whole_start);
+                Digits::Long(digits)
+            }
+        };
+        (digits, offset)
+    }
+}
+
+/// The power of ten of the most significant digit of a number
+///
+/// Magnitudes are only allocated for exponents that do not fit in an `i128`, so that comparing
+/// numbers does not allocate otherwise.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Magnitude {
+    Small(i128),
+    /// Whether the magnitude is negative, and its decimal digit values without leading zeros
+    Big(bool, Vec<u8>),
+}
+
+impl Magnitude {
+    /// The sum of an exponent and a power of ten of the digits
+    fn new(negative: bool, exponent: &str, offset: i128) -> Self {
+        let exponent = exponent.trim_start_matches('0');
+        if exponent.len() <= 36 {
+            let value = exponent.bytes().fold(0, |value: i128, digit| {
+                value * 10 + i128::from(digit - b'0')
+            });
+            return Magnitude::Small(if negative { -value } else { value } + offset);
+        }
+
+        // The offset is bounded by the length of the number and cannot change the sign of such a
+        // large exponent, only its absolute value
+        let mut digits: Vec<u8> = exponent.bytes().map(|digit| digit - b'0').collect();
+        let mut carry = if negative { -offset } else { offset };
+        for digit in digits.iter_mut().rev() {
+            if carry == 0 {
+                break;
+            }
+            let value = i128::from(*digit) + carry;
+            *digit = value.rem_euclid(10) as u8;
+            carry = value.div_euclid(10);
+        }
+        while carry > 0 {
+            digits.insert(0, (carry % 10) as u8);
+            carry /= 10;
+        }
+        let leading = digits.iter().take_while(|digit| **digit == 0).count();
+        digits.drain(..leading);
+
+        // Magnitudes that fit in an `i128` are always small, so that they have a single
+        // representation
+        let value = digits.iter().try_fold(0i128, |value, digit| {
+            value.checked_mul(10)?.checked_add(i128::from(*digit))
+        });
+        match value {
+            Some(value) => Magnitude::Small(if negative { -value } else { value }),
+            None => Magnitude::Big(negative, digits),
+        }
+    }
+}
+
+impl Ord for Magnitude {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let absolute =
+            |us: &[u8], them: &[u8]| us.len().cmp(&them.len()).then_with(|| us.cmp(them));
+        match (self, other) {
+            (Magnitude::Small(us), Magnitude::Small(them)) => us.cmp(them),
+            (Magnitude::Small(_), Magnitude::Big(negative, _)) => {
+                if *negative {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                }
+            }
+            (Magnitude::Big(negative, _), Magnitude::Small(_)) => {
+                if *negative {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+            (Magnitude::Big(us_negative, us), Magnitude::Big(them_negative, them)) => {
+                match (us_negative, them_negative) {
+                    (false, false) => absolute(us, them),
+                    (true, true) => absolute(them, us),
+                    (false, true) => Ordering::Greater,
+                    (true, false) => Ordering::Less,
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for Magnitude {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl<'a> Number<'a> {
+    fn as_big_decimal(&self) -> Result<bigdecimal::BigDecimal, crate::Error> {
+        let invalid = || crate::Error::InvalidNumber(self.input.to_string());
+        match self.radix {
+            10 => self.input.parse().map_err(|_| invalid()),
+            _ => self
+                .as_i128()
+                .map_err(|_| invalid())?
+                .to_string()
+                .parse()
+                .map_err(|_| invalid()),
+        }
+    }
+
+    fn from_big_decimal(decimal: &bigdecimal::BigDecimal) -> Result<Number<'static>, crate::Error> {
+        let string = decimal.to_string();
+        string
+            .parse()
+            .map_err(|_| crate::Error::InvalidNumber(string))
+    }
+
+    /// Add two numbers exactly using their decimal representation
+    ///
+    /// The scale of the result is the larger of the scales of the two numbers. Returns an error
+    /// if either of the numbers is not finite, or if the result cannot be written as a number
+    /// literal.
+    pub fn checked_add(&self, other: &Number) -> Result<Number<'static>, crate::Error> {
+        Self::from_big_decimal(&(self.as_big_decimal()? + other.as_big_decimal()?))
+    }
+
+    /// Subtract `other` from this number exactly using their decimal representation
+    ///
+    /// The scale of the result is the larger of the scales of the two numbers. Returns an error
+    /// if either of the numbers is not finite, or if the result cannot be written as a number
+    /// literal.
+    pub fn checked_sub(&self, other: &Number) -> Result<Number<'static>, crate::Error> {
+        Self::from_big_decimal(&(self.as_big_decimal()? - other.as_big_decimal()?))
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl<'a> Number<'a> {
+    /// Convert the number into an exact [`rust_decimal::Decimal`]
+    ///
+    /// The number is converted from its digits, so no precision is lost through a float. Returns
+    /// an error if the number is outside the range or precision that `Decimal` can represent,
+    /// including numbers with more significant digits than a `Decimal` holds, which would
+    /// otherwise be rounded.
+    pub fn as_decimal(&self) -> Result<rust_decimal::Decimal, crate::Error> {
+        let invalid = || crate::Error::InvalidNumber(self.input.to_string());
+        let decimal = self.as_rounded_decimal().map_err(|_| invalid())?;
+
+        let formatted = decimal.to_string();
+        let exact = match number(CompleteStr(&formatted)) {
+            Ok((_, round_tripped)) => round_tripped == *self,
+            Err(_) => false,
+        };
+        if exact {
+            Ok(decimal)
         } else {
-            // Signed
-            let us = self.as_i128();
-            let them = other.as_i128();
-            if us.is_err() || them.is_err() {
-                println!("Error converting to signed!");
-                return false;
+            Err(invalid())
+        }
+    }
+
+    fn as_rounded_decimal(&self) -> Result<rust_decimal::Decimal, rust_decimal::Error> {
+        use rust_decimal::Decimal;
+
+        if self.radix != 10 {
+            return Decimal::from_str_radix(&self.radix_digits(), self.radix);
+        }
+
+        let sign = if self.positive { "" } else { "-" };
+        let whole = self.whole.as_ref().map(AsRef::as_ref).unwrap_or("0");
+        let decimal = match self.fraction {
+            Some(ref fraction) if !fraction.is_empty() => {
+                format!("{}{}.{}", sign, whole, fraction)
+            }
+            _ => format!("{}{}", sign, whole),
+        };
+
+        match self.exponent {
+            None => decimal.parse(),
+            Some(ref exponent) => {
+                let sign = if exponent.positive { "" } else { "-" };
+                Decimal::from_scientific(&format!("{}e{}{}", decimal, sign, exponent.exponent))
             }
-            us.unwrap().eq(&them.unwrap())
         }
     }
 }
@@ -221,18 +1047,113 @@ impl<'a> AsOwned for Exponent<'a> {
     }
 }
 
-pub fn number<'a>(s: CompleteStr<'a>) -> IResult<CompleteStr<'a>, Number<'a>, u32> {
-    use nom::InputTake;
+/// Sign of a number or an exponent, as written in the source
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Sign {
+    /// `+`
+    Plus,
+    /// `-`
+    Minus,
+}
 
-    let (input, positive) = opt!(s, alt!(char!('+') | char!('-')))?;
-    let positive = match positive {
-        None => true,
-        Some('+') => true,
-        Some('-') => false,
-        _ => unreachable!("bug in number sign parsing"),
-    };
+impl Sign {
+    fn is_positive(sign: Option<Sign>) -> bool {
+        sign != Some(Sign::Minus)
+    }
+}
+
+impl fmt::Display for Sign {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sign::Plus => write!(f, "+"),
+            Sign::Minus => write!(f, "-"),
+        }
+    }
+}
+
+/// A number literal broken down into the parts it was written with
+///
+/// This is an alternative to [`Number`] for use cases like formatting, where the way a number was
+/// written matters more than its value. See [`number_parts`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NumberParts<'a> {
+    /// The sign in front of the number, if any
+    pub sign: Option<Sign>,
+    /// The whole number part of the number, if any, like `1` in `1.5`
+    pub digits: Option<Cow<'a, str>>,
+    /// The fraction part of the number, if any, like `5` in `1.5`. A trailing decimal point
+    /// like `1.` has an empty fraction.
+    pub fraction: Option<Cow<'a, str>>,
+    /// The exponent, if any
+    pub exponent: Option<ExponentParts<'a>>,
+}
+
+/// The exponent of a number literal, broken down into the parts it was written with
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ExponentParts<'a> {
+    /// The exponent marker, either `e` or `E`
+    pub marker: char,
+    /// The sign of the exponent, if any
+    pub sign: Option<Sign>,
+    /// The digits of the exponent
+    pub digits: Cow<'a, str>,
+}
+
+/// Writes the number out with the sign directly in front of the digits
+impl<'a> fmt::Display for NumberParts<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(sign) = self.sign {
+            write!(f, "{}", sign)?;
+        }
+        if let Some(ref digits) = self.digits {
+            write!(f, "{}", digits)?;
+        }
+        if let Some(ref fraction) = self.fraction {
+            write!(f, ".{}", fraction)?;
+        }
+        if let Some(ref exponent) = self.exponent {
+            write!(f, "{}", exponent.marker)?;
+            if let Some(sign) = exponent.sign {
+                write!(f, "{}", sign)?;
+            }
+            write!(f, "{}", exponent.digits)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> AsOwned for NumberParts<'a> {
+    type Output = NumberParts<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        NumberParts {
+            sign: self.sign,
+            digits: self.digits.as_ref().map(|s| Cow::Owned(s.to_string())),
+            fraction: self.fraction.as_ref().map(|s| Cow::Owned(s.to_string())),
+            exponent: self.exponent.as_ref().map(|exponent| ExponentParts {
+                marker: exponent.marker,
+                sign: exponent.sign,
+                digits: Cow::Owned(exponent.digits.to_string()),
+            }),
+        }
+    }
+}
+
+fn sign(input: CompleteStr) -> IResult<CompleteStr, Option<Sign>, u32> {
+    opt!(
+        input,
+        alt!(
+            char!('+') => { |_| Sign::Plus }
+            | char!('-') => { |_| Sign::Minus }
+        )
+    )
+}
 
-    let (input, (whole, fraction)) = alt!(
+#[allow(clippy::type_complexity)]
+fn mantissa(
+    input: CompleteStr,
+) -> IResult<CompleteStr, (Option<CompleteStr>, Option<CompleteStr>), u32> {
+    alt!(
         input,
         tuple!(digit, opt!(pair!(char!('.'), opt!(digit)))) => { |(digit, decimals )| {
             let decimals = match decimals {
@@ -243,14 +1164,82 @@ pub fn number<'a>(s: CompleteStr<'a>) -> IResult<CompleteStr<'a>, Number<'a>, u3
             (Some(digit), decimals)
         } }
         | tuple!(char!('.'), digit) => { |(_, decimals)| (None, Some(decimals))  }
-    )?;
+    )
+}
+
+// Prefix of an integer written in another radix, like `0x`, and the radix
+fn radix_prefix(input: CompleteStr) -> IResult<CompleteStr, u32, u32> {
+    do_parse!(
+        input,
+        char!('0')
+            >> radix: alt!(
+                one_of!("xX") => { |_| 16 }
+                | one_of!("oO") => { |_| 8 }
+                | one_of!("bB") => { |_| 2 }
+            )
+            >> (radix)
+    )
+}
 
+/// Parses a number literal
+///
+/// Besides decimal numbers, integers can be written in hexadecimal with a `0x` prefix, in octal
+/// with a `0o` prefix and in binary with a `0b` prefix. The prefixes may be uppercase. A prefix
+/// must be followed by at least one digit.
+pub fn number<'a>(s: CompleteStr<'a>) -> IResult<CompleteStr<'a>, Number<'a>, u32> {
+    use nom::InputTake;
+
+    let (input, sign) = sign(s)?;
+
+    match radix_prefix(input) {
+        Ok((input, radix)) => {
+            let (remaining, digits) = take_while!(input, |c: char| c.is_digit(radix))?;
+            if digits.is_empty() {
+                return Err(nom::Err::Error(error_position!(input, ErrorKind::Digit)));
+            }
+
+            let input = s.take(s.len() - remaining.len());
+            let number = Number {
+                radix,
+                ..Number::new(
+                    Cow::Borrowed(input.0),
+                    Sign::is_positive(sign),
+                    Some(Cow::Borrowed(digits.0)),
+                    None,
+                    None,
+                )
+            };
+            return Ok((remaining, number));
+        }
+        Err(nom::Err::Error(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    // Most numbers are plain integers, which are scanned directly. Anything that continues with
+    // a fraction or an exponent goes through the full parser.
+    let digits = input.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 {
+        let remaining = CompleteStr(&input[digits..]);
+        if !remaining.starts_with(&['.', 'e', 'E'][..]) {
+            let input = s.take(s.len() - remaining.len());
+            let number = Number::new(
+                Cow::Borrowed(input.0),
+                Sign::is_positive(sign),
+                Some(Cow::Borrowed(&input[input.len() - digits..])),
+                None,
+                None,
+            );
+            return Ok((remaining, number));
+        }
+    }
+
+    let (input, (whole, fraction)) = mantissa(input)?;
     let (remaining, exponent) = exponent(input)?;
 
     let input = s.take(s.len() - remaining.len());
     let number = Number::new(
         Cow::Borrowed(input.0),
-        positive,
+        Sign::is_positive(sign),
         whole.map(|w| Cow::Borrowed(w.0)),
         fraction.map(|f| Cow::Borrowed(f.0)),
         exponent,
@@ -259,37 +1248,292 @@ pub fn number<'a>(s: CompleteStr<'a>) -> IResult<CompleteStr<'a>, Number<'a>, u3
     Ok((remaining, number))
 }
 
-fn exponent<'a>(input: CompleteStr<'a>) -> IResult<CompleteStr<'a>, Option<Exponent<'a>>, u32> {
+/// Parses a number literal with the provided [`ParseOptions`]
+///
+/// The number is [normalized](Number::normalized) if
+/// [`normalize_numbers`](ParseOptions::normalize_numbers) is set.
+///
+/// Underscores separating digits are removed if
+/// [`allow_underscore_separators`](ParseOptions::allow_underscore_separators) is set. An
+/// underscore right after the number is a failure, whether separators are allowed or not.
+pub fn number_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Number<'a>, u32> {
+    let result = if options.allow_underscore_separators {
+        number_without_separators(input)
+    } else {
+        number(input)
+    };
+    let result = result.and_then(|(remaining, number)| {
+        if remaining.starts_with('_') {
+            Err(nom::Err::Failure(error_position!(
+                remaining,
+                ErrorKind::Custom(InternalKind::DigitSeparator as u32)
+            )))
+        } else {
+            Ok((remaining, number))
+        }
+    });
+    let (remaining, number) = in_context(input, ParseContext::Number, result)?;
+    if options.normalize_numbers {
+        Ok((remaining, number.normalized()))
+    } else {
+        Ok((remaining, number))
+    }
+}
+
+// Parse a number, removing the underscores between two of its digits
+fn number_without_separators(input: CompleteStr) -> IResult<CompleteStr, Number, u32> {
+    let unsigned = input.trim_start_matches(&['+', '-'][..]);
+    let is_hexadecimal = unsigned.starts_with("0x") || unsigned.starts_with("0X");
+    let is_digit: fn(&char) -> bool = if is_hexadecimal {
+        char::is_ascii_hexdigit
+    } else {
+        char::is_ascii_digit
+    };
+
+    // The positions of the removed underscores, in the input
+    let mut separators = vec![];
+    let mut previous = None;
+    let mut characters = input.char_indices().peekable();
+    while let Some((index, c)) = characters.next() {
+        let next = characters.peek().map(|(_, next)| *next);
+        let is_separator =
+            c == '_' && previous.filter(is_digit).is_some() && next.filter(is_digit).is_some();
+        if is_separator {
+            separators.push(index);
+        } else if !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || index == 0) {
+            let is_exponent_sign = (c == '+' || c == '-')
+                && !is_hexadecimal
+                && (previous == Some('e') || previous == Some('E'));
+            if !is_exponent_sign {
+                break;
+            }
+        }
+        previous = Some(c);
+    }
+    if separators.is_empty() {
+        return number(input);
+    }
+
+    let mut stripped = input.to_string();
+    for index in separators.iter().rev() {
+        stripped.remove(*index);
+    }
+    let (remaining, parsed) = match number(CompleteStr(&stripped)) {
+        Ok(result) => result,
+        Err(_) => return number(input),
+    };
+
+    // Find the end of the number in the input by adding back the underscores before it
+    let mut consumed = stripped.len() - remaining.len();
+    for index in separators {
+        if index < consumed {
+            consumed += 1;
+        }
+    }
+    Ok((CompleteStr(&input[consumed..]), parsed.as_owned()))
+}
+
+/// Parse a number literal into the parts it was written with
+///
+/// Unlike [`number`], spaces are allowed between the sign and the rest of the number so that
+/// formatters can normalize literals like `- 5` into `-5`.
+pub fn number_parts<'a>(s: CompleteStr<'a>) -> IResult<CompleteStr<'a>, NumberParts<'a>, u32> {
+    let (input, sign) = sign(s)?;
+    let input = match sign {
+        None => input,
+        Some(_) => nom::space0(input)?.0,
+    };
+    let (input, (digits, fraction)) = mantissa(input)?;
+    let (remaining, exponent) = exponent_parts(input)?;
+
+    Ok((
+        remaining,
+        NumberParts {
+            sign,
+            digits: digits.map(|d| Cow::Borrowed(d.0)),
+            fraction: fraction.map(|f| Cow::Borrowed(f.0)),
+            exponent,
+        },
+    ))
+}
+
+fn exponent_parts<'a>(
+    input: CompleteStr<'a>,
+) -> IResult<CompleteStr<'a>, Option<ExponentParts<'a>>, u32> {
     let (remaining, exponent) = opt!(
         input,
-        tuple!(
-            alt!(char!('e') | char!('E')),
-            opt!(alt!(char!('+') | char!('-'))),
-            digit
-        )
+        tuple!(alt!(char!('e') | char!('E')), call!(sign), digit)
     )?;
 
     Ok((
         remaining,
-        exponent.map(|(_, sign, exponent)| {
-            let positive = match sign {
-                None => true,
-                Some('+') => true,
-                Some('-') => false,
-                _ => unreachable!("bug in number sign parsing"),
-            };
-            Exponent {
-                positive,
-                exponent: Cow::Borrowed(exponent.0),
-            }
+        exponent.map(|(marker, sign, digits)| ExponentParts {
+            marker,
+            sign,
+            digits: Cow::Borrowed(digits.0),
+        }),
+    ))
+}
+
+fn exponent<'a>(input: CompleteStr<'a>) -> IResult<CompleteStr<'a>, Option<Exponent<'a>>, u32> {
+    let (remaining, exponent) = exponent_parts(input)?;
+
+    Ok((
+        remaining,
+        exponent.map(|exponent| Exponent {
+            positive: Sign::is_positive(exponent.sign),
+            exponent: exponent.digits,
         }),
     ))
 }
 
+#[cfg(feature = "serde")]
+mod serde {
+    use std::fmt;
+
+    use ::serde::de::{self, Deserialize, Deserializer, Visitor};
+    use ::serde::ser::{Serialize, Serializer};
+
+    use super::*;
+
+    /// Integers that fit in an `i64` or a `u64` are serialized as numbers. Other numbers are
+    /// serialized as strings with their original representation so that no precision is lost.
+    impl<'a> Serialize for Number<'a> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            if self.is_integer() {
+                if let Ok(integer) = self.as_i64() {
+                    return serializer.serialize_i64(integer);
+                }
+                if let Ok(integer) = self.as_u64() {
+                    return serializer.serialize_u64(integer);
+                }
+            }
+            serializer.serialize_str(&self.input)
+        }
+    }
+
+    /// Numbers are deserialized from numbers, or from strings containing a number literal
+    impl<'de, 'a> Deserialize<'de> for Number<'a> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(NumberVisitor)
+        }
+    }
+
+    struct NumberVisitor;
+
+    impl<'de> Visitor<'de> for NumberVisitor {
+        type Value = Number<'static>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a number or a string containing a number")
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(Number::from(v))
+        }
+
+        fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> {
+            Ok(Number::from(v))
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(Number::from(v))
+        }
+
+        fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> {
+            Ok(Number::from(v))
+        }
+
+        fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+            Ok(Number::from(v))
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            match number(CompleteStr(v)) {
+                Ok((remaining, number)) if remaining.is_empty() => Ok(number.as_owned()),
+                _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn numbers_keep_their_raw_text() {
+        assert_eq!(Number::from(12.34).as_raw_str(), "12.34");
+        assert_eq!(Number::from(0u8).as_raw_str(), "0");
+
+        for input in [
+            "3.14159265358979323846264338327950288419716939937510",
+            "123456789012345678901234567890",
+            "1.000000000000000000001e-300",
+            "0x00FF",
+        ]
+        .iter()
+        {
+            println!("Testing {}", input);
+            let (_, parsed) = number(CompleteStr(input)).unwrap();
+            assert_eq!(parsed.as_raw_str(), *input);
+            assert_eq!(parsed.as_owned().as_raw_str(), *input);
+        }
+    }
+
+    #[test]
+    fn numbers_are_parsed_from_str() {
+        let number: Number = "12.34".parse().unwrap();
+        assert_eq!(number, Number::from(12.34));
+        assert_eq!(&*number, "12.34");
+
+        assert_eq!("-0x1F".parse::<Number>().unwrap(), Number::from(-31));
+
+        for input in ["abc", "", " 1", "1 ", "12.34abc", "1e", "--1"].iter() {
+            println!("Testing {:?}", input);
+            assert!(input.parse::<Number>().is_err());
+        }
+    }
+
+    #[test]
+    fn numbers_are_compared_to_strings_by_value() {
+        let test_cases = [
+            (Number::from(12345), "12345", true),
+            (Number::from(12345), "+12345", true),
+            (Number::from(12345), "1.2345e4", true),
+            (Number::from(12345), "0x3039", true),
+            (Number::from(12345), "12346", false),
+            (Number::from(-1), "-1.0", true),
+            (Number::from(-1), "1", false),
+            (Number::from(0.5), "5e-1", true),
+            (Number::from(1), "1 ", false),
+            (Number::from(1), "1x", false),
+            (Number::from(1), "one", false),
+            (Number::from(1), "", false),
+        ];
+
+        for (number, string, expected) in test_cases.iter() {
+            println!("Testing {} and {:?}", &**number, string);
+            assert_eq!(number == string, *expected);
+            assert_eq!(*number == **string, *expected);
+        }
+
+        let (_, parsed) = number(CompleteStr("12345")).unwrap();
+        assert_eq!(parsed, "12345");
+    }
+
     #[test]
     fn numbers_are_parsed_correctly() {
         let cases = [
@@ -341,4 +1585,922 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn malformed_exponents_are_not_part_of_numbers() {
+        let test_cases = [
+            ("1.", "1.", ""),
+            (".5", ".5", ""),
+            ("1e", "1", "e"),
+            ("1e+", "1", "e+"),
+            ("1E-", "1", "E-"),
+            ("1e10", "1e10", ""),
+            ("1.e", "1.", "e"),
+            ("1.5e+x", "1.5", "e+x"),
+            ("-.5e-3x", "-.5e-3", "x"),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, parsed) = number(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, *expected_remaining);
+            assert_eq!(&*parsed, *expected);
+
+            // The text of every number is a valid float
+            let expected_f64: f64 = expected.parse().unwrap();
+            assert_eq!(parsed.as_f64(), Ok(expected_f64));
+        }
+
+        for input in ["e", "e5", ".", ".e5", "-", "+.", "-e1"].iter() {
+            println!("Testing {}", input);
+            assert!(number(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn integers_are_parsed_like_other_numbers() {
+        let cases = [
+            "42",
+            "-17",
+            "+9",
+            "007",
+            "99999999999999999999999",
+            "42,",
+            "7]",
+            "1 }",
+            "1.",
+            "1.5",
+            "1e",
+            "1ex",
+            "2E3",
+            "12abc",
+        ];
+
+        for case in cases.iter() {
+            println!("Testing {}", case);
+
+            let (remaining, parsed) = number(CompleteStr(*case)).unwrap();
+            let (parts_remaining, parts) = number_parts(CompleteStr(*case)).unwrap();
+            assert_eq!(remaining, parts_remaining);
+            assert_eq!(parsed.input.len(), case.len() - remaining.len());
+            assert_eq!(parsed.positive, parts.sign != Some(Sign::Minus));
+            assert_eq!(parsed.whole, parts.digits);
+            assert_eq!(parsed.fraction, parts.fraction);
+            assert_eq!(
+                parsed.exponent.map(|exponent| exponent.exponent),
+                parts.exponent.map(|exponent| exponent.digits)
+            );
+        }
+    }
+
+    #[test]
+    fn numbers_are_decomposed_into_parts() {
+        let exponent = |marker, sign, digits| {
+            Some(ExponentParts {
+                marker,
+                sign,
+                digits: Cow::Borrowed(digits),
+            })
+        };
+        let test_cases = [
+            ("5", None, Some("5"), None, None, "5"),
+            ("-5", Some(Sign::Minus), Some("5"), None, None, "-5"),
+            ("-  5", Some(Sign::Minus), Some("5"), None, None, "-5"),
+            (
+                "+3.14",
+                Some(Sign::Plus),
+                Some("3"),
+                Some("14"),
+                None,
+                "+3.14",
+            ),
+            ("1.", None, Some("1"), Some(""), None, "1."),
+            ("-.5", Some(Sign::Minus), None, Some("5"), None, "-.5"),
+            (
+                "1.2E-3",
+                None,
+                Some("1"),
+                Some("2"),
+                exponent('E', Some(Sign::Minus), "3"),
+                "1.2E-3",
+            ),
+            (
+                "- 6e+10",
+                Some(Sign::Minus),
+                Some("6"),
+                None,
+                exponent('e', Some(Sign::Plus), "10"),
+                "-6e+10",
+            ),
+            (
+                "7e2",
+                None,
+                Some("7"),
+                None,
+                exponent('e', None, "2"),
+                "7e2",
+            ),
+        ];
+
+        for (input, sign, digits, fraction, exponent, normalized) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, parts) = number_parts(CompleteStr(input)).unwrap();
+            assert!(remaining.is_empty());
+            assert_eq!(
+                parts,
+                NumberParts {
+                    sign: *sign,
+                    digits: digits.map(Cow::Borrowed),
+                    fraction: fraction.map(Cow::Borrowed),
+                    exponent: exponent.clone(),
+                }
+            );
+            assert_eq!(parts.to_string(), *normalized);
+        }
+
+        assert!(number(CompleteStr("- 5")).is_err());
+    }
+
+    #[test]
+    fn integers_and_floats_are_distinguished() {
+        let test_cases = [
+            ("0", true),
+            ("-0", true),
+            ("+42", true),
+            ("0xE", true),
+            ("0b10", true),
+            ("1e3", false),
+            ("1E3", false),
+            ("1.0", false),
+            ("1.", false),
+            ("-.5", false),
+            ("-0.0", false),
+        ];
+
+        for (input, integer) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (_, parsed) = number(CompleteStr(input)).unwrap();
+            assert_eq!(parsed.is_integer(), *integer);
+            assert_eq!(parsed.is_float(), !*integer);
+        }
+
+        assert!(Number::from(-5).is_integer());
+        assert!(Number::from(1.5).is_float());
+    }
+
+    #[test]
+    fn numbers_are_normalized() {
+        let test_cases = [
+            ("007", "7"),
+            ("1.50", "1.5"),
+            ("1E3", "1e3"),
+            ("+1", "1"),
+            ("0", "0"),
+            ("000", "0"),
+            ("-0", "0"),
+            ("-0.00", "0.0"),
+            ("1.000", "1.0"),
+            ("1.", "1.0"),
+            (".5", "0.5"),
+            ("-12.340", "-12.34"),
+            ("1E+03", "1e3"),
+            ("1e-05", "1e-5"),
+            ("1e-0", "1e0"),
+            ("-007.50E-02", "-7.5e-2"),
+            ("0XFF", "0xff"),
+            ("0x00fF", "0xff"),
+            ("0O017", "0o17"),
+            ("-0b0", "0b0"),
+            ("-0B0101", "-0b101"),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let number = number(CompleteStr(input)).unwrap().1;
+            let normalized = number.normalized();
+            assert_eq!(&*normalized, *expected);
+            assert_eq!(normalized, number);
+            assert_eq!(normalized.is_integer(), number.is_integer());
+            assert_eq!(normalized.radix(), number.radix());
+
+            // Normalizing is idempotent, and the normalized text parses to the same number
+            assert_eq!(&*normalized.normalized(), *expected);
+            let reparsed = super::number(CompleteStr(expected)).unwrap().1;
+            assert_eq!(reparsed.normalized(), normalized);
+            assert_eq!(
+                format!("{:?}", reparsed.as_owned()),
+                format!("{:?}", normalized)
+            );
+        }
+    }
+
+    #[test]
+    fn numbers_are_normalized_if_enabled() {
+        let options = ParseOptions::default().normalize_numbers(true);
+        let (_, normalized) = number_with_options(CompleteStr("+007"), &options).unwrap();
+        assert_eq!(&*normalized, "7");
+
+        let (_, number) = number_with_options(CompleteStr("+007"), &Default::default()).unwrap();
+        assert_eq!(&*number, "+007");
+    }
+
+    #[test]
+    fn underscore_separators_are_removed_if_enabled() {
+        let options = ParseOptions::default().allow_underscore_separators(true);
+        let test_cases = [
+            ("1_000", "1000", ""),
+            ("-1_000_000 + 1", "-1000000", " + 1"),
+            ("3.141_592", "3.141592", ""),
+            ("1_0e1_0]", "10e10", "]"),
+            ("1_0E-1_0", "10E-10", ""),
+            ("0xFF_FF", "0xFFFF", ""),
+            ("1000", "1000", ""),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, number) = number_with_options(CompleteStr(input), &options).unwrap();
+            assert_eq!(&*number, *expected);
+            assert_eq!(number.as_raw_str(), *expected);
+            assert_eq!(remaining, CompleteStr(expected_remaining));
+        }
+
+        let test_cases = [
+            (ParseOptions::default(), "1_000", "_000"),
+            (ParseOptions::default(), "1__0", "__0"),
+            (options.clone(), "1__0", "__0"),
+            (options.clone(), "1_", "_"),
+            (
+                ParseOptions::default(),
+                "1_000_000_000_000_000_000_000_000_000_000\nb = 2\n",
+                "_000_000_000_000_000_000_000_000",
+            ),
+        ];
+        for (options, input, remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let error = number_with_options(CompleteStr(input), options).unwrap_err();
+            assert!(match error {
+                nom::Err::Failure(_) => true,
+                _ => false,
+            });
+            match crate::Error::from_err_str(&error) {
+                crate::Error::DigitSeparator(input) => assert_eq!(input, *remaining),
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+        assert!(number_with_options(CompleteStr("_1"), &options).is_err());
+    }
+
+    #[test]
+    fn numbers_are_ordered_by_value() {
+        let parse = |s| number(CompleteStr(s)).unwrap().1;
+
+        assert_ne!(parse("-1"), parse("1e0"));
+        assert_ne!(parse("-1.5"), parse("1.5"));
+
+        let equal = [
+            ("1", "1.0"),
+            ("1", "1e0"),
+            ("1.0", "1e0"),
+            ("-0", "0"),
+            ("-0", "0.0"),
+        ];
+        for (left, right) in equal.iter() {
+            println!("Testing {} == {}", left, right);
+            assert_eq!(parse(left).cmp(&parse(right)), Ordering::Equal);
+        }
+
+        let less = [
+            ("-0", "1"),
+            ("1", "2"),
+            ("1e0", "2"),
+            ("1.0", "2"),
+            ("-2", "-1"),
+            ("-1.5", "-0"),
+            ("-100", "-1e1"),
+            ("0.015", "1.5e-1"),
+            ("0xf", "0o20"),
+            ("9", "1e1"),
+            ("99999999999999999999", "100000000000000000000"),
+        ];
+        for (left, right) in less.iter() {
+            println!("Testing {} < {}", left, right);
+            assert!(parse(left) < parse(right));
+            assert!(parse(right) > parse(left));
+        }
+
+        // Numbers are compared exactly, beyond the precision of a float
+        let precise = [
+            ("0.1", "0.10000000000000000001"),
+            ("0.1", "0.10000000000000003"),
+            ("0.10000000000000003", "0.10000000000000006"),
+            ("1", "1.0000000000000002"),
+            ("1e-400", "1e-399"),
+            ("1e20", "100000000000000000001"),
+        ];
+        for (left, right) in precise.iter() {
+            println!("Testing {} < {}", left, right);
+            assert_ne!(parse(left), parse(right));
+            assert!(parse(left) < parse(right));
+        }
+
+        // Numbers out of the range of the integer types are still equal to themselves
+        let huge = parse("340282366920938463463374607431768211457");
+        assert_eq!(huge, huge.clone());
+        assert_eq!(huge.cmp(&huge.clone()), Ordering::Equal);
+        assert_eq!(huge, parse("340282366920938463463374607431768211457.0"));
+
+        // Exponents are compared exactly, however large they are
+        let exponents = [
+            (
+                "1e99999999999999999999999999999999999998",
+                "1e99999999999999999999999999999999999999",
+            ),
+            (
+                "1e-99999999999999999999999999999999999999",
+                "1e-99999999999999999999999999999999999998",
+            ),
+            (
+                "-1e99999999999999999999999999999999999999",
+                "-1e99999999999999999999999999999999999998",
+            ),
+            (
+                "1e170141183460469231731687303715884105727",
+                "10e170141183460469231731687303715884105727",
+            ),
+            (
+                "1e999999999999999999999999999999999999999999",
+                "1e1000000000000000000000000000000000000000000",
+            ),
+        ];
+        for (left, right) in exponents.iter() {
+            println!("Testing {} < {}", left, right);
+            assert_ne!(parse(left), parse(right));
+            assert!(parse(left) < parse(right));
+            assert!(parse(right) > parse(left));
+        }
+        let exponents = [
+            (
+                "0.1e1000000000000000000000000000000000000000",
+                "1e999999999999999999999999999999999999999",
+            ),
+            (
+                "100e-1000000000000000000000000000000000000002",
+                "1.0e-1000000000000000000000000000000000000000",
+            ),
+            ("1e0000000000000000000000000000000000000000001", "10"),
+        ];
+        for (left, right) in exponents.iter() {
+            println!("Testing {} == {}", left, right);
+            assert_eq!(parse(left), parse(right));
+            assert_eq!(parse(left).cmp(&parse(right)), Ordering::Equal);
+        }
+
+        // Floats that are not finite are ordered around the finite numbers, and NaN above them all
+        let infinity = Number::from(f64::INFINITY);
+        let nan = Number::from(f64::NAN);
+        assert!(infinity > parse("1000"));
+        assert!(infinity > parse("1e99999999999999999999999999999999999999999"));
+        assert!(Number::from(f64::NEG_INFINITY) < parse("-1e400"));
+        assert!(Number::from(f64::NEG_INFINITY) < infinity);
+        assert!(nan > infinity);
+        assert_eq!(nan, Number::from(-f64::NAN));
+        assert_ne!(infinity, Number::from(f64::NEG_INFINITY));
+
+        let mut numbers: Vec<_> = ["2", "1e0", "-0", "1.5", "-1"]
+            .iter()
+            .map(|s| parse(s))
+            .collect();
+        numbers.sort();
+        let sorted: Vec<_> = numbers.iter().map(|n| n.to_string()).collect();
+        assert_eq!(sorted, ["-1", "-0", "1e0", "1.5", "2"]);
+    }
+
+    #[test]
+    fn numbers_are_hashed_by_value() {
+        use std::collections::HashSet;
+
+        let numeric = |s| number(CompleteStr(s)).unwrap().1;
+
+        let set: HashSet<_> = vec![numeric("1"), numeric("1.0")].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        let equal = [
+            ("1", "1.0"),
+            ("1", "+1"),
+            ("100", "1e2"),
+            ("0.015", "1.5E-2"),
+            ("-2.50", "-25e-1"),
+            ("0", "-0.0"),
+            ("0", "0e10"),
+            ("255", "0xff"),
+            ("8", "0o10"),
+            ("-5", "-0b101"),
+            ("18446744073709551616", "0x10000000000000000"),
+        ];
+        for (left, right) in equal.iter() {
+            println!("Testing {} == {}", left, right);
+            assert_eq!(numeric(left), numeric(right));
+
+            let set: HashSet<_> = vec![numeric(left), numeric(right)].into_iter().collect();
+            assert_eq!(set.len(), 1);
+        }
+
+        let different = [
+            ("1", "-1"),
+            ("1", "10"),
+            ("0.1", "0.01"),
+            ("1e3", "1e-3"),
+            ("0xf", "0o15"),
+            (
+                "1e99999999999999999999999999999999999999999",
+                "1e99999999999999999999999999999999999999998",
+            ),
+        ];
+        for (left, right) in different.iter() {
+            println!("Testing {} != {}", left, right);
+            assert_ne!(numeric(left), numeric(right));
+        }
+
+        // `Number` itself hashes its exact value, consistently with its equality
+        let hash = |s| {
+            use std::collections::hash_map::DefaultHasher;
+            use std::hash::{Hash, Hasher};
+
+            let mut hasher = DefaultHasher::new();
+            number(CompleteStr(s)).unwrap().1.hash(&mut hasher);
+            hasher.finish()
+        };
+        for (left, right) in equal.iter() {
+            println!("Testing hash({}) == hash({})", left, right);
+            assert_eq!(hash(left), hash(right));
+        }
+        assert_ne!(hash("1"), hash("-1"));
+
+        // Equal numbers have equal hashes, including floats that are close to each other
+        let samples = [
+            "1",
+            "1.0",
+            "1.0000000000000002",
+            "0.1",
+            "0.10000000000000002",
+            "0.10000000000000003",
+            "1e-400",
+            "0",
+            "-0.0",
+            "340282366920938463463374607431768211457",
+            "0.1e1000000000000000000000000000000000000000",
+            "1e999999999999999999999999999999999999999",
+            "1e1000000000000000000000000000000000000000",
+        ];
+        for left in samples.iter() {
+            for right in samples.iter() {
+                let (a, b) = (
+                    number(CompleteStr(left)).unwrap().1,
+                    number(CompleteStr(right)).unwrap().1,
+                );
+                if a == b {
+                    assert_eq!(hash(left), hash(right), "{} == {}", left, right);
+                }
+            }
+        }
+        assert_ne!(
+            number(CompleteStr("1")).unwrap().1,
+            number(CompleteStr("1.0000000000000002")).unwrap().1
+        );
+
+        let set: HashSet<Number> = vec![Number::from(1), "1.0".parse().unwrap()]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 1);
+        let set: HashSet<Number> = vec![Number::from(1), Number::from(2)].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn numbers_are_clamped_to_range() {
+        let number = |s| number(CompleteStr(s)).unwrap().1;
+
+        let test_cases = [
+            ("-5", "0", "10", "0"),
+            ("0", "0", "10", "0"),
+            ("3.140", "0", "10", "3.140"),
+            ("1e1", "0", "10", "1e1"),
+            ("11", "0", "10", "10"),
+            ("0.5", "1.5", "2.5", "1.5"),
+            ("2.75", "1.5", "2.5", "2.5"),
+            ("-1e3", "-1e2", "1e2", "-1e2"),
+            // The bounds are compared exactly, beyond the precision of a float
+            ("0.10000000000000001", "0", "0.1", "0.1"),
+            ("0.09999999999999999", "0.1", "1", "0.1"),
+            ("1e400", "0", "1e399", "1e399"),
+        ];
+
+        for (value, min, max, expected) in test_cases.iter() {
+            println!("Testing {} in [{}, {}]", value, min, max);
+            let clamped = number(value)
+                .checked_clamp(number(min), number(max))
+                .unwrap();
+            assert_eq!(&*clamped, *expected);
+        }
+
+        assert!(number("1")
+            .checked_clamp(number("10"), number("0"))
+            .is_err());
+    }
+
+    #[test]
+    fn negative_and_positive_numbers_are_detected() {
+        // (input, negative, positive, sign negative)
+        let test_cases = [
+            ("-5", true, false, true),
+            ("0", false, false, false),
+            ("-0", false, false, true),
+            ("0.0", false, false, false),
+            ("-0.0e3", false, false, true),
+            ("+3", false, true, false),
+            ("3", false, true, false),
+            ("-0.5", true, false, true),
+            ("-0x1", true, false, true),
+            ("-1e-400", true, false, true),
+        ];
+
+        for (input, negative, positive, sign_negative) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (_, parsed) = number(CompleteStr(input)).unwrap();
+            // The sign is kept in the text of the number
+            assert_eq!(&*parsed, *input);
+            assert_eq!(parsed.is_negative(), *negative);
+            assert_eq!(parsed.is_positive(), *positive);
+            assert_eq!(parsed.is_sign_negative(), *sign_negative);
+        }
+
+        assert!(Number::from(-5).is_negative());
+        assert!(Number::from(3u8).is_positive());
+    }
+
+    #[test]
+    fn explicit_plus_signs_are_kept() {
+        for (input, expected) in [("+5", 5.0), ("+1.5e+3", 1500.0), ("+0", 0.0)].iter() {
+            println!("Testing {}", input);
+            let (remaining, parsed) = number(CompleteStr(input)).unwrap();
+            assert!(remaining.is_empty());
+            assert_eq!(&*parsed, *input);
+            assert_eq!(&*parsed.as_owned(), *input);
+            assert!(!parsed.is_sign_negative());
+            assert_eq!(parsed.as_f64(), Ok(*expected));
+            assert_eq!(&*parsed.normalized(), &input[1..].replace("e+", "e"));
+        }
+
+        let (_, parsed) = number(CompleteStr("+5")).unwrap();
+        assert_eq!(parsed.as_i64(), Ok(5));
+        assert_eq!(parsed.as_u64(), Ok(5));
+        assert_eq!(parsed, Number::from(5));
+
+        let body = crate::parse_str("a = +5\n").unwrap();
+        assert_eq!(crate::parser::printer::to_hcl_string(&body), "a = +5\n");
+        let expression = crate::parser::expression::Expression::parse("+5").unwrap();
+        assert_eq!(expression.to_value().unwrap(), crate::Value::Integer(5));
+    }
+
+    #[test]
+    fn zeros_and_signs_are_detected() {
+        let test_cases = [
+            ("0", true, 0),
+            ("-0", true, 0),
+            ("+0", true, 0),
+            ("0.0", true, 0),
+            ("-0.000", true, 0),
+            ("000", true, 0),
+            ("0e5", true, 0),
+            ("-0.0E-12", true, 0),
+            (".0", true, 0),
+            ("0.", true, 0),
+            ("1", false, 1),
+            ("+1", false, 1),
+            ("-1", false, -1),
+            ("0.001", false, 1),
+            ("-0.001", false, -1),
+            ("1e-400", false, 1),
+            ("-10e3", false, -1),
+        ];
+
+        for (input, zero, signum) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (_, parsed) = number(CompleteStr(input)).unwrap();
+            assert_eq!(parsed.is_zero(), *zero);
+            assert_eq!(parsed.signum(), *signum);
+        }
+
+        assert!(Number::from(0).is_zero());
+        assert!(Number::from(-0.0).is_zero());
+        assert!(Number::from(-0.0).is_sign_negative());
+        assert_eq!(Number::from(-2.5).signum(), -1);
+        assert_eq!(Number::from(7u8).signum(), 1);
+    }
+
+    #[test]
+    fn lossy_float_conversions_are_reported() {
+        let exact = [
+            ("0.5", 0.5),
+            ("-2.25", -2.25),
+            ("0.1", 0.1),
+            ("1e300", 1e300),
+            ("9007199254740992", 9_007_199_254_740_992.0),
+            ("-0.0", -0.0),
+            ("0x10", 16.0),
+        ];
+        for (input, expected) in exact.iter() {
+            println!("Testing {}", input);
+            let (_, parsed) = number(CompleteStr(input)).unwrap();
+            assert_eq!(parsed.to_f64_checked().unwrap(), *expected);
+        }
+
+        let lossy = [
+            ("123456789012345678901234567890", 1.2345678901234568e29),
+            ("9007199254740993", 9_007_199_254_740_992.0),
+            ("0.12345678901234567890", 0.123_456_789_012_345_68),
+            ("1e-400", 0.0),
+            ("1e400", std::f64::INFINITY),
+        ];
+        for (input, nearest) in lossy.iter() {
+            println!("Testing {}", input);
+            let (_, parsed) = number(CompleteStr(input)).unwrap();
+            match parsed.to_f64_checked() {
+                Err(crate::Error::LossyConversion { number, value }) => {
+                    assert_eq!(number, *input);
+                    assert_eq!(value, *nearest);
+                }
+                other => panic!("Unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn radix_prefixed_integers_are_parsed_correctly() {
+        let test_cases = [
+            ("0xff", 16, 255),
+            ("0XFF", 16, 255),
+            ("0xDeadBeef", 16, 0xdead_beef),
+            ("-0x10", 16, -16),
+            ("0o17", 8, 15),
+            ("0O777", 8, 511),
+            ("0b1010", 2, 10),
+            ("+0B1", 2, 1),
+            ("0x0", 16, 0),
+        ];
+
+        for (input, radix, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, parsed) = number(CompleteStr(input)).unwrap();
+            assert!(remaining.is_empty());
+            assert_eq!(&*parsed, *input);
+            assert_eq!(parsed.radix(), *radix);
+            assert!(parsed.is_signed());
+            assert_eq!(parsed.as_i64(), Ok(*expected));
+            assert_eq!(parsed.as_f64(), Ok(*expected as f64));
+            assert_eq!(parsed, Number::from(*expected));
+        }
+
+        let (_, parsed) = number(CompleteStr("0xffffffffffffffffff")).unwrap();
+        assert!(parsed.as_u64().is_err());
+        assert_eq!(parsed.as_f64(), Ok(2f64.powi(72) - 1.0));
+        assert!(number(CompleteStr("-0x1")).unwrap().1.as_u64().is_err());
+
+        let (remaining, _) = number(CompleteStr("0b102")).unwrap();
+        assert_eq!(remaining.0, "2");
+
+        for input in ["0x", "0X", "0o", "0b", "-0x", "0xg", "0o8", "0b2"].iter() {
+            println!("Testing {}", input);
+            assert!(number(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn integers_are_converted_to_the_widest_type() {
+        let parse = |input: &'static str| number(CompleteStr(input)).unwrap().1;
+
+        assert_eq!(parse("42").as_int(), Some(42));
+        assert_eq!(parse("42").as_uint(), Some(42));
+        assert_eq!(parse("-42").as_int(), Some(-42));
+        assert_eq!(parse("-42").as_uint(), None);
+        assert_eq!(parse("0xFF").as_int(), Some(255));
+        assert_eq!(parse("0b11").as_uint(), Some(3));
+
+        let i128_max = "170141183460469231731687303715884105727";
+        assert_eq!(parse(i128_max).as_int(), Some(i128::MAX));
+        let u128_max = "340282366920938463463374607431768211455";
+        assert_eq!(parse(u128_max).as_int(), None);
+        assert_eq!(parse(u128_max).as_uint(), Some(u128::MAX));
+        let too_big = "1000000000000000000000000000000000000000";
+        assert_eq!(parse(too_big).as_int(), None);
+        assert_eq!(parse(too_big).as_uint(), None);
+
+        for input in ["1.0", "1e3", "-2.5", "0.0"].iter() {
+            println!("Testing {}", input);
+            assert_eq!(parse(input).as_int(), None);
+            assert_eq!(parse(input).as_uint(), None);
+        }
+    }
+
+    #[test]
+    fn non_zero_integers_are_converted() {
+        use std::num::IntErrorKind;
+
+        let parse = |input: &'static str| number(CompleteStr(input)).unwrap().1;
+
+        assert_eq!(
+            parse("30").as_nonzero_u32(),
+            Ok(NonZeroU32::new(30).unwrap())
+        );
+        assert_eq!(
+            parse("0x1F").as_nonzero_u8(),
+            Ok(NonZeroU8::new(31).unwrap())
+        );
+        assert_eq!(
+            parse("-5").as_nonzero_i64(),
+            Ok(NonZeroI64::new(-5).unwrap())
+        );
+        assert_eq!(
+            parse("-0x10").as_nonzero_i16(),
+            Ok(NonZeroI16::new(-16).unwrap())
+        );
+
+        for input in ["0", "0x0", "0b000"].iter() {
+            println!("Testing {}", input);
+            let number = parse(input);
+            assert_eq!(
+                number.as_nonzero_u32().unwrap_err().kind(),
+                &IntErrorKind::Zero
+            );
+            assert_eq!(
+                number.as_nonzero_i32().unwrap_err().kind(),
+                &IntErrorKind::Zero
+            );
+        }
+        assert_eq!(
+            parse("-0").as_nonzero_i32().unwrap_err().kind(),
+            &IntErrorKind::Zero
+        );
+
+        assert_eq!(
+            parse("-5").as_nonzero_u32().unwrap_err().kind(),
+            &IntErrorKind::InvalidDigit
+        );
+        assert_eq!(
+            parse("-0x5").as_nonzero_u64().unwrap_err().kind(),
+            &IntErrorKind::InvalidDigit
+        );
+        assert_eq!(
+            parse("256").as_nonzero_u8().unwrap_err().kind(),
+            &IntErrorKind::PosOverflow
+        );
+        assert!(parse("1.5").as_nonzero_u32().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "rust_decimal")]
+    fn numbers_are_converted_to_exact_decimals() {
+        use std::str::FromStr;
+
+        use rust_decimal::Decimal;
+
+        let number = |s| number(CompleteStr(s)).unwrap().1;
+
+        let test_cases = [
+            ("0.1", "0.1"),
+            ("-0.2", "-0.2"),
+            ("+3.140", "3.140"),
+            ("1.", "1"),
+            (".5", "0.5"),
+            ("1.5e3", "1500"),
+            ("-25E-4", "-0.0025"),
+            ("0xff", "255"),
+            ("-0b101", "-5"),
+            (
+                "79228162514264337593543950335",
+                "79228162514264337593543950335",
+            ),
+            (
+                "0xFFFFFFFFFFFFFFFFFFFFFFFF",
+                "79228162514264337593543950335",
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let decimal = number(input).as_decimal().unwrap();
+            assert_eq!(decimal, Decimal::from_str(expected).unwrap());
+        }
+
+        let sum = number("0.1").as_decimal().unwrap() + number("0.2").as_decimal().unwrap();
+        assert_eq!(sum, number("0.3").as_decimal().unwrap());
+        assert_ne!(
+            number("0.1").as_f64().unwrap() + number("0.2").as_f64().unwrap(),
+            number("0.3").as_f64().unwrap()
+        );
+
+        // Exactly representable, but not as a float
+        let large = number("12345678901234567890123456789");
+        assert_eq!(
+            large.as_decimal().unwrap().to_string(),
+            "12345678901234567890123456789"
+        );
+        assert_ne!(
+            large.as_f64().unwrap().to_string(),
+            "12345678901234567890123456789"
+        );
+
+        assert!(number("79228162514264337593543950336")
+            .as_decimal()
+            .is_err());
+        assert!(number("1e400").as_decimal().is_err());
+
+        // Too many significant digits to be held without rounding
+        let test_cases = [
+            "0.12345678901234567890123456789012",
+            "1.0000000000000000000000000000001",
+            "123456789012345678901234567890.5",
+            "1.2345678901234567890123456789012e5",
+        ];
+        for input in test_cases.iter() {
+            println!("Testing {}", input);
+            assert!(number(input).as_decimal().is_err());
+        }
+        assert_eq!(
+            number("1.0000000000000000000000000000000")
+                .as_decimal()
+                .unwrap(),
+            Decimal::from(1)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn numbers_are_serialized_without_losing_precision() {
+        let huge = "1234567890123456789012345678901234567890";
+        let test_cases = [
+            (Number::from(12345), "12345"),
+            (Number::from(-12345), "-12345"),
+            (Number::from(std::u64::MAX), "18446744073709551615"),
+            (number(CompleteStr("0xff")).unwrap().1, "255"),
+            (
+                number(CompleteStr(huge)).unwrap().1,
+                r#""1234567890123456789012345678901234567890""#,
+            ),
+            (Number::from(12.34), r#""12.34""#),
+            (number(CompleteStr("1e3")).unwrap().1, r#""1e3""#),
+        ];
+
+        for (number, expected) in test_cases.iter() {
+            println!("Testing {}", &**number);
+            let json = serde_json::to_string(number).unwrap();
+            assert_eq!(json, *expected);
+
+            let deserialized: Number = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+        }
+
+        let deserialized: Number = serde_json::from_str(r#""12.34""#).unwrap();
+        assert_eq!(&*deserialized, "12.34");
+        let deserialized: Number = serde_json::from_str("12.5").unwrap();
+        assert_eq!(deserialized, Number::from(12.5));
+        assert!(serde_json::from_str::<Number>(r#""12.34abc""#).is_err());
+        assert!(serde_json::from_str::<Number>("true").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "decimal")]
+    fn decimal_arithmetic_is_exact() {
+        let number = |s| number(CompleteStr(s)).unwrap().1;
+
+        let test_cases = [
+            ("0.1", "0.2", "0.3", "-0.1"),
+            ("1.50", "1.5", "3.00", "0.00"),
+            ("-2", "0.25", "-1.75", "-2.25"),
+            ("1e3", ".5", "1000.5", "999.5"),
+            (
+                "123456789012345678901234567890",
+                "0.000000001",
+                "123456789012345678901234567890.000000001",
+                "123456789012345678901234567889.999999999",
+            ),
+        ];
+
+        for (left, right, sum, difference) in test_cases.iter() {
+            println!("Testing {} and {}", left, right);
+            let left = number(left);
+            let right = number(right);
+            assert_eq!(&*left.checked_add(&right).unwrap(), *sum);
+            assert_eq!(&*left.checked_sub(&right).unwrap(), *difference);
+        }
+
+        assert_eq!(
+            number("0.1").checked_add(&number("0.2")).unwrap(),
+            number("0.3")
+        );
+        assert!(Number::from(std::f64::NAN)
+            .checked_add(&Number::from(1))
+            .is_err());
+    }
 }
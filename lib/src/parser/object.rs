@@ -8,16 +8,29 @@
 //! object = "{" (
 //!     (objectelem ("," objectelem)* ","?)?
 //! ) "}";
-//! objectelem = (Identifier | Expression) "=" Expression;
+//! objectelem = (Identifier | Expression) ("=" | ":") Expression;
 //! ```
+//!
+//! Both separators can be mixed in an object, so objects written like JSON, such as
+//! `{"a": {"b": 1}}`, are parsed too. A quoted key is the same key as an identifier with the same
+//! name once the object is converted into a [`Value`](crate::Value).
 use std::borrow::{Borrow, Cow};
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
 
 use nom::types::CompleteStr;
-use nom::{alt, call, char, do_parse, named, peek, recognize, tag, terminated, IResult};
-
-use crate::parser::expression::{expression, Expression};
+use nom::{
+    alt, call, char, do_parse, error_position, named, pair, preceded, recognize, sep, space0,
+    terminated, ErrorKind, IResult,
+};
+
+use crate::errors::{in_context, InternalKind, ParseContext};
+use crate::parser::comment::trailing_comments;
+use crate::parser::expression::{expression_with_options, Expression};
 use crate::parser::identifier::{identifier, Identifier};
-use crate::parser::whitespace::newline;
+use crate::parser::options::{newlines_ignored, ParseOptions};
+use crate::parser::whitespace::inline_whitespace;
+use crate::utils::separator_or_end;
 
 // TODO: Dealing with expressions and ambiguity. See reference
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -38,14 +51,40 @@ pub enum ObjectElementIdentifier<'a> {
 }
 
 impl<'a> ObjectElementIdentifier<'a> {
-    /// Turn an object element identifier into a string
-    pub fn as_str(&self) -> Cow<'a, str> {
+    /// The literal attribute name of the object element
+    ///
+    /// Returns `None` for a computed key, which is not evaluated. Use
+    /// [`parse_expression`](ObjectElementIdentifier::parse_expression) to parse it.
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
         match self {
-            ObjectElementIdentifier::Identifier(ident) => ident.clone(),
-            // FIXME
-            ObjectElementIdentifier::Expression(_) => {
-                unimplemented!("Expression evaluation is not yet supported")
-            }
+            ObjectElementIdentifier::Identifier(ident) => Some(ident.clone()),
+            ObjectElementIdentifier::Expression(_) => None,
+        }
+    }
+
+    /// Parse the expression of a computed key, like `(var.name)` or `"name"`
+    ///
+    /// Returns `None` for a literal attribute name. A bare identifier is always a literal name,
+    /// even if a variable has the same name, and must be wrapped in parentheses to be used as an
+    /// expression.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::expression::Expression;
+    ///
+    /// let object = match Expression::parse("{ name = 1, (name) = 2 }").unwrap() {
+    ///     Expression::Object(object) => object,
+    ///     _ => unreachable!(),
+    /// };
+    /// assert!(object[0].0.parse_expression().is_none());
+    /// assert_eq!(
+    ///     object[1].0.parse_expression().unwrap().unwrap(),
+    ///     Expression::Variable("name".into())
+    /// );
+    /// ```
+    pub fn parse_expression(&self) -> Option<Result<Expression<'_>, crate::Error>> {
+        match self {
+            ObjectElementIdentifier::Identifier(_) => None,
+            ObjectElementIdentifier::Expression(expr) => Some(Expression::parse(expr)),
         }
     }
 }
@@ -94,31 +133,187 @@ impl<'a> crate::AsOwned for ObjectElementIdentifier<'a> {
 
 pub type ObjectElement<'a> = (ObjectElementIdentifier<'a>, Expression<'a>);
 
-pub type Object<'a> = Vec<ObjectElement<'a>>;
+/// The elements of an object
+///
+/// `Object` dereferences to a `Vec` of its elements, in source order.
+#[derive(Clone, Debug, Default)]
+pub struct Object<'a> {
+    pub elements: Vec<ObjectElement<'a>>,
+    /// Whether the last element is followed by a comma, like in `{ a = 1, }`
+    ///
+    /// This is only recorded when parsing with
+    /// [comments preserved](ParseOptions::preserve_comments), so that the object can be printed
+    /// as it was written. It is ignored when comparing objects.
+    pub trailing_comma: bool,
+    /// The comments trailing each element on the same line, including their delimiters, like
+    /// `# one` in `{ a = 1 # one\n b = 2 }`
+    ///
+    /// Like [`trailing_comma`](Object::trailing_comma), the comments are only recorded when
+    /// parsing with comments preserved, in which case there is one entry for each element. They
+    /// are ignored when comparing objects.
+    pub comments: Vec<Option<Cow<'a, str>>>,
+}
+
+impl<'a> Deref for Object<'a> {
+    type Target = Vec<ObjectElement<'a>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl<'a> DerefMut for Object<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.elements
+    }
+}
+
+impl<'a> From<Vec<ObjectElement<'a>>> for Object<'a> {
+    fn from(elements: Vec<ObjectElement<'a>>) -> Self {
+        Object {
+            elements,
+            trailing_comma: false,
+            comments: vec![],
+        }
+    }
+}
+
+impl<'a> FromIterator<ObjectElement<'a>> for Object<'a> {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = ObjectElement<'a>>,
+    {
+        Object::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> IntoIterator for Object<'a> {
+    type Item = ObjectElement<'a>;
+    type IntoIter = std::vec::IntoIter<ObjectElement<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Object<'a> {
+    type Item = &'b ObjectElement<'a>;
+    type IntoIter = std::slice::Iter<'b, ObjectElement<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+impl<'a> PartialEq for Object<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.elements == other.elements
+    }
+}
+
+impl<'a> Eq for Object<'a> {}
+
+impl<'a> PartialEq<Vec<ObjectElement<'a>>> for Object<'a> {
+    fn eq(&self, other: &Vec<ObjectElement<'a>>) -> bool {
+        self.elements == *other
+    }
+}
+
+impl<'a> PartialEq<Object<'a>> for Vec<ObjectElement<'a>> {
+    fn eq(&self, other: &Object<'a>) -> bool {
+        *self == other.elements
+    }
+}
+
+impl<'a> crate::AsOwned for Object<'a> {
+    type Output = Object<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        Object {
+            elements: self.elements.as_owned(),
+            trailing_comma: self.trailing_comma,
+            comments: self
+                .comments
+                .iter()
+                .map(|comment| {
+                    comment
+                        .as_ref()
+                        .map(|comment| Cow::Owned(comment.to_string()))
+                })
+                .collect(),
+        }
+    }
+}
 
 // Cannot use `named!` because the compiler cannot determine the lifetime
-pub fn object_element_identifier<'a>(
+pub fn object_element_identifier_with_options<'a>(
     input: CompleteStr<'a>,
+    options: &ParseOptions,
 ) -> IResult<CompleteStr<'a>, ObjectElementIdentifier<'a>, u32> {
     alt!(
         input,
         call!(identifier) =>
             { |ident| ObjectElementIdentifier::Identifier(ident) }
-        | recognize!(call!(expression)) =>
+        | recognize!(call!(expression_with_options, options)) =>
             { |expr: CompleteStr<'a>| ObjectElementIdentifier::Expression(Cow::Borrowed(expr.0)) }
     )
 }
 
 named!(
-    pub object_element(CompleteStr) -> ObjectElement,
-    inline_whitespace!(
-        do_parse!(
-            identifier: call!(object_element_identifier)
-            >> char!('=')
-            >> expression: call!(expression)
-            >> (identifier, expression)
+    pub object_element_identifier(CompleteStr) -> ObjectElementIdentifier,
+    call!(object_element_identifier_with_options, &ParseOptions::default())
+);
+
+// Parses the `=` or `:` between the key and the value of an object element
+//
+// A `=>` is only valid in `for` expressions, so finding one here is a fatal error that points at
+// the arrow instead of a generic failure of the object.
+fn object_element_separator(input: CompleteStr) -> IResult<CompleteStr, char, u32> {
+    if input.starts_with("=>") {
+        Err(nom::Err::Failure(error_position!(
+            input,
+            ErrorKind::Custom(InternalKind::ArrowInObject as u32)
+        )))
+    } else {
+        alt!(input, char!('=') | char!(':'))
+    }
+}
+
+pub fn object_element_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, ObjectElement<'a>, u32> {
+    terminated!(
+        input,
+        call!(untrimmed_object_element, options),
+        inline_whitespace
+    )
+}
+
+// An object element without the whitespace and comments after it, which are left in the separator
+// so that the comments can be kept
+fn untrimmed_object_element<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, ObjectElement<'a>, u32> {
+    preceded!(
+        input,
+        inline_whitespace,
+        sep!(
+            inline_whitespace,
+            do_parse!(
+                identifier: call!(object_element_identifier_with_options, options)
+                >> call!(object_element_separator)
+                >> expression: call!(expression_with_options, options)
+                >> (identifier, expression)
+            )
         )
     )
+}
+
+named!(
+    pub object_element(CompleteStr) -> ObjectElement,
+    call!(object_element_with_options, &ParseOptions::default())
 );
 
 named!(
@@ -131,38 +326,85 @@ named!(
     char!('}')
 );
 
-named!(
-    pub object_separator(CompleteStr) -> CompleteStr,
-    alt!(
-        tag!(",")
-        | call!(newline) => {|_| CompleteStr("") }
-        | peek!(object_end) => {|_| CompleteStr("") }
-    )
-);
+/// Parses the separator after an object element, which may be left out before the closing brace
+pub fn object_separator(input: CompleteStr) -> IResult<CompleteStr, CompleteStr, u32> {
+    separator_or_end(input, '}')
+}
 
-named!(
-    pub object_body(CompleteStr) -> Object,
+pub fn object_body_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Object<'a>, u32> {
     do_parse!(
+        input,
         values: whitespace!(
             many0!(
-                terminated!(
-                    call!(object_element),
+                pair!(
+                    call!(untrimmed_object_element, options),
                     call!(object_separator)
                 )
             )
         )
-        >> (values.into_iter().collect())
+        >> (Object {
+            trailing_comma: options.preserve_comments
+                && values.last().filter(|(_, separator)| is_comma(*separator)).is_some(),
+            comments: if options.preserve_comments {
+                values
+                    .iter()
+                    .map(|(_, separator)| separator_comments(*separator))
+                    .collect()
+            } else {
+                vec![]
+            },
+            elements: values.into_iter().map(|(element, _)| element).collect(),
+        })
     )
-);
+}
+
+// The comments on the line of an object element, before and after its comma
+fn separator_comments(separator: CompleteStr) -> Option<Cow<str>> {
+    let (rest, before) = trailing_comments(separator).ok()?;
+    let after = match do_parse!(rest, space0 >> char!(',') >> comments: trailing_comments >> (comments))
+    {
+        Ok((_, after)) => after,
+        Err(_) => None,
+    };
+    match (before, after) {
+        (Some(before), Some(after)) => Some(Cow::Owned(format!("{} {}", before.0, after.0))),
+        (comments, None) | (None, comments) => comments.map(|comments| Cow::Borrowed(comments.0)),
+    }
+}
+
+// Whether an object separator is a comma rather than a newline
+fn is_comma(separator: CompleteStr) -> bool {
+    match inline_whitespace(separator) {
+        Ok((separator, _)) => separator.starts_with(','),
+        Err(_) => false,
+    }
+}
 
 named!(
-    pub object(CompleteStr) -> Object,
-    do_parse!(
+    pub object_body(CompleteStr) -> Object,
+    call!(object_body_with_options, &ParseOptions::default())
+);
+
+pub fn object_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Object<'a>, u32> {
+    let result = do_parse!(
+        input,
         whitespace!(call!(object_begin))
-        >> values: whitespace!(call!(object_body))
+        >> values: whitespace!(call!(object_body_with_options, &newlines_ignored(options, false)))
         >> call!(object_end)
         >> (values)
-    )
+    );
+    in_context(input, ParseContext::Object, result)
+}
+
+named!(
+    pub object(CompleteStr) -> Object,
+    call!(object_with_options, &ParseOptions::default())
 );
 
 #[cfg(test)]
@@ -199,6 +441,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn computed_keys_are_only_parsed_with_parentheses_or_quotes() {
+        let test_cases = [
+            ("{ foo = 1 }", None),
+            (
+                "{ (foo) = 1 }",
+                Some(Expression::Variable(From::from("foo"))),
+            ),
+            (r#"{ "foo" = 1 }"#, Some(Expression::from("foo"))),
+            (
+                "{ (var.name): 1 }",
+                Some(Expression::parse("var.name").unwrap()),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let object = match Expression::parse(input).unwrap() {
+                Expression::Object(object) => object,
+                other => panic!("Unexpected expression {:?}", other),
+            };
+            assert_eq!(object.len(), 1);
+
+            let (key, value) = &object[0];
+            assert_eq!(*value, Expression::from(1));
+            assert_eq!(
+                key.parse_expression().map(Result::unwrap).as_ref(),
+                expected.as_ref()
+            );
+            if expected.is_none() {
+                assert_eq!(*key, "foo");
+                assert_eq!(key.as_str().as_deref(), Some("foo"));
+            } else {
+                // Computed keys are not evaluated
+                assert_eq!(key.as_str(), None);
+            }
+        }
+
+        // A traversal must be wrapped in parentheses to be used as a key
+        assert!(Expression::parse("{ foo.bar = 1 }").is_err());
+    }
+
     #[test]
     fn element_objects_are_parsed_successfully() {
         let test_cases = [
@@ -212,6 +496,11 @@ mod tests {
                 ("test", Expression::Number(From::from(123))),
                 "",
             ),
+            (
+                "test: 123",
+                ("test", Expression::Number(From::from(123))),
+                "",
+            ),
             ("test = true", ("test", Expression::Boolean(true)), ""),
             (
                 "test = 123.456",
@@ -232,7 +521,11 @@ EOF
                 ("test", Expression::from("new\nline")),
                 "\n",
             ),
-            (r#"test = [],"#, ("test", Expression::Tuple(vec![])), ","),
+            (
+                r#"test = [],"#,
+                ("test", Expression::new_tuple(vec![])),
+                ",",
+            ),
             (
                 r#"test = [1,]"#,
                 ("test", Expression::new_tuple(vec![From::from(1)])),
@@ -264,6 +557,124 @@ EOF
         }
     }
 
+    #[test]
+    fn objects_with_colons_comments_and_duplicate_keys_are_parsed_correctly() {
+        let hcl = r#"{
+    # Comment
+    a = 1, // One
+    "b": 2
+    /* Three */ a : 3,
+}"#;
+        let expression = |s| ObjectElementIdentifier::Expression(Cow::Borrowed(s));
+        let expected: Vec<ObjectElement> = vec![
+            (From::from("a"), Expression::from(1)),
+            (expression(r#""b""#), Expression::from(2)),
+            (From::from("a"), Expression::from(3)),
+        ];
+
+        let (remaining, parsed) = object(CompleteStr(hcl)).unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(parsed, expected);
+
+        assert_eq!(object(CompleteStr("{ }")).unwrap_output(), vec![]);
+        assert!(object(CompleteStr("{ a = 1 :}")).is_err());
+        assert!(object(CompleteStr("{ a : }")).is_err());
+    }
+
+    #[test]
+    fn json_style_elements_are_normalized_like_native_elements() {
+        let value = |s| Expression::parse(s).unwrap().to_value().unwrap();
+
+        let native = value("{ a = 1, b = \"two\", c = [true] }");
+        let test_cases = [
+            "{ a = 1, \"b\": \"two\", c : [true] }",
+            "{ \"a\": 1, \"b\" = \"two\", c = [true] }",
+            "{\"a\": 1, \"b\": \"two\", \"c\": [true]}",
+        ];
+        for input in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(value(input), native);
+        }
+
+        let json = r#"{
+  "resource": {
+    "aws_instance": {
+      "web": {
+        "ami": "ami-123",
+        "count": 2
+      }
+    }
+  }
+}"#;
+        let native = r#"{
+  resource = {
+    aws_instance = {
+      web = {
+        ami = "ami-123"
+        count = 2
+      }
+    }
+  }
+}"#;
+        assert_eq!(value(json), value(native));
+
+        let parsed = object(CompleteStr(json)).unwrap_output();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(
+            parsed[0].0,
+            ObjectElementIdentifier::Expression(Cow::Borrowed(r#""resource""#))
+        );
+    }
+
+    #[test]
+    fn arrows_are_only_accepted_in_for_expressions() {
+        use crate::parser::for_expr::ForExpr;
+
+        assert_eq!(
+            object(CompleteStr("{a = 1}")).unwrap_output(),
+            vec![(From::from("a"), Expression::from(1))]
+        );
+        assert_eq!(
+            object(CompleteStr("{a = b ? c : d, e: f}")).unwrap_output(),
+            vec![
+                (From::from("a"), Expression::parse("b ? c : d").unwrap()),
+                (From::from("e"), Expression::parse("f").unwrap()),
+            ]
+        );
+
+        let variable = |name| Expression::Variable(From::from(name));
+        assert_eq!(
+            Expression::parse("{for x in l : x => x}").unwrap(),
+            Expression::from(ForExpr::new_object(
+                "x",
+                variable("l"),
+                variable("x"),
+                variable("x")
+            ))
+        );
+
+        for (input, remaining) in [("{a => 1}", "=> 1}"), ("{a = 1, (b) => 2}", "=> 2}")].iter() {
+            println!("Testing {}", input);
+            let error = object(CompleteStr(input)).unwrap_err();
+            assert!(match error {
+                nom::Err::Failure(_) => true,
+                _ => false,
+            });
+            match crate::Error::from_err_str(&error) {
+                crate::Error::ArrowInObject(input) => assert_eq!(input, *remaining),
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+
+        let error = crate::parser::parse_str("a = {\n  b => 1\n}\n").unwrap_err();
+        assert_eq!(error.position(), Some((2, 5)));
+        assert_eq!(
+            error.to_string(),
+            "2:5: Expected `=` or `:`, found `=>`, which is only valid in `for` expressions, at: \
+             => 1, while parsing an object, in an attribute"
+        );
+    }
+
     #[test]
     fn empty_object_body_is_parsed_correctly() {
         let hcl = "";
@@ -648,4 +1059,24 @@ EOF
 
         assert_eq!(expected, parsed);
     }
+
+    #[test]
+    fn trailing_commas_are_recorded_with_trivia() {
+        let options = ParseOptions::default().preserve_comments(true);
+        let test_cases = [
+            ("{ a = 1, }", true),
+            ("{ a = 1 }", false),
+            ("{ a = 1, b = 2 /* two */ , }", true),
+            ("{\n  a = 1,\n  b = 2, # two\n}", true),
+            ("{\n  a = 1,\n  b = 2\n}", false),
+            ("{}", false),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let parsed = object_with_options(CompleteStr(input), &options).unwrap_output();
+            assert_eq!(parsed.trailing_comma, *expected);
+            assert!(!object(CompleteStr(input)).unwrap_output().trailing_comma);
+        }
+    }
 }
@@ -0,0 +1,673 @@
+//! Operations
+//!
+//! Operations combine the values of expressions with unary and binary operators.
+//!
+//! ```ebnf
+//! Operation = unaryOp | binaryOp;
+//! unaryOp = ("-" | "!") ExprTerm;
+//! binaryOp = ExprTerm binaryOperator ExprTerm;
+//! binaryOperator = compareOperator | arithmeticOperator | logicOperator;
+//! compareOperator = "==" | "!=" | "<" | ">" | "<=" | ">=";
+//! arithmeticOperator = "+" | "-" | "*" | "/" | "%";
+//! logicOperator = "&&" | "||";
+//! ```
+//!
+//! Unary operators bind the tightest. Binary operators are left associative and bind in the
+//! following order, from the tightest to the loosest:
+//!
+//! | Level | Operators            |
+//! | ----- | -------------------- |
+//! | 6     | `*` `/` `%`          |
+//! | 5     | `+` `-`              |
+//! | 4     | `>` `>=` `<` `<=`    |
+//! | 3     | `==` `!=`            |
+//! | 2     | `&&`                 |
+//! | 1     | `\|\|`               |
+//!
+//! The operators and their operands must be on the same line.
+//!
+//! A `-` directly in front of a number is parsed as the sign of a number literal, so `-1` is the
+//! literal `-1` and not the negation of `1`. Any other `-`, like in `- 1`, `-(1 + 2)` or `-var.x`,
+//! is a negation of its operand. Operations are not evaluated, so only the literal can be converted
+//! into a [`Value`](crate::Value). There is no unary `+`: a `+` is only accepted as the sign of a
+//! number literal, like `+1`.
+//!
+//! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#operations)
+use std::cmp::Ordering;
+use std::fmt;
+
+use nom::types::CompleteStr;
+use nom::{alt, call, char, do_parse, named, not, one_of, preceded, tag, terminated, IResult};
+
+use crate::parser::expression::{expression_term_with_options, Expression};
+use crate::parser::options::{nested_options, ParseOptions};
+use crate::parser::whitespace::{expression_whitespace, inline_whitespace};
+use crate::value::Value;
+
+/// A unary operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnaryOperator {
+    /// `-`
+    Negate,
+    /// `!`
+    Not,
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnaryOperator::Negate => write!(f, "-"),
+            UnaryOperator::Not => write!(f, "!"),
+        }
+    }
+}
+
+/// A binary operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BinaryOperator {
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `%`
+    Modulo,
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+}
+
+impl BinaryOperator {
+    /// The precedence of the operator. Operators with a higher precedence bind tighter.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 6,
+            BinaryOperator::Add | BinaryOperator::Subtract => 5,
+            BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual
+            | BinaryOperator::Less
+            | BinaryOperator::LessEqual => 4,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 3,
+            BinaryOperator::And => 2,
+            BinaryOperator::Or => 1,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self {
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+        };
+        write!(f, "{}", operator)
+    }
+}
+
+/// A unary operation like `-a` or `!a`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnaryOp<'a> {
+    pub operator: UnaryOperator,
+    pub operand: Expression<'a>,
+}
+
+impl<'a> UnaryOp<'a> {
+    pub fn new(operator: UnaryOperator, operand: Expression<'a>) -> Self {
+        Self { operator, operand }
+    }
+
+    /// Evaluate the operation if its operand is a constant expression
+    ///
+    /// See [`Expression::eval_const`].
+    pub fn eval_const(&self) -> Option<Value<'a>> {
+        match (self.operator, self.operand.eval_const()?) {
+            (UnaryOperator::Negate, Value::Integer(integer)) => Some(
+                integer
+                    .checked_neg()
+                    .map_or_else(|| Value::Float(-(integer as f64)), Value::Integer),
+            ),
+            (UnaryOperator::Negate, Value::Float(float)) => Some(Value::Float(-float)),
+            (UnaryOperator::Not, Value::Boolean(boolean)) => Some(Value::Boolean(!boolean)),
+            _ => None,
+        }
+    }
+}
+
+/// A binary operation like `a + b`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinaryOp<'a> {
+    pub operator: BinaryOperator,
+    pub lhs: Expression<'a>,
+    pub rhs: Expression<'a>,
+}
+
+impl<'a> BinaryOp<'a> {
+    pub fn new(operator: BinaryOperator, lhs: Expression<'a>, rhs: Expression<'a>) -> Self {
+        Self { operator, lhs, rhs }
+    }
+
+    /// Evaluate the operation if both of its operands are constant expressions
+    ///
+    /// See [`Expression::eval_const`].
+    pub fn eval_const(&self) -> Option<Value<'a>> {
+        let lhs = self.lhs.eval_const()?;
+        let rhs = self.rhs.eval_const()?;
+
+        let ordering = || compare_numbers(&lhs, &rhs);
+        let boolean = match self.operator {
+            BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo
+            | BinaryOperator::Add
+            | BinaryOperator::Subtract => return arithmetic(self.operator, &lhs, &rhs),
+            BinaryOperator::Greater => ordering()? == Ordering::Greater,
+            BinaryOperator::GreaterEqual => ordering()? != Ordering::Less,
+            BinaryOperator::Less => ordering()? == Ordering::Less,
+            BinaryOperator::LessEqual => ordering()? != Ordering::Greater,
+            BinaryOperator::Equal => values_equal(&lhs, &rhs),
+            BinaryOperator::NotEqual => !values_equal(&lhs, &rhs),
+            BinaryOperator::And => lhs.as_bool()? && rhs.as_bool()?,
+            BinaryOperator::Or => lhs.as_bool()? || rhs.as_bool()?,
+        };
+        Some(Value::Boolean(boolean))
+    }
+}
+
+// Integer arithmetic stays exact where possible, and falls back to floats when the result is
+// fractional or overflows. Division by zero cannot be evaluated.
+fn arithmetic<'a>(operator: BinaryOperator, lhs: &Value, rhs: &Value) -> Option<Value<'a>> {
+    if let (Value::Integer(lhs), Value::Integer(rhs)) = (lhs, rhs) {
+        let (lhs, rhs) = (*lhs, *rhs);
+        let exact = match operator {
+            BinaryOperator::Multiply => lhs.checked_mul(rhs),
+            BinaryOperator::Divide if rhs != 0 && lhs.wrapping_rem(rhs) == 0 => {
+                lhs.checked_div(rhs)
+            }
+            BinaryOperator::Modulo if rhs != 0 => Some(lhs.wrapping_rem(rhs)),
+            BinaryOperator::Add => lhs.checked_add(rhs),
+            BinaryOperator::Subtract => lhs.checked_sub(rhs),
+            _ => None,
+        };
+        if let Some(exact) = exact {
+            return Some(Value::Integer(exact));
+        }
+    }
+
+    let (lhs, rhs) = (lhs.as_f64()?, rhs.as_f64()?);
+    Some(Value::Float(match operator {
+        BinaryOperator::Multiply => lhs * rhs,
+        BinaryOperator::Divide if rhs != 0.0 => lhs / rhs,
+        BinaryOperator::Modulo if rhs != 0.0 => lhs % rhs,
+        BinaryOperator::Add => lhs + rhs,
+        BinaryOperator::Subtract => lhs - rhs,
+        _ => return None,
+    }))
+}
+
+fn compare_numbers(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Integer(lhs), Value::Integer(rhs)) => Some(lhs.cmp(rhs)),
+        _ => lhs.as_f64()?.partial_cmp(&rhs.as_f64()?),
+    }
+}
+
+// Integers and floats with the same value are equal
+fn values_equal(lhs: &Value, rhs: &Value) -> bool {
+    match compare_numbers(lhs, rhs) {
+        Some(ordering) => ordering == Ordering::Equal,
+        None => lhs == rhs,
+    }
+}
+
+impl<'a> crate::AsOwned for UnaryOp<'a> {
+    type Output = UnaryOp<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        UnaryOp {
+            operator: self.operator,
+            operand: self.operand.as_owned(),
+        }
+    }
+}
+
+impl<'a> crate::AsOwned for BinaryOp<'a> {
+    type Output = BinaryOp<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        BinaryOp {
+            operator: self.operator,
+            lhs: self.lhs.as_owned(),
+            rhs: self.rhs.as_owned(),
+        }
+    }
+}
+
+named!(
+    pub unary_operator(CompleteStr) -> UnaryOperator,
+    alt!(
+        char!('-') => { |_| UnaryOperator::Negate }
+        | char!('!') => { |_| UnaryOperator::Not }
+    )
+);
+
+named!(
+    pub binary_operator(CompleteStr) -> BinaryOperator,
+    alt!(
+        tag!("==") => { |_| BinaryOperator::Equal }
+        | tag!("!=") => { |_| BinaryOperator::NotEqual }
+        | tag!(">=") => { |_| BinaryOperator::GreaterEqual }
+        | tag!("<=") => { |_| BinaryOperator::LessEqual }
+        | char!('>') => { |_| BinaryOperator::Greater }
+        | char!('<') => { |_| BinaryOperator::Less }
+        | tag!("&&") => { |_| BinaryOperator::And }
+        | tag!("||") => { |_| BinaryOperator::Or }
+        | char!('*') => { |_| BinaryOperator::Multiply }
+        // Not the start of a comment
+        | terminated!(char!('/'), not!(one_of!("/*"))) => { |_| BinaryOperator::Divide }
+        | char!('%') => { |_| BinaryOperator::Modulo }
+        | char!('+') => { |_| BinaryOperator::Add }
+        | char!('-') => { |_| BinaryOperator::Subtract }
+    )
+);
+
+pub fn unary_op_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, UnaryOp<'a>, u32> {
+    do_parse!(
+        input,
+        operator: unary_operator
+        >> options: call!(nested_options, options)
+        >> operand: preceded!(inline_whitespace, call!(operand_with_options, &options))
+        >> (UnaryOp::new(operator, operand))
+    )
+}
+
+named!(
+    pub unary_op(CompleteStr) -> UnaryOp,
+    call!(unary_op_with_options, &ParseOptions::default())
+);
+
+// An expression term, which may be preceded by unary operators
+//
+// A `-` directly in front of a number is the sign of the number, because the number literal is
+// tried as an expression term first.
+fn operand_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Expression<'a>, u32> {
+    match expression_term_with_options(input, options) {
+        Err(nom::Err::Error(_)) => {
+            unary_op_with_options(input, options).map(|(rest, op)| (rest, Expression::from(op)))
+        }
+        result => result,
+    }
+}
+
+/// Parses binary operations with the provided [`ParseOptions`]
+///
+/// If there is no operator, the single operand is returned. An operator that is not followed by
+/// an operand is not parsed.
+pub fn binary_op_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Expression<'a>, u32> {
+    operation_with_precedence(input, options, 0)
+}
+
+named!(
+    pub binary_op(CompleteStr) -> Expression,
+    call!(binary_op_with_options, &ParseOptions::default())
+);
+
+// Precedence climbing: operators that bind looser than `min_precedence` are left for the caller
+fn operation_with_precedence<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+    min_precedence: u8,
+) -> IResult<CompleteStr<'a>, Expression<'a>, u32> {
+    let (mut remaining, mut lhs) = operand_with_options(input, options)?;
+
+    loop {
+        let operator = preceded!(
+            remaining,
+            call!(expression_whitespace, options),
+            binary_operator
+        );
+        let (rest, operator) = match operator {
+            Ok(result) => result,
+            Err(nom::Err::Error(_)) => break,
+            Err(e) => return Err(e),
+        };
+        if operator.precedence() < min_precedence {
+            break;
+        }
+
+        // The right hand side only takes operators that bind tighter, which makes the
+        // operators left associative
+        let rhs = preceded!(
+            rest,
+            call!(expression_whitespace, options),
+            call!(
+                operation_with_precedence,
+                options,
+                operator.precedence() + 1
+            )
+        );
+        let (rest, rhs) = match rhs {
+            Ok(result) => result,
+            Err(nom::Err::Error(_)) => break,
+            Err(e) => return Err(e),
+        };
+
+        lhs = Expression::from(BinaryOp::new(operator, lhs, rhs));
+        remaining = rest;
+    }
+
+    Ok((remaining, lhs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::ResultUtilsString;
+
+    fn binary<'a, L, R>(lhs: L, operator: BinaryOperator, rhs: R) -> Expression<'a>
+    where
+        L: Into<Expression<'a>>,
+        R: Into<Expression<'a>>,
+    {
+        Expression::from(BinaryOp::new(operator, lhs.into(), rhs.into()))
+    }
+
+    fn unary<'a, E>(operator: UnaryOperator, operand: E) -> Expression<'a>
+    where
+        E: Into<Expression<'a>>,
+    {
+        Expression::from(UnaryOp::new(operator, operand.into()))
+    }
+
+    fn variable(name: &str) -> Expression<'_> {
+        Expression::Variable(From::from(name))
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        let actual = ResultUtilsString::unwrap_output(binary_op(CompleteStr("1 + 2 * 3")));
+        let expected = BinaryOp::new(
+            BinaryOperator::Add,
+            From::from(1),
+            Expression::from(BinaryOp::new(
+                BinaryOperator::Multiply,
+                From::from(2),
+                From::from(3),
+            )),
+        );
+        assert_eq!(actual, Expression::from(expected));
+    }
+
+    #[test]
+    fn operations_are_parsed_with_precedence() {
+        use BinaryOperator::*;
+        use UnaryOperator::*;
+
+        let test_cases = [
+            ("1", Expression::from(1)),
+            ("1 - -1", binary(1, Subtract, -1)),
+            ("1 - - 1", binary(1, Subtract, unary(Negate, 1))),
+            ("1-2-3", binary(binary(1, Subtract, 2), Subtract, 3)),
+            (
+                "8 / 4 % 3 * 2",
+                binary(binary(binary(8, Divide, 4), Modulo, 3), Multiply, 2),
+            ),
+            ("1 * 2 + 3", binary(binary(1, Multiply, 2), Add, 3)),
+            (
+                "!a == b",
+                binary(unary(Not, variable("a")), Equal, variable("b")),
+            ),
+            ("!!a", unary(Not, unary(Not, variable("a")))),
+            ("-a.b", unary(Negate, Expression::parse("a.b").unwrap())),
+            (
+                "a || b && c",
+                binary(variable("a"), Or, binary(variable("b"), And, variable("c"))),
+            ),
+            (
+                "a && b || c",
+                binary(binary(variable("a"), And, variable("b")), Or, variable("c")),
+            ),
+            (
+                "1 + 2 * 3 == 7 && x",
+                binary(
+                    binary(binary(1, Add, binary(2, Multiply, 3)), Equal, 7),
+                    And,
+                    variable("x"),
+                ),
+            ),
+            (
+                "a < b != c >= d",
+                binary(
+                    binary(variable("a"), Less, variable("b")),
+                    NotEqual,
+                    binary(variable("c"), GreaterEqual, variable("d")),
+                ),
+            ),
+            ("a<=b", binary(variable("a"), LessEqual, variable("b"))),
+            ("a > b", binary(variable("a"), Greater, variable("b"))),
+            ("(1 + 2) * 3", binary(binary(1, Add, 2), Multiply, 3)),
+            ("[1 + 2][0]", Expression::parse("[1 + 2][0]").unwrap()),
+            ("1 /* one */ + /* two */ 2", binary(1, Add, 2)),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = ResultUtilsString::unwrap_output(binary_op(CompleteStr(input)));
+            assert_eq!(actual, *expected);
+        }
+    }
+
+    #[test]
+    fn incomplete_operations_are_not_parsed() {
+        let test_cases = [
+            ("1 +", (Expression::from(1), " +")),
+            ("1 // Comment", (Expression::from(1), " // Comment")),
+            ("1 /* Comment */", (Expression::from(1), " /* Comment */")),
+            ("1\n+ 2", (Expression::from(1), "\n+ 2")),
+            ("1 + 2 +\n3", (binary(1, BinaryOperator::Add, 2), " +\n3")),
+            ("a = b", (variable("a"), " = b")),
+        ];
+
+        for (input, (expected, expected_remaining)) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) = binary_op(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, *expected_remaining);
+            assert_eq!(actual, *expected);
+        }
+
+        assert!(binary_op(CompleteStr("+ 1")).is_err());
+        assert!(unary_op(CompleteStr("!")).is_err());
+    }
+
+    #[test]
+    fn unary_operators_nest_and_bind_tighter_than_binary_operators() {
+        use UnaryOperator::*;
+
+        let test_cases = [
+            ("!a", unary(Not, variable("a"))),
+            ("-x", unary(Negate, variable("x"))),
+            ("!!x", unary(Not, unary(Not, variable("x")))),
+            ("--x", unary(Negate, unary(Negate, variable("x")))),
+            ("-!x", unary(Negate, unary(Not, variable("x")))),
+            (
+                "!a && b",
+                binary(
+                    unary(Not, variable("a")),
+                    BinaryOperator::And,
+                    variable("b"),
+                ),
+            ),
+            (
+                "-x * y",
+                binary(
+                    unary(Negate, variable("x")),
+                    BinaryOperator::Multiply,
+                    variable("y"),
+                ),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(Expression::parse(input).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn signs_of_number_literals_are_not_negations() {
+        use UnaryOperator::Negate;
+
+        let test_cases = [
+            ("-1", Expression::Number(From::from(-1))),
+            ("+1", Expression::parse("1").unwrap()),
+            ("-1.5e3", Expression::parse("-1500").unwrap()),
+            ("- 1", unary(Negate, 1)),
+            ("-(1+2)", unary(Negate, binary(1, BinaryOperator::Add, 2))),
+            ("-var.x", unary(Negate, Expression::parse("var.x").unwrap())),
+            ("--1", unary(Negate, -1)),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = Expression::parse(input).unwrap();
+            assert_eq!(actual, *expected);
+        }
+
+        assert_eq!(
+            Expression::parse("-1").unwrap().to_value().unwrap(),
+            crate::Value::Integer(-1)
+        );
+        assert!(Expression::parse("- 1").unwrap().to_value().is_err());
+        assert!(Expression::parse("+a").is_err());
+        assert!(Expression::parse("+(1)").is_err());
+    }
+
+    #[test]
+    fn constant_expressions_are_evaluated() {
+        let object = |value| Value::new_single_map(vec![(From::from("a"), value)]);
+        let test_cases = [
+            ("1 + 2 * 3", Some(Value::Integer(7))),
+            ("true || false", Some(Value::Boolean(true))),
+            ("true && !true", Some(Value::Boolean(false))),
+            ("7 / 2", Some(Value::Float(3.5))),
+            ("6 / -3", Some(Value::Integer(-2))),
+            ("7 % 3", Some(Value::Integer(1))),
+            ("1.5 * 2", Some(Value::Float(3.0))),
+            ("-(1 + 2)", Some(Value::Integer(-3))),
+            ("- 1.5", Some(Value::Float(-1.5))),
+            (
+                "9223372036854775807 + 1",
+                Some(Value::Float(9_223_372_036_854_775_808.0)),
+            ),
+            ("1 == 1.0", Some(Value::Boolean(true))),
+            (r#""a" != "b""#, Some(Value::Boolean(true))),
+            ("null == null", Some(Value::Boolean(true))),
+            ("2 >= 2.5", Some(Value::Boolean(false))),
+            ("1 < 2 == true", Some(Value::Boolean(true))),
+            ("1 < 2 ? \"yes\" : \"no\"", Some(Value::from("yes"))),
+            (
+                "[1 + 1, \"a\"]",
+                Some(Value::new_list(vec![Value::Integer(2), Value::from("a")])),
+            ),
+            ("{ a = 2 * 2 }", Some(object(Value::Integer(4)))),
+            ("\"literal\"", Some(Value::from("literal"))),
+            (
+                "\"$${var.x} %%{ if }\"",
+                Some(Value::from("${var.x} %{ if }")),
+            ),
+            ("\"${var.x}\"", None),
+            ("\"a ${var.x} b\"", None),
+            ("\"%{ if true }a%{ endif }\"", None),
+            ("[\"${var.x}\"]", None),
+            ("var.x + 1", None),
+            ("x", None),
+            ("max(1, 2)", None),
+            ("[for x in [1] : x]", None),
+            ("true ? 1 : x", None),
+            ("1 ? 2 : 3", None),
+            ("1 + \"a\"", None),
+            ("!1", None),
+            ("1 && true", None),
+            ("\"a\" < \"b\"", None),
+            ("1 / 0", None),
+            ("1 % 0", None),
+            ("1.5 / 0", None),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(Expression::parse(input).unwrap().eval_const(), *expected);
+        }
+    }
+
+    #[test]
+    fn operations_are_parsed_in_expressions() {
+        use BinaryOperator::*;
+
+        let test_cases = [
+            (
+                "a == 1 ? b + 1 : c",
+                Expression::new_conditional(
+                    binary(variable("a"), Equal, 1),
+                    binary(variable("b"), Add, 1),
+                    variable("c"),
+                ),
+            ),
+            (
+                "[1 + 2, -a]",
+                Expression::new_tuple(vec![
+                    binary(1, Add, 2),
+                    unary(UnaryOperator::Negate, variable("a")),
+                ]),
+            ),
+            (
+                "{ a = 1 * 2 }",
+                Expression::new_object(vec![("a", binary(1, Multiply, 2))]),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(Expression::parse(input).unwrap(), *expected);
+        }
+
+        let body = crate::parser::parse_str("a = 1 + 2 # Comment\nb = !c\n").unwrap();
+        assert_eq!(body.len(), 2);
+    }
+}
@@ -0,0 +1,684 @@
+//! Parser Options
+//!
+//! Configurable behaviour for the parser. The default options parse strict HCL as described in
+//! the [specification](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md).
+use std::fmt;
+use std::sync::Arc;
+
+use nom::types::CompleteStr;
+use nom::{error_position, ErrorKind, IResult};
+
+use crate::errors::InternalKind;
+use crate::span::Span;
+
+/// Options that control how HCL is parsed
+///
+/// Each option is set with the builder method named after it, and read with an `is_` method if it
+/// is a flag, or with a `get_` method otherwise.
+///
+/// ```rust
+/// use ferrous_chloride::parser::ParseOptions;
+///
+/// let options = ParseOptions::default().lenient(true).max_depth(32);
+/// assert!(options.is_lenient());
+/// assert_eq!(options.get_max_depth(), 32);
+/// ```
+#[derive(Clone)]
+pub struct ParseOptions {
+    pub(crate) lenient: bool,
+    // Shared, so that the options are cheap to clone at every level of nesting
+    pub(crate) identifier_sigils: Arc<[char]>,
+    pub(crate) identifier_char_validator: Option<IdentifierCharValidator>,
+    pub(crate) max_items: Option<usize>,
+    pub(crate) max_input_bytes: Option<usize>,
+    pub(crate) normalize_newlines: bool,
+    pub(crate) normalize_numbers: bool,
+    pub(crate) allow_underscore_separators: bool,
+    pub(crate) heredoc_tab_width: usize,
+    pub(crate) keep_heredoc_trailing_newline: bool,
+    pub(crate) strict_heredoc_indentation: bool,
+    pub(crate) braced_unicode_escapes: bool,
+    pub(crate) multiline_strings: bool,
+    pub(crate) case_insensitive_keywords: bool,
+    pub(crate) max_depth: usize,
+    pub(crate) preserve_comments: bool,
+    pub(crate) warn_heredoc_keywords: bool,
+    // How deeply nested the parser currently is
+    pub(crate) depth: usize,
+    // Whether newlines are insignificant, like inside parentheses and brackets
+    pub(crate) newlines_ignored: bool,
+    // The address of the input that spans are computed against, if spans are recorded
+    pub(crate) span_origin: Option<usize>,
+}
+
+/// A rule deciding whether a character is allowed at a position of an identifier
+///
+/// The position is the index of the character, counted in characters, in the identifier.
+pub type IdentifierCharValidator = Arc<dyn Fn(char, usize) -> bool + Send + Sync>;
+
+/// Sigils allowed in front of attribute names in lenient mode by default
+pub const DEFAULT_IDENTIFIER_SIGILS: &[char] = &['@', '$'];
+
+/// The maximum nesting depth of expressions and blocks by default
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            lenient: false,
+            identifier_sigils: Arc::from(DEFAULT_IDENTIFIER_SIGILS),
+            identifier_char_validator: None,
+            max_items: None,
+            max_input_bytes: None,
+            normalize_newlines: false,
+            normalize_numbers: false,
+            allow_underscore_separators: false,
+            heredoc_tab_width: 1,
+            keep_heredoc_trailing_newline: false,
+            strict_heredoc_indentation: false,
+            braced_unicode_escapes: false,
+            multiline_strings: false,
+            case_insensitive_keywords: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            preserve_comments: false,
+            warn_heredoc_keywords: false,
+            depth: 0,
+            newlines_ignored: false,
+            span_origin: None,
+        }
+    }
+}
+
+impl fmt::Debug for ParseOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("lenient", &self.lenient)
+            .field("identifier_sigils", &self.identifier_sigils)
+            .field(
+                "identifier_char_validator",
+                &self
+                    .identifier_char_validator
+                    .as_ref()
+                    .map(|_| "Fn(char, usize) -> bool"),
+            )
+            .field("max_items", &self.max_items)
+            .field("max_input_bytes", &self.max_input_bytes)
+            .field("normalize_newlines", &self.normalize_newlines)
+            .field("normalize_numbers", &self.normalize_numbers)
+            .field(
+                "allow_underscore_separators",
+                &self.allow_underscore_separators,
+            )
+            .field("heredoc_tab_width", &self.heredoc_tab_width)
+            .field(
+                "keep_heredoc_trailing_newline",
+                &self.keep_heredoc_trailing_newline,
+            )
+            .field(
+                "strict_heredoc_indentation",
+                &self.strict_heredoc_indentation,
+            )
+            .field("braced_unicode_escapes", &self.braced_unicode_escapes)
+            .field("multiline_strings", &self.multiline_strings)
+            .field("case_insensitive_keywords", &self.case_insensitive_keywords)
+            .field("max_depth", &self.max_depth)
+            .field("preserve_comments", &self.preserve_comments)
+            .field("warn_heredoc_keywords", &self.warn_heredoc_keywords)
+            .finish()
+    }
+}
+
+/// Options are equal if their identifier rules are the same closure
+impl PartialEq for ParseOptions {
+    fn eq(&self, other: &Self) -> bool {
+        let same_rule = match (
+            &self.identifier_char_validator,
+            &other.identifier_char_validator,
+        ) {
+            (None, None) => true,
+            (Some(ours), Some(theirs)) => Arc::ptr_eq(ours, theirs),
+            _ => false,
+        };
+        self.lenient == other.lenient
+            && self.identifier_sigils == other.identifier_sigils
+            && same_rule
+            && self.max_items == other.max_items
+            && self.max_input_bytes == other.max_input_bytes
+            && self.normalize_newlines == other.normalize_newlines
+            && self.normalize_numbers == other.normalize_numbers
+            && self.allow_underscore_separators == other.allow_underscore_separators
+            && self.heredoc_tab_width == other.heredoc_tab_width
+            && self.keep_heredoc_trailing_newline == other.keep_heredoc_trailing_newline
+            && self.strict_heredoc_indentation == other.strict_heredoc_indentation
+            && self.braced_unicode_escapes == other.braced_unicode_escapes
+            && self.multiline_strings == other.multiline_strings
+            && self.case_insensitive_keywords == other.case_insensitive_keywords
+            && self.max_depth == other.max_depth
+            && self.preserve_comments == other.preserve_comments
+            && self.warn_heredoc_keywords == other.warn_heredoc_keywords
+    }
+}
+
+impl Eq for ParseOptions {}
+
+impl ParseOptions {
+    /// Create a new set of options that parses strict HCL
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Accept syntax extensions used by some HCL dialects that are not part of strict HCL
+    ///
+    /// In lenient mode:
+    ///
+    /// - A parenthesized comma separated group like `(a, b)` is parsed as a tuple
+    /// - Attribute names may begin with one of the
+    ///   [identifier sigils](ParseOptions::identifier_sigils) like `@schema = "..."`. The sigil
+    ///   is kept as part of the name.
+    /// - The false branch of a conditional may be omitted, like `cond ? a`, and defaults to
+    ///   `null`
+    /// - The opening brace of a block may be on a line after the block type and labels
+    /// - An attribute name may be followed by a `?` to mark the attribute as
+    ///   [optional](crate::parser::attribute::Attribute), like `name? = "x"`
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Returns whether the lenient syntax extensions are enabled
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Set the sigils that attribute names may begin with in lenient mode
+    ///
+    /// Defaults to [`DEFAULT_IDENTIFIER_SIGILS`]. Sigils are never accepted in strict mode.
+    pub fn identifier_sigils<I>(mut self, sigils: I) -> Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.identifier_sigils = sigils.into_iter().collect();
+        self
+    }
+
+    /// Returns the sigils that attribute names may begin with in lenient mode
+    pub fn get_identifier_sigils(&self) -> &[char] {
+        &self.identifier_sigils
+    }
+
+    /// Override the rule deciding which characters identifiers may contain
+    ///
+    /// The rule is called with each character of a candidate identifier and its position, and
+    /// the identifier ends before the first character that is rejected. It replaces the default
+    /// Unicode based rule for attribute names and block types, and the default rule for Heredoc
+    /// markers.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::ParseOptions;
+    ///
+    /// // Allow `$` anywhere in identifiers
+    /// let options = ParseOptions::default()
+    ///     .identifier_char_validator(|c, position| {
+    ///         c == '$' || c == '_' || c.is_alphabetic() || (position > 0 && c.is_numeric())
+    ///     });
+    /// ```
+    pub fn identifier_char_validator<F>(mut self, is_valid: F) -> Self
+    where
+        F: Fn(char, usize) -> bool + Send + Sync + 'static,
+    {
+        self.identifier_char_validator = Some(Arc::new(is_valid));
+        self
+    }
+
+    /// Returns the rule overriding which characters identifiers may contain, if any
+    pub fn get_identifier_char_validator(&self) -> Option<&IdentifierCharValidator> {
+        self.identifier_char_validator.as_ref()
+    }
+
+    /// Stop parsing a body after this many top level attributes and blocks
+    ///
+    /// This is meant for previewing large documents without parsing all of them. Reaching the
+    /// limit is not an error: the elements parsed so far are returned together with the rest of
+    /// the input, which has not been parsed. The offset where parsing stopped is the difference
+    /// between the lengths of the input and of the rest. The bodies of blocks are not limited.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::body::body_with_options;
+    /// use ferrous_chloride::parser::ParseOptions;
+    /// use ferrous_chloride::nom::types::CompleteStr;
+    ///
+    /// let input = CompleteStr("a = 1\nb = 2\nc = 3\n");
+    /// let options = ParseOptions::default().max_items(2);
+    /// let (remaining, body) = body_with_options(input, &options).unwrap();
+    /// assert_eq!(body.len(), 2);
+    /// assert_eq!(remaining.0, "c = 3\n");
+    /// assert_eq!(input.len() - remaining.len(), 12);
+    /// ```
+    pub fn max_items<T>(mut self, max_items: T) -> Self
+    where
+        T: Into<Option<usize>>,
+    {
+        self.max_items = max_items.into();
+        self
+    }
+
+    /// Returns the maximum number of top level attributes and blocks to parse, if any
+    pub fn get_max_items(&self) -> Option<usize> {
+        self.max_items
+    }
+
+    /// Reject input longer than this many bytes before parsing it
+    ///
+    /// This guards services parsing untrusted input against documents that are too large to be
+    /// parsed in reasonable time or memory, like the [maximum depth](Self::max_depth) guards
+    /// against deeply nested ones. Parsing a string with [`Body::parse_with_options`] or
+    /// [`Expression::parse_with_options`] fails with
+    /// [`Error::InputTooLarge`](crate::Error::InputTooLarge) if it is longer than the limit. The
+    /// parsers working on [`CompleteStr`] do not check the limit, since they are also used on
+    /// parts of a document.
+    ///
+    /// [`Body::parse_with_options`]: crate::parser::Body::parse_with_options
+    /// [`Expression::parse_with_options`]: crate::parser::Expression::parse_with_options
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{parse_str_with_options, ParseOptions};
+    /// use ferrous_chloride::Error;
+    ///
+    /// let options = ParseOptions::default().max_input_bytes(6);
+    /// assert!(parse_str_with_options("a = 1\n", &options).is_ok());
+    /// match parse_str_with_options("a = 10\n", &options) {
+    ///     Err(Error::InputTooLarge { length, limit }) => assert_eq!((length, limit), (7, 6)),
+    ///     other => panic!("Unexpected result {:?}", other),
+    /// }
+    /// ```
+    pub fn max_input_bytes<T>(mut self, max_input_bytes: T) -> Self
+    where
+        T: Into<Option<usize>>,
+    {
+        self.max_input_bytes = max_input_bytes.into();
+        self
+    }
+
+    /// Returns the maximum length of the input in bytes, if any
+    pub fn get_max_input_bytes(&self) -> Option<usize> {
+        self.max_input_bytes
+    }
+
+    /// Convert `\r\n` line endings in the body of Heredocs to `\n`
+    ///
+    /// By default, the line endings of a Heredoc are kept as they are in the input, so the same
+    /// document saved with Windows line endings produces different strings.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::string::heredoc_string_with_options;
+    /// use ferrous_chloride::parser::ParseOptions;
+    /// use ferrous_chloride::nom::types::CompleteStr;
+    ///
+    /// let input = CompleteStr("<<EOF\r\nfoo\r\nbar\r\nEOF\r\n");
+    /// let options = ParseOptions::default().normalize_newlines(true);
+    /// let (_, string) = heredoc_string_with_options(input, &options).unwrap();
+    /// assert_eq!(string, "foo\nbar");
+    /// ```
+    pub fn normalize_newlines(mut self, normalize_newlines: bool) -> Self {
+        self.normalize_newlines = normalize_newlines;
+        self
+    }
+
+    /// Returns whether `\r\n` line endings in the body of Heredocs are converted to `\n`
+    pub fn is_normalizing_newlines(&self) -> bool {
+        self.normalize_newlines
+    }
+
+    /// Store number literals in their [normalized](crate::parser::number::Number::normalized)
+    /// form
+    ///
+    /// By default, numbers keep the text they were written with.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{Expression, ParseOptions};
+    ///
+    /// let options = ParseOptions::default().normalize_numbers(true);
+    /// match Expression::parse_with_options("+007.50E+01", &options).unwrap() {
+    ///     Expression::Number(number) => assert_eq!(&*number, "7.5e1"),
+    ///     other => panic!("Unexpected expression {:?}", other),
+    /// }
+    /// ```
+    pub fn normalize_numbers(mut self, normalize_numbers: bool) -> Self {
+        self.normalize_numbers = normalize_numbers;
+        self
+    }
+
+    /// Returns whether number literals are stored in their normalized form
+    pub fn is_normalizing_numbers(&self) -> bool {
+        self.normalize_numbers
+    }
+
+    /// Accept underscores separating the digits of number literals, like `1_000_000`
+    ///
+    /// An underscore must be between two digits, so `_1`, `1_`, `1__0` and `1_.5` are rejected.
+    /// The underscores are removed from the text of the [`Number`](crate::parser::number::Number).
+    /// By default, underscores are not allowed in numbers.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{Expression, ParseOptions};
+    ///
+    /// let options = ParseOptions::default().allow_underscore_separators(true);
+    /// match Expression::parse_with_options("1_000_000", &options).unwrap() {
+    ///     Expression::Number(number) => assert_eq!(&*number, "1000000"),
+    ///     other => panic!("Unexpected expression {:?}", other),
+    /// }
+    /// assert!(Expression::parse("1_000_000").is_err());
+    /// ```
+    pub fn allow_underscore_separators(mut self, allow_underscore_separators: bool) -> Self {
+        self.allow_underscore_separators = allow_underscore_separators;
+        self
+    }
+
+    /// Returns whether underscores may separate the digits of number literals
+    pub fn is_allowing_underscore_separators(&self) -> bool {
+        self.allow_underscore_separators
+    }
+
+    /// Set how many columns of indentation a tab is worth in indented Heredocs
+    ///
+    /// The indentation of the closing marker of an indented Heredoc like `<<-EOF` is removed from
+    /// every line, where a space is one column wide. By default, a tab is one column wide too,
+    /// which only gives the expected result if the Heredoc is indented with either tabs or spaces
+    /// consistently.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::string::heredoc_string_with_options;
+    /// use ferrous_chloride::parser::ParseOptions;
+    /// use ferrous_chloride::nom::types::CompleteStr;
+    ///
+    /// let input = CompleteStr("<<-EOF\n\tfoo\n      bar\n    EOF\n");
+    /// let options = ParseOptions::default().heredoc_tab_width(4);
+    /// let (_, string) = heredoc_string_with_options(input, &options).unwrap();
+    /// assert_eq!(string, "foo\n  bar");
+    /// ```
+    pub fn heredoc_tab_width(mut self, heredoc_tab_width: usize) -> Self {
+        self.heredoc_tab_width = heredoc_tab_width;
+        self
+    }
+
+    /// Returns how many columns of indentation a tab is worth in indented Heredocs
+    pub fn get_heredoc_tab_width(&self) -> usize {
+        self.heredoc_tab_width
+    }
+
+    /// Check that indented Heredocs are indented with the same whitespace as their end marker
+    ///
+    /// The indentation of the closing marker of an indented Heredoc like `<<-EOF` is removed from
+    /// every line by width. If the marker is indented with tabs and the lines with spaces, or the
+    /// other way round, the result depends on [`heredoc_tab_width`](Self::heredoc_tab_width) and
+    /// is rarely what was intended. When checked, the leading whitespace of every line that is
+    /// not blank has to start like the indentation of the marker, or be a prefix of it. Otherwise,
+    /// parsing fails with
+    /// [`Error::InconsistentHeredocIndent`](crate::Error::InconsistentHeredocIndent).
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::string::heredoc_string_with_options;
+    /// use ferrous_chloride::parser::ParseOptions;
+    /// use ferrous_chloride::nom::types::CompleteStr;
+    ///
+    /// let input = CompleteStr("<<-EOF\n\t\tfoo\n\tEOF\n");
+    /// let options = ParseOptions::default().strict_heredoc_indentation(true);
+    /// let (_, string) = heredoc_string_with_options(input, &options).unwrap();
+    /// assert_eq!(string, "\tfoo");
+    ///
+    /// let input = CompleteStr("<<-EOF\n  foo\n\tEOF\n");
+    /// assert!(heredoc_string_with_options(input, &options).is_err());
+    /// ```
+    pub fn strict_heredoc_indentation(mut self, strict_heredoc_indentation: bool) -> Self {
+        self.strict_heredoc_indentation = strict_heredoc_indentation;
+        self
+    }
+
+    /// Returns whether indented Heredocs have to be indented like their end marker
+    pub fn is_strict_heredoc_indentation(&self) -> bool {
+        self.strict_heredoc_indentation
+    }
+
+    /// Keep the line ending in front of the closing marker of a Heredoc in its string
+    ///
+    /// By default, a Heredoc ends with its last line of content, without a line ending. Some
+    /// tools, like the HashiCorp implementation, expect every line of a Heredoc to end with a
+    /// line ending instead. An empty Heredoc is an empty string either way.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::string::heredoc_string_with_options;
+    /// use ferrous_chloride::parser::ParseOptions;
+    /// use ferrous_chloride::nom::types::CompleteStr;
+    ///
+    /// let input = CompleteStr("<<EOF\nfoo\nbar\nEOF\n");
+    /// let options = ParseOptions::default().keep_heredoc_trailing_newline(true);
+    /// let (_, string) = heredoc_string_with_options(input, &options).unwrap();
+    /// assert_eq!(string, "foo\nbar\n");
+    /// ```
+    pub fn keep_heredoc_trailing_newline(mut self, keep_heredoc_trailing_newline: bool) -> Self {
+        self.keep_heredoc_trailing_newline = keep_heredoc_trailing_newline;
+        self
+    }
+
+    /// Returns whether the line ending in front of the closing marker of a Heredoc is kept
+    pub fn is_keeping_heredoc_trailing_newline(&self) -> bool {
+        self.keep_heredoc_trailing_newline
+    }
+
+    /// Accept Unicode escape sequences with braces, like `\u{1F600}`, in quoted strings
+    ///
+    /// HCL only has the `\uNNNN` and `\UNNNNNNNN` escape sequences, so braced escapes are
+    /// rejected by default. When enabled, the braces may contain one to six hex digits. Surrogates
+    /// and code points beyond `10FFFF` are still rejected.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{Expression, ParseOptions};
+    ///
+    /// let input = r#""\u{1F600}""#;
+    /// assert!(Expression::parse(input).is_err());
+    ///
+    /// let options = ParseOptions::default().braced_unicode_escapes(true);
+    /// let parsed = Expression::parse_with_options(input, &options).unwrap();
+    /// assert_eq!(parsed, Expression::from("😀"));
+    /// ```
+    pub fn braced_unicode_escapes(mut self, braced_unicode_escapes: bool) -> Self {
+        self.braced_unicode_escapes = braced_unicode_escapes;
+        self
+    }
+
+    /// Returns whether Unicode escape sequences with braces are accepted in quoted strings
+    pub fn is_allowing_braced_unicode_escapes(&self) -> bool {
+        self.braced_unicode_escapes
+    }
+
+    /// Accept literal newlines and carriage returns in quoted strings
+    ///
+    /// HCL requires strings spanning multiple lines to be written as heredocs or with the `\n`
+    /// escape sequence, so quoted strings containing a literal line break are rejected by
+    /// default. Older versions of this crate accepted them.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{Expression, ParseOptions};
+    ///
+    /// let input = "\"first\nsecond\"";
+    /// assert!(Expression::parse(input).is_err());
+    ///
+    /// let options = ParseOptions::default().multiline_strings(true);
+    /// let parsed = Expression::parse_with_options(input, &options).unwrap();
+    /// assert_eq!(parsed, Expression::from("first\nsecond"));
+    /// ```
+    pub fn multiline_strings(mut self, multiline_strings: bool) -> Self {
+        self.multiline_strings = multiline_strings;
+        self
+    }
+
+    /// Returns whether literal newlines are accepted in quoted strings
+    pub fn is_allowing_multiline_strings(&self) -> bool {
+        self.multiline_strings
+    }
+
+    /// Accept the keywords `true`, `false` and `null` in any case
+    ///
+    /// HCL keywords are lowercase, so `True` is an ordinary variable by default. Some tools
+    /// write the keywords capitalized, like `True` or `NULL`. When enabled, these are parsed as
+    /// the literals instead.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{Expression, ParseOptions};
+    ///
+    /// assert_eq!(Expression::parse("True").unwrap(), Expression::Variable("True".into()));
+    ///
+    /// let options = ParseOptions::default().case_insensitive_keywords(true);
+    /// let parsed = Expression::parse_with_options("True", &options).unwrap();
+    /// assert_eq!(parsed, Expression::Boolean(true));
+    /// ```
+    pub fn case_insensitive_keywords(mut self, case_insensitive_keywords: bool) -> Self {
+        self.case_insensitive_keywords = case_insensitive_keywords;
+        self
+    }
+
+    /// Returns whether the keywords `true`, `false` and `null` are accepted in any case
+    pub fn is_ignoring_keyword_case(&self) -> bool {
+        self.case_insensitive_keywords
+    }
+
+    /// Set the maximum nesting depth of expressions and blocks
+    ///
+    /// Every expression, including the elements of collections and the operands of unary
+    /// operations, and every block body is one level deeper than the expression or body containing
+    /// it. So is every template directive, and every interpolation sequence in a string nested in
+    /// an interpolation sequence. Input nested deeper than the limit fails to parse with
+    /// [`Error::RecursionLimitExceeded`](crate::Error::RecursionLimitExceeded) instead of
+    /// overflowing the stack. Defaults to [`DEFAULT_MAX_DEPTH`].
+    ///
+    /// Each level takes stack space, a lot more so in debug builds. The default limit is low enough
+    /// for the 2 MiB stack of threads spawned by the standard library, even in debug builds.
+    /// Threads with smaller stacks might need a lower limit.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{Expression, ParseOptions};
+    ///
+    /// let options = ParseOptions::default().max_depth(3);
+    /// assert!(Expression::parse_with_options("[[1]]", &options).is_ok());
+    /// assert!(Expression::parse_with_options("[[[1]]]", &options).is_err());
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Returns the maximum nesting depth of expressions and blocks
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Attach comments to the attributes and blocks they belong to
+    ///
+    /// The comments on the lines before an attribute or a block are stored in its
+    /// `leading_comments`, and the comments following it on the same line in its
+    /// `trailing_comment`. The comments after the last element of a body are stored in the
+    /// `trailing_comments` of the body, and the comments following an element of a tuple or an
+    /// object on the same line in its `comments`. By default, comments are discarded. Comments
+    /// on the line of the opening brace of a block are always discarded.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::{parse_str_with_options, ParseOptions};
+    ///
+    /// let options = ParseOptions::default().preserve_comments(true);
+    /// let body = parse_str_with_options("# Leading\na = 1 // Trailing\n", &options).unwrap();
+    /// let attribute = body[0].clone().unwrap_attribute();
+    /// assert_eq!(attribute.leading_comments, vec!["# Leading"]);
+    /// assert_eq!(attribute.trailing_comment.unwrap(), "// Trailing");
+    /// ```
+    pub fn preserve_comments(mut self, preserve_comments: bool) -> Self {
+        self.preserve_comments = preserve_comments;
+        self
+    }
+
+    /// Returns whether comments are attached to attributes and blocks
+    pub fn is_preserving_comments(&self) -> bool {
+        self.preserve_comments
+    }
+
+    /// Log a warning for every Heredoc whose identifier is a reserved keyword, like `<<true`
+    ///
+    /// Such identifiers are legal, so this is a lint and is off by default. The warning is also
+    /// available from [`HereDoc::keyword_warning`](crate::parser::string::HereDoc::keyword_warning)
+    /// without logging.
+    pub fn warn_heredoc_keywords(mut self, warn_heredoc_keywords: bool) -> Self {
+        self.warn_heredoc_keywords = warn_heredoc_keywords;
+        self
+    }
+
+    /// Returns whether Heredoc identifiers that are reserved keywords are logged as warnings
+    pub fn is_warning_heredoc_keywords(&self) -> bool {
+        self.warn_heredoc_keywords
+    }
+
+    /// Record the spans of attributes and blocks relative to `input`, the whole input that will be
+    /// parsed
+    pub(crate) fn spans_relative_to(mut self, input: &str) -> Self {
+        self.span_origin = Some(input.as_ptr() as usize);
+        self
+    }
+
+    /// Returns the span from the start of `start` to the start of `end`, if spans are recorded
+    ///
+    /// `start` and `end` are expected to be suffixes of the input that spans are recorded against.
+    pub(crate) fn span_between(&self, start: CompleteStr, end: CompleteStr) -> Option<Span> {
+        self.span_origin.map(|origin| {
+            Span::new(
+                (start.as_ptr() as usize).saturating_sub(origin),
+                (end.as_ptr() as usize).saturating_sub(origin),
+            )
+        })
+    }
+
+    /// Returns whether an attribute name may begin with `c`
+    pub(crate) fn is_identifier_sigil(&self, c: char) -> bool {
+        self.lenient && self.identifier_sigils.contains(&c)
+    }
+
+    /// Fails with [`Error::InputTooLarge`](crate::Error::InputTooLarge) if `input` is longer than
+    /// the [maximum input length](ParseOptions::max_input_bytes)
+    pub(crate) fn check_input_length(&self, input: &str) -> Result<(), crate::Error> {
+        match self.max_input_bytes {
+            Some(limit) if input.len() > limit => Err(crate::Error::InputTooLarge {
+                length: input.len(),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the options to parse the contents of parentheses or brackets with, where newlines are
+/// insignificant if `ignored`, or of braces, where they separate elements otherwise
+pub(crate) fn newlines_ignored(options: &ParseOptions, ignored: bool) -> ParseOptions {
+    ParseOptions {
+        newlines_ignored: ignored,
+        ..options.clone()
+    }
+}
+
+/// Returns the options to parse one level of nesting deeper with, without consuming any input
+///
+/// Fails with [`InternalKind::RecursionLimitExceeded`] once the
+/// [maximum depth](ParseOptions::max_depth) is exceeded.
+pub(crate) fn nested_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, ParseOptions, u32> {
+    if options.depth >= options.max_depth {
+        return Err(nom::Err::Failure(error_position!(
+            input,
+            ErrorKind::Custom(InternalKind::RecursionLimitExceeded as u32)
+        )));
+    }
+
+    let nested = ParseOptions {
+        depth: options.depth + 1,
+        ..options.clone()
+    };
+    Ok((input, nested))
+}
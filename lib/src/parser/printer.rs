@@ -0,0 +1,760 @@
+//! Printing of HCL syntax trees
+//!
+//! The [`Printer`] turns a [`Body`] back into canonical HCL text: attributes are written as
+//! `name = value`, blocks with their labels and braces, and the contents of blocks and objects
+//! are indented by two spaces, or as set with [`Printer::indent`]. The original layout of the
+//! input is not preserved, but the comments attached to attributes, blocks and tuple elements when
+//! parsing with [`parse_with_trivia`](crate::parser::parse_with_trivia) are printed. A tuple with
+//! comments is printed with one element per line.
+//!
+//! Number literals are printed exactly as they were written, so `1.500`, `007` and `0xFF` are not
+//! reformatted. The only exception is underscores separating digits, which are removed when
+//! parsing, so `1_000` is printed as `1000`. Numbers are only normalized when parsing with
+//! [`normalize_numbers`](crate::parser::options::ParseOptions::normalize_numbers) set. Converting
+//! to a [`Value`](crate::Value) keeps only the value of numbers.
+//!
+//! ```rust
+//! use ferrous_chloride::parser::parse_str;
+//! use ferrous_chloride::parser::printer::to_hcl_string;
+//!
+//! let body = parse_str("resource \"instance\" web {count=2}\n").unwrap();
+//! assert_eq!(
+//!     to_hcl_string(&body),
+//!     r#"resource "instance" web {
+//!   count = 2
+//! }
+//! "#
+//! );
+//! ```
+use std::fmt;
+
+use nom::types::CompleteStr;
+
+use crate::parser::attribute::Attribute;
+use crate::parser::block::{Block, BlockLabel};
+use crate::parser::body::{Body, BodyElement};
+use crate::parser::expression::Expression;
+use crate::parser::for_expr::ForExpr;
+use crate::parser::object::{Object, ObjectElementIdentifier};
+use crate::parser::string::{escape_template, HereDoc};
+use crate::parser::traversal::{Traversal, TraversalOperator};
+use crate::parser::tuple::Tuple;
+
+/// Print `body` as HCL, indenting the contents of blocks and objects by two spaces
+pub fn to_hcl_string(body: &Body) -> String {
+    Printer::new().print(body)
+}
+
+/// The indentation of the contents of blocks and objects, for each level of nesting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    /// Indent by a number of spaces
+    Spaces(usize),
+    /// Indent by a tab
+    Tabs,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl From<usize> for Indent {
+    fn from(spaces: usize) -> Self {
+        Indent::Spaces(spaces)
+    }
+}
+
+/// Prints syntax trees as HCL
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Printer {
+    indent: Indent,
+    heredocs: bool,
+}
+
+impl Printer {
+    /// Create a printer indenting the contents of blocks and objects by two spaces
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the indentation of the contents of blocks and objects
+    ///
+    /// A number is a number of spaces, so `indent(4)` is the same as `indent(Indent::Spaces(4))`.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::parse_str;
+    /// use ferrous_chloride::parser::printer::{Indent, Printer};
+    ///
+    /// let body = parse_str("block {\n  a = 1\n}\n").unwrap();
+    /// assert_eq!(Printer::new().indent(Indent::Tabs).print(&body), "block {\n\ta = 1\n}\n");
+    /// ```
+    pub fn indent<I>(mut self, indent: I) -> Self
+    where
+        I: Into<Indent>,
+    {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Print the strings spanning multiple lines as indented Heredocs
+    ///
+    /// Only the strings that are the value of an attribute or of an object element are printed as
+    /// Heredocs, and not if the attribute has a trailing comment, since the closing marker must be
+    /// followed by a line ending. The lines of the Heredoc and its closing marker are indented one
+    /// level deeper than the attribute or element. The marker is `EOT`, followed by a number if
+    /// the string contains a line that would end the Heredoc.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::parse_str;
+    /// use ferrous_chloride::parser::printer::Printer;
+    ///
+    /// let body = parse_str("text = \"Hello\\nWorld\"\n").unwrap();
+    /// assert_eq!(
+    ///     Printer::new().heredocs(true).print(&body),
+    ///     "text = <<-EOT\n  Hello\n  World\n  EOT\n"
+    /// );
+    /// ```
+    pub fn heredocs(mut self, heredocs: bool) -> Self {
+        self.heredocs = heredocs;
+        self
+    }
+
+    /// Print `body` as HCL
+    ///
+    /// Strings are printed as quoted strings, even if they were parsed from Heredocs, unless
+    /// [`heredocs`](Printer::heredocs) is set.
+    pub fn print(&self, body: &Body) -> String {
+        Printed {
+            printer: self,
+            body,
+        }
+        .to_string()
+    }
+
+    fn indentation(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        f.write_str(&self.indentation_string(level))
+    }
+
+    fn indentation_string(&self, level: usize) -> String {
+        match self.indent {
+            Indent::Spaces(spaces) => " ".repeat(level * spaces),
+            Indent::Tabs => "\t".repeat(level),
+        }
+    }
+
+    fn newline(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        f.write_str("\n")?;
+        self.indentation(f, level)
+    }
+
+    fn body(&self, f: &mut fmt::Formatter, body: &Body, level: usize) -> fmt::Result {
+        for element in body.iter() {
+            let (leading_comments, trailing_comment) = match element {
+                BodyElement::Attribute(attribute) => {
+                    (&attribute.leading_comments, &attribute.trailing_comment)
+                }
+                BodyElement::Block(block) => (&block.leading_comments, &block.trailing_comment),
+            };
+
+            for comment in leading_comments {
+                self.indentation(f, level)?;
+                writeln!(f, "{}", comment)?;
+            }
+            self.indentation(f, level)?;
+            match element {
+                BodyElement::Attribute(attribute) => self.attribute(f, attribute, level)?,
+                BodyElement::Block(block) => self.block(f, block, level)?,
+            }
+            if let Some(comment) = trailing_comment {
+                write!(f, " {}", comment)?;
+            }
+            f.write_str("\n")?;
+        }
+        for comment in body.trailing_comments.iter() {
+            self.indentation(f, level)?;
+            writeln!(f, "{}", comment)?;
+        }
+        Ok(())
+    }
+
+    fn attribute(
+        &self,
+        f: &mut fmt::Formatter,
+        attribute: &Attribute,
+        level: usize,
+    ) -> fmt::Result {
+        f.write_str(&attribute.name)?;
+        if attribute.optional {
+            f.write_str("?")?;
+        }
+        f.write_str(" = ")?;
+        if attribute.trailing_comment.is_some() {
+            self.expression(f, &attribute.expression, level)
+        } else {
+            self.value(f, &attribute.expression, level)
+        }
+    }
+
+    fn block(&self, f: &mut fmt::Formatter, block: &Block, level: usize) -> fmt::Result {
+        f.write_str(&block.r#type)?;
+        for label in block.labels.iter() {
+            f.write_str(" ")?;
+            match label {
+                BlockLabel::StringLiteral(string) => self.string(f, string)?,
+                BlockLabel::Identifier(identifier) => f.write_str(identifier)?,
+            }
+        }
+
+        if block.body.is_empty() && block.body.trailing_comments.is_empty() {
+            return f.write_str(" {}");
+        }
+
+        f.write_str(" {\n")?;
+        self.body(f, &block.body, level + 1)?;
+        self.indentation(f, level)?;
+        f.write_str("}")
+    }
+
+    fn string(&self, f: &mut fmt::Formatter, string: &str) -> fmt::Result {
+        write!(f, "\"{}\"", escape_template(string))
+    }
+
+    // Prints an expression that is followed by a line ending, which Heredocs must be
+    fn value(&self, f: &mut fmt::Formatter, expression: &Expression, level: usize) -> fmt::Result {
+        match expression {
+            Expression::String(string) if self.heredocs && is_multiline(string) => {
+                self.heredoc(f, string, level + 1)
+            }
+            expression => self.expression(f, expression, level),
+        }
+    }
+
+    // Prints an indented Heredoc, without the line ending after the closing marker
+    fn heredoc(&self, f: &mut fmt::Formatter, string: &str, level: usize) -> fmt::Result {
+        let mut identifier = "EOT".to_string();
+        let mut suffix = 0;
+        while string.split('\n').any(|line| line.trim() == identifier) {
+            suffix += 1;
+            identifier = format!("EOT{}", suffix);
+        }
+
+        let marker = HereDoc {
+            identifier: CompleteStr(&identifier),
+            indented: true,
+        };
+        let heredoc = marker.emit(string, &self.indentation_string(level));
+        f.write_str(heredoc.trim_end_matches('\n'))
+    }
+
+    fn expression(
+        &self,
+        f: &mut fmt::Formatter,
+        expression: &Expression,
+        level: usize,
+    ) -> fmt::Result {
+        match expression {
+            Expression::Null => f.write_str("null"),
+            Expression::Number(number) => f.write_str(number.as_raw_str()),
+            Expression::Boolean(boolean) => write!(f, "{}", boolean),
+            Expression::String(string) => self.string(f, string),
+            Expression::Tuple(tuple) => self.tuple(f, tuple, level),
+            Expression::Object(object) => self.object(f, object, level),
+            Expression::Conditional(conditional) => {
+                self.operand(f, &conditional.condition, level, is_conditional)?;
+                f.write_str(" ? ")?;
+                self.operand(f, &conditional.true_expr, level, is_conditional)?;
+                f.write_str(" : ")?;
+                self.expression(f, &conditional.false_expr, level)
+            }
+            Expression::Variable(identifier) => f.write_str(identifier),
+            Expression::Traversal(traversal) => self.traversal(f, traversal, level),
+            Expression::UnaryOp(operation) => {
+                write!(f, "{}", operation.operator)?;
+                self.operand(f, &operation.operand, level, |operand| match operand {
+                    Expression::BinaryOp(_) | Expression::Conditional(_) => true,
+                    // `- -1` must not be printed as `--1`
+                    Expression::UnaryOp(_) | Expression::Number(_) => true,
+                    _ => false,
+                })
+            }
+            Expression::BinaryOp(operation) => {
+                let precedence = operation.operator.precedence();
+                // Binary operators are left associative
+                self.operand(f, &operation.lhs, level, |operand| {
+                    binds_looser(operand, precedence)
+                })?;
+                write!(f, " {} ", operation.operator)?;
+                self.operand(f, &operation.rhs, level, |operand| {
+                    binds_looser(operand, precedence + 1)
+                })
+            }
+            Expression::FunctionCall(call) => {
+                write!(f, "{}(", call.name)?;
+                self.list(f, &call.arguments, level)?;
+                if call.expand_final {
+                    f.write_str("...")?;
+                }
+                f.write_str(")")
+            }
+            Expression::ForExpr(for_expr) => self.for_expr(f, for_expr, level),
+        }
+    }
+
+    // Prints `operand`, surrounded by parentheses if `needs_parentheses` returns true for it
+    fn operand<F>(
+        &self,
+        f: &mut fmt::Formatter,
+        operand: &Expression,
+        level: usize,
+        needs_parentheses: F,
+    ) -> fmt::Result
+    where
+        F: Fn(&Expression) -> bool,
+    {
+        if needs_parentheses(operand) {
+            f.write_str("(")?;
+            self.expression(f, operand, level)?;
+            f.write_str(")")
+        } else {
+            self.expression(f, operand, level)
+        }
+    }
+
+    fn list(
+        &self,
+        f: &mut fmt::Formatter,
+        expressions: &[Expression],
+        level: usize,
+    ) -> fmt::Result {
+        for (index, expression) in expressions.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            self.expression(f, expression, level)?;
+        }
+        Ok(())
+    }
+
+    // Prints a tuple on one line, or with one element per line if it has comments to keep after
+    // its elements
+    fn tuple(&self, f: &mut fmt::Formatter, tuple: &Tuple, level: usize) -> fmt::Result {
+        f.write_str("[")?;
+        if tuple.comments.iter().all(Option::is_none) {
+            self.list(f, tuple, level)?;
+            return f.write_str("]");
+        }
+
+        for (index, expression) in tuple.iter().enumerate() {
+            self.newline(f, level + 1)?;
+            self.expression(f, expression, level + 1)?;
+            f.write_str(",")?;
+            if let Some(Some(comment)) = tuple.comments.get(index) {
+                write!(f, " {}", comment)?;
+            }
+        }
+        self.newline(f, level)?;
+        f.write_str("]")
+    }
+
+    fn object(&self, f: &mut fmt::Formatter, object: &Object, level: usize) -> fmt::Result {
+        if object.is_empty() {
+            return f.write_str("{}");
+        }
+
+        f.write_str("{")?;
+        for (index, (identifier, expression)) in object.iter().enumerate() {
+            self.newline(f, level + 1)?;
+            match identifier {
+                ObjectElementIdentifier::Identifier(identifier) => f.write_str(identifier)?,
+                ObjectElementIdentifier::Expression(expression) => f.write_str(expression)?,
+            }
+            f.write_str(" = ")?;
+            // A Heredoc cannot be followed by a comment on the line of its closing marker
+            match object.comments.get(index) {
+                Some(Some(comment)) => {
+                    self.expression(f, expression, level + 1)?;
+                    write!(f, " {}", comment)?;
+                }
+                _ => self.value(f, expression, level + 1)?,
+            }
+        }
+        self.newline(f, level)?;
+        f.write_str("}")
+    }
+
+    fn traversal(
+        &self,
+        f: &mut fmt::Formatter,
+        traversal: &Traversal,
+        level: usize,
+    ) -> fmt::Result {
+        // A `.` right after a number would be read as its decimal point
+        let starts_with_dot = match traversal.steps.first() {
+            Some(step) => matches!(
+                step.operator,
+                TraversalOperator::GetAttr(_) | TraversalOperator::AttrSplat
+            ),
+            None => false,
+        };
+        self.operand(
+            f,
+            &traversal.expression,
+            level,
+            |expression| match expression {
+                Expression::Conditional(_) | Expression::UnaryOp(_) | Expression::BinaryOp(_) => {
+                    true
+                }
+                Expression::Number(_) => starts_with_dot,
+                _ => false,
+            },
+        )?;
+        for step in traversal.steps.iter() {
+            match &step.operator {
+                TraversalOperator::GetAttr(identifier) => write!(f, ".{}", identifier)?,
+                TraversalOperator::Index(index) => {
+                    f.write_str("[")?;
+                    self.expression(f, index, level)?;
+                    f.write_str("]")?;
+                }
+                TraversalOperator::AttrSplat => f.write_str(".*")?,
+                TraversalOperator::FullSplat => f.write_str("[*]")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn for_expr(&self, f: &mut fmt::Formatter, for_expr: &ForExpr, level: usize) -> fmt::Result {
+        f.write_str(if for_expr.is_object() {
+            "{for "
+        } else {
+            "[for "
+        })?;
+        if let Some(key_variable) = &for_expr.key_variable {
+            write!(f, "{}, ", key_variable)?;
+        }
+        write!(f, "{} in ", for_expr.value_variable)?;
+        self.expression(f, &for_expr.collection, level)?;
+        f.write_str(" : ")?;
+        if let Some(key) = &for_expr.key {
+            self.expression(f, key, level)?;
+            f.write_str(" => ")?;
+        }
+        self.expression(f, &for_expr.value, level)?;
+        if for_expr.grouping {
+            f.write_str("...")?;
+        }
+        if let Some(condition) = &for_expr.condition {
+            f.write_str(" if ")?;
+            self.expression(f, condition, level)?;
+        }
+        f.write_str(if for_expr.is_object() { "}" } else { "]" })
+    }
+}
+
+// Whether `string` spans multiple lines and can be printed as a Heredoc. The line ending in front
+// of the closing marker is not part of the string, so a string ending with `\r` cannot be.
+fn is_multiline(string: &str) -> bool {
+    string.contains('\n') && !string.ends_with('\r')
+}
+
+fn is_conditional(expression: &Expression) -> bool {
+    matches!(expression, Expression::Conditional(_))
+}
+
+// Whether `expression` binds looser than an operator of `precedence`
+fn binds_looser(expression: &Expression, precedence: u8) -> bool {
+    match expression {
+        Expression::Conditional(_) => true,
+        Expression::BinaryOp(operation) => operation.operator.precedence() < precedence,
+        _ => false,
+    }
+}
+
+struct Printed<'a, 'b> {
+    printer: &'b Printer,
+    body: &'b Body<'a>,
+}
+
+impl<'a, 'b> fmt::Display for Printed<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.printer.body(f, self.body, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_str;
+
+    #[test]
+    fn bodies_are_printed_canonically() {
+        let hcl = r#"
+name="app"   # Comment
+resource "instance" web {
+    count=2
+    tags = { Name = "web", "env" = "prod" }
+  empty {}
+}
+"#;
+        let body = parse_str(hcl).unwrap();
+
+        let expected = r#"name = "app"
+resource "instance" web {
+  count = 2
+  tags = {
+    Name = "web"
+    "env" = "prod"
+  }
+  empty {}
+}
+"#;
+        assert_eq!(to_hcl_string(&body), expected);
+
+        let expected = r#"name = "app"
+resource "instance" web {
+    count = 2
+    tags = {
+        Name = "web"
+        "env" = "prod"
+    }
+    empty {}
+}
+"#;
+        assert_eq!(Printer::new().indent(4).print(&body), expected);
+    }
+
+    #[test]
+    fn indentation_is_consistent_at_every_depth() {
+        use crate::parser::parse_with_trivia;
+
+        let hcl = r#"
+outer {
+  # Comment
+  inner label {
+    list = [{ a = 1 }]
+  }
+}
+"#;
+        let body = parse_with_trivia(hcl).unwrap();
+        let expected = r#"outer {
+  # Comment
+  inner label {
+    list = [{
+      a = 1
+    }]
+  }
+}
+"#;
+        let test_cases = [
+            (Indent::Spaces(2), "  "),
+            (Indent::Spaces(4), "    "),
+            (Indent::Tabs, "\t"),
+        ];
+
+        for (indent, unit) in test_cases.iter() {
+            println!("Testing {:?}", indent);
+            let expected = expected.replace("  ", unit);
+            assert_eq!(Printer::new().indent(*indent).print(&body), expected);
+        }
+        assert_eq!(to_hcl_string(&body), expected);
+        assert_eq!(
+            Printer::new().indent(4),
+            Printer::new().indent(Indent::Spaces(4))
+        );
+    }
+
+    #[test]
+    fn printed_bodies_are_parsed_into_the_same_body() {
+        let hcl = r#"
+null_value = null
+number = 1.5e3
+string = "say \"hi\"\n\t${name}"
+template = "<${join(",", [a, "b\n"])}> ${a == "" ? "x" : "y"} $${literal}"
+heredoc = <<EOF
+first line
+  second line
+EOF
+tuple = [1, "two", [true, false], {}]
+object = {
+  nested = {
+    list = [a, b.c]
+  }
+  (local.key) = 1
+}
+conditional = a ? b ? 1 : 2 : c ? 3 : 4
+arithmetic = (1 + 2) * -(3 - 4) / 5 - (6 - 7)
+logic = !(a && b) || c == d
+traversal = var.list[0].attr[*].id
+splat = local.objects.*.name
+number_attribute = (1).x
+number_splat = (1.5).*.x
+number_index = 1[0]
+call = max(1, min(2, 3), values...)
+namespaced = provider::aws::arn_parse("arn")
+tuple_for = [for i, x in list : x * 2 if i > 0]
+object_for = {for k, v in map : v => k...}
+
+resource "instance" "web" {
+  count = 2
+
+  lifecycle {
+    ignore = [tags]
+  }
+
+  empty {}
+}
+
+one_line { value = true }
+"#;
+        let body = parse_str(hcl).unwrap();
+        let printed = to_hcl_string(&body);
+        println!("{}", printed);
+
+        assert_eq!(parse_str(&printed).unwrap(), body);
+        // Printing is idempotent
+        assert_eq!(to_hcl_string(&parse_str(&printed).unwrap()), printed);
+    }
+
+    #[test]
+    fn number_literals_are_printed_as_written() {
+        use crate::parser::options::ParseOptions;
+        use crate::parser::parse_str_with_options;
+        use crate::AsOwned;
+
+        for literal in &[
+            "007", "1.500", "1.000", "1e3", "1E10", "1.5E-07", "0xFF", "0o17", "0B101", "-0.0",
+            "-007",
+        ] {
+            let hcl = format!("number = {}\n", literal);
+            let body = parse_str(&hcl).unwrap();
+            assert_eq!(to_hcl_string(&body), hcl);
+            assert_eq!(to_hcl_string(&body.as_owned()), hcl);
+        }
+
+        let options = ParseOptions::default().normalize_numbers(true);
+        let body = parse_str_with_options("number = 1.500\n", &options).unwrap();
+        assert_eq!(to_hcl_string(&body), "number = 1.5\n");
+    }
+
+    #[test]
+    fn comments_are_printed() {
+        let hcl = r#"
+# The name of the application
+name = "app" // Trailing
+
+resource "instance" "web" { # Discarded
+    /* Inline */ count = 2
+    # First
+    # Second
+    lifecycle {
+        ignore = [tags]
+    } # After the block
+    # At the end of the block
+}
+# At the end
+"#;
+        let body = crate::parser::parse_with_trivia(hcl).unwrap();
+        let name = body[0].clone().unwrap_attribute();
+        assert_eq!(name.leading_comments, vec!["# The name of the application"]);
+        assert_eq!(name.trailing_comment.unwrap(), "// Trailing");
+
+        let expected = r#"# The name of the application
+name = "app" // Trailing
+resource "instance" "web" {
+  /* Inline */
+  count = 2
+  # First
+  # Second
+  lifecycle {
+    ignore = [tags]
+  } # After the block
+  # At the end of the block
+}
+# At the end
+"#;
+        let printed = to_hcl_string(&body);
+        assert_eq!(printed, expected);
+
+        let reparsed = crate::parser::parse_with_trivia(&printed).unwrap();
+        assert_eq!(reparsed, body);
+        assert_eq!(to_hcl_string(&reparsed), printed);
+
+        let object = r#"
+a = 1
+# Before b
+b = { # Discarded
+  c = 2 /* After c */, d = 3 # After d
+}
+"#;
+        let expected = r#"a = 1
+# Before b
+b = {
+  c = 2 /* After c */
+  d = 3 # After d
+}
+"#;
+        let body = crate::parser::parse_with_trivia(object).unwrap();
+        assert_eq!(to_hcl_string(&body), expected);
+
+        // Comments are not attached by default
+        let name = parse_str(hcl).unwrap()[0].clone().unwrap_attribute();
+        assert!(name.leading_comments.is_empty());
+        assert_eq!(name.trailing_comment, None);
+    }
+
+    #[test]
+    fn multiline_strings_are_printed_as_heredocs() {
+        let hcl = r#"
+text = <<EOF
+first line
+  second line
+
+EOT
+EOF
+block {
+  object = {
+    nested = "a\nb\n"
+  }
+  commented = "a\nb" # Comment
+  list = ["a\nb"]
+  single = "a"
+  carriage_return = "a\r"
+}
+"#;
+        let body = crate::parser::parse_with_trivia(hcl).unwrap();
+        let printer = Printer::new().heredocs(true);
+
+        let expected = r#"text = <<-EOT1
+  first line
+    second line
+
+  EOT
+  EOT1
+block {
+  object = {
+    nested = <<-EOT
+      a
+      b
+
+      EOT
+  }
+  commented = "a\nb" # Comment
+  list = ["a\nb"]
+  single = "a"
+  carriage_return = "a\r"
+}
+"#;
+        let printed = printer.print(&body);
+        assert_eq!(printed, expected);
+
+        let reparsed = crate::parser::parse_with_trivia(&printed).unwrap();
+        assert_eq!(reparsed, body);
+        assert_eq!(printer.print(&reparsed), printed);
+
+        let tabs = printer.indent(Indent::Tabs).print(&body);
+        assert_eq!(crate::parser::parse_with_trivia(&tabs).unwrap(), body);
+    }
+}
@@ -5,15 +5,22 @@
 //!
 
 use std::borrow::Cow;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
 use std::str;
 
-use crate::errors::InternalKind;
-use log::debug;
+use crate::errors::{in_context, InternalKind, ParseContext};
+use crate::parser::identifier::{custom_identifier, identifier, Identifier};
+use crate::parser::options::{nested_options, ParseOptions};
+use crate::utils::{balanced, until_unbalanced};
+use log::{debug, warn};
 use nom::types::CompleteStr;
 use nom::ErrorKind;
 use nom::{
-    alt, call, complete, delimited, do_parse, escaped_transform, map, map_res, named, opt, peek,
-    preceded, return_error, tag, take_while1, take_while_m_n, IResult,
+    alt, call, char, complete, delimited, do_parse, eof, error_position, escaped_transform, many0,
+    map, map_res, named, named_attr, opt, pair, peek, preceded, return_error, tag, take_while1,
+    take_while_m_n, terminated, IResult, Slice,
 };
 
 /// The StringLit production permits the escape sequences discussed for quoted template expressions
@@ -43,50 +50,108 @@ fn legal_string_literal_single_line_character(c: char) -> bool {
 fn octal_to_string(s: &str) -> Result<String, InternalKind> {
     use std::char;
 
-    let octal = u32::from_str_radix(s, 8).expect("Parser to have caught invalid inputs");
+    let octal = u32::from_str_radix(s, 8).map_err(|_| InternalKind::InvalidUnicodeCodePoint)?;
+    // Like C and Go, an octal escape names a single byte, so `\377` is the largest allowed
+    if octal > 0o377 {
+        return Err(InternalKind::InvalidUnicodeCodePoint);
+    }
     Ok(char::from_u32(octal)
         .ok_or_else(|| InternalKind::InvalidUnicodeCodePoint)?
         .to_string())
 }
 
 fn hex_to_string(s: &str) -> Result<String, InternalKind> {
-    let byte = u32::from_str_radix(s, 16).expect("Parser to have caught invalid inputs");
+    let byte = u32::from_str_radix(s, 16).map_err(|_| InternalKind::InvalidUnicodeCodePoint)?;
     Ok(std::char::from_u32(byte)
         .ok_or_else(|| InternalKind::InvalidUnicodeCodePoint)?
         .to_string())
 }
 
-// Tab spaces are illegal and will cause bad output
-fn unindent_heredoc(string: &str, indentation: usize) -> Cow<str> {
-    if indentation == 0 {
+// Remove up to `indentation` columns of leading spaces and tabs from every line. Like in the
+// indentation of the closing marker, a space is one column wide and a tab is `tab_width` columns
+// wide. A tab reaching past `indentation` is removed entirely. Lines that are indented less lose
+// all of their leading whitespace.
+fn unindent_heredoc(string: &str, indentation: usize, tab_width: usize) -> Cow<str> {
+    // Spaces and tabs are a single byte, so the line can be sliced after them
+    let leading_spaces = |line: &str| {
+        let mut width = 0;
+        line.bytes()
+            .take_while(|byte| {
+                let byte_width = match byte {
+                    b' ' => 1,
+                    b'\t' => tab_width,
+                    _ => return false,
+                };
+                if width >= indentation {
+                    return false;
+                }
+                width += byte_width;
+                true
+            })
+            .count()
+    };
+
+    let removed: usize = string.split('\n').map(leading_spaces).sum();
+    if removed == 0 {
         return Cow::Borrowed(string);
     }
 
-    let mut result = String::with_capacity(string.len());
-    for line in string.split('\n') {
-        // Trim spaces at the beginning first
-        // Let's find a start index up to `indentation` to slice away
-        let mut beginning = line.char_indices().take(indentation);
-        let all_spaces = beginning.all(|(_, c)| c == ' ');
-        let rest = if all_spaces {
-            &line[indentation..]
-        } else {
-            let (start, _) = beginning.next().expect("to not be None");
-            &line[start - 1..]
-        };
-        result.push_str(rest);
-        result.push('\n');
+    let mut result = String::with_capacity(string.len() - removed);
+    for (index, line) in string.split('\n').enumerate() {
+        if index > 0 {
+            result.push('\n');
+        }
+        result.push_str(&line[leading_spaces(line)..]);
     }
-    // Remove the last `\n`
-    result.truncate(result.len() - 1);
     Cow::Owned(result)
 }
 
+// The first line that is not blank and whose leading whitespace neither starts with `marker` nor is
+// a prefix of it
+fn inconsistently_indented_line<'a>(lines: &'a str, marker: &str) -> Option<&'a str> {
+    lines
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .find(|line| {
+            let leading = line.len() - line.trim_start_matches(&[' ', '\t'][..]).len();
+            let length = leading.min(marker.len());
+            line.as_bytes()[..length] != marker.as_bytes()[..length]
+        })
+}
+
+/// Indent every non-empty line of `string` by `indentation` spaces
+///
+/// This is the inverse of the removal of the indentation of `<<-` Heredocs: a Heredoc body
+/// reindented by as many spaces as its closing marker is parsed back into `string`.
+///
+/// ```rust
+/// use ferrous_chloride::parser::string::reindent_heredoc;
+///
+/// assert_eq!(reindent_heredoc("a\n\n  b", 2), "  a\n\n    b");
+/// ```
+pub fn reindent_heredoc(string: &str, indentation: usize) -> String {
+    prefix_lines(string, &" ".repeat(indentation))
+}
+
+fn prefix_lines(string: &str, prefix: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+    for (index, line) in string.split('\n').enumerate() {
+        if index > 0 {
+            result.push('\n');
+        }
+        if !line.is_empty() {
+            result.push_str(prefix);
+        }
+        result.push_str(line);
+    }
+    result
+}
+
 // Unescape characters according to the reference https://en.cppreference.com/w/cpp/language/escape
 // Source: https://github.com/hashicorp/hcl/blob/ef8a98b0bbce4a65b5aa4c368430a80ddc533168/hcl/scanner/scanner.go#L513
 // Unicode References: https://en.wikipedia.org/wiki/List_of_Unicode_characters
 // TODO: Issues with variable length alt https://docs.rs/nom/4.2.0/nom/macro.alt.html#behaviour-of-alt
-named!(unescape(CompleteStr) -> Cow<str>,
+named!(unescape_sequence(CompleteStr) -> Cow<str>,
     alt!(
         // Control Chracters
         tag!("a")  => { |_| Cow::Borrowed("\x07") }
@@ -104,6 +169,18 @@ named!(unescape(CompleteStr) -> Cow<str>,
     )
 );
 
+// Unescape the character after a `\`. A line break after it is a failure rather than an unknown
+// escape sequence, since HCL has no line continuations.
+fn unescape(input: CompleteStr) -> IResult<CompleteStr, Cow<str>, u32> {
+    if input.starts_with('\n') || input.starts_with("\r\n") {
+        return Err(nom::Err::Failure(error_position!(
+            input,
+            ErrorKind::Custom(InternalKind::LineContinuation as u32)
+        )));
+    }
+    unescape_sequence(input)
+}
+
 named!(hex_to_unicode(CompleteStr) -> Cow<str>,
     return_error!(
         ErrorKind::Custom(InternalKind::InvalidUnicodeCodePoint as u32),
@@ -121,27 +198,105 @@ named!(hex_to_unicode(CompleteStr) -> Cow<str>,
     )
 );
 
-// Contents of a single line string
-named!(
-    multiline_string_content(CompleteStr) -> String,
-    escaped_transform!(
-        take_while1!(legal_string_literal_character),
-        '\\',
-        unescape
+// `\u{1F600}`, with one to six hex digits, like in Rust
+named!(braced_unicode(CompleteStr) -> Cow<str>,
+    return_error!(
+        ErrorKind::Custom(InternalKind::InvalidUnicodeCodePoint as u32),
+        map!(
+            map_res!(
+                delimited!(tag!("u{"), take_while_m_n!(1, 6, is_hex_digit), tag!("}")),
+                |s: CompleteStr| hex_to_string(s.0)
+            ),
+            Cow::Owned
+        )
     )
 );
 
-named!(
-    quoted_string(CompleteStr) -> String,
-    delimited!(
+// Unescape a character with the escape sequences enabled in the provided `ParseOptions`
+fn unescape_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32> {
+    if options.braced_unicode_escapes && input.starts_with("u{") {
+        braced_unicode(input)
+    } else {
+        unescape(input)
+    }
+}
+
+// Contents of a quoted string. Most strings have no escape sequences, so they are borrowed from
+// the input and only unescaped into a new string when a `\` is found.
+//
+// Interpolation sequences and directives are kept as they are, including the quotes and escape
+// sequences of the strings nested in them. Literal line breaks end the content, unless
+// multi-line strings are enabled in the options.
+fn quoted_string_content<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32> {
+    let is_legal: fn(char) -> bool = if options.multiline_strings {
+        legal_string_literal_character
+    } else {
+        legal_string_literal_single_line_character
+    };
+
+    let mut index = 0;
+    let mut unescaped: Option<String> = None;
+    while let Some(c) = input.0[index..].chars().next() {
+        let rest = input.slice(index..);
+        let length = if rest.starts_with("$${") || rest.starts_with("%%{") {
+            3
+        } else if rest.starts_with("${") || rest.starts_with("%{") {
+            // An unclosed sequence is literal text
+            let max_depth = options.max_depth.saturating_sub(options.depth + 1);
+            match until_unbalanced(rest.slice(2..), '{', '}', max_depth) {
+                Ok((remaining, _)) => rest.len() - remaining.len() + 1,
+                Err(nom::Err::Error(_)) => 1,
+                Err(error) => return Err(error),
+            }
+        } else if c == '\\' {
+            let (remaining, character) = unescape_with_options(rest.slice(1..), options)?;
+            unescaped
+                .get_or_insert_with(|| input.0[..index].to_string())
+                .push_str(&character);
+            index = input.len() - remaining.len();
+            continue;
+        } else if is_legal(c) {
+            c.len_utf8()
+        } else {
+            break;
+        };
+
+        if let Some(unescaped) = unescaped.as_mut() {
+            unescaped.push_str(&rest.0[..length]);
+        }
+        index += length;
+    }
+
+    let content = match unescaped {
+        Some(unescaped) => Cow::Owned(unescaped),
+        None => Cow::Borrowed(&input.0[..index]),
+    };
+    Ok((input.slice(index..), content))
+}
+
+// A quoted string, which only spans multiple lines if enabled in the options
+pub(crate) fn quoted_string<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32> {
+    let result = delimited!(
+        input,
         tag!("\""),
-        call!(multiline_string_content),
+        call!(quoted_string_content, options),
         tag!("\"")
-    )
-);
+    );
+    in_context(input, ParseContext::String, result)
+}
 
-named!(
-    pub string_literal_content(CompleteStr) -> StringLiteral,
+named_attr!(
+    #[doc = "Parses the content of a [`single_line_string`], without the surrounding quotes"],
+    pub single_line_string_content(CompleteStr) -> StringLiteral,
     escaped_transform!(
         take_while1!(legal_string_literal_single_line_character),
         '\\',
@@ -149,15 +304,198 @@ named!(
     )
 );
 
-named!(
-    pub string_literal(CompleteStr) -> StringLiteral,
+named_attr!(
+    #[doc = r#"Parses a quoted string that fits on a single line
+
+Like [`string`], the content of the string cannot contain a literal carriage return or newline,
+although they can still be written with the `\r` and `\n` escape sequences. Unlike [`string`],
+Heredocs are not accepted, and literal line breaks are rejected regardless of the
+[`ParseOptions`]. Use this where a value must not span multiple lines, like block labels."#],
+    pub single_line_string(CompleteStr) -> StringLiteral,
     delimited!(
         tag!("\""),
-        call!(string_literal_content),
+        call!(single_line_string_content),
         tag!("\"")
     )
 );
 
+/// Unescape the contents of a string with the escape sequences of HCL strings
+///
+/// Escape sequences like `\n` or `\u00e9` are replaced with the characters they stand for and
+/// everything else, including quotes and newlines, is kept as is. If there are no escape
+/// sequences, `s` is returned borrowed.
+///
+/// Octal escapes like `\251` have one to three digits and follow the byte semantics of C: the
+/// value must be at most `\377`, and is the code point with that value, so `\377` is `ÿ`.
+///
+/// ```rust
+/// use std::borrow::Cow;
+///
+/// use ferrous_chloride::parser::string::unescape_str;
+///
+/// assert_eq!(unescape_str("caf\\u00e9").unwrap(), "café");
+/// assert_eq!(unescape_str("café").unwrap(), Cow::Borrowed("café"));
+/// assert_eq!(unescape_str("\\377").unwrap(), "ÿ");
+/// assert!(unescape_str("\\q").is_err());
+/// assert!(unescape_str("\\400").is_err());
+/// ```
+pub fn unescape_str(s: &str) -> Result<Cow<str>, crate::Error> {
+    if !s.contains('\\') {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    let mut unescaped = String::with_capacity(s.len());
+    let mut remaining = CompleteStr(s);
+    while let Some(index) = remaining.find('\\') {
+        unescaped.push_str(&remaining[..index]);
+        let (rest, character) =
+            unescape(remaining.slice(index + 1..)).map_err(|e| crate::Error::from_err_str(&e))?;
+        unescaped.push_str(&character);
+        remaining = rest;
+    }
+    unescaped.push_str(&remaining);
+
+    Ok(Cow::Owned(unescaped))
+}
+
+/// Pairs of byte offsets in an unescaped string and in the source it was unescaped from
+///
+/// See [`unescape_str_with_map`].
+pub type OffsetMap = Vec<(usize, usize)>;
+
+/// Unescape the contents of a string like [`unescape_str`], and map the unescaped string back to
+/// `s`
+///
+/// The map has an entry `(unescaped, source)` for every character of the unescaped string, with
+/// the byte offset of the character in the unescaped string and the byte offset in `s` of the
+/// character or escape sequence it was written as. A last entry maps the end of the unescaped
+/// string to the end of `s`. The entries are sorted by both offsets, so the source of any byte of
+/// the unescaped string is found in the last entry starting at or before it.
+///
+/// ```rust
+/// use ferrous_chloride::parser::string::unescape_str_with_map;
+///
+/// let (unescaped, map) = unescape_str_with_map("a\\nb").unwrap();
+/// assert_eq!(unescaped, "a\nb");
+/// assert_eq!(map, vec![(0, 0), (1, 1), (2, 3), (3, 4)]);
+///
+/// // Find the source of the `b`
+/// let index = match map.binary_search_by_key(&2, |(unescaped, _)| *unescaped) {
+///     Ok(index) => index,
+///     Err(index) => index - 1,
+/// };
+/// assert_eq!(map[index].1, 3);
+/// ```
+pub fn unescape_str_with_map(s: &str) -> Result<(Cow<'_, str>, OffsetMap), crate::Error> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut map = Vec::with_capacity(s.len() + 1);
+    let mut remaining = CompleteStr(s);
+    loop {
+        let offset = s.len() - remaining.len();
+        let index = remaining.find('\\').unwrap_or_else(|| remaining.len());
+        for (character_index, _) in remaining[..index].char_indices() {
+            map.push((unescaped.len() + character_index, offset + character_index));
+        }
+        unescaped.push_str(&remaining[..index]);
+        if index == remaining.len() {
+            break;
+        }
+
+        map.push((unescaped.len(), offset + index));
+        let (rest, character) =
+            unescape(remaining.slice(index + 1..)).map_err(|e| crate::Error::from_err_str(&e))?;
+        unescaped.push_str(&character);
+        remaining = rest;
+    }
+    map.push((unescaped.len(), s.len()));
+
+    if unescaped.len() == s.len() && !s.contains('\\') {
+        Ok((Cow::Borrowed(s), map))
+    } else {
+        Ok((Cow::Owned(unescaped), map))
+    }
+}
+
+/// Escape a string so that it can be written as the contents of a quoted HCL string
+///
+/// `"`, `\` and control characters are escaped, while everything else, including non-ASCII
+/// characters, is kept as is. `${` and `%{` are escaped as `$${` and `%%{`, so that they are
+/// literal text instead of the start of a template sequence. This is the inverse of parsing the
+/// string as a [`quoted_template`] without sequences. If nothing needs to be escaped, `s` is
+/// returned borrowed.
+///
+/// ```rust
+/// use ferrous_chloride::parser::string::escape_str;
+///
+/// assert_eq!(escape_str("say \"café\"\n"), r#"say \"café\"\n"#);
+/// assert_eq!(escape_str("${literal}"), "$${literal}");
+/// ```
+pub fn escape_str(s: &str) -> Cow<'_, str> {
+    if !s.contains(needs_escape) && !s.contains("${") && !s.contains("%{") {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len() + 2);
+    let mut index = 0;
+    while let Some(c) = s[index..].chars().next() {
+        let rest = &s[index..];
+        if rest.starts_with("${") || rest.starts_with("%{") {
+            escaped.push(c);
+        }
+        escape_char(c, &mut escaped);
+        index += c.len_utf8();
+    }
+    Cow::Owned(escaped)
+}
+
+// Escape the text of a string expression like `escape_str`, but keep its interpolation sequences
+// and directives as they are, so that the strings nested in them are not escaped
+pub(crate) fn escape_template(s: &str) -> Cow<'_, str> {
+    if !s.contains(needs_escape) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len() + 2);
+    let mut index = 0;
+    while let Some(c) = s[index..].chars().next() {
+        let rest = &s[index..];
+        let length = if rest.starts_with("$${") || rest.starts_with("%%{") {
+            3
+        } else if rest.starts_with("${") || rest.starts_with("%{") {
+            balanced(CompleteStr(&rest[1..]), '{', '}')
+                .map(|(remaining, _)| rest.len() - remaining.len())
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        if length > 0 {
+            escaped.push_str(&rest[..length]);
+            index += length;
+        } else {
+            escape_char(c, &mut escaped);
+            index += c.len_utf8();
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn needs_escape(c: char) -> bool {
+    c == '"' || c == '\\' || c.is_control()
+}
+
+fn escape_char(c: char, escaped: &mut String) {
+    match c {
+        '"' => escaped.push_str("\\\""),
+        '\\' => escaped.push_str("\\\\"),
+        '\n' => escaped.push_str("\\n"),
+        '\r' => escaped.push_str("\\r"),
+        '\t' => escaped.push_str("\\t"),
+        c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+        c => escaped.push(c),
+    }
+}
+
 /// Heredoc marker
 #[derive(Debug, Eq, PartialEq)]
 pub struct HereDoc<'a> {
@@ -165,29 +503,137 @@ pub struct HereDoc<'a> {
     pub indented: bool,
 }
 
+impl<'a> HereDoc<'a> {
+    fn new(identifier: CompleteStr<'a>, indented: bool) -> Self {
+        Self {
+            identifier,
+            indented,
+        }
+    }
+
+    /// An advisory warning if the heredoc identifier collides with a reserved keyword
+    ///
+    /// Identifiers like `<<true` or `<<if` are legal, but are confusing to read. The warning is
+    /// only logged when the heredoc is parsed if
+    /// [`ParseOptions::warn_heredoc_keywords`] is set.
+    pub fn keyword_warning(&self) -> Option<String> {
+        if crate::constants::KEYWORDS.contains(&self.identifier.0) {
+            Some(format!(
+                "Heredoc identifier `{}` shadows a reserved keyword",
+                self.identifier
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Write a heredoc with this marker and `content`, ending with a newline
+    ///
+    /// For indented heredocs, every non-empty line and the closing marker are prefixed with
+    /// `indentation`, which is removed again when the heredoc is parsed. The content must not
+    /// contain a line that consists of the identifier alone.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parser::string::{heredoc_string, HereDoc};
+    /// use nom::types::CompleteStr;
+    ///
+    /// let marker = HereDoc {
+    ///     identifier: CompleteStr("EOT"),
+    ///     indented: true,
+    /// };
+    /// let heredoc = marker.emit("Hello\nWorld", "  ");
+    /// assert_eq!(heredoc, "<<-EOT\n  Hello\n  World\n  EOT\n");
+    ///
+    /// let (_, parsed) = heredoc_string(CompleteStr(&heredoc)).unwrap();
+    /// assert_eq!(parsed, "Hello\nWorld");
+    /// ```
+    pub fn emit(&self, content: &str, indentation: &str) -> String {
+        let indentation = if self.indented { indentation } else { "" };
+        let mut heredoc = format!("{}\n", self);
+        if !content.is_empty() {
+            heredoc.push_str(&prefix_lines(content, indentation));
+            heredoc.push('\n');
+        }
+        heredoc.push_str(indentation);
+        heredoc.push_str(&self.identifier);
+        heredoc.push('\n');
+        heredoc
+    }
+}
+
+/// Writes the opening marker, like `<<EOF` or `<<-EOF`
+impl<'a> fmt::Display for HereDoc<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let indented = if self.indented { "-" } else { "" };
+        write!(f, "<<{}{}", indented, self.identifier)
+    }
+}
+
+// Heredoc identifier, using the identifier rule of the options if any
+fn heredoc_identifier<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, CompleteStr<'a>, u32> {
+    match options.get_identifier_char_validator() {
+        Some(is_valid) => {
+            let (remaining, _) = custom_identifier(input, is_valid)?;
+            Ok((remaining, input.slice(..input.len() - remaining.len())))
+        }
+        None => crate::utils::while_predicate1(input, |c| c.is_alphanumeric() || c == '_'),
+    }
+}
+
+/// Start of heredoc identifier with the provided [`ParseOptions`]. Must end with an EOL
+///
+/// EOL is not consumed. The lines of a Heredoc end with `\n` or `\r\n`. A lone `\r` after the
+/// identifier is a failure with [`Error::BareCarriageReturn`](crate::Error::BareCarriageReturn).
+pub fn heredoc_begin_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, HereDoc<'a>, u32> {
+    let (remaining, heredoc) = do_parse!(
+        input,
+        tag!("<<")
+            >> indented: opt!(complete!(tag!("-")))
+            >> identifier: call!(heredoc_identifier, options)
+            >> (HereDoc::new(identifier, indented == Some(CompleteStr("-"))))
+    )?;
+    if options.warn_heredoc_keywords {
+        if let Some(warning) = heredoc.keyword_warning() {
+            warn!("{}", warning);
+        }
+    }
+    if is_bare_carriage_return(&remaining) {
+        return Err(nom::Err::Failure(error_position!(
+            remaining,
+            ErrorKind::Custom(InternalKind::BareCarriageReturn as u32)
+        )));
+    }
+
+    let (remaining, _) = peek!(remaining, call!(nom::eol))?;
+    Ok((remaining, heredoc))
+}
+
+fn is_bare_carriage_return(s: &str) -> bool {
+    s.starts_with('\r') && !s.starts_with("\r\n")
+}
+
 // Start of heredoc identifier. Must end with an EOL
 // EOL is not consumed
 named!(
     pub heredoc_begin(CompleteStr) -> HereDoc,
-    do_parse!(
-        tag!("<<")
-        >> indented: opt!(complete!(tag!("-")))
-        >> identifier: call!(crate::utils::while_predicate1, |c| c.is_alphanumeric() || c == '_')
-        >> peek!(call!(nom::eol))
-        >> (HereDoc {
-                identifier,
-                indented: indented == Some(CompleteStr("-"))
-           })
-    )
+    call!(heredoc_begin_with_options, &ParseOptions::default())
 );
 
-/// End of heredoc. Must end with an EOL
+/// End of heredoc with the provided [`ParseOptions`]. Must end with an EOL
 /// EOL is not consumed
 ///
-/// Returns the identation level if the Heredoc was marked as indented
-pub fn heredoc_end<'a>(
+/// Returns the identation level in columns if the Heredoc was marked as indented. Tabs are
+/// [`heredoc_tab_width`](ParseOptions::heredoc_tab_width) columns wide.
+pub fn heredoc_end_with_options<'a>(
     input: CompleteStr<'a>,
     identifier: &'_ HereDoc<'_>,
+    options: &ParseOptions,
 ) -> IResult<CompleteStr<'a>, usize, u32> {
     let (remaining, identation) = do_parse!(
         input,
@@ -199,353 +645,2704 @@ pub fn heredoc_end<'a>(
     )?;
 
     if identifier.indented {
-        Ok((remaining, identation.len()))
+        let width = identation
+            .chars()
+            .map(|c| {
+                if c == '\t' {
+                    options.heredoc_tab_width
+                } else {
+                    1
+                }
+            })
+            .sum();
+        Ok((remaining, width))
     } else {
         Ok((remaining, 0))
     }
 }
 
+/// End of heredoc. Must end with an EOL
+/// EOL is not consumed
+///
+/// Returns the identation level if the Heredoc was marked as indented
+pub fn heredoc_end<'a>(
+    input: CompleteStr<'a>,
+    identifier: &'_ HereDoc<'_>,
+) -> IResult<CompleteStr<'a>, usize, u32> {
+    heredoc_end_with_options(input, identifier, &ParseOptions::default())
+}
+
+// The lines of a Heredoc up to its end marker, including the line ending in front of the marker,
+// and the indentation to remove
+fn heredoc_lines<'a>(
+    input: CompleteStr<'a>,
+    identifier: &HereDoc,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, (CompleteStr<'a>, usize), u32> {
+    let (remaining, (content, indentation)) =
+        take_till_match!(input, call!(heredoc_end_with_options, identifier, options))?;
+    // The end marker starts with the line ending
+    let (_, line_ending) = nom::eol(input.slice(content.len()..))?;
+    let lines = input.slice(..content.len() + line_ending.len());
+    Ok((remaining, (lines, indentation)))
+}
+
+// Remove the line ending in front of the end marker from the lines of a Heredoc
+fn trim_line_ending(lines: &str) -> &str {
+    lines
+        .strip_suffix("\r\n")
+        .or_else(|| lines.strip_suffix('\n'))
+        .unwrap_or(lines)
+}
+
+// The input at the lone `\r` next to the end marker of a Heredoc that would only be terminated if a
+// lone `\r` was a line ending, like on classic Mac OS
+fn bare_carriage_return_at_end<'a>(
+    input: CompleteStr<'a>,
+    identifier: &HereDoc,
+    options: &ParseOptions,
+) -> Option<CompleteStr<'a>> {
+    let bare = |index: &usize| is_bare_carriage_return(&input[*index..]);
+    let indices: Vec<_> = input.char_indices().map(|(index, _)| index).collect();
+    if !indices.iter().any(bare) {
+        return None;
+    }
+
+    // Replacing lone carriage returns with newlines keeps the byte offsets
+    let normalized: String = input
+        .char_indices()
+        .map(|(index, c)| if bare(&index) { '\n' } else { c })
+        .collect();
+    let (rest, _) = preceded!(
+        CompleteStr(&normalized),
+        call!(nom::eol),
+        call!(heredoc_lines, identifier, options)
+    )
+    .ok()?;
+
+    // The line ending after the end marker is part of the Heredoc here
+    let end = (normalized.len() - rest.len() + 1).min(input.len());
+    indices
+        .into_iter()
+        .rev()
+        .find(|index| *index < end && bare(index))
+        .map(|index| input.slice(index..))
+}
+
+// Lines of a Heredoc before they are unindented, and the indentation to remove. The lines include
+// the line ending in front of the end marker, unless the Heredoc is empty.
+//
+// Once the start of the Heredoc has been parsed, a missing end marker is a failure so that a
+// truncated Heredoc is reported instead of being tried as some other production. If the end
+// marker is only missing because lone carriage returns are used as line endings, that is reported
+// instead. So is a line indented inconsistently with the end marker, if that is checked.
+pub(crate) fn heredoc_content<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, (CompleteStr<'a>, usize), u32> {
+    let (remaining, identifier) = heredoc_begin_with_options(input, options)?;
+    let content = alt!(
+        remaining,
+        call!(heredoc_end_with_options, &identifier, options) => {|_| (CompleteStr(""), 0) }
+        | preceded!(call!(nom::eol), call!(heredoc_lines, &identifier, options))
+    );
+
+    match content {
+        Err(nom::Err::Error(_)) => {
+            match bare_carriage_return_at_end(remaining, &identifier, options) {
+                Some(carriage_return) => Err(nom::Err::Failure(error_position!(
+                    carriage_return,
+                    ErrorKind::Custom(InternalKind::BareCarriageReturn as u32)
+                ))),
+                None => Err(nom::Err::Failure(error_position!(
+                    input,
+                    ErrorKind::Custom(InternalKind::UnterminatedHeredoc as u32)
+                ))),
+            }
+        }
+        Ok((rest, (lines, indentation)))
+            if options.strict_heredoc_indentation && identifier.indented && !lines.is_empty() =>
+        {
+            // The lines are followed by the end marker in the input
+            let offset = lines.as_ptr() as usize - remaining.as_ptr() as usize + lines.len();
+            let marker = remaining[offset..].trim_start_matches(&[' ', '\t'][..]);
+            let marker = &remaining[offset..remaining.len() - marker.len()];
+            match inconsistently_indented_line(&lines, marker) {
+                Some(line) => Err(nom::Err::Failure(error_position!(
+                    CompleteStr(&remaining[line.as_ptr() as usize - remaining.as_ptr() as usize..]),
+                    ErrorKind::Custom(InternalKind::InconsistentHeredocIndent as u32)
+                ))),
+                None => Ok((rest, (lines, indentation))),
+            }
+        }
+        content => content,
+    }
+}
+
+/// Parse a Heredoc string with the provided [`ParseOptions`]
+///
+/// The lines of a Heredoc, including the line with the end marker, end with `\n` or `\r\n`. A lone
+/// `\r` is kept as part of the content, so a Heredoc using it as a line ending fails with
+/// [`Error::BareCarriageReturn`](crate::Error::BareCarriageReturn).
+///
+/// The line endings of the Heredoc are kept unless
+/// [`normalize_newlines`](ParseOptions::normalize_newlines) is set. The line ending in front of
+/// the end marker is only part of the string if
+/// [`keep_heredoc_trailing_newline`](ParseOptions::keep_heredoc_trailing_newline) is set.
+pub fn heredoc_string_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32> {
+    let (remaining, (lines, indentation)) = in_context(
+        input,
+        ParseContext::Heredoc,
+        heredoc_content(input, options),
+    )?;
+    let content = if options.keep_heredoc_trailing_newline {
+        lines.0
+    } else {
+        trim_line_ending(lines.0)
+    };
+    let string = unindent_heredoc(content, indentation, options.heredoc_tab_width);
+
+    if options.normalize_newlines && string.contains("\r\n") {
+        Ok((remaining, Cow::Owned(string.replace("\r\n", "\n"))))
+    } else {
+        Ok((remaining, string))
+    }
+}
+
 // Parse a Heredoc string
 named!(
     pub heredoc_string(CompleteStr) -> Cow<str>,
-    do_parse!(
-        identifier: call!(heredoc_begin)
-        >> content: alt!(
-            call!(heredoc_end, &identifier) => {|_| ("", 0) }
-            | do_parse!(
-                call!(nom::eol)
-                >> content: take_till_match!(call!(heredoc_end, &identifier))
-                >> ((content.0).0, content.1)
-            )
-        )
-        >> (unindent_heredoc(content.0, content.1))
-    )
+    call!(heredoc_string_with_options, &ParseOptions::default())
 );
 
-named!(
-    pub string(CompleteStr) -> Cow<str>,
+/// Parse a quoted string or a Heredoc string with the provided [`ParseOptions`]
+pub fn string_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32> {
     alt!(
-        quoted_string => { |s| Cow::Owned(s) }
-        | heredoc_string
+        input,
+        call!(quoted_string, options) | call!(heredoc_string_with_options, options)
     )
+}
+
+named_attr!(
+    #[doc = r#"Parses a quoted string or a Heredoc string
+
+The content of a quoted string cannot contain a literal carriage return or newline, as required by
+HCL. Strings spanning multiple lines must be written as Heredocs, or with the `\r` and `\n` escape
+sequences. Literal line breaks can be allowed with
+[`multiline_strings`](ParseOptions::multiline_strings).
+
+Interpolation sequences and directives are kept as unparsed text, and the quoted strings nested in
+them do not end the string."#],
+    pub string(CompleteStr) -> Cow<str>,
+    call!(string_with_options, &ParseOptions::default())
 );
 
-// TODO:
-// - Interpolation `${test("...")}`
+/// Parse a quoted string or a Heredoc string as a template expression with the provided
+/// [`ParseOptions`]
+///
+/// Like [`string_with_options`], the interpolation sequences and directives are kept as unparsed
+/// text. They are checked like [`template`] does, so an unclosed sequence, or a directive without
+/// its matching end, is a failure.
+///
+/// ```rust
+/// use ferrous_chloride::parser::string::template_string_with_options;
+/// use ferrous_chloride::parser::ParseOptions;
+/// use ferrous_chloride::nom::types::CompleteStr;
+///
+/// let options = ParseOptions::default();
+/// let input = CompleteStr(r#""%{ if a }x%{ endif }""#);
+/// assert!(template_string_with_options(input, &options).is_ok());
+/// let input = CompleteStr(r#""%{ if a }x""#);
+/// assert!(template_string_with_options(input, &options).is_err());
+/// ```
+pub fn template_string_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32> {
+    let (remaining, string) = string_with_options(input, options)?;
+    if string.contains("${") || string.contains("%{") {
+        let parsed = if input.starts_with('"') {
+            let template = quoted_template_with_options(input, options);
+            in_context(input, ParseContext::String, template)
+        } else {
+            let template = heredoc_template_with_options(input, options);
+            in_context(input, ParseContext::Heredoc, template)
+        };
+        match parsed {
+            Ok(_) => {}
+            Err(nom::Err::Error(error)) => return Err(nom::Err::Failure(error)),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok((remaining, string))
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Parse one or more adjacent strings with the provided [`ParseOptions`] and join them together
+///
+/// See [`concatenated_strings`].
+pub fn concatenated_strings_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32> {
+    let (mut remaining, mut concatenated) = string_with_options(input, options)?;
+    loop {
+        match preceded!(
+            remaining,
+            call!(nom::multispace0),
+            call!(string_with_options, options)
+        ) {
+            Ok((rest, next)) => {
+                concatenated.to_mut().push_str(&next);
+                remaining = rest;
+            }
+            Err(nom::Err::Error(_)) => break,
+            Err(e) => return Err(e),
+        }
+    }
 
-    use crate::utils::*;
+    Ok((remaining, concatenated))
+}
 
-    #[test]
-    fn unescaping_works_correctly() {
-        let test_cases = [
-            (r#"a"#, "\x07"),
-            (r#"b"#, "\x08"),
-            (r#"f"#, "\x0c"),
-            (r#"n"#, "\n"),
-            (r#"r"#, "\r"),
-            (r#"t"#, "\t"),
-            (r#"v"#, "\x0b"),
-            (r#"\"#, "\\"),
-            (r#"""#, "\""),
-            ("?", "?"),
-            (r#"xff"#, "ÿ"),           // Hex
-            (r#"251"#, "©"),           // Octal
-            (r#"uD000"#, "\u{D000}"),   // Unicode up to 4 bytes
-            (r#"U29000"#, "\u{29000}"), // Unicode up to 8 bytes... but max unicode is only up to 6
-        ];
+named_attr!(
+    #[doc = r#"Parses one or more adjacent strings and joins them together
 
-        for (input, expected) in test_cases.iter() {
-            println!("Testing {}", input);
-            let actual = unescape(CompleteStr(input)).map(|(i, o)| (i, o.into_owned()));
-            assert_eq!(ResultUtilsString::unwrap_output(actual), *expected);
+The strings, quoted or Heredoc, may only be separated by whitespace, including newlines, so a
+long message can be split across lines. Whitespace after the last string is not consumed. A
+single string without escape sequences is returned borrowed."#],
+    pub concatenated_strings(CompleteStr) -> Cow<str>,
+    call!(concatenated_strings_with_options, &ParseOptions::default())
+);
+
+/// A part of a [Template](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#templates)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplatePart<'a> {
+    /// Literal text
+    Literal(Cow<'a, str>),
+    /// An interpolation sequence like `${ var.name }`
+    ///
+    /// The expression is kept as unparsed text. Like directives, the sequence may have `~`
+    /// markers, like `${~ var.name ~}`, to strip the whitespace of the literals around it.
+    Interpolation(Cow<'a, str>),
+    /// A template directive like `%{ if }` or `%{ for }`
+    Directive(Directive<'a>),
+}
+
+/// A template directive
+///
+/// The expressions in the directives are kept as unparsed text. The bodies of the directives are
+/// templates themselves, and may contain interpolations and further directives.
+///
+/// ```ebnf
+/// TemplateIf = (
+///     ("%{" "if" Expression "}")
+///     Template
+///     (
+///         ("%{" "else" "}")
+///         Template
+///     )?
+///     ("%{" "endif" "}")
+/// );
+///
+/// TemplateFor = (
+///     ("%{" "for" Identifier ("," Identifier)? "in" Expression "}")
+///     Template
+///     ("%{" "endfor" "}")
+/// );
+/// ```
+///
+/// Each of the `%{ ... }` sequences may have a `~` after the `%{` or before the `}` to strip the
+/// whitespace around it, like `%{~ if x ~}`. The whitespace is stripped from the literal text
+/// when the directive is parsed. See [`Trim`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Directive<'a> {
+    /// `%{ if condition }truthy%{ else }falsy%{ endif }`
+    If {
+        condition: Cow<'a, str>,
+        truthy: Vec<TemplatePart<'a>>,
+        falsy: Vec<TemplatePart<'a>>,
+        trim: DirectiveTrim,
+    },
+    /// `%{ for key, value in collection }body%{ endfor }`
+    For {
+        key: Option<Identifier<'a>>,
+        value: Identifier<'a>,
+        collection: Cow<'a, str>,
+        body: Vec<TemplatePart<'a>>,
+        trim: DirectiveTrim,
+    },
+}
+
+/// Whitespace stripping markers of a template sequence
+///
+/// Only spaces and tabs on the same line as the sequence, and the newline ending or beginning
+/// that line, are stripped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Trim {
+    /// `%{~` or `${~`: Strip the spaces and tabs before the sequence, up to and including a
+    /// newline
+    pub left: bool,
+    /// `~}`: Strip the spaces and tabs after the sequence, up to and including a newline
+    pub right: bool,
+}
+
+/// Whitespace stripping markers of the sequences of a [`Directive`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DirectiveTrim {
+    /// The opening `%{ if }` or `%{ for }`
+    pub opening: Trim,
+    /// The `%{ else }` of an `if` directive. Unused otherwise.
+    pub alternative: Trim,
+    /// The closing `%{ endif }` or `%{ endfor }`
+    pub closing: Trim,
+}
+
+impl<'a> Directive<'a> {
+    fn trim(&self) -> DirectiveTrim {
+        match self {
+            Directive::If { trim, .. } | Directive::For { trim, .. } => *trim,
         }
     }
 
-    #[test]
-    #[should_panic(expected = "Invalid Unicode Code Points \\UD800")]
-    fn unescaping_invalid_unicode_errors() {
-        let actual = unescape(CompleteStr("UD800")).map(|(i, o)| (i, o.into_owned()));
-        ResultUtilsString::unwrap_output(actual);
+    // Markers stripping the whitespace around the whole directive
+    fn outer_trim(&self) -> Trim {
+        let trim = self.trim();
+        Trim {
+            left: trim.opening.left,
+            right: trim.closing.right,
+        }
+    }
+}
+
+impl<'a> crate::AsOwned for TemplatePart<'a> {
+    type Output = TemplatePart<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        match self {
+            TemplatePart::Literal(literal) => {
+                TemplatePart::Literal(Cow::Owned(literal.to_string()))
+            }
+            TemplatePart::Interpolation(expression) => {
+                TemplatePart::Interpolation(Cow::Owned(expression.to_string()))
+            }
+            TemplatePart::Directive(directive) => TemplatePart::Directive(directive.as_owned()),
+        }
+    }
+}
+
+impl<'a> crate::AsOwned for Directive<'a> {
+    type Output = Directive<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        match self {
+            Directive::If {
+                condition,
+                truthy,
+                falsy,
+                trim,
+            } => Directive::If {
+                condition: Cow::Owned(condition.to_string()),
+                truthy: truthy.as_owned(),
+                falsy: falsy.as_owned(),
+                trim: *trim,
+            },
+            Directive::For {
+                key,
+                value,
+                collection,
+                body,
+                trim,
+            } => Directive::For {
+                key: key.as_ref().map(|key| Cow::Owned(key.to_string())),
+                value: Cow::Owned(value.to_string()),
+                collection: Cow::Owned(collection.to_string()),
+                body: body.as_owned(),
+                trim: *trim,
+            },
+        }
+    }
+}
+
+/// A parsed [Template](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#templates)
+///
+/// The parts of the template are kept in the order they appear in the source. `Template`
+/// dereferences to a `Vec` of [`TemplatePart`]s.
+///
+/// ```rust
+/// use ferrous_chloride::parser::string::{template, TemplatePart};
+/// use ferrous_chloride::nom::types::CompleteStr;
+///
+/// let (_, parsed) = template(CompleteStr(r#""Hello, ${var.name}%{ if x }!%{ endif }""#)).unwrap();
+/// assert_eq!(parsed.len(), 3);
+/// assert_eq!(parsed[0], TemplatePart::Literal("Hello, ".into()));
+/// assert_eq!(parsed.interpolations(), vec!["var.name"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Template<'a>(Vec<TemplatePart<'a>>);
+
+impl<'a> Template<'a> {
+    /// Create a template from its parts
+    pub fn new(parts: Vec<TemplatePart<'a>>) -> Self {
+        Template(parts)
+    }
+
+    /// Unwrap the template into the underlying `Vec` of parts
+    pub fn into_inner(self) -> Vec<TemplatePart<'a>> {
+        self.0
+    }
+
+    /// Returns the unparsed expressions of the interpolation sequences of the template, including
+    /// the ones in the bodies of directives, in the order they appear in the source
+    pub fn interpolations(&self) -> Vec<&str> {
+        fn collect<'b>(parts: &'b [TemplatePart], interpolations: &mut Vec<&'b str>) {
+            for part in parts {
+                match part {
+                    TemplatePart::Literal(_) => {}
+                    TemplatePart::Interpolation(expression) => interpolations.push(expression),
+                    TemplatePart::Directive(Directive::If { truthy, falsy, .. }) => {
+                        collect(truthy, interpolations);
+                        collect(falsy, interpolations);
+                    }
+                    TemplatePart::Directive(Directive::For { body, .. }) => {
+                        collect(body, interpolations)
+                    }
+                }
+            }
+        }
+
+        let mut interpolations = vec![];
+        collect(&self.0, &mut interpolations);
+        interpolations
+    }
+}
+
+impl<'a> Deref for Template<'a> {
+    type Target = Vec<TemplatePart<'a>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> DerefMut for Template<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'a> From<Vec<TemplatePart<'a>>> for Template<'a> {
+    fn from(parts: Vec<TemplatePart<'a>>) -> Self {
+        Template(parts)
+    }
+}
+
+impl<'a> FromIterator<TemplatePart<'a>> for Template<'a> {
+    fn from_iter<T: IntoIterator<Item = TemplatePart<'a>>>(iter: T) -> Self {
+        Template(iter.into_iter().collect())
+    }
+}
+
+impl<'a> crate::AsOwned for Template<'a> {
+    type Output = Template<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        Template(self.0.as_owned())
+    }
+}
+
+// Parser for the literal text of a template, which differs between quoted templates and
+// heredoc templates
+type TemplateLiteral = for<'a> fn(CompleteStr<'a>) -> IResult<CompleteStr<'a>, Cow<'a, str>, u32>;
+
+// Unparsed expression text in a template sequence up to, but not including, the closing `}`.
+// Nested braces, quoted strings, Heredocs and comments are skipped over so that they do not
+// terminate the sequence early.
+fn template_expression_text(input: CompleteStr) -> IResult<CompleteStr, CompleteStr, u32> {
+    let (remaining, text) = until_unbalanced(input, '{', '}', usize::MAX)?;
+    if text.trim().is_empty() {
+        return Err(nom::Err::Error(error_position!(
+            input,
+            ErrorKind::TakeUntil
+        )));
+    }
+    Ok((remaining, CompleteStr(text.trim())))
+}
+
+// Literal text in a template up to the next template sequence. `$${` and `%%{` are escaped
+// literal `${` and `%{`.
+//
+// In quoted templates, the text also ends at the closing quote and backslash escape sequences
+// are unescaped.
+fn template_text(input: CompleteStr, quoted: bool) -> IResult<CompleteStr, Cow<str>, u32> {
+    let mut remaining = input;
+    let mut unescaped = String::new();
+    let mut escaped = false;
+
+    while let Some(c) = remaining.chars().next() {
+        if remaining.starts_with("${")
+            || remaining.starts_with("%{")
+            || (quoted && remaining.starts_with('"'))
+        {
+            break;
+        } else if remaining.starts_with("$${") || remaining.starts_with("%%{") {
+            unescaped.push_str(&remaining[1..3]);
+            remaining = remaining.slice(3..);
+            escaped = true;
+        } else if quoted && remaining.starts_with('\\') {
+            let (rest, character) = unescape(remaining.slice(1..))?;
+            unescaped.push_str(&character);
+            remaining = rest;
+            escaped = true;
+        } else {
+            unescaped.push(c);
+            remaining = remaining.slice(c.len_utf8()..);
+        }
+    }
+
+    let consumed = input.len() - remaining.len();
+    if consumed == 0 {
+        Err(nom::Err::Error(error_position!(
+            input,
+            ErrorKind::TakeUntil
+        )))
+    } else if escaped {
+        Ok((remaining, Cow::Owned(unescaped)))
+    } else {
+        Ok((remaining, Cow::Borrowed(&input[..consumed])))
+    }
+}
+
+fn template_literal(input: CompleteStr) -> IResult<CompleteStr, Cow<str>, u32> {
+    template_text(input, false)
+}
+
+fn quoted_template_literal(input: CompleteStr) -> IResult<CompleteStr, Cow<str>, u32> {
+    template_text(input, true)
+}
+
+fn template_parts<'a>(
+    input: CompleteStr<'a>,
+    literal: TemplateLiteral,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Vec<TemplatePart<'a>>, u32> {
+    let (remaining, parts) = many0!(
+        input,
+        alt!(
+            call!(template_directive_with, literal, options) => { |directive: Directive<'a>| {
+                let trim = directive.outer_trim();
+                (TemplatePart::Directive(directive), trim)
+            }}
+            | template_interpolation_with_trim
+            | call!(literal) => { |literal| (TemplatePart::Literal(literal), Trim::default()) }
+        )
+    )?;
+    let (mut parts, trims): (Vec<_>, Vec<_>) = parts.into_iter().unzip();
+
+    // Strip the whitespace around directives and interpolations. The whitespace inside the
+    // directives has been stripped when they were parsed.
+    for (index, trim) in trims.into_iter().enumerate() {
+        if trim.left && index > 0 {
+            trim_literal_end(&mut parts[index - 1]);
+        }
+        if trim.right && index + 1 < parts.len() {
+            trim_literal_start(&mut parts[index + 1]);
+        }
+    }
+    parts.retain(|part| match part {
+        TemplatePart::Literal(literal) => !literal.is_empty(),
+        _ => true,
+    });
+
+    Ok((remaining, parts))
+}
+
+// Strip the spaces and tabs at the start of a literal, up to and including a newline
+fn trim_literal_start(part: &mut TemplatePart) {
+    if let TemplatePart::Literal(literal) = part {
+        let trimmed = literal.trim_start_matches(&[' ', '\t'][..]);
+        let trimmed = trimmed
+            .strip_prefix("\r\n")
+            .or_else(|| trimmed.strip_prefix('\n'))
+            .unwrap_or(trimmed);
+        let start = literal.len() - trimmed.len();
+        *literal = match literal {
+            Cow::Borrowed(literal) => Cow::Borrowed(&literal[start..]),
+            Cow::Owned(literal) => Cow::Owned(literal[start..].to_string()),
+        };
+    }
+}
+
+// Strip the spaces and tabs at the end of a literal, up to and including a newline
+fn trim_literal_end(part: &mut TemplatePart) {
+    if let TemplatePart::Literal(literal) = part {
+        let trimmed = literal.trim_end_matches(&[' ', '\t'][..]);
+        let trimmed = trimmed
+            .strip_suffix("\r\n")
+            .or_else(|| trimmed.strip_suffix('\n'))
+            .unwrap_or(trimmed);
+        let end = trimmed.len();
+        *literal = match literal {
+            Cow::Borrowed(literal) => Cow::Borrowed(&literal[..end]),
+            Cow::Owned(literal) => Cow::Owned(literal[..end].to_string()),
+        };
+    }
+}
+
+fn trim_parts_start(parts: &mut Vec<TemplatePart>) {
+    if let Some(part) = parts.first_mut() {
+        trim_literal_start(part);
+    }
+    if let Some(TemplatePart::Literal(literal)) = parts.first() {
+        if literal.is_empty() {
+            parts.remove(0);
+        }
+    }
+}
+
+fn trim_parts_end(parts: &mut Vec<TemplatePart>) {
+    if let Some(part) = parts.last_mut() {
+        trim_literal_end(part);
+    }
+    if let Some(TemplatePart::Literal(literal)) = parts.last() {
+        if literal.is_empty() {
+            parts.pop();
+        }
+    }
+}
+
+// `%{` with an optional `~` marker
+named!(
+    directive_open(CompleteStr) -> bool,
+    do_parse!(
+        tag!("%{")
+        >> left: opt!(complete!(char!('~')))
+        >> call!(nom::multispace0)
+        >> (left.is_some())
+    )
+);
+
+// `}` with an optional `~` marker
+named!(
+    directive_close(CompleteStr) -> bool,
+    do_parse!(
+        call!(nom::multispace0)
+        >> right: opt!(complete!(char!('~')))
+        >> char!('}')
+        >> (right.is_some())
+    )
+);
+
+// Expression text of a directive or interpolation and its closing `}`, with a `~` marker that
+// ended up in the text
+named!(
+    directive_expression(CompleteStr) -> (CompleteStr, bool),
+    do_parse!(
+        text: call!(template_expression_text)
+        >> char!('}')
+        >> (if text.ends_with('~') {
+            (CompleteStr(text[..text.len() - 1].trim_end()), true)
+        } else {
+            (text, false)
+        })
+    )
+);
+
+// A directive without an expression like `%{ else }` or `%{ endif }`
+fn directive_keyword<'a>(
+    input: CompleteStr<'a>,
+    keyword: &'static str,
+) -> IResult<CompleteStr<'a>, Trim, u32> {
+    do_parse!(
+        input,
+        left: call!(directive_open)
+        >> tag!(keyword)
+        >> right: call!(directive_close)
+        >> (Trim { left, right })
+    )
+}
+
+named!(
+    template_if_begin(CompleteStr) -> (CompleteStr, Trim),
+    do_parse!(
+        left: call!(directive_open)
+        >> tag!("if")
+        >> call!(nom::multispace1)
+        >> condition: call!(directive_expression)
+        >> (condition.0, Trim { left, right: condition.1 })
+    )
+);
+
+named!(
+    template_for_begin(CompleteStr) -> (Identifier, Option<Identifier>, CompleteStr, Trim),
+    do_parse!(
+        left: call!(directive_open)
+        >> tag!("for")
+        >> call!(nom::multispace1)
+        >> first: call!(identifier)
+        >> second: opt!(
+            complete!(
+                preceded!(
+                    delimited!(call!(nom::multispace0), char!(','), call!(nom::multispace0)),
+                    call!(identifier)
+                )
+            )
+        )
+        >> call!(nom::multispace1)
+        >> tag!("in")
+        >> call!(nom::multispace1)
+        >> collection: call!(directive_expression)
+        >> (first, second, collection.0, Trim { left, right: collection.1 })
+    )
+);
+
+// Once the opening of a directive has been parsed, failing to find the matching end is fatal so
+// that the error names the unclosed directive instead of some enclosing one.
+fn unclosed_directive<O>(input: CompleteStr) -> IResult<CompleteStr, O, u32> {
+    Err(nom::Err::Failure(error_position!(
+        input,
+        ErrorKind::Custom(InternalKind::UnclosedTemplateDirective as u32)
+    )))
+}
+
+fn template_if<'a>(
+    input: CompleteStr<'a>,
+    literal: TemplateLiteral,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Directive<'a>, u32> {
+    let (remaining, (condition, opening)) = template_if_begin(input)?;
+    let (_, options) = nested_options(input, options)?;
+    let result = do_parse!(
+        remaining,
+        truthy: call!(template_parts, literal, &options)
+        >> falsy: opt!(
+            pair!(
+                call!(directive_keyword, "else"),
+                call!(template_parts, literal, &options)
+            )
+        )
+        >> closing: call!(directive_keyword, "endif")
+        >> (truthy, falsy, closing)
+    );
+
+    match result {
+        Ok((remaining, (mut truthy, falsy, closing))) => {
+            let has_alternative = falsy.is_some();
+            let (alternative, mut falsy) = falsy.unwrap_or_default();
+            if opening.right {
+                trim_parts_start(&mut truthy);
+            }
+            if alternative.left {
+                trim_parts_end(&mut truthy);
+            }
+            if alternative.right {
+                trim_parts_start(&mut falsy);
+            }
+            if closing.left {
+                if has_alternative {
+                    trim_parts_end(&mut falsy);
+                } else {
+                    trim_parts_end(&mut truthy);
+                }
+            }
+
+            Ok((
+                remaining,
+                Directive::If {
+                    condition: Cow::Borrowed(condition.0),
+                    truthy,
+                    falsy,
+                    trim: DirectiveTrim {
+                        opening,
+                        alternative,
+                        closing,
+                    },
+                },
+            ))
+        }
+        Err(nom::Err::Error(_)) => unclosed_directive(input),
+        Err(e) => Err(e),
+    }
+}
+
+fn template_for<'a>(
+    input: CompleteStr<'a>,
+    literal: TemplateLiteral,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Directive<'a>, u32> {
+    let (remaining, (first, second, collection, opening)) = template_for_begin(input)?;
+    let (_, options) = nested_options(input, options)?;
+    let result = do_parse!(
+        remaining,
+        body: call!(template_parts, literal, &options)
+        >> closing: call!(directive_keyword, "endfor")
+        >> (body, closing)
+    );
+
+    match result {
+        Ok((remaining, (mut body, closing))) => {
+            let (key, value) = match second {
+                Some(value) => (Some(first), value),
+                None => (None, first),
+            };
+            if opening.right {
+                trim_parts_start(&mut body);
+            }
+            if closing.left {
+                trim_parts_end(&mut body);
+            }
+
+            Ok((
+                remaining,
+                Directive::For {
+                    key,
+                    value,
+                    collection: Cow::Borrowed(collection.0),
+                    body,
+                    trim: DirectiveTrim {
+                        opening,
+                        alternative: Default::default(),
+                        closing,
+                    },
+                },
+            ))
+        }
+        Err(nom::Err::Error(_)) => unclosed_directive(input),
+        Err(e) => Err(e),
+    }
+}
+
+fn template_directive_with<'a>(
+    input: CompleteStr<'a>,
+    literal: TemplateLiteral,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Directive<'a>, u32> {
+    alt!(
+        input,
+        call!(template_if, literal, options) | call!(template_for, literal, options)
+    )
+}
+
+named_attr!(
+    #[doc = r#"Parses a template directive like `%{ if cond }yes%{ else }no%{ endif }`
+
+The bodies of the directive are parsed as heredoc templates."#],
+    pub template_directive(CompleteStr) -> Directive,
+    call!(template_directive_with, template_literal, &ParseOptions::default())
+);
+
+// An interpolation sequence and its `~` markers
+named!(
+    template_interpolation_with_trim(CompleteStr) -> (TemplatePart, Trim),
+    do_parse!(
+        tag!("${")
+        >> left: opt!(complete!(char!('~')))
+        >> expression: call!(directive_expression)
+        >> ((
+            TemplatePart::Interpolation(Cow::Borrowed((expression.0).0)),
+            Trim { left: left.is_some(), right: expression.1 }
+        ))
+    )
+);
+
+named_attr!(
+    #[doc = r#"Parses an interpolation sequence like `${ var.name }`
+
+The `~` markers of the sequence are not part of the expression. They are applied to the literals
+around the sequence when it is parsed as part of a template."#],
+    pub template_interpolation(CompleteStr) -> TemplatePart,
+    map!(call!(template_interpolation_with_trim), |(part, _)| part)
+);
+
+/// Parse a quoted template into its parts with the provided [`ParseOptions`]
+///
+/// Directives nested deeper than the [maximum depth](ParseOptions::max_depth) are a failure.
+pub fn quoted_template_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Template<'a>, u32> {
+    map!(
+        input,
+        delimited!(
+            tag!("\""),
+            call!(template_parts, quoted_template_literal, options),
+            tag!("\"")
+        ),
+        Template
+    )
+}
+
+named_attr!(
+    #[doc = r#"Parses a quoted template like `"Hello, ${ var.name }!"` into its parts
+
+Unlike [`string`], interpolation sequences and directives are not treated as literal text."#],
+    pub quoted_template(CompleteStr) -> Template,
+    call!(quoted_template_with_options, &ParseOptions::default())
+);
+
+// The whole input as a heredoc template
+pub(crate) fn heredoc_template_parts<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Vec<TemplatePart<'a>>, u32> {
+    terminated!(
+        input,
+        call!(template_parts, template_literal, options),
+        eof!()
+    )
+}
+
+/// Parse a Heredoc into the parts of its template with the provided [`ParseOptions`]
+///
+/// See [`heredoc_template`]. Directives nested deeper than the
+/// [maximum depth](ParseOptions::max_depth) are a failure.
+pub fn heredoc_template_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Template<'a>, u32> {
+    use crate::AsOwned;
+
+    let (remaining, (lines, indentation)) = heredoc_content(input, options)?;
+    let content = CompleteStr(trim_line_ending(lines.0));
+    // Errors are reported against the content as it appears in the input
+    let (_, parts) = heredoc_template_parts(content, options)?;
+
+    match unindent_heredoc(content.0, indentation, options.heredoc_tab_width) {
+        Cow::Borrowed(_) => Ok((remaining, Template(parts))),
+        Cow::Owned(unindented) => match heredoc_template_parts(CompleteStr(&unindented), options) {
+            Ok((_, parts)) => Ok((remaining, Template(parts.as_owned()))),
+            Err(_) => Err(nom::Err::Error(error_position!(input, ErrorKind::Verify))),
+        },
+    }
+}
+
+/// Parses a Heredoc into the parts of its template
+///
+/// The Heredoc is unindented in the same way as [`heredoc_string`] before the template is
+/// parsed.
+pub fn heredoc_template(input: CompleteStr) -> IResult<CompleteStr, Template, u32> {
+    heredoc_template_with_options(input, &ParseOptions::default())
+}
+
+named_attr!(
+    #[doc = r#"Parses a quoted template or a Heredoc template into its parts"#],
+    pub template(CompleteStr) -> Template,
+    alt!(
+        quoted_template
+        | heredoc_template
+    )
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::*;
+
+    #[test]
+    fn unescaping_works_correctly() {
+        let test_cases = [
+            (r#"a"#, "\x07"),
+            (r#"b"#, "\x08"),
+            (r#"f"#, "\x0c"),
+            (r#"n"#, "\n"),
+            (r#"r"#, "\r"),
+            (r#"t"#, "\t"),
+            (r#"v"#, "\x0b"),
+            (r#"\"#, "\\"),
+            (r#"""#, "\""),
+            ("?", "?"),
+            (r#"xff"#, "ÿ"),            // Hex
+            (r#"251"#, "©"),            // Octal
+            (r#"uD000"#, "\u{D000}"),   // Unicode up to 4 bytes
+            (r#"U29000"#, "\u{29000}"), // Unicode up to 8 bytes... but max unicode is only up to 6
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = unescape(CompleteStr(input)).map(|(i, o)| (i, o.into_owned()));
+            assert_eq!(ResultUtilsString::unwrap_output(actual), *expected);
+        }
+    }
+
+    #[test]
+    fn unescaping_invalid_unicode_errors() {
+        for input in ["UD800", "uD800", "uDFFF", "U110000", "UFFFFFFFF"].iter() {
+            println!("Testing {}", input);
+            let error = unescape(CompleteStr(input)).unwrap_err();
+            assert!(match error {
+                nom::Err::Failure(_) => true,
+                _ => false,
+            });
+            match crate::Error::from_err_str(&error) {
+                crate::Error::InvalidUnicodeCodePoint(code_point) => {
+                    assert_eq!(code_point, *input)
+                }
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+
+        let error = crate::parser::expression::Expression::parse(r#""a\uD800b""#).unwrap_err();
+        assert_eq!(error.position(), Some((1, 4)));
+        match error.without_position() {
+            crate::Error::InvalidUnicodeCodePoint(code_point) => {
+                assert_eq!(code_point, "uD800")
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn braced_unicode_escapes_are_unescaped_if_enabled() {
+        let braced = ParseOptions::default().braced_unicode_escapes(true);
+        let test_cases = [
+            (r#""\u{41}""#, "A"),
+            (r#""\u{1F600}""#, "😀"),
+            (r#""\u{00e9}\u00e9""#, "éé"),
+            (r#""\u{10FFFF}""#, "\u{10FFFF}"),
+        ];
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = string_with_options(CompleteStr(input), &braced).unwrap();
+            assert_eq!(remaining.0, "");
+            assert_eq!(actual, *expected);
+
+            match string(CompleteStr(input)).map_err(|e| crate::Error::from_err_str(&e)) {
+                Err(crate::Error::InvalidUnicodeCodePoint(_)) => {}
+                // Regular escapes are still accepted
+                Ok(_) if !input.contains('{') => {}
+                other => panic!("Unexpected result {:?}", other),
+            }
+        }
+
+        let invalid = [
+            (r#""\u{}""#, "u{}"),
+            (r#""\u{D800}""#, "u{D800}"),
+            (r#""\u{110000}""#, "u{110000}"),
+            (r#""\u{1234567}""#, "u{1234567}"),
+            (r#""\u{41""#, "u{41\""),
+        ];
+        for (input, escape) in invalid.iter() {
+            println!("Testing {}", input);
+            let error = string_with_options(CompleteStr(input), &braced).unwrap_err();
+            match crate::Error::from_err_str(&error) {
+                crate::Error::InvalidUnicodeCodePoint(code_point) => {
+                    assert_eq!(code_point, *escape)
+                }
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn octal_escapes_are_decoded() {
+        // Octal escapes take one to three digits, so `\0`, `\00` and `\000` are all the null
+        // byte and any further digit is a literal character. Like C and Go, an octal escape names
+        // a single byte: anything above `\377` is rejected rather than read as a wider code point.
+        let test_cases = [
+            (r#"\0"#, "\0"),
+            (r#"\00"#, "\0"),
+            (r#"\000"#, "\0"),
+            (r#"\0008"#, "\08"),
+            (r#"\08"#, "\08"),
+            (r#"a\0b"#, "a\0b"),
+            (r#"\377"#, "ÿ"),
+            (r#"\3777"#, "ÿ7"),
+            (r#"\40"#, " "),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(unescape_str(input).unwrap(), *expected);
+        }
+
+        let rejected = [(r#"\400"#, "400"), (r#"\777"#, "777"), (r#"\7771"#, "777")];
+        for (input, escape) in rejected.iter() {
+            println!("Testing {}", input);
+            match unescape_str(input).unwrap_err() {
+                crate::Error::InvalidUnicodeCodePoint(code_point) => {
+                    assert_eq!(code_point, *escape)
+                }
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+
+        // Quoted strings reject them the same way
+        assert_eq!(
+            ResultUtilsString::unwrap_output(string(CompleteStr(r#""\377""#))),
+            "ÿ"
+        );
+        assert!(match string(CompleteStr(r#""\777""#)).unwrap_err() {
+            nom::Err::Failure(_) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn whole_strings_are_unescaped() {
+        for input in ["", "abc", r#"a "b" ${c}"#].iter() {
+            println!("Testing {}", input);
+            match unescape_str(input).unwrap() {
+                Cow::Borrowed(actual) => assert_eq!(actual, *input),
+                Cow::Owned(actual) => panic!("Expected a borrowed string, got {:?}", actual),
+            }
+        }
+
+        let test_cases = [
+            (r#"a\nb"#, "a\nb"),
+            (r#"\t"#, "\t"),
+            (r#"\\\""#, "\\\""),
+            (r#"caf\u00e9 \251 \x41"#, "café © A"),
+            (r#"\1234"#, "S4"),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            match unescape_str(input).unwrap() {
+                Cow::Owned(actual) => assert_eq!(actual, *expected),
+                Cow::Borrowed(actual) => panic!("Expected an owned string, got {:?}", actual),
+            }
+        }
+
+        assert!(unescape_str(r#"a\"#).is_err());
+        assert!(unescape_str(r#"\q"#).is_err());
+        match unescape_str(r#"\uD800"#) {
+            Err(crate::Error::InvalidUnicodeCodePoint(code_point)) => {
+                assert_eq!(code_point, "uD800")
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unescaped_strings_are_mapped_back_to_the_source() {
+        let (unescaped, map) = unescape_str_with_map("é\\nx").unwrap();
+        assert_eq!(unescaped, "é\nx");
+        assert_eq!(map, vec![(0, 0), (2, 2), (3, 4), (4, 5)]);
+
+        // A single escape sequence is unescaped into a character of four bytes
+        let input = r#"a\U0001F600b"#;
+        let (unescaped, map) = unescape_str_with_map(input).unwrap();
+        assert_eq!(unescaped, "a😀b");
+        assert_eq!(map, vec![(0, 0), (1, 1), (5, 11), (6, 12)]);
+
+        let source = |offset: usize| {
+            let index = match map.binary_search_by_key(&offset, |(unescaped, _)| *unescaped) {
+                Ok(index) => index,
+                Err(index) => index - 1,
+            };
+            map[index].1
+        };
+        for offset in 1..5 {
+            assert_eq!(source(offset), 1);
+        }
+        assert_eq!(&input[source(5)..], "b");
+
+        for input in ["", "abc", "café"].iter() {
+            println!("Testing {}", input);
+            let (unescaped, map) = unescape_str_with_map(input).unwrap();
+            assert_eq!(unescaped, Cow::Borrowed(*input));
+            assert!(map.iter().all(|(unescaped, source)| unescaped == source));
+            assert_eq!(map.last(), Some(&(input.len(), input.len())));
+        }
+
+        assert!(unescape_str_with_map(r#"a\q"#).is_err());
+    }
+
+    #[test]
+    fn escaped_strings_are_unescaped_to_the_original() {
+        for input in ["", "abc", "café 😀", "$ {a} %%", "$$"].iter() {
+            println!("Testing {}", input);
+            match escape_str(input) {
+                Cow::Borrowed(actual) => assert_eq!(actual, *input),
+                Cow::Owned(actual) => panic!("Expected a borrowed string, got {:?}", actual),
+            }
+        }
+
+        let test_cases = [
+            ("a\"b", r#"a\"b"#),
+            ("a\\b", r#"a\\b"#),
+            ("a\nb\r\n\t", r#"a\nb\r\n\t"#),
+            ("\x00\x07\x7f\u{85}", r#"\u0000\u0007\u007F\u0085"#),
+            ("\x01a", r#"\u0001a"#),
+            ("${a} %{b}", "$${a} %%{b}"),
+            ("$${a}", "$$${a}"),
+        ];
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            assert_eq!(escape_str(input), *expected);
+        }
+
+        // Every string of up to three characters from the alphabet round trips
+        let alphabet = [
+            'a', ' ', '"', '\\', '\n', '\r', '\t', '\x00', '\x1b', '\u{85}', 'é', '😀', '$', '{',
+            '0', 'u', 'x', '%',
+        ];
+        let mut inputs = vec![String::new()];
+        for length in 1..=3 {
+            let previous: Vec<_> = inputs
+                .iter()
+                .filter(|input| input.chars().count() == length - 1)
+                .cloned()
+                .collect();
+            for input in previous {
+                for c in alphabet.iter() {
+                    inputs.push(format!("{}{}", input, c));
+                }
+            }
+        }
+
+        for input in inputs.iter() {
+            let escaped = escape_str(input);
+            assert!(!escaped.chars().any(char::is_control), "{:?}", escaped);
+            if !input.contains("${") && !input.contains("%{") {
+                assert_eq!(unescape_str(&escaped).unwrap(), *input);
+            }
+            let quoted = format!("\"{}\"", escaped);
+            let parts = ResultUtilsString::unwrap_output(quoted_template(CompleteStr(&quoted)));
+            let literal: String = parts
+                .iter()
+                .map(|part| match part {
+                    TemplatePart::Literal(literal) => literal.as_ref(),
+                    other => panic!("Unexpected template part {:?}", other),
+                })
+                .collect();
+            assert_eq!(literal, *input);
+        }
+    }
+
+    #[test]
+    fn string_content_are_parsed_correctly() {
+        let test_cases = [
+            ("", ""),
+            (r#"abcd"#, r#"abcd"#),
+            (r#"ab\"cd"#, r#"ab"cd"#),
+            (r#"ab \\ cd"#, r#"ab \ cd"#),
+            (r#"ab \n cd"#, "ab \n cd"),
+            (r#"ab \? cd"#, "ab ? cd"),
+            (
+                r#"ab \xff \251 \uD000 \U29000"#,
+                "ab ÿ © \u{D000} \u{29000}",
+            ),
+        ];
+
+        let options = ParseOptions::default();
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let actual = quoted_string_content(CompleteStr(input), &options);
+            assert_eq!(
+                ResultUtilsString::unwrap_output(actual.map(|s| s.to_owned())),
+                *expected
+            );
+        }
+    }
+
+    #[test]
+    fn literal_newlines_are_only_accepted_in_multi_line_strings() {
+        let multiline = ParseOptions::default().multiline_strings(true);
+        for input in ["\"a\nb\"", "\"a\r\nb\"", "\"a\rb\"", "\"a\\t\nb\""].iter() {
+            println!("Testing {:?}", input);
+            assert!(single_line_string(CompleteStr(input)).is_err());
+            assert!(string(CompleteStr(input)).is_err());
+            assert!(crate::parse_str(&format!("a = {}\n", input)).is_err());
+
+            let expected = input.trim_matches('"').replace("\\t", "\t");
+            assert_eq!(
+                ResultUtilsString::unwrap_output(string_with_options(
+                    CompleteStr(input),
+                    &multiline
+                )),
+                expected
+            );
+        }
+
+        assert_eq!(
+            ResultUtilsString::unwrap_output(string(CompleteStr(r#""a\nb""#))),
+            "a\nb"
+        );
+
+        assert_eq!(
+            ResultUtilsString::unwrap_output(single_line_string(CompleteStr(r#""a\nb""#))),
+            "a\nb"
+        );
+    }
+
+    #[test]
+    fn quoted_string_literals_are_parsed_correctly() {
+        let test_cases = [
+            (r#""""#, ""),
+            (r#""abcd""#, r#"abcd"#),
+            (r#""ab\"cd""#, r#"ab"cd"#),
+            (r#""ab \\ cd""#, r#"ab \ cd"#),
+            (r#""ab \n cd""#, "ab \n cd"),
+            (r#""ab \? cd""#, "ab ? cd"),
+            (
+                r#""ab \xff \251 \uD000 \U29000""#,
+                "ab ÿ © \u{D000} \u{29000}",
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(
+                ResultUtilsString::unwrap_output(quoted_string(
+                    CompleteStr(input),
+                    &ParseOptions::default()
+                )),
+                *expected
+            );
+        }
+
+        let multiline = ParseOptions::default().multiline_strings(true);
+        assert!(quoted_string(CompleteStr("\"ab\ncd\""), &ParseOptions::default()).is_err());
+        assert_eq!(
+            ResultUtilsString::unwrap_output(quoted_string(CompleteStr("\"ab\ncd\""), &multiline)),
+            "ab\ncd"
+        );
+    }
+
+    #[test]
+    fn quoted_strings_without_escapes_are_borrowed() {
+        let test_cases = [
+            (r#""""#, true),
+            (r#""abcd""#, true),
+            ("\"ab\ncd\"", true),
+            (r#""ab\"cd""#, false),
+            (r#""abcd\n""#, false),
+        ];
+
+        let options = ParseOptions::default().multiline_strings(true);
+        for (input, borrowed) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (_, actual) = string_with_options(CompleteStr(input), &options).unwrap();
+            match actual {
+                Cow::Borrowed(actual) => {
+                    assert!(borrowed, "Expected an owned string, got {:?}", actual);
+                    assert_eq!(actual, &input[1..input.len() - 1]);
+                }
+                Cow::Owned(actual) => {
+                    assert!(!borrowed, "Expected a borrowed string, got {:?}", actual)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn heredoc_identifier_is_parsed_correctly() {
+        let test_cases = [
+            (
+                "<<EOF\n",
+                HereDoc {
+                    identifier: CompleteStr("EOF"),
+                    indented: false,
+                },
+                "\n",
+            ),
+            (
+                "<<-EOH\n",
+                HereDoc {
+                    identifier: CompleteStr("EOH"),
+                    indented: true,
+                },
+                "\n",
+            ),
+            (
+                "<<藏_\r\n",
+                HereDoc {
+                    identifier: CompleteStr("藏_"),
+                    indented: false,
+                },
+                "\r\n",
+            ),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = heredoc_begin(CompleteStr(input)).unwrap();
+            assert_eq!(&remaining.0, expected_remaining);
+            assert_eq!(&actual, expected);
+        }
+    }
+
+    #[test]
+    fn heredocs_are_displayed_and_emitted() {
+        let indented = HereDoc {
+            identifier: CompleteStr("EOF"),
+            indented: true,
+        };
+        let plain = HereDoc {
+            identifier: CompleteStr("EOT"),
+            indented: false,
+        };
+        assert_eq!(indented.to_string(), "<<-EOF");
+        assert_eq!(plain.to_string(), "<<EOT");
+
+        let test_cases = [
+            "",
+            "one line",
+            "two\nlines",
+            "  indented\n\n\tand blank lines\n",
+            "trailing\n\n",
+            "${not} %{ interpolated }",
+        ];
+        for marker in [&indented, &plain].iter() {
+            for content in test_cases.iter() {
+                let heredoc = marker.emit(content, "    ");
+                println!("Testing {}", heredoc);
+                // The newline after the closing marker ends the attribute
+                let (remaining, parsed) = heredoc_string(CompleteStr(&heredoc)).unwrap();
+                assert_eq!(remaining.0, "\n");
+                assert_eq!(parsed, *content);
+                assert_eq!(heredoc_begin(CompleteStr(&heredoc)).unwrap().1, **marker);
+            }
+        }
+
+        assert_eq!(indented.emit("a\n\nb", "  "), "<<-EOF\n  a\n\n  b\n  EOF\n");
+        assert_eq!(plain.emit("a", "  "), "<<EOT\na\nEOT\n");
+        assert_eq!(plain.emit("", "  "), "<<EOT\nEOT\n");
+    }
+
+    #[test]
+    fn heredoc_identifiers_shadowing_keywords_are_warned() {
+        let test_cases = [
+            ("<<true\n", true),
+            ("<<-if\n", true),
+            ("<<EOF\n", false),
+            ("<<TRUE\n", false),
+            ("<<endfor_\n", false),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (_, heredoc) = heredoc_begin(CompleteStr(input)).unwrap();
+            assert_eq!(heredoc.keyword_warning().is_some(), *expected);
+
+            let options = ParseOptions::default().warn_heredoc_keywords(true);
+            let (_, linted) = heredoc_begin_with_options(CompleteStr(input), &options).unwrap();
+            assert_eq!(linted, heredoc);
+        }
+
+        assert!(!ParseOptions::default().is_warning_heredoc_keywords());
+    }
+
+    #[test]
+    fn heredoc_end_is_parsed_correctly() {
+        let test_cases = [
+            (
+                "\nEOF\n",
+                HereDoc {
+                    identifier: CompleteStr("EOF"),
+                    indented: false,
+                },
+                0,
+                "\n",
+            ),
+            (
+                "\n    EOH\n",
+                HereDoc {
+                    identifier: CompleteStr("EOH"),
+                    indented: true,
+                },
+                4,
+                "\n",
+            ),
+            (
+                "\r\nEOF\r\n",
+                HereDoc {
+                    identifier: CompleteStr("EOF"),
+                    indented: false,
+                },
+                0,
+                "\r\n",
+            ),
+            (
+                "\n\t \tEOH\n",
+                HereDoc {
+                    identifier: CompleteStr("EOH"),
+                    indented: true,
+                },
+                3,
+                "\n",
+            ),
+        ];
+
+        for (input, identifier, identation, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual_identation) =
+                heredoc_end(CompleteStr(input), &identifier).unwrap();
+            assert_eq!(*identation, actual_identation);
+            assert_eq!(
+                &remaining.0, expected_remaining,
+                "Input: {}; Remaining: {}",
+                input, remaining
+            );
+        }
+    }
+
+    #[test]
+    fn heredoc_end_markers_must_be_alone_on_their_line() {
+        // The identifier must be followed by the end of the line, so a longer word or trailing
+        // content does not end the Heredoc
+        let identifier = HereDoc {
+            identifier: CompleteStr("EOF"),
+            indented: false,
+        };
+        for input in ["\nEOFX\n", "\nEOF extra\n", "\nEOF", "\nXEOF\n"].iter() {
+            println!("Testing {}", input);
+            assert!(heredoc_end(CompleteStr(input), &identifier).is_err());
+        }
+
+        let input = "<<EOF\nEOFX\nEOF extra\n  EOF_\nEOF\n";
+        let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+        assert_eq!(remaining.0, "\n");
+        assert_eq!(actual, "EOFX\nEOF extra\n  EOF_");
+    }
+
+    #[test]
+    fn heredoc_strings_are_pased_correctly() {
+        let test_cases = [
+            (
+                r#"<<EOF
+EOF
+"#,
+                "",
+            ),
+            (
+                r#"<<EOF
+something 老虎
+EOF
+"#,
+                "something 老虎",
+            ),
+            (
+                r#"<<EOH
+something
+with 老虎
+new lines
+and quotes "
+                    EOH
+"#,
+                r#"something
+with 老虎
+new lines
+and quotes ""#,
+            ),
+            (
+                r#"<<-EOF
+    strip
+    the
+    spaces
+    but    not   these 老虎
+    EOF
+"#,
+                r#"strip
+the
+spaces
+but    not   these 老虎"#,
+            ),
+            (
+                r#"<<-EOF
+    strip
+    the
+    spaces
+    but    not   these 老虎
+  EOF
+"#,
+                r#"  strip
+  the
+  spaces
+  but    not   these 老虎"#,
+            ),
+            (
+                r#"<<-EOF
+strip
+    the
+spaces
+    but    not   these 老虎
+  EOF
+"#,
+                r#"strip
+  the
+spaces
+  but    not   these 老虎"#,
+            ),
+            (
+                r#"<<-EOF
+  strip
+    the
+  spaces
+    but    not   these 老虎
+    EOF
+"#,
+                r#"strip
+the
+spaces
+but    not   these 老虎"#,
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, "\n");
+            assert_eq!(actual, expected.to_string());
+        }
+    }
+
+    #[test]
+    fn heredocs_are_unindented() {
+        let test_cases = [
+            ("    a\n  é\n老虎\n    b", 4, "a\né\n老虎\nb"),
+            ("  é\n 老虎\n  a", 4, "é\n老虎\na"),
+            ("    a\n\n  \n      b", 4, "a\n\n\n  b"),
+            ("\u{3000}a\n    b", 2, "\u{3000}a\n  b"),
+        ];
+
+        for (input, indentation, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            match unindent_heredoc(input, *indentation, 1) {
+                Cow::Owned(actual) => assert_eq!(actual, *expected),
+                Cow::Borrowed(actual) => panic!("Expected an owned string, got {:?}", actual),
+            }
+        }
+
+        for (input, indentation) in [("a\n  b", 0), ("a\n老虎\nb", 4), ("", 2)].iter() {
+            println!("Testing {:?}", input);
+            match unindent_heredoc(input, *indentation, 1) {
+                Cow::Borrowed(actual) => assert_eq!(actual, *input),
+                Cow::Owned(actual) => panic!("Expected a borrowed string, got {:?}", actual),
+            }
+        }
+
+        let input = "<<-EOF\n  é\n\n 老虎\n    EOF\n";
+        let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+        assert_eq!(remaining.0, "\n");
+        assert_eq!(actual, "é\n\n老虎");
+    }
+
+    #[test]
+    fn under_indented_heredoc_lines_are_sliced_on_character_boundaries() {
+        let test_cases = [
+            // Empty lines and lines of only whitespace become empty
+            ("<<-EOF\n    a\n\n    b\n    EOF\n", "a\n\nb"),
+            (
+                "<<-EOF\r\n    a\r\n\r\n  \r\n    b\r\n    EOF\r\n",
+                "a\r\n\r\n\r\nb",
+            ),
+            // Lines starting with a multi-byte character keep it
+            ("<<-EOF\n    a\né\n    EOF\n", "a\né"),
+            ("<<-EOF\n    a\n 老虎\n\t 🦀\n    EOF\n", "a\n老虎\n🦀"),
+            // Multi-byte whitespace is not indentation
+            (
+                "<<-EOF\n    a\n\u{3000}b\n \u{a0}c\n    EOF\n",
+                "a\n\u{3000}b\n\u{a0}c",
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+            assert!(remaining.0 == "\n" || remaining.0 == "\r\n");
+            assert_eq!(actual, *expected);
+        }
+    }
+
+    #[test]
+    fn tab_indented_heredocs_are_unindented() {
+        let test_cases = [
+            ("<<-EOF\n\t\tfoo\n\t\t\tbar\n\t\tEOF\n", "foo\n\tbar"),
+            ("<<-EOF\n\tfoo\n\t\t\n\tbar\n\tEOF\n", "foo\n\t\nbar"),
+            // Tabs and spaces are both one unit of indentation
+            (
+                "<<-EOF\n\t  foo\n  \tbar\n     baz\n\t\tqux\n \t EOF\n",
+                "foo\nbar\n  baz\nqux",
+            ),
+            // Under-indented lines lose all of their leading whitespace
+            ("<<-EOF\n\tfoo\n \tbar\n\t\t\tEOF\n", "foo\nbar"),
+            // Tabs are kept in heredocs that are not indented
+            ("<<EOF\n\tfoo\n\t\tbar\n\tEOF\n", "\tfoo\n\t\tbar"),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, "\n");
+            assert_eq!(actual, *expected);
+        }
+    }
+
+    #[test]
+    fn heredoc_tab_width_is_configurable() {
+        let options = ParseOptions::default().heredoc_tab_width(4);
+        let test_cases = [
+            ("<<-EOF\n\tfoo\n\t\tbar\n\tEOF\n", "foo\n\tbar"),
+            // A tab is as wide as four spaces
+            (
+                "<<-EOF\n\tfoo\n    bar\n      baz\n    EOF\n",
+                "foo\nbar\n  baz",
+            ),
+            ("<<-EOF\n    foo\n\t\tbar\n\tEOF\n", "foo\n\tbar"),
+            // A tab reaching past the indentation is removed entirely
+            ("<<-EOF\n  foo\n \tbar\n  EOF\n", "foo\nbar"),
+            ("<<EOF\n\tfoo\nEOF\n", "\tfoo"),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) =
+                heredoc_string_with_options(CompleteStr(input), &options).unwrap();
+            assert_eq!(remaining.0, "\n");
+            assert_eq!(actual, *expected);
+        }
+
+        // By default, a tab is as wide as a space
+        let input = "<<-EOF\n\tfoo\n    bar\n    EOF\n";
+        let (_, actual) = heredoc_string(CompleteStr(input)).unwrap();
+        assert_eq!(actual, "foo\nbar");
+    }
+
+    #[test]
+    fn inconsistent_heredoc_indentation_is_an_error_in_strict_mode() {
+        let strict = ParseOptions::default().strict_heredoc_indentation(true);
+        let consistent = [
+            ("<<-EOF\n\tfoo\n\t\tbar\n\tEOF\n", "foo\n\tbar"),
+            ("<<-EOF\n\t  foo\n\n\tbar\n\tEOF\n", "  foo\n\nbar"),
+            ("<<-EOF\n  foo\n bar\n  EOF\n", "foo\nbar"),
+            // Only indented Heredocs are checked
+            ("<<EOF\n  foo\n\tEOF\n", "  foo"),
+        ];
+        for (input, expected) in consistent.iter() {
+            println!("Testing {:?}", input);
+            let (_, actual) = heredoc_string_with_options(CompleteStr(input), &strict).unwrap();
+            assert_eq!(actual, *expected);
+        }
+
+        let input = "a = <<-EOF\n\tfoo\n  bar\n\tEOF\n";
+        let error = crate::parser::parse_str_with_options(input, &strict).unwrap_err();
+        assert_eq!(error.position(), Some((3, 1)));
+        match error.without_position() {
+            crate::Error::InconsistentHeredocIndent(input) => {
+                assert_eq!(input, "  bar")
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+
+        // The marker is indented by a tab, which is as wide as a space by default
+        let input = CompleteStr("<<-EOF\n  foo\n  bar\n\tEOF\n");
+        let (_, actual) = heredoc_string(input).unwrap();
+        assert_eq!(actual, " foo\n bar");
+        let error = heredoc_string_with_options(input, &strict).unwrap_err();
+        match crate::Error::from_err_str(&error) {
+            crate::Error::InconsistentHeredocIndent(input) => {
+                assert_eq!(input, "  foo")
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn heredoc_line_endings_are_normalized_if_enabled() {
+        let normalize = ParseOptions::default().normalize_newlines(true);
+        let test_cases = [
+            (
+                "<<EOF\r\nfoo\r\nbar\r\nEOF\r\n",
+                "foo\r\nbar",
+                "foo\nbar",
+                "\r\n",
+            ),
+            (
+                "<<-EOF\r\n  foo\r\n\r\n    bar\r\n  EOF\r\n",
+                "foo\r\n\r\n  bar",
+                "foo\n\n  bar",
+                "\r\n",
+            ),
+            ("<<EOF\nfoo\r\nbar\nEOF\n", "foo\r\nbar", "foo\nbar", "\n"),
+            ("<<EOF\r\nEOF\r\n", "", "", "\r\n"),
+            // A lone `\r` is not a line ending
+            (
+                "<<EOF\r\nfoo\rbar\r\nEOF\r\n",
+                "foo\rbar",
+                "foo\rbar",
+                "\r\n",
+            ),
+        ];
+
+        for (input, expected, normalized, expected_remaining) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, *expected_remaining);
+            assert_eq!(actual, *expected);
+
+            let (remaining, actual) =
+                heredoc_string_with_options(CompleteStr(input), &normalize).unwrap();
+            assert_eq!(remaining.0, *expected_remaining);
+            assert_eq!(actual, *normalized);
+        }
+    }
+
+    #[test]
+    fn heredoc_trailing_newlines_are_kept_if_enabled() {
+        let keep = ParseOptions::default().keep_heredoc_trailing_newline(true);
+        let test_cases = [
+            ("<<EOF\nfoo\nbar\nEOF\n", "foo\nbar", "foo\nbar\n"),
+            ("<<EOF\r\nfoo\r\nEOF\r\n", "foo", "foo\r\n"),
+            (
+                "<<-EOF\n    foo\n      bar\n    EOF\n",
+                "foo\n  bar",
+                "foo\n  bar\n",
+            ),
+            ("<<EOF\nfoo\n\nEOF\n", "foo\n", "foo\n\n"),
+            ("<<EOF\n\nEOF\n", "", "\n"),
+            ("<<EOF\nEOF\n", "", ""),
+        ];
+
+        for (input, trimmed, kept) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+            assert!(remaining.trim().is_empty());
+            assert_eq!(actual, *trimmed);
+
+            let (remaining, actual) =
+                heredoc_string_with_options(CompleteStr(input), &keep).unwrap();
+            assert!(remaining.trim().is_empty());
+            assert_eq!(actual, *kept);
+        }
+
+        // The kept line ending is still borrowed from the input, and can be normalized
+        let input = "<<EOF\nfoo\nEOF\n";
+        match heredoc_string_with_options(CompleteStr(input), &keep).unwrap() {
+            (_, Cow::Borrowed(actual)) => assert_eq!(actual, "foo\n"),
+            (_, Cow::Owned(actual)) => panic!("Expected a borrowed string, got {:?}", actual),
+        }
+        let normalize = keep.normalize_newlines(true);
+        let (_, actual) =
+            heredoc_string_with_options(CompleteStr("<<EOF\r\nfoo\r\nEOF\r\n"), &normalize)
+                .unwrap();
+        assert_eq!(actual, "foo\n");
+    }
+
+    #[test]
+    fn heredoc_openers_in_content_are_plain_text() {
+        let input = r#"<<EOF
+cat <<EOT > script.sh
+#!/bin/sh
+echo "<<EOF" << EOF
+EOF is not alone on this line
+  EOFX
+EOT
+EOF
+"#;
+        let expected = r#"cat <<EOT > script.sh
+#!/bin/sh
+echo "<<EOF" << EOF
+EOF is not alone on this line
+  EOFX
+EOT"#;
+
+        let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+        assert_eq!(remaining.0, "\n");
+        assert_eq!(actual, expected);
+
+        let input = "<<-EOF\n  <<-EOF\n  <<EOF\n  EOF\n";
+        let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
+        assert_eq!(remaining.0, "\n");
+        assert_eq!(actual, "<<-EOF\n<<EOF");
+    }
+
+    #[test]
+    fn unterminated_heredocs_are_errors() {
+        let test_cases = [
+            ("<<EOF\nsome content\n", "EOF"),
+            ("<<EOF\n", "EOF"),
+            ("<<-EOT\n  some content\n  EOTX\n", "EOT"),
+            ("<<EOF\nsome content\nEOT\n", "EOF"),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let error = heredoc_string(CompleteStr(input)).unwrap_err();
+            assert!(match error {
+                nom::Err::Failure(_) => true,
+                _ => false,
+            });
+            match crate::Error::from_err_str(&error) {
+                crate::Error::UnterminatedHeredoc(identifier) => assert_eq!(identifier, *expected),
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+
+        let error = crate::parser::parse_str("a = 1\nb = <<EOF\nsome content\n").unwrap_err();
+        assert_eq!(error.position(), Some((2, 5)));
+        match error.without_position() {
+            crate::Error::UnterminatedHeredoc(identifier) => assert_eq!(identifier, "EOF"),
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bare_carriage_returns_do_not_end_heredoc_lines() {
+        let test_cases = [
+            ("<<EOF\rfoo\rEOF\r", "\rfoo\rEOF\r"),
+            ("<<-EOF\r  foo\r  EOF", "\r  foo\r  EOF"),
+            ("<<EOF\nfoo\rEOF\n", "\rEOF\n"),
+            ("<<EOF\r\nfoo\r\nEOF\rbar = 1", "\rbar = 1"),
+        ];
+
+        for (input, position) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let error = heredoc_string(CompleteStr(input)).unwrap_err();
+            match &error {
+                nom::Err::Failure(nom::Context::List(list)) => {
+                    let (remaining, _) = list[0];
+                    assert_eq!(remaining.0, *position)
+                }
+                other => panic!("Unexpected error {:?}", other),
+            }
+            match crate::Error::from_err_str(&error) {
+                crate::Error::BareCarriageReturn => {}
+                other => panic!("Unexpected error {:?}", other),
+            }
+        }
+
+        // Lone carriage returns within the content are kept
+        let (_, actual) = heredoc_string(CompleteStr("<<EOF\nfoo\rbar\nEOF\n")).unwrap();
+        assert_eq!(actual, "foo\rbar");
+
+        let error = crate::parser::parse_str("a = 1\nb = <<EOF\nfoo\rEOF\n").unwrap_err();
+        assert_eq!(error.position(), Some((3, 4)));
+        match error.without_position() {
+            crate::Error::BareCarriageReturn => {}
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn backslashes_do_not_continue_strings_on_the_next_line() {
+        let multiline = ParseOptions::default().multiline_strings(true);
+        let test_cases = ["\"abc\\\ndef\"", "\"abc\\\r\ndef\"", "\"${a}\\\ndef\""];
+
+        for input in test_cases.iter() {
+            println!("Testing {:?}", input);
+            for options in [ParseOptions::default(), multiline.clone()].iter() {
+                let error = string_with_options(CompleteStr(input), options).unwrap_err();
+                match crate::Error::from_err_str(&error) {
+                    crate::Error::LineContinuation => {}
+                    other => panic!("Unexpected error {:?}", other),
+                }
+            }
+        }
+
+        let error = crate::parser::parse_str("a = 1\nb = \"abc\\\ndef\"\n").unwrap_err();
+        assert_eq!(error.position(), Some((2, 10)));
+        assert_eq!(
+            error.to_string(),
+            "2:10: A `\\` at the end of a line does not continue a quoted string; use a Heredoc \
+             or the `\\n` escape sequence for strings spanning multiple lines, while parsing a \
+             string, in an attribute"
+        );
+
+        let error = unescape_str("abc\\\ndef").unwrap_err();
+        match error.without_position() {
+            crate::Error::LineContinuation => {}
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn strings_are_parsed_correctly() {
+        let test_cases = [
+            (r#""""#, "", ""),
+            (r#""abcd""#, r#"abcd"#, ""),
+            (r#""ab\"cd""#, r#"ab"cd"#, ""),
+            (r#""ab \\ cd""#, r#"ab \ cd"#, ""),
+            (r#""ab \n cd""#, "ab \n cd", ""),
+            (r#""ab \? cd""#, "ab ? cd", ""),
+            (
+                r#"<<EOF
+    EOF
+"#,
+                "",
+                "\n",
+            ),
+            (
+                r#""ab \xff \251 \uD000 \U29000""#,
+                "ab ÿ © \u{D000} \u{29000}",
+                "",
+            ),
+            (
+                r#"<<EOF
+something
+    EOF
+"#,
+                "something",
+                "\n",
+            ),
+            (
+                r#"<<EOH
+something
+with
+new lines
+and quotes "
+                        EOH
+"#,
+                r#"something
+with
+new lines
+and quotes ""#,
+                "\n",
+            ),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = string(CompleteStr(input)).unwrap();
+            assert_eq!(&remaining.0, expected_remaining);
+            assert_eq!(&actual, expected, "Input: {}", input);
+        }
+    }
+
+    #[test]
+    fn adjacent_strings_are_concatenated() {
+        let (remaining, actual) = concatenated_strings(CompleteStr(r#""abcd" "#)).unwrap();
+        assert_eq!(remaining.0, " ");
+        match actual {
+            Cow::Borrowed(actual) => assert_eq!(actual, "abcd"),
+            Cow::Owned(actual) => panic!("Expected a borrowed string, got {:?}", actual),
+        }
+
+        let test_cases = [
+            (r#""ab" "cd""#, "abcd", ""),
+            ("\"ab\\n\"\n  \t\"cd\" = 1", "ab\ncd", " = 1"),
+            (
+                r#""ab"
+<<EOF
+  cd
+EOF
+"#,
+                "ab  cd",
+                "\n",
+            ),
+            (
+                r#"<<EOF
+ab
+EOF
+"cd""#,
+                "abcd",
+                "",
+            ),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = concatenated_strings(CompleteStr(input)).unwrap();
+            assert_eq!(&remaining.0, expected_remaining);
+            assert_eq!(&actual, expected, "Input: {}", input);
+        }
+    }
+
+    #[test]
+    fn template_interpolations_are_parsed_correctly() {
+        let test_cases = [
+            ("${foo}", "foo", ""),
+            ("${ var.name }rest", "var.name", "rest"),
+            ("${ { a = { b = 1 } } }", "{ a = { b = 1 } }", ""),
+            (r#"${ "${nested}" }"#, r#""${nested}""#, ""),
+        ];
+
+        for (input, expected, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = template_interpolation(CompleteStr(input)).unwrap();
+            assert_eq!(&remaining.0, expected_remaining);
+            assert_eq!(actual, TemplatePart::Interpolation(Cow::Borrowed(expected)));
+        }
+
+        assert_eq!(
+            template_interpolation(CompleteStr("${~ foo ~}")),
+            Ok((
+                CompleteStr(""),
+                TemplatePart::Interpolation(Cow::Borrowed("foo"))
+            ))
+        );
+        assert!(template_interpolation(CompleteStr("${}")).is_err());
+        assert!(template_interpolation(CompleteStr("${~}")).is_err());
+        assert!(template_interpolation(CompleteStr("${ unclosed")).is_err());
     }
 
     #[test]
-    fn string_content_are_parsed_correctly() {
+    fn interpolations_with_nested_braces_are_captured_whole() {
         let test_cases = [
-            ("", ""),
-            (r#"abcd"#, r#"abcd"#),
-            (r#"ab\"cd"#, r#"ab"cd"#),
-            (r#"ab \\ cd"#, r#"ab \ cd"#),
-            (r#"ab \n cd"#, "ab \n cd"),
-            (r#"ab \? cd"#, "ab ? cd"),
+            ("${var}", "var"),
+            (r#"${ {a = 1}["a"] }"#, r#"{a = 1}["a"]"#),
+            (r#"${{a = {b = 1}}.a.b}"#, r#"{a = {b = 1}}.a.b"#),
             (
-                r#"ab \xff \251 \uD000 \U29000"#,
-                "ab ÿ © \u{D000} \u{29000}",
+                r#"${ merge({a = {b = 1}}, {c = [{d = 2}]}) }"#,
+                r#"merge({a = {b = 1}}, {c = [{d = 2}]})"#,
             ),
-            ("ab\ncd", "ab\ncd"),
+            (r#"${lookup({a = "}"}, "a")}"#, r#"lookup({a = "}"}, "a")"#),
+            (r#"${ "${ "}" }" }"#, r#""${ "}" }""#),
+            (r#"${ upper("${ "a\"}" }") }"#, r#"upper("${ "a\"}" }")"#),
+            ("${ a /* } */ }", "a /* } */"),
         ];
 
         for (input, expected) in test_cases.iter() {
             println!("Testing {}", input);
-            let actual = multiline_string_content(CompleteStr(input));
             assert_eq!(
-                ResultUtilsString::unwrap_output(actual.map(|s| s.to_owned())),
-                *expected
+                template_interpolation(CompleteStr(input)),
+                Ok((
+                    CompleteStr(""),
+                    TemplatePart::Interpolation(Cow::Borrowed(expected))
+                ))
+            );
+
+            let quoted = format!(r#""<{}>""#, input);
+            assert_eq!(
+                *ResultUtilsString::unwrap_output(quoted_template(CompleteStr(&quoted))),
+                vec![
+                    TemplatePart::Literal(Cow::Borrowed("<")),
+                    TemplatePart::Interpolation(Cow::Borrowed(expected)),
+                    TemplatePart::Literal(Cow::Borrowed(">")),
+                ]
             );
         }
     }
 
     #[test]
-    fn quoted_string_literals_are_parsed_correctly() {
+    fn quoted_templates_are_parsed_correctly() {
         let test_cases = [
-            (r#""""#, ""),
-            (r#""abcd""#, r#"abcd"#),
-            (r#""ab\"cd""#, r#"ab"cd"#),
-            (r#""ab \\ cd""#, r#"ab \ cd"#),
-            (r#""ab \n cd""#, "ab \n cd"),
-            (r#""ab \? cd""#, "ab ? cd"),
+            (r#""""#, vec![]),
             (
-                r#""ab \xff \251 \uD000 \U29000""#,
-                "ab ÿ © \u{D000} \u{29000}",
+                r#""abcd""#,
+                vec![TemplatePart::Literal(Cow::Borrowed("abcd"))],
+            ),
+            (
+                r#""Hello, ${ var.name }!""#,
+                vec![
+                    TemplatePart::Literal(Cow::Borrowed("Hello, ")),
+                    TemplatePart::Interpolation(Cow::Borrowed("var.name")),
+                    TemplatePart::Literal(Cow::Borrowed("!")),
+                ],
+            ),
+            (
+                r#""a \"${b}\" c""#,
+                vec![
+                    TemplatePart::Literal(Cow::Owned("a \"".to_string())),
+                    TemplatePart::Interpolation(Cow::Borrowed("b")),
+                    TemplatePart::Literal(Cow::Owned("\" c".to_string())),
+                ],
+            ),
+            (
+                r#""${a}${ {b = "}"} }""#,
+                vec![
+                    TemplatePart::Interpolation(Cow::Borrowed("a")),
+                    TemplatePart::Interpolation(Cow::Borrowed(r#"{b = "}"}"#)),
+                ],
+            ),
+            (
+                r#""costs $5, not $${price}""#,
+                vec![TemplatePart::Literal(Cow::Owned(
+                    "costs $5, not ${price}".to_string(),
+                ))],
             ),
-            ("\"ab\ncd\"", "ab\ncd"),
         ];
 
         for (input, expected) in test_cases.iter() {
             println!("Testing {}", input);
             assert_eq!(
-                ResultUtilsString::unwrap_output(quoted_string(CompleteStr(input))),
+                *ResultUtilsString::unwrap_output(quoted_template(CompleteStr(input))),
                 *expected
             );
         }
     }
 
     #[test]
-    fn heredoc_identifier_is_parsed_correctly() {
+    fn strings_keep_interpolations_as_literal_text() {
+        let (_, actual) = string(CompleteStr(r#""Hello, ${name}!""#)).unwrap();
+        assert_eq!(actual, "Hello, ${name}!");
+    }
+
+    #[test]
+    fn template_directives_may_contain_interpolations() {
+        let (_, actual) = template_directive(CompleteStr("%{ if x }${x}!%{ endif }")).unwrap();
+        assert_eq!(
+            actual,
+            Directive::If {
+                condition: Cow::Borrowed("x"),
+                truthy: vec![
+                    TemplatePart::Interpolation(Cow::Borrowed("x")),
+                    TemplatePart::Literal(Cow::Borrowed("!")),
+                ],
+                falsy: vec![],
+                trim: Default::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn nested_template_directives_are_matched() {
         let test_cases = [
             (
-                "<<EOF\n",
-                HereDoc {
-                    identifier: CompleteStr("EOF"),
-                    indented: false,
+                "%{ for x in xs }%{ if x }yes%{ else }no%{ endif }%{ endfor }",
+                Directive::For {
+                    key: None,
+                    value: Cow::Borrowed("x"),
+                    collection: Cow::Borrowed("xs"),
+                    body: vec![TemplatePart::Directive(Directive::If {
+                        condition: Cow::Borrowed("x"),
+                        truthy: vec![TemplatePart::Literal(Cow::Borrowed("yes"))],
+                        falsy: vec![TemplatePart::Literal(Cow::Borrowed("no"))],
+                        trim: Default::default(),
+                    })],
+                    trim: Default::default(),
                 },
-                "\n",
             ),
             (
-                "<<-EOH\n",
-                HereDoc {
-                    identifier: CompleteStr("EOH"),
-                    indented: true,
+                "%{if a}%{if b}x%{endif}y%{endif}",
+                Directive::If {
+                    condition: Cow::Borrowed("a"),
+                    truthy: vec![
+                        TemplatePart::Directive(Directive::If {
+                            condition: Cow::Borrowed("b"),
+                            truthy: vec![TemplatePart::Literal(Cow::Borrowed("x"))],
+                            falsy: vec![],
+                            trim: Default::default(),
+                        }),
+                        TemplatePart::Literal(Cow::Borrowed("y")),
+                    ],
+                    falsy: vec![],
+                    trim: Default::default(),
                 },
-                "\n",
             ),
             (
-                "<<藏_\r\n",
-                HereDoc {
-                    identifier: CompleteStr("藏_"),
-                    indented: false,
+                "%{ for k, v in { a = 1 } }%{ for i in v }100% %{ endfor }%{ endfor }",
+                Directive::For {
+                    key: Some(Cow::Borrowed("k")),
+                    value: Cow::Borrowed("v"),
+                    collection: Cow::Borrowed("{ a = 1 }"),
+                    body: vec![TemplatePart::Directive(Directive::For {
+                        key: None,
+                        value: Cow::Borrowed("i"),
+                        collection: Cow::Borrowed("v"),
+                        body: vec![TemplatePart::Literal(Cow::Borrowed("100% "))],
+                        trim: Default::default(),
+                    })],
+                    trim: Default::default(),
                 },
-                "\r\n",
             ),
         ];
 
-        for (input, expected, expected_remaining) in test_cases.iter() {
+        for (input, expected) in test_cases.iter() {
             println!("Testing {}", input);
-            let (remaining, actual) = heredoc_begin(CompleteStr(input)).unwrap();
-            assert_eq!(&remaining.0, expected_remaining);
-            assert_eq!(&actual, expected);
+            let actual = ResultUtilsString::unwrap_output(template_directive(CompleteStr(input)));
+            assert_eq!(actual, *expected);
         }
     }
 
     #[test]
-    fn heredoc_end_is_parsed_correctly() {
+    fn templates_with_directives_are_parsed_correctly() {
         let test_cases = [
             (
-                "\nEOF\n",
-                HereDoc {
-                    identifier: CompleteStr("EOF"),
-                    indented: false,
-                },
-                0,
-                "\n",
+                r#""%{ if x }${x}%{ else }none%{ endif }""#,
+                vec![TemplatePart::Directive(Directive::If {
+                    condition: Cow::Borrowed("x"),
+                    truthy: vec![TemplatePart::Interpolation(Cow::Borrowed("x"))],
+                    falsy: vec![TemplatePart::Literal(Cow::Borrowed("none"))],
+                    trim: Default::default(),
+                })],
             ),
             (
-                "\n    EOH\n",
-                HereDoc {
-                    identifier: CompleteStr("EOH"),
-                    indented: true,
-                },
-                4,
-                "\n",
+                r#""%{ for s in xs }\"${s}\" %{ endfor }100%%{}""#,
+                vec![
+                    TemplatePart::Directive(Directive::For {
+                        key: None,
+                        value: Cow::Borrowed("s"),
+                        collection: Cow::Borrowed("xs"),
+                        body: vec![
+                            TemplatePart::Literal(Cow::Owned("\"".to_string())),
+                            TemplatePart::Interpolation(Cow::Borrowed("s")),
+                            TemplatePart::Literal(Cow::Owned("\" ".to_string())),
+                        ],
+                        trim: Default::default(),
+                    }),
+                    TemplatePart::Literal(Cow::Owned("100%{}".to_string())),
+                ],
             ),
             (
-                "\r\nEOF\r\n",
-                HereDoc {
-                    identifier: CompleteStr("EOF"),
-                    indented: false,
-                },
-                0,
-                "\r\n",
+                r#"<<EOF
+%{ for k, v in m }
+${k} = "${v}"
+%{ endfor }
+EOF
+"#,
+                vec![TemplatePart::Directive(Directive::For {
+                    key: Some(Cow::Borrowed("k")),
+                    value: Cow::Borrowed("v"),
+                    collection: Cow::Borrowed("m"),
+                    body: vec![
+                        TemplatePart::Literal(Cow::Borrowed("\n")),
+                        TemplatePart::Interpolation(Cow::Borrowed("k")),
+                        TemplatePart::Literal(Cow::Borrowed(" = \"")),
+                        TemplatePart::Interpolation(Cow::Borrowed("v")),
+                        TemplatePart::Literal(Cow::Borrowed("\"\n")),
+                    ],
+                    trim: Default::default(),
+                })],
+            ),
+            (
+                r#"<<-EOF
+    Hello
+      %{ if loud }${name}!%{ endif }
+    EOF
+"#,
+                vec![
+                    TemplatePart::Literal(Cow::Borrowed("Hello\n  ")),
+                    TemplatePart::Directive(Directive::If {
+                        condition: Cow::Borrowed("loud"),
+                        truthy: vec![
+                            TemplatePart::Interpolation(Cow::Borrowed("name")),
+                            TemplatePart::Literal(Cow::Borrowed("!")),
+                        ],
+                        falsy: vec![],
+                        trim: Default::default(),
+                    }),
+                ],
             ),
         ];
 
-        for (input, identifier, identation, expected_remaining) in test_cases.iter() {
+        for (input, expected) in test_cases.iter() {
             println!("Testing {}", input);
-            let (remaining, actual_identation) =
-                heredoc_end(CompleteStr(input), &identifier).unwrap();
-            assert_eq!(*identation, actual_identation);
-            assert_eq!(
-                &remaining.0, expected_remaining,
-                "Input: {}; Remaining: {}",
-                input, remaining
-            );
+            let (_, actual) = template(CompleteStr(input)).unwrap();
+            assert_eq!(*actual, *expected);
         }
     }
 
     #[test]
-    fn heredoc_strings_are_pased_correctly() {
+    fn unclosed_directives_in_heredocs_are_errors() {
+        let error = heredoc_template(CompleteStr("<<EOF\n%{ if x }yes\nEOF\n"))
+            .map_err(|e| crate::Error::from_err_str(&e))
+            .unwrap_err();
+        match error {
+            crate::Error::UnclosedTemplateDirective(directive) => {
+                assert_eq!(directive, "%{ if x }")
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+
+        assert!(heredoc_template(CompleteStr("<<EOF\nyes%{ endif }\nEOF\n")).is_err());
+    }
+
+    #[test]
+    fn template_escape_sequences_are_literal_text() {
+        let literal = |s: &str| vec![TemplatePart::Literal(Cow::Owned(s.to_string()))];
         let test_cases = [
+            (r#""$${foo}""#, literal("${foo}")),
+            (r#""%%{ if }""#, literal("%{ if }")),
+            (r#""$$${foo}""#, literal("$${foo}")),
+            (r#""%%%{ if }""#, literal("%%{ if }")),
+            (r#""$ and $$ and %%""#, literal("$ and $$ and %%")),
             (
-                r#"<<EOF
-EOF
-"#,
-                "",
+                r#""$${a}${b}""#,
+                vec![
+                    TemplatePart::Literal(Cow::Owned("${a}".to_string())),
+                    TemplatePart::Interpolation(Cow::Borrowed("b")),
+                ],
             ),
             (
-                r#"<<EOF
-something 老虎
-EOF
-"#,
-                "something 老虎",
+                "<<EOF\n$${foo} %%{ if } $$${bar}\nEOF\n",
+                literal("${foo} %{ if } $${bar}"),
             ),
             (
-                r#"<<EOH
-something
-with 老虎
-new lines
-and quotes "
-                    EOH
-"#,
-                r#"something
-with 老虎
-new lines
-and quotes ""#,
+                "<<EOF\n%{ if x }$${x}%{ endif }\nEOF\n",
+                vec![TemplatePart::Directive(Directive::If {
+                    condition: Cow::Borrowed("x"),
+                    truthy: literal("${x}"),
+                    falsy: vec![],
+                    trim: Default::default(),
+                })],
             ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = template(CompleteStr(input)).unwrap();
+            assert!(remaining.trim().is_empty());
+            assert_eq!(*actual, *expected);
+        }
+    }
+
+    #[test]
+    fn template_markers_at_the_end_of_input_are_literal_text() {
+        // A `$` or `%` is only the start of a template sequence when it is followed by `{`, so
+        // one at the end of a string or of the input is kept as literal text.
+        let literal = |s: &'static str| vec![TemplatePart::Literal(Cow::Borrowed(s))];
+        let test_cases = [
+            (r#""price: $""#, literal("price: $")),
+            (r#""100%""#, literal("100%")),
+            (r#""$""#, literal("$")),
+            (r#""%""#, literal("%")),
+            (r#""$$""#, literal("$$")),
+            (r#""%%""#, literal("%%")),
+            ("<<EOF\nprice: $\nEOF\n", literal("price: $")),
+            ("<<EOF\n100%\nEOF\n", literal("100%")),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = template(CompleteStr(input)).unwrap();
+            assert!(remaining.trim().is_empty());
+            assert_eq!(*actual, *expected);
+        }
+
+        for input in ["price: $", "100%", "$$"].iter() {
+            println!("Testing {}", input);
+            let (remaining, actual) = template_literal(CompleteStr(input)).unwrap();
+            assert_eq!(remaining.0, "");
+            assert_eq!(actual, *input);
+        }
+
+        let unterminated = [
+            r#""${""#,
+            r#""${foo""#,
+            r#""price: ${""#,
+            "<<EOF\n${\nEOF\n",
+        ];
+        for input in unterminated.iter() {
+            println!("Testing {}", input);
+            assert!(template(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn template_directives_strip_whitespace_with_trim_markers() {
+        let both = Trim {
+            left: true,
+            right: true,
+        };
+        let test_cases = [
             (
-                r#"<<-EOF
-    strip
-    the
-    spaces
-    but    not   these 老虎
-    EOF
+                r#"<<EOF
+%{ for x in xs ~}
+  item ${x}
+%{~ endfor }
+done
+EOF
 "#,
-                r#"strip
-the
-spaces
-but    not   these 老虎"#,
+                vec![
+                    TemplatePart::Directive(Directive::For {
+                        key: None,
+                        value: Cow::Borrowed("x"),
+                        collection: Cow::Borrowed("xs"),
+                        body: vec![
+                            TemplatePart::Literal(Cow::Borrowed("  item ")),
+                            TemplatePart::Interpolation(Cow::Borrowed("x")),
+                        ],
+                        trim: DirectiveTrim {
+                            opening: Trim {
+                                left: false,
+                                right: true,
+                            },
+                            alternative: Default::default(),
+                            closing: Trim {
+                                left: true,
+                                right: false,
+                            },
+                        },
+                    }),
+                    TemplatePart::Literal(Cow::Borrowed("\ndone")),
+                ],
             ),
             (
-                r#"<<-EOF
-    strip
-    the
-    spaces
-    but    not   these 老虎
-  EOF
-"#,
-                r#"  strip
-  the
-  spaces
-  but    not   these 老虎"#,
+                "<<EOF\nstart\n  %{~ if ok ~}\r\n    yes\n  %{~ else ~}\n    no\n\t%{~ endif ~}\nend\nEOF\n",
+                vec![
+                    TemplatePart::Literal(Cow::Borrowed("start")),
+                    TemplatePart::Directive(Directive::If {
+                        condition: Cow::Borrowed("ok"),
+                        truthy: vec![TemplatePart::Literal(Cow::Borrowed("    yes"))],
+                        falsy: vec![TemplatePart::Literal(Cow::Borrowed("    no"))],
+                        trim: DirectiveTrim {
+                            opening: both,
+                            alternative: both,
+                            closing: both,
+                        },
+                    }),
+                    TemplatePart::Literal(Cow::Borrowed("end")),
+                ],
             ),
             (
                 r#"<<-EOF
-strip
-    the
-spaces
-    but    not   these 老虎
-  EOF
+    %{ if ok~}
+      indented
+    %{~endif}
+    EOF
 "#,
-                r#"strip
-  the
-spaces
-  but    not   these 老虎"#,
+                vec![TemplatePart::Directive(Directive::If {
+                    condition: Cow::Borrowed("ok"),
+                    truthy: vec![TemplatePart::Literal(Cow::Borrowed("  indented"))],
+                    falsy: vec![],
+                    trim: DirectiveTrim {
+                        opening: Trim {
+                            left: false,
+                            right: true,
+                        },
+                        alternative: Default::default(),
+                        closing: Trim {
+                            left: true,
+                            right: false,
+                        },
+                    },
+                })],
             ),
             (
-                r#"<<-EOF
-  strip
-    the
-  spaces
-    but    not   these 老虎
-    EOF
-"#,
-                r#"strip
-the
-spaces
-but    not   these 老虎"#,
+                r#""a %{~ if x ~} b %{~ endif ~} c""#,
+                vec![
+                    TemplatePart::Literal(Cow::Borrowed("a")),
+                    TemplatePart::Directive(Directive::If {
+                        condition: Cow::Borrowed("x"),
+                        truthy: vec![TemplatePart::Literal(Cow::Borrowed("b"))],
+                        falsy: vec![],
+                        trim: DirectiveTrim {
+                            opening: both,
+                            alternative: Default::default(),
+                            closing: both,
+                        },
+                    }),
+                    TemplatePart::Literal(Cow::Borrowed("c")),
+                ],
             ),
         ];
 
         for (input, expected) in test_cases.iter() {
             println!("Testing {}", input);
-            let (remaining, actual) = heredoc_string(CompleteStr(input)).unwrap();
-            assert_eq!(remaining.0, "\n");
-            assert_eq!(actual, expected.to_string());
+            let (_, actual) = template(CompleteStr(input)).unwrap();
+            assert_eq!(*actual, *expected);
         }
     }
 
     #[test]
-    fn strings_are_parsed_correctly() {
+    fn interpolations_strip_whitespace_with_trim_markers() {
+        let literal = |s| TemplatePart::Literal(Cow::Borrowed(s));
+        let interpolation = |s| TemplatePart::Interpolation(Cow::Borrowed(s));
         let test_cases = [
-            (r#""""#, "", ""),
-            (r#""abcd""#, r#"abcd"#, ""),
-            (r#""ab\"cd""#, r#"ab"cd"#, ""),
-            (r#""ab \\ cd""#, r#"ab \ cd"#, ""),
-            (r#""ab \n cd""#, "ab \n cd", ""),
-            (r#""ab \? cd""#, "ab ? cd", ""),
-            (
-                r#"<<EOF
-    EOF
-"#,
-                "",
-                "\n",
-            ),
             (
-                r#""ab \xff \251 \uD000 \U29000""#,
-                "ab ÿ © \u{D000} \u{29000}",
-                "",
+                r#""a ${~ x ~} b""#,
+                vec![literal("a"), interpolation("x"), literal("b")],
             ),
             (
-                r#"<<EOF
-something
-    EOF
-"#,
-                "something",
-                "\n",
+                r#""a ${~x} b ${y~} c""#,
+                vec![
+                    literal("a"),
+                    interpolation("x"),
+                    literal(" b "),
+                    interpolation("y"),
+                    literal("c"),
+                ],
             ),
             (
-                r#"<<EOH
-something
-with
-new lines
-and quotes "
-                        EOH
-"#,
-                r#"something
-with
-new lines
-and quotes ""#,
-                "\n",
+                "<<EOF\nlist:\n  ${~ join(\", \", xs) ~}  \n\t  done\nEOF\n",
+                vec![
+                    literal("list:"),
+                    interpolation(r#"join(", ", xs)"#),
+                    literal("\t  done"),
+                ],
             ),
+            (r#""${~ x ~}""#, vec![interpolation("x")]),
         ];
 
-        for (input, expected, expected_remaining) in test_cases.iter() {
+        for (input, expected) in test_cases.iter() {
             println!("Testing {}", input);
-            let (remaining, actual) = string(CompleteStr(input)).unwrap();
-            assert_eq!(&remaining.0, expected_remaining);
-            assert_eq!(&actual, expected, "Input: {}", input);
+            let (_, actual) = template(CompleteStr(input)).unwrap();
+            assert_eq!(*actual, *expected);
+        }
+    }
+
+    #[test]
+    fn directives_and_interpolations_strip_whitespace_together() {
+        let input = r#"<<-EOF
+    servers:
+    %{~ for name in names ~}
+      - ${~ name ~}
+    %{~ endfor ~}
+    end
+    EOF
+"#;
+        let (_, actual) = template(CompleteStr(input)).unwrap();
+        let both = Trim {
+            left: true,
+            right: true,
+        };
+        assert_eq!(
+            *actual,
+            vec![
+                TemplatePart::Literal(Cow::Borrowed("servers:")),
+                TemplatePart::Directive(Directive::For {
+                    key: None,
+                    value: Cow::Borrowed("name"),
+                    collection: Cow::Borrowed("names"),
+                    body: vec![
+                        TemplatePart::Literal(Cow::Borrowed("  -")),
+                        TemplatePart::Interpolation(Cow::Borrowed("name")),
+                    ],
+                    trim: DirectiveTrim {
+                        opening: both,
+                        alternative: Default::default(),
+                        closing: both,
+                    },
+                }),
+                TemplatePart::Literal(Cow::Borrowed("end")),
+            ]
+        );
+    }
+
+    #[test]
+    fn templates_keep_the_sequence_of_their_parts() {
+        let input = r#""a ${b} c %{ if d }e%{ endif }""#;
+        let (remaining, actual) = template(CompleteStr(input)).unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(
+            actual,
+            Template::new(vec![
+                TemplatePart::Literal(Cow::Borrowed("a ")),
+                TemplatePart::Interpolation(Cow::Borrowed("b")),
+                TemplatePart::Literal(Cow::Borrowed(" c ")),
+                TemplatePart::Directive(Directive::If {
+                    condition: Cow::Borrowed("d"),
+                    truthy: vec![TemplatePart::Literal(Cow::Borrowed("e"))],
+                    falsy: vec![],
+                    trim: Default::default(),
+                }),
+            ])
+        );
+        assert_eq!(actual.interpolations(), vec!["b"]);
+
+        let (_, nested) = template(CompleteStr(
+            r#""${a}%{ for x in y }${x}%{ if z }${w}%{ endif }%{ endfor }""#,
+        ))
+        .unwrap();
+        assert_eq!(nested.interpolations(), vec!["a", "x", "w"]);
+    }
+
+    #[test]
+    fn unclosed_template_directives_are_named_in_errors() {
+        let test_cases = [
+            ("%{ for x in xs }%{ if x }yes%{ endfor }", "%{ if x }"),
+            ("%{ if x }%{ for y in x }%{ endif }", "%{ for y in x }"),
+            ("%{ for x in xs }%{ if x }yes%{ endif }", "%{ for x in xs }"),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let error = template_directive(CompleteStr(input))
+                .map_err(|e| crate::Error::from_err_str(&e))
+                .unwrap_err();
+            match error {
+                crate::Error::UnclosedTemplateDirective(directive) => {
+                    assert_eq!(directive, *expected)
+                }
+                other => panic!("Unexpected error {:?}", other),
+            }
         }
     }
 }
@@ -0,0 +1,486 @@
+//! Variables and Traversals
+//!
+//! A variable is an identifier that refers to a value in the evaluation context. A traversal
+//! accesses a value nested inside the value of an expression.
+//!
+//! ```ebnf
+//! VariableExpr = Identifier;
+//! GetAttr = "." Identifier;
+//! Index = "[" Expression "]";
+//! Splat = attrSplat | fullSplat;
+//! attrSplat = "." "*" GetAttr*;
+//! fullSplat = "[" "*" "]" (GetAttr | Index)*;
+//! ```
+//!
+//! Any expression term can be traversed, so literals like `[1, 2, 3][1]` or `{a = 1}.a` are
+//! traversals too.
+//!
+//! A splat applies the steps that follow it to each element of a collection, so
+//! `aws_instance.web[*].id` is the `id` of every instance. The attribute splat `.*` only applies
+//! the attribute accesses that directly follow it, so in `a.*.b[0]` the index is applied to the
+//! collection of `b`s. The full splat `[*]` applies indices too. The steps are kept in the order
+//! they were written, and evaluators decide which steps a splat applies to.
+//!
+//! Each step of a traversal keeps the source it was parsed from so that an evaluator can point
+//! precisely at the step that failed, like a `.missing_key`.
+//!
+//! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#index-operator)
+use std::borrow::Cow;
+
+use nom::types::CompleteStr;
+use nom::{alt, call, char, delimited, do_parse, many0, map, named, preceded, IResult};
+
+use crate::parser::expression::{expression_with_options, Expression};
+use crate::parser::identifier::{identifier, Identifier};
+use crate::parser::options::ParseOptions;
+use crate::span::Span;
+
+/// A traversal like `var.foo.bar`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Traversal<'a> {
+    /// The expression being traversed
+    pub expression: Expression<'a>,
+    /// The steps of the traversal, in order. There is at least one step.
+    pub steps: Vec<TraversalStep<'a>>,
+}
+
+impl<'a> Traversal<'a> {
+    pub fn new(expression: Expression<'a>, steps: Vec<TraversalStep<'a>>) -> Self {
+        Self { expression, steps }
+    }
+}
+
+/// A single step of a [`Traversal`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraversalStep<'a> {
+    /// The operation performed by the step
+    pub operator: TraversalOperator<'a>,
+    /// The source the step was parsed from, like `.foo`
+    pub source: Cow<'a, str>,
+}
+
+impl<'a> TraversalStep<'a> {
+    pub fn new<S>(operator: TraversalOperator<'a>, source: S) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        Self {
+            operator,
+            source: source.into(),
+        }
+    }
+
+    /// Compute the span of the step in `source`, the input that was parsed
+    ///
+    /// Returns `None` if the step was not borrowed from `source`, for example if it has been
+    /// converted with [`AsOwned`](crate::AsOwned).
+    pub fn span(&self, source: &str) -> Option<Span> {
+        match self.source {
+            Cow::Borrowed(step) => Span::from_fragment(source, step),
+            Cow::Owned(_) => None,
+        }
+    }
+}
+
+/// The operation of a [`TraversalStep`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraversalOperator<'a> {
+    /// Access an attribute of an object, like `.foo`
+    GetAttr(Identifier<'a>),
+    /// Access an element of a collection by an expression, like `[0]` or `["key"]`
+    Index(Expression<'a>),
+    /// Apply the attribute accesses that follow to each element of a collection, like `.*`
+    AttrSplat,
+    /// Apply the steps that follow to each element of a collection, like `[*]`
+    FullSplat,
+}
+
+impl<'a> crate::AsOwned for Traversal<'a> {
+    type Output = Traversal<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        Traversal {
+            expression: self.expression.as_owned(),
+            steps: self.steps.as_owned(),
+        }
+    }
+}
+
+impl<'a> crate::AsOwned for TraversalStep<'a> {
+    type Output = TraversalStep<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        TraversalStep {
+            operator: self.operator.as_owned(),
+            source: Cow::Owned(self.source.to_string()),
+        }
+    }
+}
+
+impl<'a> crate::AsOwned for TraversalOperator<'a> {
+    type Output = TraversalOperator<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        match self {
+            TraversalOperator::GetAttr(ident) => {
+                TraversalOperator::GetAttr(Cow::Owned(ident.to_string()))
+            }
+            TraversalOperator::Index(expression) => TraversalOperator::Index(expression.as_owned()),
+            TraversalOperator::AttrSplat => TraversalOperator::AttrSplat,
+            TraversalOperator::FullSplat => TraversalOperator::FullSplat,
+        }
+    }
+}
+
+named!(
+    pub get_attr(CompleteStr) -> TraversalOperator,
+    map!(preceded!(char!('.'), call!(identifier)), TraversalOperator::GetAttr)
+);
+
+pub fn index_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, TraversalOperator<'a>, u32> {
+    map!(
+        input,
+        delimited!(
+            char!('['),
+            whitespace!(call!(expression_with_options, options)),
+            char!(']')
+        ),
+        TraversalOperator::Index
+    )
+}
+
+named!(
+    pub index(CompleteStr) -> TraversalOperator,
+    call!(index_with_options, &ParseOptions::default())
+);
+
+named!(
+    pub splat(CompleteStr) -> TraversalOperator,
+    alt!(
+        preceded!(char!('.'), char!('*')) => { |_| TraversalOperator::AttrSplat }
+        | delimited!(char!('['), whitespace!(char!('*')), char!(']')) => {
+            |_| TraversalOperator::FullSplat
+        }
+    )
+);
+
+pub fn traversal_step_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, TraversalStep<'a>, u32> {
+    use nom::InputTake;
+
+    let (remaining, operator) = alt!(input, splat | get_attr | call!(index_with_options, options))?;
+    let source = input.take(input.len() - remaining.len());
+    Ok((remaining, TraversalStep::new(operator, source.0)))
+}
+
+named!(
+    pub traversal_step(CompleteStr) -> TraversalStep,
+    call!(traversal_step_with_options, &ParseOptions::default())
+);
+
+pub fn traversal_steps_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Vec<TraversalStep<'a>>, u32> {
+    many0!(input, call!(traversal_step_with_options, options))
+}
+
+named!(
+    pub traversal_steps(CompleteStr) -> Vec<TraversalStep>,
+    call!(traversal_steps_with_options, &ParseOptions::default())
+);
+
+/// Wraps `expression` in a [`Traversal`] if there are any steps
+pub(crate) fn traverse<'a>(
+    expression: Expression<'a>,
+    steps: Vec<TraversalStep<'a>>,
+) -> Expression<'a> {
+    if steps.is_empty() {
+        expression
+    } else {
+        Expression::from(Traversal::new(expression, steps))
+    }
+}
+
+/// Parses a variable, followed by any traversal steps
+pub fn variable_expression(input: CompleteStr) -> IResult<CompleteStr, Expression, u32> {
+    do_parse!(
+        input,
+        variable: call!(identifier)
+        >> steps: call!(traversal_steps)
+        >> (traverse(Expression::Variable(variable), steps))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::ResultUtilsString;
+
+    #[test]
+    fn variables_are_parsed_correctly() {
+        let test_cases = [
+            ("foo", Expression::Variable(From::from("foo"))),
+            (
+                "var.foo",
+                Expression::from(Traversal::new(
+                    Expression::Variable(From::from("var")),
+                    vec![TraversalStep::new(
+                        TraversalOperator::GetAttr(From::from("foo")),
+                        ".foo",
+                    )],
+                )),
+            ),
+            (
+                "a.b-c.d_e",
+                Expression::from(Traversal::new(
+                    Expression::Variable(From::from("a")),
+                    vec![
+                        TraversalStep::new(TraversalOperator::GetAttr(From::from("b-c")), ".b-c"),
+                        TraversalStep::new(TraversalOperator::GetAttr(From::from("d_e")), ".d_e"),
+                    ],
+                )),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(
+                variable_expression(CompleteStr(input)).unwrap_output(),
+                *expected
+            );
+        }
+    }
+
+    #[test]
+    fn collection_literals_are_traversed() {
+        let step = |operator, source| TraversalStep::new(operator, source);
+        let test_cases = [
+            (
+                "[1,2,3][1]",
+                Expression::from(Traversal::new(
+                    Expression::new_tuple(vec![From::from(1), From::from(2), From::from(3)]),
+                    vec![step(TraversalOperator::Index(From::from(1)), "[1]")],
+                )),
+            ),
+            (
+                "{a=1}.a",
+                Expression::from(Traversal::new(
+                    Expression::new_object(vec![("a", Expression::from(1))]),
+                    vec![step(TraversalOperator::GetAttr(From::from("a")), ".a")],
+                )),
+            ),
+            (
+                r#"{a = [true]}["a"][ 0 ]"#,
+                Expression::from(Traversal::new(
+                    Expression::new_object(vec![(
+                        "a",
+                        Expression::new_tuple(vec![From::from(true)]),
+                    )]),
+                    vec![
+                        step(TraversalOperator::Index(From::from("a")), r#"["a"]"#),
+                        step(TraversalOperator::Index(From::from(0)), "[ 0 ]"),
+                    ],
+                )),
+            ),
+            (
+                "var.list[var.index].name",
+                Expression::from(Traversal::new(
+                    Expression::Variable(From::from("var")),
+                    vec![
+                        step(TraversalOperator::GetAttr(From::from("list")), ".list"),
+                        step(
+                            TraversalOperator::Index(Expression::from(Traversal::new(
+                                Expression::Variable(From::from("var")),
+                                vec![step(
+                                    TraversalOperator::GetAttr(From::from("index")),
+                                    ".index",
+                                )],
+                            ))),
+                            "[var.index]",
+                        ),
+                        step(TraversalOperator::GetAttr(From::from("name")), ".name"),
+                    ],
+                )),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(Expression::parse(input).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn splats_are_parsed_correctly() {
+        let test_cases = [
+            (".*", TraversalStep::new(TraversalOperator::AttrSplat, ".*")),
+            (
+                "[*]",
+                TraversalStep::new(TraversalOperator::FullSplat, "[*]"),
+            ),
+            (
+                "[ * ]",
+                TraversalStep::new(TraversalOperator::FullSplat, "[ * ]"),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(
+                traversal_step(CompleteStr(input)).unwrap_output(),
+                *expected
+            );
+        }
+
+        for input in [".", "[]", "[**]", ". *"].iter() {
+            println!("Testing {}", input);
+            assert!(traversal_step(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn mixed_traversals_are_parsed_correctly() {
+        let step = |operator, source| TraversalStep::new(operator, source);
+        let get_attr = |name| TraversalOperator::GetAttr(From::from(name));
+        let test_cases = [
+            (
+                "local.list[0]",
+                Traversal::new(
+                    Expression::Variable(From::from("local")),
+                    vec![
+                        step(get_attr("list"), ".list"),
+                        step(TraversalOperator::Index(From::from(0)), "[0]"),
+                    ],
+                ),
+            ),
+            (
+                "aws_instance.web[*].id",
+                Traversal::new(
+                    Expression::Variable(From::from("aws_instance")),
+                    vec![
+                        step(get_attr("web"), ".web"),
+                        step(TraversalOperator::FullSplat, "[*]"),
+                        step(get_attr("id"), ".id"),
+                    ],
+                ),
+            ),
+            (
+                "aws_instance.web.*.id",
+                Traversal::new(
+                    Expression::Variable(From::from("aws_instance")),
+                    vec![
+                        step(get_attr("web"), ".web"),
+                        step(TraversalOperator::AttrSplat, ".*"),
+                        step(get_attr("id"), ".id"),
+                    ],
+                ),
+            ),
+            (
+                "a.b[0].c[*].d",
+                Traversal::new(
+                    Expression::Variable(From::from("a")),
+                    vec![
+                        step(get_attr("b"), ".b"),
+                        step(TraversalOperator::Index(From::from(0)), "[0]"),
+                        step(get_attr("c"), ".c"),
+                        step(TraversalOperator::FullSplat, "[*]"),
+                        step(get_attr("d"), ".d"),
+                    ],
+                ),
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            assert_eq!(
+                Expression::parse(input).unwrap(),
+                Expression::from(expected.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn attribute_and_full_splats_are_distinct() {
+        let steps = |input| match Expression::parse(input).unwrap() {
+            Expression::Traversal(traversal) => traversal
+                .steps
+                .into_iter()
+                .map(|step| step.operator)
+                .collect::<Vec<_>>(),
+            other => panic!("Unexpected expression {:?}", other),
+        };
+        let get_attr = |name| TraversalOperator::GetAttr(From::from(name));
+
+        assert_eq!(
+            steps("a.*.b"),
+            [TraversalOperator::AttrSplat, get_attr("b")]
+        );
+        assert_eq!(
+            steps("a[*].b"),
+            [TraversalOperator::FullSplat, get_attr("b")]
+        );
+        assert_eq!(
+            steps("a.*.b.*.c"),
+            [
+                TraversalOperator::AttrSplat,
+                get_attr("b"),
+                TraversalOperator::AttrSplat,
+                get_attr("c")
+            ]
+        );
+        assert_eq!(
+            steps("a.*.b[0]"),
+            [
+                TraversalOperator::AttrSplat,
+                get_attr("b"),
+                TraversalOperator::Index(From::from(0))
+            ]
+        );
+        assert_ne!(steps("a.*.b"), steps("a[*].b"));
+    }
+
+    #[test]
+    fn decimal_numbers_are_not_traversed() {
+        assert_eq!(Expression::parse("1.5").unwrap(), Expression::from(1.5));
+        assert_eq!(
+            Expression::parse("[1.5][0]").unwrap(),
+            Expression::from(Traversal::new(
+                Expression::new_tuple(vec![From::from(1.5)]),
+                vec![TraversalStep::new(
+                    TraversalOperator::Index(From::from(0)),
+                    "[0]"
+                )],
+            ))
+        );
+    }
+
+    #[test]
+    fn traversal_steps_carry_their_span() {
+        let hcl = "foo = 1\nvalue = var.config.missing_key\n";
+        let body = crate::parser::parse_str(hcl).unwrap();
+        let attribute = body[1].clone().unwrap_attribute();
+
+        let traversal = match attribute.expression {
+            Expression::Traversal(traversal) => traversal,
+            other => panic!("Expected a traversal, got {:?}", other),
+        };
+        assert_eq!(
+            traversal.steps[1].operator,
+            TraversalOperator::GetAttr(From::from("missing_key"))
+        );
+
+        let span = traversal.steps[1].span(hcl).unwrap();
+        assert_eq!(span, Span::new(26, 38));
+        assert_eq!(&hcl[span.start..span.end], ".missing_key");
+
+        let owned = crate::AsOwned::as_owned(&traversal.steps[1]);
+        assert_eq!(owned.span(hcl), None);
+    }
+}
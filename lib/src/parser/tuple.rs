@@ -9,12 +9,134 @@
 //! ```
 //!
 //! [Reference](https://github.com/hashicorp/hcl2/blob/master/hcl/hclsyntax/spec.md#collection-values)
+use std::borrow::Cow;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
 use nom::types::CompleteStr;
-use nom::{char, named, opt, preceded, terminated};
+use nom::{
+    alt_complete, call, char, complete, do_parse, map, named, opt, preceded, recognize, terminated,
+    IResult,
+};
+
+use crate::errors::{in_context, ParseContext};
+
+use super::comment::{block_comment, line_comment};
+use super::expression::{expression_with_options, Expression};
+use super::options::{newlines_ignored, ParseOptions};
+use super::whitespace::whitespace;
+
+/// The elements of a tuple
+///
+/// `Tuple` dereferences to a `Vec` of its elements.
+#[derive(Clone, Debug, Default)]
+pub struct Tuple<'a> {
+    pub elements: Vec<Expression<'a>>,
+    /// Whether the last element is followed by a comma, like in `[1, 2,]`
+    ///
+    /// This is only recorded when parsing with
+    /// [comments preserved](ParseOptions::preserve_comments), so that the tuple can be printed as
+    /// it was written. It is ignored when comparing tuples.
+    pub trailing_comma: bool,
+    /// The comment trailing each element on the same line, including its delimiters, like
+    /// `# one` in `[1, # one\n 2]`
+    ///
+    /// Like [`trailing_comma`](Tuple::trailing_comma), the comments are only recorded when
+    /// parsing with comments preserved, in which case there is one entry for each element. They
+    /// are ignored when comparing tuples.
+    pub comments: Vec<Option<Cow<'a, str>>>,
+}
+
+impl<'a> Deref for Tuple<'a> {
+    type Target = Vec<Expression<'a>>;
 
-use super::expression::{expression, Expression};
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
 
-pub type Tuple<'a> = Vec<Expression<'a>>;
+impl<'a> DerefMut for Tuple<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.elements
+    }
+}
+
+impl<'a> From<Vec<Expression<'a>>> for Tuple<'a> {
+    fn from(elements: Vec<Expression<'a>>) -> Self {
+        Tuple {
+            elements,
+            trailing_comma: false,
+            comments: vec![],
+        }
+    }
+}
+
+impl<'a> FromIterator<Expression<'a>> for Tuple<'a> {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = Expression<'a>>,
+    {
+        Tuple::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> IntoIterator for Tuple<'a> {
+    type Item = Expression<'a>;
+    type IntoIter = std::vec::IntoIter<Expression<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Tuple<'a> {
+    type Item = &'b Expression<'a>;
+    type IntoIter = std::slice::Iter<'b, Expression<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+impl<'a> PartialEq for Tuple<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.elements == other.elements
+    }
+}
+
+impl<'a> Eq for Tuple<'a> {}
+
+impl<'a> PartialEq<Vec<Expression<'a>>> for Tuple<'a> {
+    fn eq(&self, other: &Vec<Expression<'a>>) -> bool {
+        self.elements == *other
+    }
+}
+
+impl<'a> PartialEq<Tuple<'a>> for Vec<Expression<'a>> {
+    fn eq(&self, other: &Tuple<'a>) -> bool {
+        *self == other.elements
+    }
+}
+
+impl<'a> crate::AsOwned for Tuple<'a> {
+    type Output = Tuple<'static>;
+
+    fn as_owned(&self) -> Self::Output {
+        Tuple {
+            elements: self.elements.as_owned(),
+            trailing_comma: self.trailing_comma,
+            comments: self
+                .comments
+                .iter()
+                .map(|comment| {
+                    comment
+                        .as_ref()
+                        .map(|comment| Cow::Owned(comment.to_string()))
+                })
+                .collect(),
+        }
+    }
+}
 
 named!(
     tuple_begin(CompleteStr) -> char,
@@ -30,21 +152,79 @@ named!(
 // whitespace! Must not be captured after `]`!
 
 // TODO: Deal with for syntax ambiguity when implementing later
+pub fn tuple_with_options<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Tuple<'a>, u32> {
+    let options = newlines_ignored(options, true);
+    let result = tuple_elements(input, &options);
+    in_context(input, ParseContext::Tuple, result)
+}
+
+// "[" and the elements up to the closing "]", with the comments trailing the elements if
+// comments are preserved
+fn tuple_elements<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Tuple<'a>, u32> {
+    let (mut remaining, _) = terminated!(input, tuple_begin, whitespace)?;
+    let mut tuple = Tuple::default();
+    // A trailing separator must follow an element
+    let mut separated = false;
+
+    loop {
+        if let Ok((remaining, _)) = char!(remaining, ']') {
+            tuple.trailing_comma = options.preserve_comments && separated;
+            return Ok((remaining, tuple));
+        }
+
+        let (rest, expression) = expression_with_options(remaining, options)?;
+        tuple.elements.push(expression);
+        let (rest, separator) = if options.preserve_comments {
+            let (rest, (separator, comment)) = do_parse!(
+                rest,
+                call!(nom::space0)
+                >> separator: opt!(complete!(tuple_separator))
+                >> comment: opt!(complete!(trailing_comment))
+                >> ((separator, comment))
+            )?;
+            tuple
+                .comments
+                .push(comment.map(|comment| Cow::Borrowed(comment.0)));
+            (rest, separator)
+        } else {
+            (rest, None)
+        };
+
+        let (rest, _) = whitespace(rest)?;
+        let (rest, separator) = match separator {
+            Some(separator) => (rest, Some(separator)),
+            None => opt!(rest, complete!(tuple_separator))?,
+        };
+        let (rest, _) = whitespace(rest)?;
+        remaining = rest;
+
+        separated = separator.is_some();
+        if !separated {
+            let (remaining, _) = char!(remaining, ']')?;
+            return Ok((remaining, tuple));
+        }
+    }
+}
+
 named!(
     pub tuple(CompleteStr) -> Tuple,
+    call!(tuple_with_options, &ParseOptions::default())
+);
+
+// A comment after an element, or after its separator, on the same line
+named!(
+    trailing_comment(CompleteStr) -> CompleteStr,
     preceded!(
-        tuple_begin,
-        terminated!(
-            whitespace!(
-                separated_list!(
-                    tuple_separator,
-                    expression
-                )
-            ),
-            terminated!(
-                whitespace!(opt!(tuple_separator)),
-                char!(']')
-            )
+        call!(nom::space0),
+        alt_complete!(
+            map!(recognize!(line_comment), |comment| CompleteStr(comment.0.trim_end()))
+            | recognize!(block_comment)
         )
     )
 );
@@ -59,7 +239,17 @@ mod tests {
     fn simple_tuples_are_parsed_successfully() {
         let test_cases = [
             (r#"[]"#, vec![]),
+            (r#"[  ]"#, vec![]),
+            ("[\n  # Nothing here\n]", vec![]),
             (r#"[1,]"#, vec![Expression::from(1)]),
+            (
+                "[null, /* two */ 2 ,\n[]]",
+                vec![
+                    Expression::Null,
+                    Expression::from(2),
+                    Expression::new_tuple(vec![]),
+                ],
+            ),
             (
                 r#"[true, false, 123, -123.456, "foobar"]"#,
                 vec![
@@ -101,4 +291,88 @@ mod tests {
             assert_eq!(actual_value, *expected_value);
         }
     }
+
+    #[test]
+    fn invalid_tuples_are_not_accepted() {
+        // Elements must be separated by commas, even on separate lines
+        let test_cases = ["[1\n2]", "[1 2]", "[,]", "[1,,2]", "[1", "[1,"];
+
+        for input in test_cases.iter() {
+            println!("Testing {:?}", input);
+            assert!(tuple(CompleteStr(input)).is_err());
+        }
+    }
+
+    #[test]
+    fn trailing_commas_are_recorded_with_trivia() {
+        let options = ParseOptions::default().preserve_comments(true);
+        let test_cases = [
+            ("[1,2,]", true),
+            ("[1,2]", false),
+            ("[1, 2 , ]", true),
+            ("[\n  1,\n  2, # two\n]", true),
+            ("[\n  1,\n  2 # two\n]", false),
+            ("[]", false),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            let parsed = tuple_with_options(CompleteStr(input), &options).unwrap_output();
+            assert_eq!(parsed.trailing_comma, *expected);
+            assert!(!tuple(CompleteStr(input)).unwrap_output().trailing_comma);
+        }
+
+        // The trailing comma does not matter when comparing tuples
+        let with_comma = tuple_with_options(CompleteStr("[1,2,]"), &options).unwrap_output();
+        assert_eq!(with_comma, tuple(CompleteStr("[1,2]")).unwrap_output());
+    }
+
+    #[test]
+    fn trailing_comments_are_attached_to_elements() {
+        let options = ParseOptions::default().preserve_comments(true);
+        let test_cases = [
+            ("[1, # one\n 2]", vec![Some("# one"), None]),
+            (
+                "[\n  # leading\n  1 // one\n  , \"two\" /* two */\n]",
+                vec![Some("// one"), Some("/* two */")],
+            ),
+            ("[true,\n  false, # false\n]", vec![None, Some("# false")]),
+            ("[1, 2]", vec![None, None]),
+            ("[]", vec![]),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {}", input);
+            let parsed = tuple_with_options(CompleteStr(input), &options).unwrap_output();
+            let comments: Vec<_> = parsed.comments.iter().map(|c| c.as_deref()).collect();
+            assert_eq!(comments, *expected);
+            assert_eq!(parsed.len(), expected.len());
+
+            let plain = tuple(CompleteStr(input)).unwrap_output();
+            assert!(plain.comments.is_empty());
+            assert_eq!(plain, parsed);
+        }
+    }
+
+    #[test]
+    fn trailing_comments_stay_attached_after_a_round_trip() {
+        use crate::parser::parse_with_trivia;
+        use crate::parser::printer::{Indent, Printer};
+
+        let body = parse_with_trivia("list = [1, # one\n 2]\n").unwrap();
+        let printed = Printer::new().print(&body);
+        assert_eq!(printed, "list = [\n  1, # one\n  2,\n]\n");
+
+        let reparsed = parse_with_trivia(&printed).unwrap();
+        assert_eq!(reparsed, body);
+        match reparsed[0].clone().unwrap_attribute().expression {
+            Expression::Tuple(list) => {
+                assert_eq!(list.comments, vec![Some(Cow::Borrowed("# one")), None])
+            }
+            other => panic!("Unexpected expression {:?}", other),
+        }
+
+        let printed = Printer::new().indent(Indent::Tabs).print(&body);
+        assert_eq!(printed, "list = [\n\t1, # one\n\t2,\n]\n");
+    }
 }
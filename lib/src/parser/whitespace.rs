@@ -25,42 +25,28 @@
 //! Comments and whitespace cannot begin within within other comments, or within
 //! template literals except inside an interpolation sequence or template directive.
 use nom::types::CompleteStr;
-use nom::{
-    alt_complete, call, delimited, do_parse, eat_separator, eol, many0, many1, named, tag,
-    take_until, take_while,
-};
+use nom::{alt_complete, call, do_parse, eof, eol, is_a, many0, many1, named, terminated, IResult};
 
-fn not_eol(c: char) -> bool {
-    c != '\r' && c != '\n'
-}
-
-named!(
-    pub inline_comment(CompleteStr) -> CompleteStr,
-    delimited!(tag!("/*"), take_until!("*/"), tag!("*/"))
-);
+use crate::parser::comment::{block_comment, line_comment};
+use crate::parser::options::ParseOptions;
 
+// A line comment on the last line of the input might not be terminated by a newline
 named!(
-    pub hash_comment(CompleteStr) -> CompleteStr,
-    delimited!(tag!("#"), take_while!(not_eol), call!(eol))
+    line_comment_end(CompleteStr) -> CompleteStr,
+    alt_complete!(call!(eol) | eof!())
 );
 
+// A line comment together with the newline ending it
 named!(
-    pub slash_comment(CompleteStr) -> CompleteStr,
-    delimited!(tag!("//"), take_while!(not_eol), call!(eol))
-);
-
-named!(
-    pub line_comment(CompleteStr) -> CompleteStr,
-    alt_complete!(
-        hash_comment | hash_comment
-    )
+    terminated_line_comment(CompleteStr) -> CompleteStr,
+    terminated!(line_comment, line_comment_end)
 );
 
 named!(pub inline_whitespace(CompleteStr) -> Vec<CompleteStr>,
     many0!(
         alt_complete!(
-            inline_comment
-            | eat_separator!(" \t")
+            block_comment
+            | is_a!(" \t")
         )
     )
 );
@@ -68,22 +54,35 @@ named!(pub inline_whitespace(CompleteStr) -> Vec<CompleteStr>,
 named!(pub whitespace(CompleteStr) -> Vec<CompleteStr>,
     many0!(
         alt_complete!(
-            hash_comment
-            | slash_comment
-            | inline_comment
-            | eat_separator!(" \t\r\n")
+            terminated_line_comment
+            | block_comment
+            | is_a!(" \t\r\n")
         )
     )
 );
 
+/// Whitespace between the parts of an expression
+///
+/// Newlines and line comments are only skipped where they are insignificant, like inside
+/// parentheses, so that an expression never consumes the newline ending an attribute.
+pub(crate) fn expression_whitespace<'a>(
+    input: CompleteStr<'a>,
+    options: &ParseOptions,
+) -> IResult<CompleteStr<'a>, Vec<CompleteStr<'a>>, u32> {
+    if options.newlines_ignored {
+        whitespace(input)
+    } else {
+        inline_whitespace(input)
+    }
+}
+
 named!(
     pub newline(CompleteStr) -> Vec<CompleteStr>,
     many1!(
         alt_complete!(
-            hash_comment
-            | slash_comment
+            terminated_line_comment
             | do_parse!(
-                comment: inline_comment
+                comment: block_comment
                 >> call!(eol)
                 >> (comment)
             )
@@ -176,6 +175,8 @@ mod tests {
             ("  \t\r\n", vec!["  \t\r\n"]),
             ("# Test Comment\r\n", vec![" Test Comment"]),
             ("// Test Comment\n", vec![" Test Comment"]),
+            ("# No newline", vec![" No newline"]),
+            ("// No newline", vec![" No newline"]),
             (
                 "/* Test Comment One liner */",
                 vec![" Test Comment One liner "],
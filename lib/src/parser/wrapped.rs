@@ -0,0 +1,113 @@
+//! Parsers that take `&str` directly
+//!
+//! The parsers in the other modules work on [`CompleteStr`](nom::types::CompleteStr) and return
+//! nom errors. The parsers here wrap them so that they can be used without importing any nom
+//! types: they take a `&str` and return the remaining input together with the output, or an
+//! [`Error`] with the line and column where parsing failed.
+//!
+//! ```rust
+//! use ferrous_chloride::parser::wrapped;
+//!
+//! let (remaining, number) = wrapped::number("123 + 4").unwrap();
+//! assert_eq!(remaining, " + 4");
+//! assert_eq!(number, ferrous_chloride::parser::number::Number::from(123));
+//!
+//! assert!(wrapped::boolean("maybe").is_err());
+//! ```
+use std::borrow::Cow;
+
+use crate::parser::attribute::{self, Attribute};
+use crate::parser::block::{self, Block};
+use crate::parser::body::{self, Body};
+use crate::parser::boolean;
+use crate::parser::conditional::{self, Conditional};
+use crate::parser::expression::{self, Expression};
+use crate::parser::for_expr::{self, ForExpr};
+use crate::parser::function_call::{self, FunctionCall};
+use crate::parser::identifier::{self, Identifier};
+use crate::parser::null;
+use crate::parser::number::{self, Number};
+use crate::parser::object::{self, Object};
+use crate::parser::string::{self, StringLiteral, Template};
+use crate::parser::tuple::{self, Tuple};
+use crate::utils::wrap_str;
+use crate::Error;
+
+macro_rules! wrap_parsers {
+    ($($parser:path => $name:ident -> $output:ty;)*) => {
+        $(
+            #[doc = concat!("`&str` version of [`", stringify!($parser), "`]")]
+            pub fn $name(input: &str) -> Result<(&str, $output), Error> {
+                wrap_str(input, $parser)
+            }
+        )*
+    };
+}
+
+wrap_parsers! {
+    attribute::attribute => attribute -> Attribute;
+    block::block => block -> Block;
+    body::body => body -> Body;
+    boolean::boolean => boolean -> bool;
+    conditional::conditional => conditional -> Conditional;
+    expression::expression => expression -> Expression;
+    for_expr::for_expr => for_expr -> ForExpr;
+    function_call::function_call => function_call -> FunctionCall;
+    identifier::identifier => identifier -> Identifier;
+    null::null => null -> ();
+    number::number => number -> Number;
+    object::object => object -> Object;
+    string::single_line_string => single_line_string -> StringLiteral;
+    string::string => string -> Cow<str>;
+    string::template => template -> Template;
+    tuple::tuple => tuple -> Tuple;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsers_take_str() {
+        assert_eq!(boolean("true").unwrap(), ("", true));
+        assert_eq!(null("null, 1").unwrap(), (", 1", ()));
+        assert_eq!(number("1.5e3").unwrap(), ("", Number::from(1500)));
+        assert_eq!(
+            identifier("foo = 1").unwrap(),
+            (" = 1", Cow::Borrowed("foo"))
+        );
+        assert_eq!(
+            string(r#""foo" bar"#).unwrap(),
+            (" bar", Cow::Borrowed("foo"))
+        );
+        assert_eq!(
+            single_line_string(r#""foo""#).unwrap(),
+            ("", "foo".to_string())
+        );
+        assert_eq!(
+            tuple("[1, 2]").unwrap(),
+            ("", vec![From::from(1), From::from(2)].into())
+        );
+        assert_eq!(
+            expression("a ? 1 : 2").unwrap(),
+            ("", Expression::parse("a ? 1 : 2").unwrap())
+        );
+        assert_eq!(
+            Expression::from(conditional("a > 1 ? 2 : 3").unwrap().1),
+            Expression::parse("a > 1 ? 2 : 3").unwrap()
+        );
+        assert_eq!(
+            attribute("foo = true\n").unwrap(),
+            ("\n", Attribute::new("foo", true))
+        );
+    }
+
+    #[test]
+    fn errors_have_a_position() {
+        let error = number("\n  abc").unwrap_err();
+        assert_eq!(error.position(), Some((1, 1)));
+
+        let error = single_line_string("\"foo\nbar\"").unwrap_err();
+        assert!(error.position().is_some());
+    }
+}
@@ -0,0 +1,56 @@
+//! Commonly used types and functions
+//!
+//! Glob import this module to bring the main types of the crate into scope:
+//!
+//! ```rust
+//! use ferrous_chloride::prelude::*;
+//!
+//! let body: Body = parse_str("answer = 42\ngreeting = <<EOF\nhello\nEOF\n").unwrap();
+//! let value: Value = body.to_value().unwrap();
+//! assert!(value.is_aggregate());
+//!
+//! let options = ParseOptions::default().normalize_numbers(true);
+//! let body = parse_str_with_options("answer = 042", &options).unwrap();
+//! match body.query("answer") {
+//!     Some(Expression::Number(number)) => assert_eq!(number, "42"),
+//!     other => panic!("Unexpected {:?}", other),
+//! }
+//!
+//! let marker = HereDoc {
+//!     identifier: nom::types::CompleteStr("EOT"),
+//!     indented: false,
+//! };
+//! assert_eq!(marker.emit("hello", ""), "<<EOT\nhello\nEOT\n");
+//!
+//! let (remaining, number): (&str, Number) = wrap_str("1.5 + 2", number).unwrap();
+//! assert_eq!((remaining, &*number), (" + 2", "1.5"));
+//!
+//! let error: Error = parse_str("answer = ").unwrap_err();
+//! assert!(error.position().is_some());
+//! ```
+//!
+//! # What is public
+//!
+//! The types re-exported here, the `parse_*` functions and the [`Error`] type are the supported
+//! entry points of the crate. The parsers in the [`parser`](crate::parser) modules, such as
+//! [`number`], are public for advanced users who want to parse fragments of HCL; use
+//! [`wrap_str`] to run one of them over a `&str`. Helpers that are only used to implement or test
+//! the parsers, like the key type of objects and the assertions in tests, are internal.
+
+#[doc(no_inline)]
+pub use crate::errors::Error;
+#[doc(no_inline)]
+pub use crate::parser::number::{number, Number};
+#[doc(no_inline)]
+pub use crate::parser::string::HereDoc;
+#[doc(no_inline)]
+pub use crate::parser::{
+    parse_reader, parse_slice, parse_str, parse_str_with_options, parse_with_spans,
+    parse_with_trivia, try_parse, Body, Expression, ParseOptions,
+};
+#[doc(no_inline)]
+pub use crate::utils::wrap_str;
+#[doc(no_inline)]
+pub use crate::value::Value;
+#[doc(no_inline)]
+pub use crate::AsOwned;
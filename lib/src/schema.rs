@@ -0,0 +1,405 @@
+//! Schema inference
+//!
+//! [`infer_schema`] walks a [`Value`] and produces a best guess of its type. The [`Schema`] is
+//! displayed as a type constraint in the same notation as Terraform, like
+//! `object({name = string, ports = list(number)})`, so that it can be used as a starting point
+//! when authoring a schema from example configuration.
+//!
+//! [`infer_type`] instead gives the type of the literal that a value was written as, telling
+//! tuples apart from lists, so that validators can check values against declared types.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use nom::types::CompleteStr;
+
+use crate::parser::identifier::identifier;
+use crate::value::{MapValues, QuotedString, Value};
+
+/// The inferred type of a [`Value`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schema {
+    /// Any type, used when the values seen have different types or when nothing is known about
+    /// the type, like for `null` or an empty list
+    Any,
+    /// An integer or a float
+    Number,
+    /// `true` or `false`
+    Bool,
+    /// A string
+    String,
+    /// A list where every element has the same type
+    List(Box<Schema>),
+    /// A tuple with a type for each of its elements
+    Tuple(Vec<Schema>),
+    /// An object with typed attributes
+    Object(BTreeMap<String, Schema>),
+    /// Blocks with a number of labels and a body with typed attributes
+    Block {
+        labels: usize,
+        body: BTreeMap<String, Schema>,
+    },
+}
+
+/// Infer the [`Schema`] of a value
+///
+/// Lists are inferred to contain the type of their elements, ignoring `null` elements, and
+/// objects to have the union of the attributes of all their maps. `null` and empty lists are
+/// inferred as [`Schema::Any`].
+///
+/// When the types seen for the elements of a list or for an attribute differ, they are widened:
+/// lists, objects and blocks with the same number of labels are widened element by element, and
+/// anything else that differs is widened to [`Schema::Any`].
+///
+/// ```rust
+/// use ferrous_chloride::parse_str;
+/// use ferrous_chloride::schema::infer_schema;
+///
+/// let body = parse_str(r#"
+/// name  = "web"
+/// ports = [80, 443.5]
+/// "#).unwrap();
+/// let schema = infer_schema(&body.to_value().unwrap());
+/// assert_eq!(schema.to_string(), "object({name = string, ports = list(number)})");
+/// ```
+pub fn infer_schema(value: &Value) -> Schema {
+    infer(value, false)
+}
+
+/// Infer the type of the literal a value was written as
+///
+/// Unlike [`infer_schema`], the elements of a list are not widened: a list is inferred as a
+/// [`Schema::List`] only when all its elements have the same type, and as a [`Schema::Tuple`]
+/// otherwise, including when it is empty. Objects are always inferred as [`Schema::Object`], like
+/// object literals are in HCL.
+///
+/// ```rust
+/// use ferrous_chloride::Value;
+///
+/// let value = Value::new_list(vec![Value::Integer(1), Value::from("a")]);
+/// assert_eq!(value.inferred_type().to_string(), "tuple([number, string])");
+/// ```
+pub fn infer_type(value: &Value) -> Schema {
+    infer(value, true)
+}
+
+fn infer(value: &Value, tuples: bool) -> Schema {
+    match value {
+        Value::Null => Schema::Any,
+        Value::Integer(_) | Value::Float(_) => Schema::Number,
+        Value::Boolean(_) => Schema::Bool,
+        Value::String(_) => Schema::String,
+        Value::List(list) if tuples => {
+            let mut elements: Vec<Schema> = list.iter().map(infer_type).collect();
+            match elements.split_first() {
+                Some((first, rest)) if rest.iter().all(|element| element == first) => {
+                    Schema::List(Box::new(elements.swap_remove(0)))
+                }
+                _ => Schema::Tuple(elements),
+            }
+        }
+        Value::List(list) => Schema::List(Box::new(
+            list.iter()
+                .filter(|value| **value != Value::Null)
+                .map(infer_schema)
+                .fold(None, widen)
+                .unwrap_or(Schema::Any),
+        )),
+        Value::Object(maps) => Schema::Object(
+            maps.iter()
+                .map(|map| infer_attributes(map, tuples))
+                .fold(BTreeMap::new(), widen_attributes),
+        ),
+        Value::Block(block) => block
+            .iter()
+            .map(|(labels, body)| Schema::Block {
+                labels: labels.len(),
+                body: infer_attributes(body, tuples),
+            })
+            .fold(None, widen)
+            .unwrap_or(Schema::Any),
+    }
+}
+
+fn infer_attributes(map: &MapValues, tuples: bool) -> BTreeMap<String, Schema> {
+    let mut attributes = BTreeMap::new();
+    for (key, value) in map.iter() {
+        // Duplicate keys in an unmerged map are widened too
+        let existing = attributes.remove(&**key);
+        let schema = widen(existing, infer(value, tuples)).unwrap_or(Schema::Any);
+        attributes.insert(key.to_string(), schema);
+    }
+    attributes
+}
+
+fn widen(schema: Option<Schema>, other: Schema) -> Option<Schema> {
+    let schema = match schema {
+        None => return Some(other),
+        Some(schema) => schema,
+    };
+
+    Some(match (schema, other) {
+        (Schema::List(element), Schema::List(other)) => {
+            Schema::List(Box::new(widen(Some(*element), *other)?))
+        }
+        (Schema::Tuple(elements), Schema::Tuple(other)) if elements.len() == other.len() => {
+            Schema::Tuple(
+                elements
+                    .into_iter()
+                    .zip(other)
+                    .map(|(element, other)| widen(Some(element), other))
+                    .collect::<Option<_>>()?,
+            )
+        }
+        (Schema::Object(attributes), Schema::Object(other)) => {
+            Schema::Object(widen_attributes(attributes, other))
+        }
+        (
+            Schema::Block { labels, body },
+            Schema::Block {
+                labels: other_labels,
+                body: other,
+            },
+        ) if labels == other_labels => Schema::Block {
+            labels,
+            body: widen_attributes(body, other),
+        },
+        (schema, other) => {
+            if schema == other {
+                schema
+            } else {
+                Schema::Any
+            }
+        }
+    })
+}
+
+fn widen_attributes(
+    mut attributes: BTreeMap<String, Schema>,
+    other: BTreeMap<String, Schema>,
+) -> BTreeMap<String, Schema> {
+    for (key, schema) in other {
+        let existing = attributes.remove(&key);
+        let schema = widen(existing, schema).unwrap_or(Schema::Any);
+        attributes.insert(key, schema);
+    }
+    attributes
+}
+
+/// Writes the schema as a type constraint
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Schema::Any => write!(f, "any"),
+            Schema::Number => write!(f, "number"),
+            Schema::Bool => write!(f, "bool"),
+            Schema::String => write!(f, "string"),
+            Schema::List(element) => write!(f, "list({})", element),
+            Schema::Tuple(elements) => {
+                write!(f, "tuple([")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "])")
+            }
+            Schema::Object(attributes) => {
+                write!(f, "object(")?;
+                write_attributes(f, attributes)?;
+                write!(f, ")")
+            }
+            Schema::Block { labels, body } => {
+                write!(f, "block({}, ", labels)?;
+                write_attributes(f, body)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn write_attributes(f: &mut fmt::Formatter, attributes: &BTreeMap<String, Schema>) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, (key, schema)) in attributes.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        match identifier(CompleteStr(key)) {
+            Ok((remaining, _)) if remaining.is_empty() => write!(f, "{}", key)?,
+            _ => write!(f, "{}", QuotedString(key))?,
+        }
+        write!(f, " = {}", schema)?;
+    }
+    write!(f, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::literals::Key;
+    use crate::value::Block;
+
+    fn object(attributes: &[(&str, Schema)]) -> BTreeMap<String, Schema> {
+        attributes
+            .iter()
+            .map(|(key, schema)| (key.to_string(), schema.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn scalars_and_lists_are_inferred() {
+        let list = |element| Schema::List(Box::new(element));
+        let test_cases = [
+            (Value::Null, Schema::Any),
+            (Value::Integer(1), Schema::Number),
+            (Value::Float(1.5), Schema::Number),
+            (Value::from(true), Schema::Bool),
+            (Value::from("a"), Schema::String),
+            (Value::new_list(vec![]), list(Schema::Any)),
+            (
+                Value::new_list(vec![Value::Integer(1), Value::Float(1.5), Value::Null]),
+                list(Schema::Number),
+            ),
+            (
+                Value::new_list(vec![Value::Integer(1), Value::from("a")]),
+                list(Schema::Any),
+            ),
+            (
+                Value::new_list(vec![
+                    Value::new_list(vec![Value::Integer(1)]),
+                    Value::new_list(vec![]),
+                ]),
+                list(list(Schema::Any)),
+            ),
+        ];
+
+        for (value, expected) in test_cases.iter() {
+            println!("Testing {:?}", value);
+            assert_eq!(infer_schema(value), *expected);
+        }
+    }
+
+    #[test]
+    fn objects_are_inferred_and_widened() {
+        let value = Value::new_list(vec![
+            Value::new_single_map(vec![
+                (Key::new_identifier("name"), Value::from("a")),
+                (Key::new_identifier("port"), Value::Integer(80)),
+                (Key::new_string("the tags"), Value::Null),
+            ]),
+            Value::new_single_map(vec![
+                (Key::new_identifier("name"), Value::from("b")),
+                (Key::new_identifier("port"), Value::from("http")),
+                (Key::new_identifier("enabled"), Value::from(true)),
+            ]),
+        ]);
+
+        let schema = infer_schema(&value);
+        assert_eq!(
+            schema,
+            Schema::List(Box::new(Schema::Object(object(&[
+                ("enabled", Schema::Bool),
+                ("name", Schema::String),
+                ("port", Schema::Any),
+                ("the tags", Schema::Any),
+            ]))))
+        );
+        assert_eq!(
+            schema.to_string(),
+            r#"list(object({enabled = bool, name = string, port = any, "the tags" = any}))"#
+        );
+    }
+
+    #[test]
+    fn literal_types_are_inferred() {
+        let list = |element| Schema::List(Box::new(element));
+        let test_cases = [
+            ("[1, 2, 3]", list(Schema::Number), "list(number)"),
+            (
+                r#"[1, "a"]"#,
+                Schema::Tuple(vec![Schema::Number, Schema::String]),
+                "tuple([number, string])",
+            ),
+            ("[]", Schema::Tuple(vec![]), "tuple([])"),
+            (
+                "[[1], [2, true]]",
+                Schema::Tuple(vec![
+                    list(Schema::Number),
+                    Schema::Tuple(vec![Schema::Number, Schema::Bool]),
+                ]),
+                "tuple([list(number), tuple([number, bool])])",
+            ),
+            (
+                "{a = 1}",
+                Schema::Object(object(&[("a", Schema::Number)])),
+                "object({a = number})",
+            ),
+            (
+                r#"{a = [1, "b"]}"#,
+                Schema::Object(object(&[(
+                    "a",
+                    Schema::Tuple(vec![Schema::Number, Schema::String]),
+                )])),
+                "object({a = tuple([number, string])})",
+            ),
+        ];
+
+        for (input, expected, displayed) in test_cases.iter() {
+            println!("Testing {}", input);
+            let value = crate::parser::expression::Expression::parse(input)
+                .unwrap()
+                .to_value()
+                .unwrap();
+            let inferred = value.inferred_type();
+            assert_eq!(inferred, *expected);
+            assert_eq!(inferred.to_string(), *displayed);
+        }
+
+        // Tuples are still widened into lists when inferring a schema
+        let tuple = Value::new_list(vec![Value::Integer(1), Value::from("a")]);
+        assert_eq!(infer_schema(&tuple), list(Schema::Any));
+    }
+
+    #[test]
+    fn blocks_are_inferred() {
+        let block = Value::Block(Block::new_unmerged(vec![
+            (
+                vec!["instance", "a"],
+                MapValues::new_unmerged(vec![(Key::new_identifier("count"), Value::Integer(1))]),
+            ),
+            (
+                vec!["instance", "b"],
+                MapValues::new_unmerged(vec![
+                    (Key::new_identifier("count"), Value::Float(2.5)),
+                    (
+                        Key::new_identifier("tags"),
+                        Value::new_list(vec![Value::from("x")]),
+                    ),
+                ]),
+            ),
+        ]));
+
+        let schema = infer_schema(&block);
+        assert_eq!(
+            schema,
+            Schema::Block {
+                labels: 2,
+                body: object(&[
+                    ("count", Schema::Number),
+                    ("tags", Schema::List(Box::new(Schema::String))),
+                ]),
+            }
+        );
+        assert_eq!(
+            schema.to_string(),
+            "block(2, {count = number, tags = list(string)})"
+        );
+
+        let mixed = Value::Block(Block::new_unmerged(vec![
+            (vec!["a"], MapValues::new_unmerged(vec![])),
+            (vec![], MapValues::new_unmerged(vec![])),
+        ]));
+        assert_eq!(infer_schema(&mixed), Schema::Any);
+    }
+}
@@ -53,6 +53,12 @@ mod error {
         #[fail(display = "Body has duplicate key {}", _0)]
         BodyDuplicateKey(String),
 
+        #[fail(
+            display = "Expression {} cannot be deserialized without evaluation",
+            _0
+        )]
+        UnevaluatedExpression(&'static str),
+
         #[fail(display = "{}", _0)]
         Custom(String),
     }
@@ -175,7 +181,7 @@ where
         }
     }
 
-    visitor.visit_seq(tuple.into_deserializer())
+    visitor.visit_seq(tuple.elements.into_deserializer())
 }
 
 fn deserialize_object<'de, V>(
@@ -1,4 +1,4 @@
-use std::collections::{hash_map, HashMap};
+use std::collections::{btree_map, BTreeMap};
 use std::vec;
 
 use serde::de::{self, Deserializer, IntoDeserializer, Visitor};
@@ -117,11 +117,11 @@ impl<'de> de::Deserializer<'de> for BlockBody<'de> {
 #[derive(Debug)]
 pub struct LabelsSeqAccess<'de> {
     empty: vec::IntoIter<Body<'de>>,
-    labels: hash_map::IntoIter<BlockLabel<'de>, BlockBody<'de>>,
+    labels: btree_map::IntoIter<BlockLabel<'de>, BlockBody<'de>>,
 }
 
 impl<'de> LabelsSeqAccess<'de> {
-    pub fn new(empty: Vec<Body<'de>>, labels: HashMap<BlockLabel<'de>, BlockBody<'de>>) -> Self {
+    pub fn new(empty: Vec<Body<'de>>, labels: BTreeMap<BlockLabel<'de>, BlockBody<'de>>) -> Self {
         Self {
             empty: empty.into_iter(),
             labels: labels.into_iter(),
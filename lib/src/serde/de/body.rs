@@ -113,7 +113,7 @@ pub(crate) fn build_map_acces_iter<'de>(
     let attributes = attributes
         .into_iter()
         .map(BodyElement::unwrap_attribute)
-        .map(|(ident, expr)| (ident, BodyValue::Expression(expr)));
+        .map(|attribute| (attribute.name, BodyValue::Expression(attribute.expression)));
     let blocks = block::Blocks::new(blocks.into_iter().map(BodyElement::unwrap_block))
         .into_iter()
         .map(|(ident, bodies)| (ident, BodyValue::Block(bodies)));
@@ -236,6 +236,22 @@ list = ["foo", "bar", "baz"]
         assert_eq!(expected, deserialized);
     }
 
+    #[test]
+    fn deserialize_objects_with_computed_keys() {
+        use std::collections::HashMap;
+
+        let input = r#"map = { plain = 1, "quoted key" = 2, (3) = 3 }"#;
+        let deserialized: HashMap<String, HashMap<String, i64>> = from_str(input).unwrap();
+        let expected: HashMap<_, _> = vec![("plain", 1), ("quoted key", 2), ("3", 3)]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        assert_eq!(deserialized["map"], expected);
+
+        let input = "map = { (var.name) = 1 }";
+        assert!(from_str::<HashMap<String, HashMap<String, i64>>>(input).is_err());
+    }
+
     #[test]
     fn deserialize_nested_single_block_labeless() {
         #[derive(Deserialize, PartialEq, Debug)]
@@ -3,7 +3,7 @@ use serde::forward_to_deserialize_any;
 
 use crate::parser::expression::Expression;
 use crate::serde::de::{
-    deserialize_number, deserialize_object, deserialize_string, deserialize_tuple, Compat,
+    deserialize_number, deserialize_object, deserialize_string, deserialize_tuple, Compat, Error,
 };
 
 impl<'de> de::Deserializer<'de> for Expression<'de> {
@@ -21,6 +21,15 @@ impl<'de> de::Deserializer<'de> for Expression<'de> {
             String(string) => deserialize_string(string, visitor),
             Tuple(tuple) => deserialize_tuple(tuple, visitor, None),
             Object(object) => deserialize_object(object, visitor),
+            expression @ Conditional(_)
+            | expression @ Variable(_)
+            | expression @ Traversal(_)
+            | expression @ UnaryOp(_)
+            | expression @ BinaryOp(_)
+            | expression @ FunctionCall(_)
+            | expression @ ForExpr(_) => {
+                Err(Error::UnevaluatedExpression(expression.variant_name()).into())
+            }
         }
     }
 
@@ -191,7 +200,8 @@ mod tests {
     fn deserialize_bytes_errors_on_invalid_entries() {
         use serde_bytes::ByteBuf;
 
-        let deserializer = Expression::Tuple(vec![Expression::from(false), Expression::from("hi")]);
+        let deserializer =
+            Expression::new_tuple(vec![Expression::from(false), Expression::from("hi")]);
         let _ = ByteBuf::deserialize(deserializer).unwrap();
     }
 
@@ -200,7 +210,7 @@ mod tests {
     fn deserialize_bytes_errors_on_overflow() {
         use serde_bytes::ByteBuf;
 
-        let deserializer = Expression::Tuple(vec![Expression::from(1), Expression::from(999)]);
+        let deserializer = Expression::new_tuple(vec![Expression::from(1), Expression::from(999)]);
         let _ = ByteBuf::deserialize(deserializer).unwrap();
     }
 
@@ -285,7 +295,7 @@ mod tests {
         let deserialized: (u32, i32, i16) = Deserialize::deserialize(deserializer).unwrap();
         assert_eq!(deserialized, (1, 2, 3));
 
-        let deserializer = Expression::Tuple(vec![
+        let deserializer = Expression::new_tuple(vec![
             Expression::from(1),
             Expression::from(true),
             Expression::Null,
@@ -315,7 +325,7 @@ mod tests {
         #[derive(Deserialize, PartialEq, Debug)]
         struct TupleTwo<'a>(f32, bool, &'a str);
 
-        let deserializer = Expression::Tuple(vec![
+        let deserializer = Expression::new_tuple(vec![
             Expression::from(1),
             Expression::from(true),
             Expression::from("null"),
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 
 use serde::de::{DeserializeSeed, IntoDeserializer, MapAccess};
 
-use crate::parser::expression::Expression;
+use crate::parser::expression::{object_key, Expression};
 use crate::parser::object::{Object, ObjectElementIdentifier};
 use crate::serde::de::{Compat, Error};
 
@@ -40,7 +40,8 @@ impl<'de> MapAccess<'de> for ObjectMapAccess<'de> {
             None => return Ok(None),
             Some((key, value)) => (key, value),
         };
-        let key = key.as_str();
+        // Computed keys must evaluate to a string, like when converting into a `Value`
+        let key = object_key(&key).map_err(Error::from)?.unwrap();
 
         if !self.seen_keys.insert(key.clone()) {
             return Err(Error::ObjectDuplicateKey(key.to_string()).into());
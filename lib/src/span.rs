@@ -0,0 +1,253 @@
+//! Source locations
+//!
+//! Utilities to locate parsed input in the original source and to render source excerpts for
+//! diagnostics.
+//!
+//! Attributes and blocks record their spans when parsed with
+//! [`parse_with_spans`](crate::parser::parse_with_spans). Expressions do not have spans.
+use std::fmt::Write;
+
+/// A range of bytes in the source
+///
+/// `start` is inclusive and `end` is exclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a new span from byte offsets
+    ///
+    /// If `end` is before `start`, the span will be empty.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end: std::cmp::max(start, end),
+        }
+    }
+
+    /// Create a span for a `fragment` that is a subslice of `source`
+    ///
+    /// Returns `None` if `fragment` does not point into `source`.
+    pub fn from_fragment(source: &str, fragment: &str) -> Option<Self> {
+        let source_start = source.as_ptr() as usize;
+        let fragment_start = fragment.as_ptr() as usize;
+        if fragment_start < source_start
+            || fragment_start + fragment.len() > source_start + source.len()
+        {
+            return None;
+        }
+
+        let start = fragment_start - source_start;
+        Some(Self::new(start, start + fragment.len()))
+    }
+
+    /// Number of bytes covered by the span
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns whether the span covers no bytes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A line and column in the source
+///
+/// Both are one-based. The column counts characters, and not bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Compute the position of the byte `offset` in `source`
+    ///
+    /// Offsets past the end of the source are clamped to the end, and offsets that are not on a
+    /// character boundary are moved back to the previous boundary.
+    pub fn from_offset(source: &str, offset: usize) -> Self {
+        let offset = char_boundary(source, offset);
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+
+        Self { line, column }
+    }
+}
+
+fn char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = std::cmp::min(offset, source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Render an excerpt of `source` with the characters covered by `span` underlined
+///
+/// Each line that the span touches is printed with its line number in a gutter, followed by
+/// a line of carets under the covered characters. An empty span is rendered as a single caret.
+///
+/// ```rust
+/// use ferrous_chloride::span::{render_span, Span};
+///
+/// let source = "foo = 1\nbar = baz\n";
+/// let rendered = render_span(source, Span::new(14, 17));
+/// assert_eq!(
+///     rendered,
+///     " --> 2:7
+///   |
+/// 2 | bar = baz
+///   |       ^^^
+/// "
+/// );
+/// ```
+pub fn render_span(source: &str, span: Span) -> String {
+    let start = char_boundary(source, span.start);
+    let end = char_boundary(source, span.end);
+    let start_position = Position::from_offset(source, start);
+    let end_position = Position::from_offset(source, end);
+
+    let gutter = end_position.line.to_string().len();
+    let mut rendered = String::new();
+    writeln!(
+        rendered,
+        "{:width$}--> {}:{}",
+        "",
+        start_position.line,
+        start_position.column,
+        width = gutter
+    )
+    .expect("writing to a String to succeed");
+    writeln!(rendered, "{:width$} |", "", width = gutter).expect("writing to a String to succeed");
+
+    let mut line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    for number in start_position.line..=end_position.line {
+        let line_end = source[line_start..]
+            .find('\n')
+            .map_or(source.len(), |i| line_start + i);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+
+        let underline_start = if number == start_position.line {
+            start_position.column - 1
+        } else {
+            0
+        };
+        let underline_end = if number == end_position.line {
+            end_position.column - 1
+        } else {
+            line.chars().count()
+        };
+        let underline_width = std::cmp::max(underline_end.saturating_sub(underline_start), 1);
+
+        writeln!(rendered, "{:>width$} | {}", number, line, width = gutter)
+            .expect("writing to a String to succeed");
+        writeln!(
+            rendered,
+            "{:width$} | {:indent$}{}",
+            "",
+            "",
+            "^".repeat(underline_width),
+            width = gutter,
+            indent = underline_start
+        )
+        .expect("writing to a String to succeed");
+
+        line_start = std::cmp::min(line_end + 1, source.len());
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_computed_from_offsets() {
+        let source = "foo = 1\nbär = baz\r\n\nlast";
+        let test_cases = [
+            (0, (1, 1)),
+            (4, (1, 5)),
+            (8, (2, 1)),
+            (12, (2, 4)),
+            (11, (2, 3)),
+            (21, (4, 1)),
+            (100, (4, 5)),
+        ];
+
+        for (offset, (line, column)) in test_cases.iter() {
+            println!("Testing {}", offset);
+            assert_eq!(
+                Position::from_offset(source, *offset),
+                Position {
+                    line: *line,
+                    column: *column
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn spans_are_computed_from_fragments() {
+        let source = "foo = 1\nbar = baz\n";
+        assert_eq!(
+            Span::from_fragment(source, &source[14..17]),
+            Some(Span::new(14, 17))
+        );
+        assert_eq!(Span::from_fragment(source, "baz"), None);
+    }
+
+    #[test]
+    fn spans_are_rendered_with_underlines() {
+        let source = r#"a = 1
+b = 2
+c = 3
+d = 4
+e = 5
+f = 6
+g = 7
+h = 8
+i = 9
+j = "multi
+line"
+"#;
+        let test_cases = [
+            (
+                Span::new(12, 13),
+                r#" --> 3:1
+  |
+3 | c = 3
+  | ^
+"#,
+            ),
+            (
+                Span::new(22, 22),
+                r#" --> 4:5
+  |
+4 | d = 4
+  |     ^
+"#,
+            ),
+            (
+                Span::new(58, 70),
+                r#"  --> 10:5
+   |
+10 | j = "multi
+   |     ^^^^^^
+11 | line"
+   | ^^^^^
+"#,
+            ),
+        ];
+
+        for (span, expected) in test_cases.iter() {
+            println!("Testing {:?}", span);
+            assert_eq!(render_span(source, *span), *expected);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use nom::types::{CompleteByteSlice, CompleteStr, Input};
 use std::ops::RangeFull;
 
+use crate::span::{Position, Span};
+
 /// Recognizes at least 1 character while a predicate holds true
 pub fn while_predicate1<T, F>(input: T, predicate: F) -> nom::IResult<T, T>
 where
@@ -16,6 +18,283 @@ where
     )
 }
 
+/// Compute the one-based line and column in `input` where `remaining` begins
+///
+/// `remaining` is expected to be a subslice of `input`, such as the input left over when a parser
+/// fails. Otherwise, it is assumed to be a suffix of `input`. The column counts characters, and not
+/// bytes.
+///
+/// ```rust
+/// use ferrous_chloride::utils::error_position;
+///
+/// let input = "foo = 1\nbar = @\n";
+/// assert_eq!(error_position(input, &input[14..]), (2, 7));
+/// ```
+pub fn error_position(input: &str, remaining: &str) -> (usize, usize) {
+    let offset = match Span::from_fragment(input, remaining) {
+        Some(span) => span.start,
+        None => input.len().saturating_sub(remaining.len()),
+    };
+    let position = Position::from_offset(input, offset);
+    (position.line, position.column)
+}
+
+/// The start of `input` to show in an error message: up to the end of the first line, and at most
+/// 32 characters
+pub(crate) fn snippet(input: &str) -> String {
+    let line = input.split(&['\n', '\r'][..]).next().unwrap_or("");
+    match line.char_indices().nth(32) {
+        Some((end, _)) => line[..end].to_string(),
+        None => line.to_string(),
+    }
+}
+
+/// Run a parser that works on [`CompleteStr`] on a `&str`
+///
+/// Returns the remaining input together with the output of the parser. Errors are converted into
+/// an [`Error`](crate::Error) with the line and column in `input` where the parser failed.
+///
+/// ```rust
+/// use ferrous_chloride::parser::boolean::boolean;
+/// use ferrous_chloride::utils::wrap_str;
+///
+/// assert_eq!(wrap_str("true, false", boolean).unwrap(), (", false", true));
+/// assert!(wrap_str("maybe", boolean).is_err());
+/// ```
+pub fn wrap_str<'a, O, F>(input: &'a str, parser: F) -> Result<(&'a str, O), crate::Error>
+where
+    F: Fn(CompleteStr<'a>) -> nom::IResult<CompleteStr<'a>, O, u32>,
+{
+    parser(CompleteStr(input))
+        .map(|(remaining, output)| (remaining.0, output))
+        .map_err(|err| crate::Error::from_err_str_with_input(&err, input))
+}
+
+/// Run a parser and return its output together with the input it consumed
+///
+/// Like `recognize!` in nom, but the output of the parser is kept, so that the exact source text
+/// of a value, like the formatting of a number or the quotes of a string, can be kept alongside it.
+///
+/// ```rust
+/// use ferrous_chloride::parser::number::number;
+/// use ferrous_chloride::utils::with_raw;
+/// use nom::types::CompleteStr;
+///
+/// let (remaining, (number, raw)) = with_raw(CompleteStr("0x1F, 2"), number).unwrap();
+/// assert_eq!(number.as_u64().unwrap(), 31);
+/// assert_eq!(raw, "0x1F");
+/// assert_eq!(remaining.0, ", 2");
+/// ```
+pub fn with_raw<'a, O, F>(
+    input: CompleteStr<'a>,
+    parser: F,
+) -> nom::IResult<CompleteStr<'a>, (O, &'a str), u32>
+where
+    F: Fn(CompleteStr<'a>) -> nom::IResult<CompleteStr<'a>, O, u32>,
+{
+    let (remaining, output) = parser(input)?;
+    let raw = &input.0[..input.len() - remaining.len()];
+    Ok((remaining, (output, raw)))
+}
+
+/// Recognizes text enclosed in balanced `open` and `close` delimiters
+///
+/// The input must start with `open`. The text between it and the matching `close` is returned,
+/// and the remaining input starts after `close`. Nested pairs of the same delimiters are skipped
+/// over, as are delimiters inside comments, Heredocs and quoted strings, including the
+/// interpolation sequences and directives of the strings.
+///
+/// ```rust
+/// use ferrous_chloride::utils::balanced;
+/// use nom::types::CompleteStr;
+///
+/// let (remaining, inside) = balanced(CompleteStr(r#"{ a = "}" } rest"#), '{', '}').unwrap();
+/// assert_eq!(inside.0, r#" a = "}" "#);
+/// assert_eq!(remaining.0, " rest");
+/// ```
+pub fn balanced(
+    input: CompleteStr,
+    open: char,
+    close: char,
+) -> nom::IResult<CompleteStr, CompleteStr, u32> {
+    use nom::{error_position, ErrorKind, Slice};
+
+    if !input.starts_with(open) {
+        return Err(nom::Err::Error(error_position!(input, ErrorKind::Char)));
+    }
+
+    let (remaining, inside) =
+        until_unbalanced(input.slice(open.len_utf8()..), open, close, usize::MAX)?;
+    Ok((remaining.slice(close.len_utf8()..), inside))
+}
+
+// The text up to, but not including, the first `close` that is not matched by an `open` in the
+// text, skipping over comments, Heredocs and quoted strings like `balanced`.
+//
+// The quoted strings and the template sequences in them are tracked on a stack rather than by
+// recursion, so that deeply nested input cannot overflow the stack. Fails with
+// `RecursionLimitExceeded` if the template sequences are nested more than `max_depth` deep.
+pub(crate) fn until_unbalanced(
+    input: CompleteStr,
+    open: char,
+    close: char,
+    max_depth: usize,
+) -> nom::IResult<CompleteStr, CompleteStr, u32> {
+    use crate::errors::InternalKind;
+    use crate::parser::string::{heredoc_begin, heredoc_string};
+    use nom::{error_position, ErrorKind, Slice};
+
+    #[derive(Clone, Copy)]
+    enum Frame {
+        // Delimited text, with the number of nested pairs of delimiters that are open
+        Delimited {
+            open: char,
+            close: char,
+            depth: usize,
+        },
+        // A quoted string
+        Quoted,
+    }
+
+    // Delimited text and quoted strings alternate, starting with the text of `input`
+    let mut frames = vec![Frame::Delimited {
+        open,
+        close,
+        depth: 0,
+    }];
+    let mut index = 0;
+    while let (Some(c), Some(&frame)) = (input.0[index..].chars().next(), frames.last()) {
+        let rest = input.slice(index..);
+        index += match frame {
+            Frame::Quoted => match c {
+                '"' => {
+                    frames.pop();
+                    1
+                }
+                '\\' => match rest.0[1..].chars().next() {
+                    Some(escaped) => 1 + escaped.len_utf8(),
+                    None => break,
+                },
+                '$' if rest.starts_with("$${") => 3,
+                '%' if rest.starts_with("%%{") => 3,
+                '$' | '%' if rest.0[1..].starts_with('{') => {
+                    if frames.len() / 2 >= max_depth {
+                        return Err(nom::Err::Failure(error_position!(
+                            rest,
+                            ErrorKind::Custom(InternalKind::RecursionLimitExceeded as u32)
+                        )));
+                    }
+                    frames.push(Frame::Delimited {
+                        open: '{',
+                        close: '}',
+                        depth: 0,
+                    });
+                    2
+                }
+                c => c.len_utf8(),
+            },
+            Frame::Delimited { open, close, depth } => match c {
+                '"' => {
+                    frames.push(Frame::Quoted);
+                    1
+                }
+                '<' if heredoc_begin(rest).is_ok() => match heredoc_string(rest) {
+                    Ok((remaining, _)) => rest.len() - remaining.len(),
+                    Err(_) => break,
+                },
+                '#' => line_length(rest.0),
+                '/' if rest.starts_with("//") => line_length(rest.0),
+                '/' if rest.starts_with("/*") => match rest.0[2..].find("*/") {
+                    Some(end) => end + 4,
+                    None => break,
+                },
+                c if c == close && depth == 0 => {
+                    frames.pop();
+                    if frames.is_empty() {
+                        return Ok((input.slice(index..), input.slice(..index)));
+                    }
+                    c.len_utf8()
+                }
+                c if c == close || c == open => {
+                    let nested = if c == open { depth + 1 } else { depth - 1 };
+                    if let Some(last) = frames.last_mut() {
+                        *last = Frame::Delimited {
+                            open,
+                            close,
+                            depth: nested,
+                        };
+                    }
+                    c.len_utf8()
+                }
+                c => c.len_utf8(),
+            },
+        };
+    }
+
+    Err(nom::Err::Error(error_position!(
+        input,
+        ErrorKind::TakeUntil
+    )))
+}
+
+// The length of the line at the start of `input`, with its line ending
+fn line_length(input: &str) -> usize {
+    input
+        .find('\n')
+        .map(|end| end + 1)
+        .unwrap_or_else(|| input.len())
+}
+
+/// Recognizes a separator between the elements of a collection
+///
+/// A separator is a single comma or one or more newlines, together with the whitespace, comments
+/// and blank lines around it. A line comment counts as a newline. There must be a comma or a
+/// newline: whitespace and inline comments alone are not a separator.
+///
+/// ```rust
+/// use ferrous_chloride::utils::separator;
+/// use nom::types::CompleteStr;
+///
+/// let (remaining, separator) = separator(CompleteStr(" , # Comment\n\n  b = 2")).unwrap();
+/// assert_eq!(separator.0, " , # Comment\n\n  ");
+/// assert_eq!(remaining.0, "b = 2");
+/// ```
+pub fn separator(input: CompleteStr) -> nom::IResult<CompleteStr, CompleteStr, u32> {
+    use nom::{alt, call, char, do_parse, recognize};
+
+    use crate::parser::whitespace::{inline_whitespace, newline, whitespace};
+
+    recognize!(
+        input,
+        do_parse!(
+            call!(inline_whitespace)
+                >> alt!(
+                    do_parse!(char!(',') >> call!(whitespace) >> ())
+                        | do_parse!(call!(newline) >> call!(whitespace) >> ())
+                )
+                >> ()
+        )
+    )
+}
+
+/// Recognizes a [`separator`], or the end of a collection closed by `close`
+///
+/// The closing delimiter is not consumed, so that the last element may be followed either by a
+/// trailing separator or directly by the delimiter.
+pub fn separator_or_end(
+    input: CompleteStr,
+    close: char,
+) -> nom::IResult<CompleteStr, CompleteStr, u32> {
+    use nom::{alt, call, char, pair, peek, recognize};
+
+    use crate::parser::whitespace::inline_whitespace;
+
+    alt!(
+        input,
+        call!(separator) | recognize!(pair!(call!(inline_whitespace), peek!(char!(close))))
+    )
+}
+
 pub trait SafeIndexing: nom::Slice<RangeFull> + Sized {
     type Iter: Iterator<Item = usize>;
 
@@ -276,4 +555,194 @@ mod tests {
             .zip(&expected_indices)
             .all(|(actual, expected)| actual == expected),)
     }
+
+    #[test]
+    fn parsers_return_the_raw_input_they_consumed() {
+        use crate::parser::number::number;
+        use crate::parser::string::string;
+
+        let test_cases = [
+            ("-1.50e+3 + 1", "-1.50e+3", " + 1"),
+            ("0xFF", "0xFF", ""),
+            ("007]", "007", "]"),
+        ];
+        for (input, expected_raw, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, (number, raw)) = with_raw(CompleteStr(input), number).unwrap();
+            assert_eq!(raw, *expected_raw);
+            assert_eq!(&*number, *expected_raw);
+            assert_eq!(remaining.0, *expected_remaining);
+        }
+
+        let test_cases = [
+            (r#""a \"b\"" c"#, "a \"b\"", r#""a \"b\"""#, " c"),
+            (r#""""#, "", r#""""#, ""),
+            ("<<EOF\nline\nEOF\n", "line", "<<EOF\nline\nEOF", "\n"),
+        ];
+        for (input, expected, expected_raw, expected_remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let (remaining, (string, raw)) = with_raw(CompleteStr(input), string).unwrap();
+            assert_eq!(string, *expected);
+            assert_eq!(raw, *expected_raw);
+            assert_eq!(remaining.0, *expected_remaining);
+        }
+
+        assert!(with_raw(CompleteStr("abc"), number).is_err());
+    }
+
+    #[test]
+    fn balanced_delimiters_are_recognized() {
+        let test_cases = [
+            ("{}", "", ""),
+            ("{a = {b = 1}}", "a = {b = 1}", ""),
+            (r#"{x = "}"}"#, r#"x = "}""#, ""),
+            (r#"{x = "\"}"} y"#, r#"x = "\"}""#, " y"),
+            ("{a # }\n} b", "a # }\n", " b"),
+            ("{a // }\n}", "a // }\n", ""),
+            ("{a /* } */ }", "a /* } */ ", ""),
+            ("{a / b}", "a / b", ""),
+            ("[[1], [2]], 3", "[1], [2]", ", 3"),
+            ("(f(x) + (1))", "f(x) + (1)", ""),
+            ("{[}]}", "[", "]}"),
+            (r#"{x = "${"}"}"}"#, r#"x = "${"}"}""#, ""),
+            (
+                r#"{x = "%{ if a }}%{ endif }"}"#,
+                r#"x = "%{ if a }}%{ endif }""#,
+                "",
+            ),
+            (
+                r#"{x = "${ {a = "}"}.a }"} y"#,
+                r#"x = "${ {a = "}"}.a }""#,
+                " y",
+            ),
+            (r#"{x = "$${"} y"#, r#"x = "$${""#, " y"),
+            ("{x = <<EOF\n} \"\nEOF\n}", "x = <<EOF\n} \"\nEOF\n", ""),
+            (
+                "{x = <<-EOF\n  }\n  EOF\n} y",
+                "x = <<-EOF\n  }\n  EOF\n",
+                " y",
+            ),
+        ];
+
+        for (input, inside, remaining) in test_cases.iter() {
+            println!("Testing {}", input);
+            let open = input.chars().next().unwrap();
+            let close = match open {
+                '{' => '}',
+                '[' => ']',
+                _ => ')',
+            };
+            let (actual_remaining, actual) = balanced(CompleteStr(input), open, close).unwrap();
+            assert_eq!(actual.0, *inside);
+            assert_eq!(actual_remaining.0, *remaining);
+        }
+    }
+
+    #[test]
+    fn unbalanced_delimiters_are_errors() {
+        let test_cases = [
+            "",
+            "a{}",
+            "{",
+            "{a = {b = 1}",
+            r#"{x = "}"#,
+            r#"{x = "${"}"#,
+            "{x = <<EOF\n}\n",
+            "{a # }",
+            "{a /* } */",
+            "}",
+        ];
+
+        for input in test_cases.iter() {
+            println!("Testing {}", input);
+            assert!(balanced(CompleteStr(input), '{', '}').is_err());
+        }
+    }
+
+    #[test]
+    fn separators_are_recognized() {
+        let cases = [
+            (", \nb", ", \n", "b"),
+            ("\n\nb", "\n\n", "b"),
+            (", # c\nb", ", # c\n", "b"),
+            ("  ,b", "  ,", "b"),
+            ("// c\n  /* d */\nb", "// c\n  /* d */\n", "b"),
+            (",\n", ",\n", ""),
+            ("\r\nb", "\r\n", "b"),
+        ];
+        for (input, separator_text, remaining) in cases.iter() {
+            println!("Testing {:?}", input);
+            assert_eq!(
+                separator(CompleteStr(input)),
+                Ok((CompleteStr(remaining), CompleteStr(separator_text)))
+            );
+        }
+
+        for input in ["", "b", "  b", " /* c */ b", ",,"].iter() {
+            println!("Testing {:?}", input);
+            let result = separator(CompleteStr(input));
+            if *input == ",," {
+                // Only a single comma is a separator
+                assert_eq!(result, Ok((CompleteStr(","), CompleteStr(","))));
+            } else {
+                assert!(result.is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn separators_may_be_replaced_by_the_closing_delimiter() {
+        assert_eq!(
+            separator_or_end(CompleteStr(", }"), '}'),
+            Ok((CompleteStr("}"), CompleteStr(", ")))
+        );
+        assert_eq!(
+            separator_or_end(CompleteStr("  }"), '}'),
+            Ok((CompleteStr("}"), CompleteStr("  ")))
+        );
+        assert_eq!(
+            separator_or_end(CompleteStr("\n]"), ']'),
+            Ok((CompleteStr("]"), CompleteStr("\n")))
+        );
+        assert!(separator_or_end(CompleteStr(" b }"), '}').is_err());
+        assert!(separator_or_end(CompleteStr(""), '}').is_err());
+    }
+
+    #[test]
+    fn error_positions_are_computed_from_the_remaining_input() {
+        let input = "foo = 1\nbär = @\r\n\nbaz";
+        let test_cases = [
+            (&input[..], (1, 1)),
+            (&input[6..], (1, 7)),
+            (&input[8..], (2, 1)),
+            (&input[15..], (2, 7)),
+            (&input[input.len()..], (4, 4)),
+            // Not a subslice of the input, so assumed to be a suffix
+            ("baz", (4, 1)),
+        ];
+
+        for (remaining, expected) in test_cases.iter() {
+            println!("Testing {:?}", remaining);
+            assert_eq!(error_position(input, remaining), *expected);
+        }
+    }
+
+    #[test]
+    fn snippets_end_at_the_first_line_or_32_characters() {
+        let test_cases = [
+            ("", ""),
+            ("garbage", "garbage"),
+            ("} junk\nmore = 1\n", "} junk"),
+            ("a = 1\r\n", "a = 1"),
+            (
+                "ééééééééééééééééééééééééééééééééééé",
+                "éééééééééééééééééééééééééééééééé",
+            ),
+        ];
+
+        for (input, expected) in test_cases.iter() {
+            println!("Testing {:?}", input);
+            assert_eq!(snippet(input), *expected);
+        }
+    }
 }
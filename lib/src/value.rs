@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::iter::FromIterator;
 use std::string::ToString;
 
@@ -6,6 +7,7 @@ use nom::types::CompleteStr;
 
 use crate::constants::*;
 use crate::parser::literals::Key;
+use crate::parser::string::escape_str;
 use crate::MergeBehaviour;
 use crate::{AsOwned, Error, KeyValuePairs, ScalarLength};
 
@@ -37,6 +39,23 @@ pub type MapValues<'a> = KeyValuePairs<Key<'a>, Value<'a>>;
 
 pub type List<'a> = Vec<Value<'a>>;
 
+// Used by `hcl_value!` to build keys
+#[doc(hidden)]
+pub use crate::parser::literals::Key as __Key;
+
+/// A key repeated in the same object, found by [`Value::validate_unique_keys`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateKey {
+    /// The keys, block labels and list indices leading to the object, from the outermost value
+    ///
+    /// Values do not keep where they were parsed from, so this is the location of the key.
+    pub path: Vec<String>,
+    /// The repeated key
+    pub key: String,
+    /// The number of times the key appears in the object
+    pub count: usize,
+}
+
 impl<'a> Value<'a> {
     pub fn new_list<T>(iterator: T) -> Self
     where
@@ -191,6 +210,20 @@ impl<'a> Value<'a> {
         self.borrow_str().unwrap()
     }
 
+    /// Borrow the UTF-8 bytes of a string value
+    ///
+    /// Escape sequences and heredoc indentation have already been processed, so the bytes are
+    /// those of the decoded string content.
+    pub fn as_bytes(&self) -> Result<&[u8], Error> {
+        self.borrow_str().map(str::as_bytes)
+    }
+
+    /// # Panics
+    /// Panics if the variant is not a string
+    pub fn unwrap_as_bytes(&self) -> &[u8] {
+        self.as_bytes().unwrap()
+    }
+
     pub fn borrow_string_mut(&mut self) -> Result<&mut String, Error> {
         if let Value::String(ref mut v) = self {
             Ok(v)
@@ -489,6 +522,450 @@ impl<'a> Value<'a> {
     pub fn is_body(&self) -> bool {
         self.is_map()
     }
+
+    /// Returns the string, if the value is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if the value is a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer, if the value is an integer
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the float, if the value is a float
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as a `f64`, if the value is an integer or a float
+    ///
+    /// Integers with more than 53 significant bits are rounded.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(v) => Some(*v as f64),
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the list, if the value is a list
+    pub fn as_list(&self) -> Option<&List<'a>> {
+        match self {
+            Value::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the object, if the value is an object
+    pub fn as_object(&self) -> Option<&Object<'a>> {
+        match self {
+            Value::Object(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the block, if the value is a block
+    pub fn as_block(&self) -> Option<&Block<'a>> {
+        match self {
+            Value::Block(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Infer the type of the literal the value was written as
+    ///
+    /// See [`infer_type`](crate::schema::infer_type).
+    pub fn inferred_type(&self) -> crate::schema::Schema {
+        crate::schema::infer_type(self)
+    }
+
+    /// Recursively count the values, including this value, for which `predicate` returns `true`
+    ///
+    /// Lists, objects and blocks are counted themselves before their elements are visited.
+    pub fn count_matching<F>(&self, predicate: F) -> usize
+    where
+        F: Fn(&Value<'a>) -> bool,
+    {
+        self.count_matching_by(&predicate)
+    }
+
+    fn count_matching_by<F>(&self, predicate: &F) -> usize
+    where
+        F: Fn(&Value<'a>) -> bool,
+    {
+        let count = if predicate(self) { 1 } else { 0 };
+        let children = match self {
+            Value::Null
+            | Value::Integer(_)
+            | Value::Float(_)
+            | Value::Boolean(_)
+            | Value::String(_) => 0,
+            Value::List(list) => list
+                .iter()
+                .map(|value| value.count_matching_by(predicate))
+                .sum(),
+            Value::Object(maps) => maps
+                .iter()
+                .flat_map(MapValues::values)
+                .map(|value| value.count_matching_by(predicate))
+                .sum(),
+            Value::Block(block) => block
+                .values()
+                .flat_map(MapValues::values)
+                .map(|value| value.count_matching_by(predicate))
+                .sum(),
+        };
+        count + children
+    }
+
+    /// Call `visitor` on this value and then, recursively, on every value nested in it
+    ///
+    /// Lists, objects and blocks are visited before their elements, which are visited in order.
+    /// The keys of objects and blocks are not visited.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parse_str;
+    ///
+    /// let value = parse_str("a = [1, 2]\nb = { c = 3 }\n").unwrap().to_value().unwrap();
+    /// let mut integers = vec![];
+    /// value.walk(|value| integers.extend(value.as_integer()));
+    /// assert_eq!(integers, [1, 2, 3]);
+    /// ```
+    pub fn walk<F>(&self, mut visitor: F)
+    where
+        F: FnMut(&Value<'a>),
+    {
+        self.walk_by(&mut visitor)
+    }
+
+    fn walk_by<F>(&self, visitor: &mut F)
+    where
+        F: FnMut(&Value<'a>),
+    {
+        visitor(self);
+        match self {
+            Value::Null
+            | Value::Integer(_)
+            | Value::Float(_)
+            | Value::Boolean(_)
+            | Value::String(_) => {}
+            Value::List(list) => list.iter().for_each(|value| value.walk_by(visitor)),
+            Value::Object(maps) => maps
+                .iter()
+                .flat_map(MapValues::values)
+                .for_each(|value| value.walk_by(visitor)),
+            Value::Block(block) => block
+                .values()
+                .flat_map(MapValues::values)
+                .for_each(|value| value.walk_by(visitor)),
+        }
+    }
+
+    /// Recursively replace every string in the value with the result of `f`
+    ///
+    /// Only string values are replaced: the keys of objects and the labels of blocks are kept.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parse_str;
+    /// use ferrous_chloride::Value;
+    ///
+    /// let value = parse_str("version = \"1.0\"\n").unwrap().to_value().unwrap();
+    /// let value = value.map_strings(|string| string.replace("1.0", "1.1"));
+    /// assert_eq!(
+    ///     value.unwrap_borrow_map()[0].get_single("version"),
+    ///     Some(&Value::from("1.1"))
+    /// );
+    /// ```
+    pub fn map_strings<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&str) -> String,
+    {
+        self.map_strings_by(&mut f);
+        self
+    }
+
+    fn map_strings_by<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(&str) -> String,
+    {
+        match self {
+            Value::Null | Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => {}
+            Value::String(string) => *string = f(string),
+            Value::List(list) => list.iter_mut().for_each(|value| value.map_strings_by(f)),
+            Value::Object(maps) => maps
+                .iter_mut()
+                .flat_map(map_values_mut)
+                .for_each(|value| value.map_strings_by(f)),
+            Value::Block(block) => {
+                let bodies: Box<dyn Iterator<Item = &mut MapValues<'a>>> = match block {
+                    KeyValuePairs::Merged(hashmap) => Box::new(hashmap.values_mut()),
+                    KeyValuePairs::Unmerged(vec) => Box::new(vec.iter_mut().map(|(_, v)| v)),
+                };
+                bodies
+                    .flat_map(map_values_mut)
+                    .for_each(|value| value.map_strings_by(f))
+            }
+        }
+    }
+
+    /// Recursively replace the values of the keys for which `matcher` returns `true` with the
+    /// string `***`
+    ///
+    /// The keys of objects and block bodies are matched. A matched value is replaced as a whole,
+    /// so nothing nested in it is kept. The elements of lists and the bodies of blocks under keys
+    /// that do not match are visited, so that keys nested in them can be redacted too.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parse_str;
+    /// use ferrous_chloride::Value;
+    ///
+    /// let body = parse_str(r#"
+    /// user     = "admin"
+    /// password = "hunter2"
+    /// "#).unwrap();
+    /// let mut value = body.to_value().unwrap();
+    /// value.redact(|key| key.contains("password"));
+    ///
+    /// let map = value.unwrap_borrow_map();
+    /// assert_eq!(map[0].get_single("password"), Some(&Value::from("***")));
+    /// assert_eq!(map[0].get_single("user"), Some(&Value::from("admin")));
+    /// ```
+    pub fn redact<F>(&mut self, matcher: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.redact_by(&matcher)
+    }
+
+    fn redact_by<F>(&mut self, matcher: &F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        match self {
+            Value::Null
+            | Value::Integer(_)
+            | Value::Float(_)
+            | Value::Boolean(_)
+            | Value::String(_) => {}
+            Value::List(list) => list.iter_mut().for_each(|value| value.redact_by(matcher)),
+            Value::Object(maps) => maps.iter_mut().for_each(|map| map.redact_by(matcher)),
+            Value::Block(block) => {
+                let bodies: Box<dyn Iterator<Item = &mut MapValues<'a>>> = match block {
+                    KeyValuePairs::Merged(hashmap) => Box::new(hashmap.values_mut()),
+                    KeyValuePairs::Unmerged(vec) => Box::new(vec.iter_mut().map(|(_, v)| v)),
+                };
+                bodies.for_each(|body| body.redact_by(matcher))
+            }
+        }
+    }
+
+    /// Compare two values, with the keys of objects and blocks in the same order
+    ///
+    /// Unlike `==`, integers and floats are compared by their value, so `1` is equal to `1.0`.
+    /// The key value pairs of unmerged objects and blocks must be in the same order. Merged
+    /// objects and blocks have no order, so they are compared by their keys.
+    ///
+    /// See [`semantic_eq`](Value::semantic_eq) to ignore the order of keys.
+    pub fn deep_eq(&self, other: &Self) -> bool {
+        self.eq_by(other, false)
+    }
+
+    /// Compare two values, ignoring the order of the keys of objects and blocks
+    ///
+    /// This is the comparison to use when the meaning of two documents matters more than how
+    /// they are written. Like [`deep_eq`](Value::deep_eq), integers and floats are compared by
+    /// their value. Objects and blocks are equal if they have the same keys with equal values,
+    /// in any order. When a key is repeated, its values must be in the same order.
+    ///
+    /// Lists are still compared in order, as are the maps of an object, which come from
+    /// repeated definitions of the same key.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parse_str;
+    ///
+    /// let value = |hcl| parse_str(hcl).unwrap().to_value().unwrap();
+    ///
+    /// let ours = value("a = 1\nb = [1, 2]\n");
+    /// let theirs = value("b = [1.0, 2]\na = 1\n");
+    /// assert!(ours.semantic_eq(&theirs));
+    /// assert!(!ours.deep_eq(&theirs));
+    ///
+    /// assert!(!ours.semantic_eq(&value("a = 1\nb = [2, 1]\n")));
+    /// ```
+    pub fn semantic_eq(&self, other: &Self) -> bool {
+        self.eq_by(other, true)
+    }
+
+    /// Check that the keys of each object in the value, recursively, are unique
+    ///
+    /// Unmerged objects keep repeated keys, which are usually a mistake like a copy-pasted
+    /// attribute. Each repeated key is reported once per object, objects before the objects
+    /// nested in them. Blocks are not checked against each other, since blocks of the same type
+    /// may be repeated.
+    ///
+    /// ```rust
+    /// use ferrous_chloride::parse_str;
+    ///
+    /// let value = parse_str("a = {b = 1, b = 2}\n").unwrap().to_value().unwrap();
+    /// let duplicates = value.validate_unique_keys().unwrap_err();
+    /// assert_eq!(duplicates.len(), 1);
+    /// assert_eq!(duplicates[0].path, ["a"]);
+    /// assert_eq!(duplicates[0].key, "b");
+    /// ```
+    pub fn validate_unique_keys(&self) -> Result<(), Vec<DuplicateKey>> {
+        let mut duplicates = vec![];
+        self.collect_duplicate_keys(&mut vec![], &mut duplicates);
+        if duplicates.is_empty() {
+            Ok(())
+        } else {
+            Err(duplicates)
+        }
+    }
+
+    fn collect_duplicate_keys(&self, path: &mut Vec<String>, duplicates: &mut Vec<DuplicateKey>) {
+        match self {
+            Value::List(list) => {
+                for (index, value) in list.iter().enumerate() {
+                    path.push(index.to_string());
+                    value.collect_duplicate_keys(path, duplicates);
+                    path.pop();
+                }
+            }
+            Value::Object(maps) => {
+                for map in maps {
+                    map_duplicate_keys(map, path, duplicates);
+                }
+            }
+            Value::Block(block) => {
+                for (labels, body) in block.iter() {
+                    let length = path.len();
+                    path.extend(labels.iter().cloned());
+                    map_duplicate_keys(body, path, duplicates);
+                    path.truncate(length);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn eq_by(&self, other: &Self, ignore_key_order: bool) -> bool {
+        match (self, other) {
+            (Value::Integer(ours), Value::Float(theirs))
+            | (Value::Float(theirs), Value::Integer(ours)) => *ours as f64 == *theirs,
+            (Value::List(ours), Value::List(theirs)) => {
+                ours.len() == theirs.len()
+                    && ours
+                        .iter()
+                        .zip(theirs)
+                        .all(|(ours, theirs)| ours.eq_by(theirs, ignore_key_order))
+            }
+            (Value::Object(ours), Value::Object(theirs)) => {
+                ours.len() == theirs.len()
+                    && ours.iter().zip(theirs).all(|(ours, theirs)| {
+                        pairs_eq(ours, theirs, ignore_key_order, |ours, theirs| {
+                            ours.eq_by(theirs, ignore_key_order)
+                        })
+                    })
+            }
+            (Value::Block(ours), Value::Block(theirs)) => {
+                pairs_eq(ours, theirs, ignore_key_order, |ours, theirs| {
+                    pairs_eq(ours, theirs, ignore_key_order, |ours, theirs| {
+                        ours.eq_by(theirs, ignore_key_order)
+                    })
+                })
+            }
+            (ours, theirs) => ours == theirs,
+        }
+    }
+}
+
+fn map_duplicate_keys(map: &MapValues, path: &mut Vec<String>, duplicates: &mut Vec<DuplicateKey>) {
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for (key, _) in map.iter().filter(|(_, value)| !value.is_block()) {
+        match counts.iter_mut().find(|(counted, _)| *counted == &**key) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((key, 1)),
+        }
+    }
+    duplicates.extend(
+        counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(key, count)| DuplicateKey {
+                path: path.clone(),
+                key: key.to_string(),
+                count,
+            }),
+    );
+
+    for (key, value) in map.iter() {
+        path.push(key.to_string());
+        value.collect_duplicate_keys(path, duplicates);
+        path.pop();
+    }
+}
+
+// Compare key value pairs with `eq` comparing their values
+//
+// Unmerged pairs are compared in order unless `ignore_order` is set. Otherwise, the values of
+// each key are compared in order.
+fn pairs_eq<K, V, F>(
+    ours: &KeyValuePairs<K, V>,
+    theirs: &KeyValuePairs<K, V>,
+    ignore_order: bool,
+    eq: F,
+) -> bool
+where
+    K: std::hash::Hash + Eq,
+    F: Fn(&V, &V) -> bool,
+{
+    if ours.len() != theirs.len() {
+        return false;
+    }
+
+    match (ours, theirs) {
+        (KeyValuePairs::Unmerged(ours), KeyValuePairs::Unmerged(theirs)) if !ignore_order => ours
+            .iter()
+            .zip(theirs)
+            .all(|((our_key, ours), (their_key, theirs))| our_key == their_key && eq(ours, theirs)),
+        _ => ours.keys().all(|key| {
+            let ours: Vec<_> = values_of(ours, key).collect();
+            let theirs: Vec<_> = values_of(theirs, key).collect();
+            ours.len() == theirs.len()
+                && ours
+                    .into_iter()
+                    .zip(theirs)
+                    .all(|(ours, theirs)| eq(ours, theirs))
+        }),
+    }
+}
+
+fn values_of<'p, K, V>(pairs: &'p KeyValuePairs<K, V>, key: &'p K) -> impl Iterator<Item = &'p V>
+where
+    K: std::hash::Hash + Eq,
+{
+    pairs
+        .iter()
+        .filter(move |(other, _)| *other == key)
+        .map(|(_, value)| value)
 }
 
 impl<'a> ScalarLength for Value<'a> {
@@ -557,9 +1034,25 @@ impl_from_value!(Integer, i64);
 impl_from_value!(Float, f64);
 impl_from_value!(Boolean, bool);
 impl_from_value!(String, String);
+impl_from_value!(List, List<'a>);
 impl_from_value!(Object, Vec<MapValues<'a>>);
 impl_from_value!(Block, Block<'a>);
 
+macro_rules! impl_from_lossless (
+    ($variant: ident, $target: ty, $($type: ty),+) => (
+        $(
+            impl<'a> From<$type> for Value<'a> {
+                fn from(v: $type) -> Self {
+                    Value::$variant(<$target>::from(v))
+                }
+            }
+        )+
+    )
+);
+
+impl_from_lossless!(Integer, i64, i8, i16, i32, u8, u16, u32);
+impl_from_lossless!(Float, f64, f32);
+
 /// Special Snowflake treatment for &str and friends
 impl<'a, 'b> From<&'b str> for Value<'a> {
     fn from(s: &'b str) -> Self {
@@ -592,6 +1085,32 @@ impl<'a> FromIterator<Value<'a>> for Value<'a> {
     }
 }
 
+/// Collects key and value pairs into an object
+///
+/// ```rust
+/// use ferrous_chloride::Value;
+///
+/// let object: Value = vec![("name", Value::from("web")), ("count", Value::from(2))]
+///     .into_iter()
+///     .collect();
+/// assert_eq!(object.to_string(), r#"{ name = "web", count = 2 }"#);
+/// ```
+impl<'a, K, V> FromIterator<(K, V)> for Value<'a>
+where
+    K: Into<Key<'a>>,
+    V: Into<Value<'a>>,
+{
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = (K, V)>,
+    {
+        Value::new_single_map(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        )
+    }
+}
+
 impl<'a> AsOwned for Value<'a> {
     type Output = Value<'static>;
 
@@ -609,6 +1128,238 @@ impl<'a> AsOwned for Value<'a> {
     }
 }
 
+/// Formats the value in a HCL-like syntax
+///
+/// - `{}` produces a compact single line form. Bodies with blocks, and the bodies of blocks with
+///   more than one attribute, are still written with one unindented entry per line, since HCL
+///   requires attributes and blocks to end with a newline.
+/// - `{:#}` produces an indented multi-line form. The width (e.g. `{:#4}`) sets the number of
+///   spaces used per indentation level, and defaults to two.
+/// - The precision (e.g. `{:.2}`) sets the number of decimal places for floats
+///
+/// HCL has no literal for floats that are not finite, so they are written as the strings
+/// `"NaN"`, `"inf"` and `"-inf"`, like `to_json` does.
+impl<'a> fmt::Display for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printer = Printer {
+            pretty: f.alternate(),
+            indent: f.width().unwrap_or(2),
+            precision: f.precision(),
+        };
+        printer.value(f, self, 0)
+    }
+}
+
+struct Printer {
+    pretty: bool,
+    indent: usize,
+    precision: Option<usize>,
+}
+
+impl Printer {
+    fn newline(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        write!(f, "\n{:width$}", "", width = level * self.indent)
+    }
+
+    fn value(&self, f: &mut fmt::Formatter, value: &Value, level: usize) -> fmt::Result {
+        match value {
+            Value::Null => f.write_str("null"),
+            Value::Integer(integer) => write!(f, "{}", integer),
+            Value::Float(float) if !float.is_finite() => write!(f, "\"{}\"", float),
+            Value::Float(float) => match self.precision {
+                Some(precision) => write!(f, "{:.*}", precision, float),
+                None => write!(f, "{:?}", float),
+            },
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+            Value::String(string) => self.string(f, string),
+            Value::List(list) => self.list(f, list.iter(), level, |f, value| {
+                self.value(f, value, level + 1)
+            }),
+            Value::Object(maps) if maps.len() == 1 => self.map(f, &maps[0], level),
+            Value::Object(maps) => {
+                self.list(f, maps.iter(), level, |f, map| self.map(f, map, level + 1))
+            }
+            Value::Block(block) => {
+                for (index, (labels, body)) in block.iter().enumerate() {
+                    if index > 0 {
+                        self.line(f, level)?;
+                    }
+                    self.block(f, labels, body, level)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn string(&self, f: &mut fmt::Formatter, string: &str) -> fmt::Result {
+        write!(f, "\"{}\"", escape_str(string))
+    }
+
+    fn separator(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        if self.pretty {
+            self.newline(f, level)
+        } else {
+            f.write_str(", ")
+        }
+    }
+
+    // Blocks and the attributes of bodies must be on lines of their own, even in the compact form
+    fn line(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        if self.pretty {
+            self.newline(f, level)
+        } else {
+            f.write_str("\n")
+        }
+    }
+
+    fn list<I, T, F>(&self, f: &mut fmt::Formatter, items: I, level: usize, item: F) -> fmt::Result
+    where
+        I: ExactSizeIterator<Item = T>,
+        F: Fn(&mut fmt::Formatter, T) -> fmt::Result,
+    {
+        if items.len() == 0 {
+            return f.write_str("[]");
+        }
+
+        f.write_str("[")?;
+        for (index, value) in items.enumerate() {
+            if self.pretty {
+                self.newline(f, level + 1)?;
+            } else if index > 0 {
+                f.write_str(", ")?;
+            }
+            item(f, value)?;
+            if self.pretty {
+                f.write_str(",")?;
+            }
+        }
+        if self.pretty {
+            self.newline(f, level)?;
+        }
+        f.write_str("]")
+    }
+
+    fn map(&self, f: &mut fmt::Formatter, map: &MapValues, level: usize) -> fmt::Result {
+        if map.is_empty() {
+            return f.write_str("{}");
+        }
+        if map.values().any(Value::is_block) {
+            return self.body(f, map, level);
+        }
+
+        f.write_str("{")?;
+        if self.pretty {
+            self.newline(f, level + 1)?;
+        } else {
+            f.write_str(" ")?;
+        }
+        for (index, (key, value)) in map.iter().enumerate() {
+            if index > 0 {
+                self.separator(f, level + 1)?;
+            }
+            self.entry(f, key, value, level + 1)?;
+        }
+        if self.pretty {
+            self.newline(f, level)?;
+        } else {
+            f.write_str(" ")?;
+        }
+        f.write_str("}")
+    }
+
+    // Writes a body with one entry per line
+    fn body(&self, f: &mut fmt::Formatter, body: &MapValues, level: usize) -> fmt::Result {
+        f.write_str("{")?;
+        for (key, value) in body.iter() {
+            self.line(f, level + 1)?;
+            self.entry(f, key, value, level + 1)?;
+        }
+        self.line(f, level)?;
+        f.write_str("}")
+    }
+
+    fn entry(&self, f: &mut fmt::Formatter, key: &Key, value: &Value, level: usize) -> fmt::Result {
+        match value {
+            Value::Block(block) => {
+                for (index, (labels, body)) in block.iter().enumerate() {
+                    if index > 0 {
+                        self.line(f, level)?;
+                    }
+                    self.key(f, key)?;
+                    f.write_str(" ")?;
+                    self.block(f, labels, body, level)?;
+                }
+                Ok(())
+            }
+            value => {
+                self.key(f, key)?;
+                f.write_str(" = ")?;
+                self.value(f, value, level)
+            }
+        }
+    }
+
+    fn key(&self, f: &mut fmt::Formatter, key: &Key) -> fmt::Result {
+        match key {
+            Key::Identifier(identifier) => f.write_str(identifier),
+            Key::String(string) => self.string(f, string),
+        }
+    }
+
+    fn block(
+        &self,
+        f: &mut fmt::Formatter,
+        labels: &[String],
+        body: &MapValues,
+        level: usize,
+    ) -> fmt::Result {
+        for label in labels {
+            self.string(f, label)?;
+            f.write_str(" ")?;
+        }
+        // Only a block with a single attribute can be written on one line
+        if body.len() > 1 {
+            self.body(f, body, level)
+        } else {
+            self.map(f, body, level)
+        }
+    }
+}
+
+/// Pretty prints a single `key = value` entry of a body, or blocks if the value is a block,
+/// nested at an indentation `level`
+pub(crate) struct PrettyEntry<'a, 'b> {
+    pub key: &'b Key<'a>,
+    pub value: &'b Value<'a>,
+    pub level: usize,
+    pub indent: usize,
+}
+
+impl<'a, 'b> fmt::Display for PrettyEntry<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printer = Printer {
+            pretty: true,
+            indent: self.indent,
+            precision: None,
+        };
+        printer.entry(f, self.key, self.value, self.level)
+    }
+}
+
+/// Prints a string as a quoted HCL string literal
+pub(crate) struct QuotedString<'a>(pub &'a str);
+
+impl<'a> fmt::Display for QuotedString<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printer = Printer {
+            pretty: false,
+            indent: 0,
+            precision: None,
+        };
+        printer.string(f, self.0)
+    }
+}
+
 impl<'a> Block<'a> {
     // TODO: Customise behaviour wrt duplicate block keys
     pub fn new_merged<T, K, S>(iter: T) -> Result<Self, Error>
@@ -880,6 +1631,33 @@ impl<'a> MapValues<'a> {
             self.clone()
         }
     }
+
+    fn redact_by<F>(&mut self, matcher: &F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        let pairs: Box<dyn Iterator<Item = (&Key<'a>, &mut Value<'a>)>> = match self {
+            KeyValuePairs::Merged(hashmap) => Box::new(hashmap.iter_mut()),
+            KeyValuePairs::Unmerged(vec) => Box::new(vec.iter_mut().map(|(k, v)| (&*k, v))),
+        };
+        for (key, value) in pairs {
+            if matcher(key) {
+                *value = Value::from("***");
+            } else {
+                value.redact_by(matcher);
+            }
+        }
+    }
+}
+
+// The values of a map, in order if the map is unmerged
+fn map_values_mut<'m, 'a>(
+    map: &'m mut MapValues<'a>,
+) -> Box<dyn Iterator<Item = &'m mut Value<'a>> + 'm> {
+    match map {
+        KeyValuePairs::Merged(hashmap) => Box::new(hashmap.values_mut()),
+        KeyValuePairs::Unmerged(vec) => Box::new(vec.iter_mut().map(|(_, v)| v)),
+    }
 }
 
 impl<'a> FromIterator<(Key<'a>, Value<'a>)> for MapValues<'a> {
@@ -894,8 +1672,8 @@ impl<'a> FromIterator<(Key<'a>, Value<'a>)> for MapValues<'a> {
 /// You can opt to merge the parsed body after parsing. The behaviour of merging is determined by
 /// the [`MergeBehaviour`] enum.
 pub fn from_str(input: &str, merge: Option<MergeBehaviour>) -> Result<Body, Error> {
-    let (remaining_input, unmerged) =
-        crate::parser::body(CompleteStr(input)).map_err(|e| Error::from_err_str(&e))?;
+    let (remaining_input, unmerged) = crate::parser::body(CompleteStr(input))
+        .map_err(|e| Error::from_err_str_with_input(&e, input))?;
 
     if !remaining_input.is_empty() {
         return Err(Error::Bug(format!(
@@ -941,6 +1719,185 @@ pub fn from_slice(bytes: &[u8], merge: Option<MergeBehaviour>) -> Result<Body, E
     from_str(input, merge)
 }
 
+#[cfg(feature = "toml")]
+impl<'a> Value<'a> {
+    /// Convert the value into a [`toml::Value`]
+    ///
+    /// TOML has no equivalent of some HCL values, so the conversion is lossy:
+    ///
+    /// - Members of objects and block bodies that are `null` are omitted. A `null` anywhere else,
+    ///   like in a list or as the value itself, is an error.
+    /// - An object with a single map becomes a table, while an object with more than one map,
+    ///   like repeated blocks, becomes an array of tables.
+    /// - Block bodies are nested in tables keyed by each of their labels. Bodies with the same
+    ///   labels are merged, and an error is returned if they have keys in common.
+    /// - Duplicate keys in an unmerged map are an error.
+    pub fn to_toml(&self) -> Result<toml::Value, Error> {
+        Ok(match self {
+            Value::Null => {
+                return Err(Error::UnexpectedVariant {
+                    enum_type: VALUE,
+                    expected: "a value representable in TOML",
+                    actual: NULL,
+                })
+            }
+            Value::Integer(integer) => toml::Value::Integer(*integer),
+            Value::Float(float) => toml::Value::Float(*float),
+            Value::Boolean(boolean) => toml::Value::Boolean(*boolean),
+            Value::String(string) => toml::Value::String(string.clone()),
+            Value::List(list) => {
+                toml::Value::Array(list.iter().map(Value::to_toml).collect::<Result<_, _>>()?)
+            }
+            Value::Object(maps) => match maps.as_slice() {
+                [map] => toml::Value::Table(map_to_toml(map, OBJECT)?),
+                maps => toml::Value::Array(
+                    maps.iter()
+                        .map(|map| map_to_toml(map, OBJECT).map(toml::Value::Table))
+                        .collect::<Result<_, _>>()?,
+                ),
+            },
+            Value::Block(block) => {
+                let mut table = toml::value::Table::new();
+                for (labels, body) in block.iter() {
+                    let mut leaf = &mut table;
+                    for label in labels {
+                        leaf = match leaf
+                            .entry(label.to_string())
+                            .or_insert_with(|| toml::Value::Table(Default::default()))
+                        {
+                            toml::Value::Table(ref mut table) => table,
+                            _ => {
+                                return Err(Error::IllegalMultipleEntries {
+                                    key: label.to_string(),
+                                    variant: BLOCK,
+                                })
+                            }
+                        };
+                    }
+
+                    for (key, value) in map_to_toml(body, BLOCK)? {
+                        if leaf.contains_key(&key) {
+                            return Err(Error::IllegalMultipleEntries {
+                                key,
+                                variant: BLOCK,
+                            });
+                        }
+                        let _ = leaf.insert(key, value);
+                    }
+                }
+                toml::Value::Table(table)
+            }
+        })
+    }
+}
+
+#[cfg(feature = "toml")]
+fn map_to_toml(map: &MapValues, variant: &'static str) -> Result<toml::value::Table, Error> {
+    let mut table = toml::value::Table::new();
+    for (key, value) in map.iter().filter(|(_, value)| !value.is_null()) {
+        if table.contains_key(&**key) {
+            return Err(Error::IllegalMultipleEntries {
+                key: key.to_string(),
+                variant,
+            });
+        }
+        let _ = table.insert(key.to_string(), value.to_toml()?);
+    }
+    Ok(table)
+}
+
+#[cfg(feature = "serde_json")]
+impl<'a> Value<'a> {
+    /// Convert the value into a [`serde_json::Value`]
+    ///
+    /// Unlike TOML, JSON can represent every HCL value, but some conversions are still lossy:
+    ///
+    /// - Floats that are not finite are not valid JSON numbers, and are converted into strings
+    ///   like `"NaN"` or `"inf"` instead.
+    /// - An object with a single map becomes a JSON object, while an object with more than one
+    ///   map, like repeated blocks, becomes an array of objects.
+    /// - Block bodies are nested in objects keyed by each of their labels.
+    /// - When a key is repeated in an unmerged map, or bodies of blocks have the same labels,
+    ///   objects are merged recursively and other values are replaced by the last one seen.
+    /// - The order of keys is only preserved if the `preserve_order` feature of `serde_json` is
+    ///   enabled. Otherwise, keys are sorted.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Integer(integer) => serde_json::Value::from(*integer),
+            Value::Float(float) => match serde_json::Number::from_f64(*float) {
+                Some(number) => serde_json::Value::Number(number),
+                None => serde_json::Value::String(float.to_string()),
+            },
+            Value::Boolean(boolean) => serde_json::Value::Bool(*boolean),
+            Value::String(string) => serde_json::Value::String(string.clone()),
+            Value::List(list) => {
+                serde_json::Value::Array(list.iter().map(Value::to_json).collect())
+            }
+            Value::Object(maps) => match maps.as_slice() {
+                [map] => serde_json::Value::Object(map_to_json(map)),
+                maps => serde_json::Value::Array(
+                    maps.iter()
+                        .map(|map| serde_json::Value::Object(map_to_json(map)))
+                        .collect(),
+                ),
+            },
+            Value::Block(block) => {
+                let mut object = serde_json::Map::new();
+                for (labels, body) in block.iter() {
+                    let body = serde_json::Value::Object(map_to_json(body));
+                    let nested = labels.iter().rev().fold(body, |nested, label| {
+                        let mut object = serde_json::Map::new();
+                        let _ = object.insert(label.to_string(), nested);
+                        serde_json::Value::Object(object)
+                    });
+
+                    if let serde_json::Value::Object(nested) = nested {
+                        merge_json(&mut object, nested);
+                    }
+                }
+                serde_json::Value::Object(object)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<'a> From<Value<'a>> for serde_json::Value {
+    fn from(value: Value<'a>) -> Self {
+        value.to_json()
+    }
+}
+
+#[cfg(feature = "serde_json")]
+fn map_to_json(map: &MapValues) -> serde_json::Map<String, serde_json::Value> {
+    let mut object = serde_json::Map::new();
+    for (key, value) in map.iter() {
+        let mut entry = serde_json::Map::new();
+        let _ = entry.insert(key.to_string(), value.to_json());
+        merge_json(&mut object, entry);
+    }
+    object
+}
+
+// Merge the entries of `other` into `object`, recursing into objects present in both
+#[cfg(feature = "serde_json")]
+fn merge_json(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    other: serde_json::Map<String, serde_json::Value>,
+) {
+    for (key, value) in other {
+        match (object.get_mut(&key), value) {
+            (Some(serde_json::Value::Object(existing)), serde_json::Value::Object(value)) => {
+                merge_json(existing, value)
+            }
+            (_, value) => {
+                let _ = object.insert(key, value);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -962,4 +1919,729 @@ mod tests {
             assert!(parsed.is_merged());
         }
     }
+
+    #[test]
+    fn string_values_are_decoded_as_bytes() {
+        let parsed = from_str(
+            r#"escaped = "tab\there\u00e9\n"
+heredoc = <<EOF
+line
+EOF
+number = 1
+"#,
+            Some(MergeBehaviour::Error),
+        )
+        .unwrap();
+
+        assert_eq!(
+            parsed["escaped"].unwrap_as_bytes(),
+            "tab\there\u{e9}\n".as_bytes()
+        );
+        assert_eq!(parsed["heredoc"].unwrap_as_bytes(), b"line");
+        assert!(parsed["number"].as_bytes().is_err());
+    }
+
+    #[test]
+    fn values_are_accessed_as_their_variant() {
+        let list = Value::new_list(vec![Value::Integer(1)]);
+        let object = Value::new_single_map(vec![(Key::new_identifier("a"), Value::Null)]);
+        let block = Value::new_block(&["a"], vec![]);
+        let values = [
+            Value::Null,
+            Value::Integer(2),
+            Value::Float(1.5),
+            Value::Boolean(true),
+            Value::String("x".to_string()),
+            list.clone(),
+            object.clone(),
+            block.clone(),
+        ];
+
+        for value in values.iter() {
+            println!("Testing {:?}", value);
+            assert_eq!(value.is_null(), *value == Value::Null);
+            assert_eq!(value.as_integer(), value.integer().ok());
+            assert_eq!(value.as_float(), value.float().ok());
+            assert_eq!(value.as_bool(), value.boolean().ok());
+            assert_eq!(value.as_str(), value.borrow_str().ok());
+            assert_eq!(value.as_list().is_some(), value.is_list());
+            assert_eq!(value.as_object().is_some(), value.is_map());
+            assert_eq!(value.as_block().is_some(), value.is_block());
+        }
+
+        assert_eq!(Value::Integer(2).as_integer(), Some(2));
+        assert_eq!(Value::Integer(2).as_f64(), Some(2.0));
+        assert_eq!(Value::Float(1.5).as_f64(), Some(1.5));
+        assert_eq!(Value::Float(1.5).as_integer(), None);
+        assert_eq!(Value::Boolean(true).as_f64(), None);
+        assert_eq!(Value::String("x".to_string()).as_str(), Some("x"));
+        assert_eq!(list.as_list(), Some(&vec![Value::Integer(1)]));
+        assert_eq!(object.as_object().map(Vec::len), Some(1));
+        assert_eq!(block.as_block().map(|block| block.len()), Some(1));
+        assert_eq!(Value::Null.as_list(), None);
+    }
+
+    #[test]
+    fn values_are_formatted_compactly_and_pretty() {
+        let value = Value::new_single_map(vec![
+            (Key::new_identifier("number"), Value::Integer(42)),
+            (Key::new_string("float key"), Value::Float(1.5)),
+            (
+                Key::new_identifier("list"),
+                Value::new_list(vec![
+                    Value::Boolean(true),
+                    Value::Null,
+                    Value::from("quote \" and\nnewline"),
+                ]),
+            ),
+            (
+                Key::new_identifier("empty"),
+                Value::new_single_map(Vec::<(Key, Value)>::new()),
+            ),
+            (
+                Key::new_identifier("resource"),
+                Value::new_block(
+                    &["aws_instance", "foo"],
+                    vec![(Key::new_identifier("count"), Value::Integer(1))],
+                ),
+            ),
+        ]);
+
+        // The attributes of a body with blocks are on lines of their own
+        assert_eq!(
+            format!("{}", value),
+            r#"{
+number = 42
+"float key" = 1.5
+list = [true, null, "quote \" and\nnewline"]
+empty = {}
+resource "aws_instance" "foo" { count = 1 }
+}"#
+        );
+
+        assert_eq!(
+            format!("{:#}", value),
+            r#"{
+  number = 42
+  "float key" = 1.5
+  list = [
+    true,
+    null,
+    "quote \" and\nnewline",
+  ]
+  empty = {}
+  resource "aws_instance" "foo" {
+    count = 1
+  }
+}"#
+        );
+
+        assert_eq!(
+            format!("{:#4}", Value::new_list(vec![Value::Integer(1)])),
+            "[\n    1,\n]"
+        );
+        assert_eq!(format!("{:.3}", Value::Float(1.0)), "1.000");
+        assert_eq!(format!("{}", Value::Float(1.0)), "1.0");
+    }
+
+    #[test]
+    fn values_are_displayed_as_hcl() {
+        let cases = vec![
+            (Value::Null, "null"),
+            (Value::Integer(-42), "-42"),
+            (Value::Float(1.5), "1.5"),
+            (Value::Float(1e21), "1e21"),
+            (Value::Boolean(false), "false"),
+            (Value::from("tab\t \\ \"café\""), r#""tab\t \\ \"café\"""#),
+            (
+                Value::from("${literal} %{literal}"),
+                r#""$${literal} %%{literal}""#,
+            ),
+            (Value::new_list(Vec::<Value>::new()), "[]"),
+            (
+                Value::new_list(vec![Value::Integer(1), Value::from("b"), Value::Null]),
+                r#"[1, "b", null]"#,
+            ),
+            (
+                Value::new_single_map(vec![
+                    (Key::new_identifier("k"), Value::Integer(1)),
+                    (Key::new_string("quoted key"), Value::Boolean(true)),
+                ]),
+                r#"{ k = 1, "quoted key" = true }"#,
+            ),
+            (
+                Value::new_single_map(vec![(
+                    Key::new_identifier("nested"),
+                    Value::new_list(vec![Value::new_single_map(vec![(
+                        Key::new_identifier("list"),
+                        Value::new_list(vec![Value::Float(0.5), Value::new_list(vec![])]),
+                    )])]),
+                )]),
+                "{ nested = [{ list = [0.5, []] }] }",
+            ),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(value.to_string(), expected);
+
+            // The compact form is a valid HCL expression for the same value
+            let expression = crate::parser::expression::Expression::parse(expected).unwrap();
+            assert_eq!(expression.eval_const().unwrap(), value);
+        }
+
+        // Unmerged objects with more than one map are written as a list of objects
+        let objects = Value::new_map(vec![
+            vec![(Key::new_identifier("a"), Value::Integer(1))],
+            vec![(Key::new_identifier("a"), Value::Integer(2))],
+        ]);
+        assert_eq!(objects.to_string(), "[{ a = 1 }, { a = 2 }]");
+
+        let block = Value::new_block(
+            &["label"],
+            vec![(Key::new_identifier("count"), Value::Integer(1))],
+        );
+        assert_eq!(block.to_string(), r#""label" { count = 1 }"#);
+
+        assert_eq!(Value::Float(f64::NAN).to_string(), r#""NaN""#);
+        assert_eq!(Value::Float(f64::NEG_INFINITY).to_string(), r#""-inf""#);
+    }
+
+    #[test]
+    fn bodies_with_blocks_are_displayed_as_hcl() {
+        let body = Value::new_single_map(vec![
+            (Key::new_identifier("name"), Value::from("web")),
+            (
+                Key::new_identifier("resource"),
+                Value::Block(Block::new_unmerged(vec![
+                    (
+                        vec!["a".to_string()],
+                        MapValues::new_unmerged(vec![
+                            (Key::new_identifier("count"), Value::Integer(1)),
+                            (Key::new_identifier("ratio"), Value::Float(0.5)),
+                        ]),
+                    ),
+                    (
+                        vec!["b".to_string()],
+                        MapValues::new_unmerged(vec![(
+                            Key::new_identifier("nested"),
+                            Value::new_block(
+                                &[] as &[String],
+                                vec![(Key::new_identifier("enabled"), Value::Boolean(true))],
+                            ),
+                        )]),
+                    ),
+                ])),
+            ),
+        ]);
+
+        let expected = r#"{
+name = "web"
+resource "a" {
+count = 1
+ratio = 0.5
+}
+resource "b" {
+nested { enabled = true }
+}
+}"#;
+        assert_eq!(body.to_string(), expected);
+
+        // The entries between the outer braces of both forms are a valid HCL body
+        let written = r#"
+name = "web"
+resource "a" {
+  count = 1
+  ratio = 0.5
+}
+resource "b" {
+  nested {
+    enabled = true
+  }
+}
+"#;
+        let written = crate::parser::parse_str(written)
+            .unwrap()
+            .to_value()
+            .unwrap();
+        for printed in &[body.to_string(), format!("{:#}", body)] {
+            let entries = &printed[1..printed.len() - 1];
+            let reparsed = crate::parser::parse_str(entries).unwrap();
+            assert_eq!(reparsed.to_value().unwrap(), written);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn values_are_converted_to_toml() {
+        let value = Value::new_single_map(vec![
+            (Key::new_identifier("integer"), Value::Integer(42)),
+            (Key::new_identifier("float"), Value::Float(1.5)),
+            (Key::new_identifier("missing"), Value::Null),
+            (
+                Key::new_identifier("list"),
+                Value::new_list(vec![Value::Boolean(true), Value::from("string")]),
+            ),
+            (
+                Key::new_identifier("repeated"),
+                Value::new_map(vec![
+                    vec![(Key::new_identifier("a"), Value::Integer(1))],
+                    vec![(Key::new_identifier("a"), Value::Integer(2))],
+                ]),
+            ),
+            (
+                Key::new_identifier("resource"),
+                Value::Block(Block::new_unmerged(vec![
+                    (
+                        vec!["instance", "foo"],
+                        MapValues::new_unmerged(vec![(
+                            Key::new_identifier("count"),
+                            Value::Integer(1),
+                        )]),
+                    ),
+                    (
+                        vec!["instance", "bar"],
+                        MapValues::new_unmerged(vec![
+                            (Key::new_identifier("count"), Value::Integer(2)),
+                            (Key::new_identifier("image"), Value::Null),
+                        ]),
+                    ),
+                ])),
+            ),
+        ]);
+
+        let expected: toml::Value = toml::from_str(
+            r#"
+integer = 42
+float = 1.5
+list = [true, "string"]
+
+[[repeated]]
+a = 1
+
+[[repeated]]
+a = 2
+
+[resource.instance.foo]
+count = 1
+
+[resource.instance.bar]
+count = 2
+"#,
+        )
+        .unwrap();
+        assert_eq!(value.to_toml().unwrap(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn null_values_outside_maps_cannot_be_converted_to_toml() {
+        assert!(Value::Null.to_toml().is_err());
+        assert!(Value::new_list(vec![Value::Integer(1), Value::Null])
+            .to_toml()
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn values_are_converted_to_json() {
+        let hcl = r#"
+name    = "app"
+enabled = true
+ratio   = 0.5
+missing = null
+ports   = [80, 443]
+
+settings = {
+  nested = {
+    depth = 2
+  }
+  tags = ["a", "b"]
+}
+
+resource "instance" "foo" {
+  count = 1
+}
+
+resource "instance" "bar" {
+  count = 2
+
+  lifecycle {
+    prevent_destroy = false
+  }
+}
+"#;
+        let value = crate::parse_str(hcl).unwrap().to_value().unwrap();
+
+        // Bodies of blocks are lists, because blocks can be repeated
+        let expected = serde_json::json!({
+            "name": "app",
+            "enabled": true,
+            "ratio": 0.5,
+            "missing": null,
+            "ports": [80, 443],
+            "settings": {
+                "nested": {
+                    "depth": 2
+                },
+                "tags": ["a", "b"]
+            },
+            "resource": {
+                "instance": {
+                    "foo": [{
+                        "count": 1
+                    }],
+                    "bar": [{
+                        "count": 2,
+                        "lifecycle": [{
+                            "prevent_destroy": false
+                        }]
+                    }]
+                }
+            }
+        });
+        assert_eq!(value.to_json(), expected);
+        assert_eq!(serde_json::Value::from(value), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn values_outside_the_range_of_json_numbers_are_converted_to_strings() {
+        assert_eq!(Value::Integer(i64::min_value()).to_json(), i64::min_value());
+        assert_eq!(Value::Float(std::f64::NAN).to_json(), "NaN");
+        assert_eq!(Value::Float(std::f64::INFINITY).to_json(), "inf");
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn objects_and_blocks_are_converted_to_json() {
+        assert_eq!(
+            Value::new_map(vec![
+                vec![(Key::new_identifier("a"), Value::Integer(1))],
+                vec![(Key::new_identifier("a"), Value::Integer(2))],
+            ])
+            .to_json(),
+            serde_json::json!([{"a": 1}, {"a": 2}])
+        );
+
+        let block = Value::Block(Block::new_unmerged(vec![
+            (
+                vec!["instance", "foo"],
+                MapValues::new_unmerged(vec![(Key::new_identifier("count"), Value::Integer(1))]),
+            ),
+            (
+                vec!["instance", "bar"],
+                MapValues::new_unmerged(vec![(Key::new_identifier("count"), Value::Integer(2))]),
+            ),
+        ]));
+        assert_eq!(
+            block.to_json(),
+            serde_json::json!({
+                "instance": {
+                    "foo": {"count": 1},
+                    "bar": {"count": 2}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn matching_values_are_counted() {
+        let value = crate::parse_str(crate::fixtures::LIST)
+            .unwrap()
+            .to_value()
+            .unwrap();
+
+        assert_eq!(
+            value.count_matching(|value| value.is_integer() || value.is_float()),
+            8
+        );
+        assert_eq!(value.count_matching(Value::is_list), 5);
+        assert_eq!(value.count_matching(|_| false), 0);
+
+        let block = Value::new_block(
+            &["instance"],
+            vec![(Key::new_identifier("count"), Value::Integer(1))],
+        );
+        assert_eq!(block.count_matching(Value::is_integer), 1);
+    }
+
+    #[test]
+    fn values_are_walked_and_strings_are_mapped() {
+        let hcl = r#"
+name = "app"
+tags = { owner = "me", count = 2 }
+list = ["a", ["b", true], { c = "d" }]
+resource "instance" "web" {
+    image = "ubuntu"
+}
+"#;
+        let value = crate::parse_str(hcl).unwrap().to_value().unwrap();
+
+        let mut visited = vec![];
+        value.walk(|value| visited.push(value.clone()));
+        assert_eq!(visited.len(), value.count_matching(|_| true));
+        assert_eq!(visited[0], value);
+        let strings: Vec<_> = visited.iter().filter_map(Value::as_str).collect();
+        assert_eq!(strings, ["app", "me", "a", "b", "d", "ubuntu"]);
+        // Each nested value is visited after its parent
+        let list_index = visited.iter().position(Value::is_list).unwrap();
+        assert_eq!(visited[list_index + 1], Value::from("a"));
+
+        let mapped = value.clone().map_strings(|string| string.to_uppercase());
+        let mut strings = vec![];
+        mapped.walk(|value| strings.extend(value.as_str().map(str::to_string)));
+        assert_eq!(strings, ["APP", "ME", "A", "B", "D", "UBUNTU"]);
+
+        // Everything else is kept, including keys and block labels
+        let restored = mapped.map_strings(|string| string.to_lowercase());
+        assert_eq!(restored, value);
+        let block = Value::new_block(
+            &["Instance"],
+            vec![(Key::new_identifier("Name"), Value::from("Web"))],
+        );
+        assert_eq!(
+            block.map_strings(|string| string.to_uppercase()),
+            Value::new_block(
+                &["Instance"],
+                vec![(Key::new_identifier("Name"), Value::from("WEB"))],
+            )
+        );
+    }
+
+    #[test]
+    fn values_under_matching_keys_are_redacted() {
+        let hcl = r#"
+name        = "app"
+db_password = "hunter2"
+credentials = {
+    api_secret = ["a", "b"]
+    user       = "admin"
+}
+users = [{ name = "a", password = "b" }, "plain"]
+provider "aws" {
+    secret_key = "xyz"
+    region     = "eu-west-1"
+}
+secret "tls" {
+    certificate = "pem"
+}
+"#;
+        let mut value = crate::parse_str(hcl).unwrap().to_value().unwrap();
+        value.redact(|key| key.contains("password") || key.contains("secret"));
+
+        let expected = crate::parse_str(
+            r#"
+name        = "app"
+db_password = "***"
+credentials = {
+    api_secret = "***"
+    user       = "admin"
+}
+users = [{ name = "a", password = "***" }, "plain"]
+provider "aws" {
+    secret_key = "***"
+    region     = "eu-west-1"
+}
+secret = "***"
+"#,
+        )
+        .unwrap()
+        .to_value()
+        .unwrap();
+        assert_eq!(value, expected);
+
+        let mut block = Value::new_block(
+            &["aws"],
+            vec![
+                (Key::new_identifier("region"), Value::from("eu-west-1")),
+                (Key::new_identifier("count"), Value::Integer(1)),
+            ],
+        )
+        .merge()
+        .unwrap();
+        block.redact(|key| key == "region");
+        let expected = Value::new_block(
+            &["aws"],
+            vec![
+                (Key::new_identifier("region"), Value::from("***")),
+                (Key::new_identifier("count"), Value::Integer(1)),
+            ],
+        )
+        .merge()
+        .unwrap();
+        assert_eq!(block, expected);
+
+        let mut list = Value::new_list(vec![Value::Integer(1)]);
+        list.redact(|_| true);
+        assert_eq!(list, Value::new_list(vec![Value::Integer(1)]));
+    }
+
+    #[test]
+    fn duplicate_keys_are_reported_with_their_path() {
+        use crate::parser::Expression;
+
+        let expression = |hcl| Expression::parse(hcl).unwrap().to_value().unwrap();
+        let duplicate = |path: &[&str], key: &str, count| DuplicateKey {
+            path: path.iter().map(|s| s.to_string()).collect(),
+            key: key.to_string(),
+            count,
+        };
+
+        assert_eq!(
+            expression("{a = 1, a = 2}").validate_unique_keys(),
+            Err(vec![duplicate(&[], "a", 2)])
+        );
+        assert_eq!(
+            expression(r#"{a = 1, "a" = 2, x = [1, {b = 1, c = 2, b = 3, b = 4}]}"#)
+                .validate_unique_keys(),
+            Err(vec![duplicate(&[], "a", 2), duplicate(&["x", "1"], "b", 3)])
+        );
+        assert_eq!(
+            expression("{a = 1, b = {a = 2}, c = [{a = 3}, {a = 4}]}").validate_unique_keys(),
+            Ok(())
+        );
+
+        let body = crate::parse_str(
+            r#"
+name = "app"
+resource "instance" "web" {
+  count = 1
+  tags = { env = "prod", env = "dev" }
+}
+resource "instance" "db" {
+  count = 1
+  lifecycle = { ignore = true, ignore = false, ignore = true }
+}
+"#,
+        )
+        .unwrap()
+        .to_value()
+        .unwrap();
+        assert_eq!(
+            body.validate_unique_keys(),
+            Err(vec![
+                duplicate(&["resource", "instance", "web", "0", "tags"], "env", 2),
+                duplicate(
+                    &["resource", "instance", "db", "0", "lifecycle"],
+                    "ignore",
+                    3
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn semantic_equality_ignores_key_order_but_not_list_order() {
+        let value = |hcl| crate::parse_str(hcl).unwrap().to_value().unwrap();
+
+        let ours = value(
+            r#"
+name  = "app"
+ports = [80, 443]
+tags  = { a = 1, b = 2.5 }
+"#,
+        );
+        let reordered = value(
+            r#"
+tags  = { b = 2.5, a = 1.0 }
+ports = [80.0, 443]
+name  = "app"
+"#,
+        );
+        assert!(ours.semantic_eq(&reordered));
+        assert!(reordered.semantic_eq(&ours));
+        assert!(!ours.deep_eq(&reordered));
+        assert_ne!(ours, reordered);
+
+        let numbers_only =
+            value("name = \"app\"\nports = [80.0, 443]\ntags = { a = 1.0, b = 2.5 }\n");
+        assert!(ours.deep_eq(&numbers_only));
+        assert!(ours.semantic_eq(&numbers_only));
+
+        let list_reordered =
+            value("name = \"app\"\nports = [443, 80]\ntags = { a = 1, b = 2.5 }\n");
+        assert!(!ours.semantic_eq(&list_reordered));
+        assert!(!ours.deep_eq(&list_reordered));
+
+        let missing = value("name = \"app\"\nports = [80, 443]\ntags = { a = 1 }\n");
+        assert!(!ours.semantic_eq(&missing));
+        assert!(!missing.semantic_eq(&ours));
+
+        let block = |pairs: Vec<(&'static str, i64)>| {
+            Value::Block(Block::new_unmerged(vec![(
+                vec!["aws"],
+                MapValues::new_unmerged(
+                    pairs
+                        .into_iter()
+                        .map(|(key, value)| (Key::new_identifier(key), Value::Integer(value))),
+                ),
+            )]))
+        };
+        let ours = block(vec![("a", 1), ("b", 2), ("a", 3)]);
+        assert!(ours.semantic_eq(&block(vec![("b", 2), ("a", 1), ("a", 3)])));
+        assert!(!ours.deep_eq(&block(vec![("b", 2), ("a", 1), ("a", 3)])));
+        assert!(!ours.semantic_eq(&block(vec![("a", 3), ("b", 2), ("a", 1)])));
+
+        let merged = block(vec![("a", 1), ("b", 2)]).merge().unwrap();
+        assert!(merged.deep_eq(&merged.clone()));
+        assert!(!merged.semantic_eq(&Value::Null));
+    }
+
+    #[test]
+    fn values_are_built_from_rust_values() {
+        let ports: Vec<Value> = vec![80u16.into(), 443u16.into()];
+        let tags: Value = vec![("Name", "web"), ("env", "prod")].into_iter().collect();
+        let rule: Value = vec![("protocol", "tcp")].into_iter().collect();
+        let value: Value = vec![
+            ("name", Value::from("app")),
+            ("replicas", Value::from(3)),
+            ("ratio", Value::from(0.5f32)),
+            ("enabled", Value::from(true)),
+            ("ports", Value::from(ports)),
+            ("tags", tags),
+            ("rules", Value::from(vec![rule])),
+            ("nothing", Value::Null),
+        ]
+        .into_iter()
+        .collect();
+
+        let expected = r#"{
+  name = "app"
+  replicas = 3
+  ratio = 0.5
+  enabled = true
+  ports = [
+    80,
+    443,
+  ]
+  tags = {
+    Name = "web"
+    env = "prod"
+  }
+  rules = [
+    {
+      protocol = "tcp"
+    },
+  ]
+  nothing = null
+}"#;
+        assert_eq!(format!("{:#}", value), expected);
+
+        let built = hcl_value!({
+            name = "app",
+            replicas = 3,
+            ratio = 0.5,
+            enabled = true,
+            ports = [80, 443],
+            tags = { Name = "web", env = "prod" },
+            rules = [{ protocol = "tcp" }],
+            nothing = null,
+        });
+        assert_eq!(format!("{:#}", built), expected);
+        assert_eq!(hcl_value!([(-1), "a", []]).to_string(), r#"[-1, "a", []]"#);
+        assert_eq!(
+            hcl_value!({ "quoted key" = (1 + 1) }).to_string(),
+            r#"{ "quoted key" = 2 }"#
+        );
+    }
 }
@@ -0,0 +1,235 @@
+//! Streaming HCL writer
+//!
+//! [`HclWriter`] writes formatted HCL incrementally to any [`std::io::Write`] without building a
+//! tree of the whole document first. The output is formatted in the same way as the pretty
+//! printed form of [`Value`].
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crate::parser::literals::Key;
+use crate::value::{PrettyEntry, QuotedString, Value};
+
+/// Writes formatted HCL to a [`std::io::Write`] incrementally
+///
+/// Attributes and blocks are written as soon as they are added. The writer keeps track of the
+/// blocks that are open and indents their contents.
+///
+/// ```rust
+/// use ferrous_chloride::writer::HclWriter;
+/// use ferrous_chloride::Value;
+///
+/// let mut writer = HclWriter::new(vec![]);
+/// writer.attribute("name", &Value::from("example")).unwrap();
+/// writer.begin_block("resource", &["instance", "web"]).unwrap();
+/// writer.attribute("count", &Value::Integer(2)).unwrap();
+/// writer.end_block().unwrap();
+///
+/// let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+/// assert_eq!(
+///     output,
+///     r#"name = "example"
+/// resource "instance" "web" {
+///   count = 2
+/// }
+/// "#
+/// );
+/// ```
+#[derive(Debug)]
+pub struct HclWriter<W> {
+    writer: W,
+    indent: usize,
+    depth: usize,
+}
+
+impl<W: Write> HclWriter<W> {
+    /// Create a writer indenting the contents of blocks by two spaces
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            indent: 2,
+            depth: 0,
+        }
+    }
+
+    /// Set the number of spaces the contents of blocks are indented by
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Number of blocks that are currently open
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Write an attribute in the current block
+    ///
+    /// If `value` is a [`Value::Block`], the blocks are written with `name` as their type.
+    pub fn attribute(&mut self, name: &str, value: &Value) -> io::Result<()> {
+        self.write_indent()?;
+        let entry = PrettyEntry {
+            key: &Key::Identifier(Cow::Borrowed(name)),
+            value,
+            level: self.depth,
+            indent: self.indent,
+        };
+        writeln!(self.writer, "{}", entry)
+    }
+
+    /// Open a new block in the current block
+    ///
+    /// Subsequent attributes and blocks are written in the new block until
+    /// [`end_block`](HclWriter::end_block) is called.
+    pub fn begin_block<S>(&mut self, r#type: &str, labels: &[S]) -> io::Result<()>
+    where
+        S: AsRef<str>,
+    {
+        self.write_indent()?;
+        write!(self.writer, "{} ", r#type)?;
+        for label in labels {
+            write!(self.writer, "{} ", QuotedString(label.as_ref()))?;
+        }
+        writeln!(self.writer, "{{")?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Close the current block
+    ///
+    /// Returns an error of kind [`InvalidInput`](io::ErrorKind::InvalidInput) if there is no
+    /// open block.
+    pub fn end_block(&mut self) -> io::Result<()> {
+        if self.depth == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "there is no open block to end",
+            ));
+        }
+        self.depth -= 1;
+        self.write_indent()?;
+        writeln!(self.writer, "}}")
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flush and return the underlying writer
+    ///
+    /// Returns an error of kind [`InvalidInput`](io::ErrorKind::InvalidInput) if there are
+    /// blocks that have not been ended.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.depth > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} block(s) have not been ended", self.depth),
+            ));
+        }
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_indent(&mut self) -> io::Result<()> {
+        write!(
+            self.writer,
+            "{:width$}",
+            "",
+            width = self.depth * self.indent
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::value::{Block, MapValues};
+
+    /// Strip the braces around a pretty printed map and remove one level of indentation
+    fn pretty_body(value: &Value, indent: usize) -> String {
+        let formatted = format!("{:#width$}", value, width = indent);
+        let lines: Vec<_> = formatted.lines().collect();
+        lines[1..lines.len() - 1]
+            .iter()
+            .map(|line| format!("{}\n", &line[indent..]))
+            .collect()
+    }
+
+    #[test]
+    fn writer_output_matches_pretty_printed_values() {
+        let value = Value::new_single_map(vec![
+            (
+                Key::new_identifier("name"),
+                Value::from("quote \" and\nnewline"),
+            ),
+            (
+                Key::new_identifier("list"),
+                Value::new_list(vec![Value::Integer(1), Value::Float(1.5)]),
+            ),
+            (
+                Key::new_identifier("resource"),
+                Value::Block(Block::new_unmerged(vec![(
+                    vec!["instance", "web"],
+                    MapValues::new_unmerged(vec![
+                        (Key::new_identifier("count"), Value::Integer(2)),
+                        (
+                            Key::new_identifier("tags"),
+                            Value::new_single_map(vec![(
+                                Key::new_identifier("env"),
+                                Value::from("prod"),
+                            )]),
+                        ),
+                        (
+                            Key::new_identifier("disk"),
+                            Value::new_block(
+                                &["root"],
+                                vec![(Key::new_identifier("size"), Value::Integer(10))],
+                            ),
+                        ),
+                    ]),
+                )])),
+            ),
+        ]);
+
+        for indent in [2, 4].iter() {
+            let mut writer = HclWriter::new(vec![]).indent(*indent);
+            writer
+                .attribute("name", &Value::from("quote \" and\nnewline"))
+                .unwrap();
+            writer
+                .attribute(
+                    "list",
+                    &Value::new_list(vec![Value::Integer(1), Value::Float(1.5)]),
+                )
+                .unwrap();
+            writer
+                .begin_block("resource", &["instance", "web"])
+                .unwrap();
+            writer.attribute("count", &Value::Integer(2)).unwrap();
+            writer
+                .attribute(
+                    "tags",
+                    &Value::new_single_map(vec![(Key::new_identifier("env"), Value::from("prod"))]),
+                )
+                .unwrap();
+            writer.begin_block("disk", &["root"]).unwrap();
+            writer.attribute("size", &Value::Integer(10)).unwrap();
+            writer.end_block().unwrap();
+            writer.end_block().unwrap();
+
+            let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+            assert_eq!(output, pretty_body(&value, *indent));
+        }
+    }
+
+    #[test]
+    fn unbalanced_blocks_are_errors() {
+        let mut writer = HclWriter::new(vec![]);
+        assert!(writer.end_block().is_err());
+
+        writer.begin_block::<&str>("block", &[]).unwrap();
+        assert_eq!(writer.depth(), 1);
+        assert!(writer.finish().is_err());
+    }
+}